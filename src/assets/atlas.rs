@@ -0,0 +1,697 @@
+//! Texture atlases: many sprites in a few big textures (pages) so that the renderer does not need
+//! a texture per sprite.
+//!
+//! An atlas is described in `assets/atlases/<name>.json`. Folders end with '/' and contain the
+//! PNG directly in them:
+//!
+//! ```json
+//! { "sprites": ["explosion4/", "explosion5/", "missile-01.png"], "max_size": 4096, "padding": 2 }
+//! ```
+//!
+//! The atlas is built by the workers when it is loaded (see `AtlasLoader`), unless `pack_assets`
+//! has already built it. In that case the pack contains the pages
+//! (`sprites/atlases/<name>_<page>.png`) and the layout (`atlases/<name>.layout.json`).
+//! `Atlases::update` adds the atlases that are ready.
+//!
+//! The regions are named after their sprite, e.g. `explosion4/k2_0001.png`, so `Sprite` and the
+//! animation keyframes use the atlas without any change once it is loaded. The other sprites are
+//! still loaded from their own file.
+use crate::assets::source::AssetSource;
+use crate::assets::sprite::{SamplerDef, SpriteAsset, SpriteAssetMetadata};
+use crate::assets::{AssetError, AssetManager, Finish, Handle, LoadJob, Loader, StrongHandle};
+use image::RgbaImage;
+use luminance::context::GraphicsContext;
+use luminance::texture::Sampler;
+use luminance_gl::GL33;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+fn default_max_size() -> u32 {
+    2048
+}
+
+fn default_padding() -> u32 {
+    2
+}
+
+/// What to put in an atlas.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AtlasSpec {
+    /// Sprites or folders of sprites, relative to the sprites folder.
+    pub sprites: Vec<String>,
+    /// Maximum width and height of a page. Bigger sprites get their own page.
+    #[serde(default = "default_max_size")]
+    pub max_size: u32,
+    /// Transparent pixels between the sprites so that they do not bleed into each other.
+    #[serde(default = "default_padding")]
+    pub padding: u32,
+    /// Sampler of the pages. The metadata files of the sprites are ignored.
+    #[serde(default)]
+    pub sampler: Option<SamplerDef>,
+}
+
+impl AtlasSpec {
+    pub fn sampler(&self) -> SamplerDef {
+        self.sampler
+            .clone()
+            .unwrap_or_else(|| SpriteAssetMetadata::default().sampler)
+    }
+
+    /// The sprites with the folders replaced by their PNG.
+    pub fn sprite_names(&self, sprites: &AssetSource) -> Vec<String> {
+        let mut names = vec![];
+        for sprite in &self.sprites {
+            if sprite.ends_with('/') {
+                names.extend(
+                    sprites
+                        .list(sprite)
+                        .into_iter()
+                        .filter(|name| name.ends_with(".png"))
+                        .map(|name| format!("{}{}", sprite, name)),
+                );
+            } else {
+                names.push(sprite.clone());
+            }
+        }
+        names
+    }
+}
+
+/// Part of a page, as texture coordinates.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UvRect {
+    pub offset: [f32; 2],
+    pub size: [f32; 2],
+}
+
+impl UvRect {
+    /// The whole texture, for the sprites that are not in an atlas.
+    pub const FULL: UvRect = UvRect {
+        offset: [0.0, 0.0],
+        size: [1.0, 1.0],
+    };
+
+    /// Offset then size, as sent to the shader.
+    pub fn to_array(&self) -> [f32; 4] {
+        [self.offset[0], self.offset[1], self.size[0], self.size[1]]
+    }
+}
+
+/// Position of a sprite in its page, in pixels from the top left corner.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AtlasRegion {
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AtlasPage {
+    /// Name of the page in the sprite manager.
+    pub sprite: String,
+    pub w: u32,
+    pub h: u32,
+}
+
+/// Result of packing an atlas.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AtlasLayout {
+    pub pages: Vec<AtlasPage>,
+    pub regions: BTreeMap<String, AtlasRegion>,
+}
+
+impl AtlasLayout {
+    pub fn uv(&self, region: &AtlasRegion) -> UvRect {
+        let page = &self.pages[region.page];
+        let (pw, ph) = (page.w as f32, page.h as f32);
+        // textures are flipped when loaded so v starts at the bottom.
+        UvRect {
+            offset: [
+                region.x as f32 / pw,
+                1.0 - (region.y + region.h) as f32 / ph,
+            ],
+            size: [region.w as f32 / pw, region.h as f32 / ph],
+        }
+    }
+}
+
+/// Name of a page in the sprites folder.
+pub fn page_name(atlas: &str, page: usize) -> String {
+    format!("atlases/{}_{}.png", atlas, page)
+}
+
+/// Name of the layout in the atlases folder.
+pub fn layout_name(atlas: &str) -> String {
+    format!("{}.layout.json", atlas)
+}
+
+/// One row of sprites in a page.
+struct Shelf {
+    y: u32,
+    h: u32,
+    /// Where the next sprite goes.
+    x: u32,
+}
+
+#[derive(Default)]
+struct PageShelves {
+    shelves: Vec<Shelf>,
+    w: u32,
+    h: u32,
+}
+
+/// Put images in pages. Images are placed in rows, from the tallest to the smallest.
+pub struct AtlasBuilder {
+    max_size: u32,
+    padding: u32,
+    images: Vec<(String, RgbaImage)>,
+}
+
+impl AtlasBuilder {
+    pub fn new(max_size: u32, padding: u32) -> Self {
+        Self {
+            max_size,
+            padding,
+            images: vec![],
+        }
+    }
+
+    /// The image should not be flipped, i.e. its first row is the top of the sprite.
+    pub fn add(&mut self, name: String, image: RgbaImage) {
+        self.images.push((name, image));
+    }
+
+    /// Read the sprites of the spec.
+    pub fn from_spec(spec: &AtlasSpec, sprites: &AssetSource) -> Result<Self, AssetError> {
+        let mut builder = Self::new(spec.max_size, spec.padding);
+        for name in spec.sprite_names(sprites) {
+            let content = sprites.read(&name).map_err(|e| {
+                error!("Cannot read {} for the atlas = {}", name, e);
+                e
+            })?;
+            builder.add(name, image::load_from_memory(&content)?.to_rgba8());
+        }
+        Ok(builder)
+    }
+
+    /// Pack the images. The pages are named with `page_name`.
+    pub fn build(&self, atlas: &str) -> (AtlasLayout, Vec<RgbaImage>) {
+        // same layout every time.
+        let mut order: Vec<usize> = (0..self.images.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (na, a) = &self.images[a];
+            let (nb, b) = &self.images[b];
+            b.height()
+                .cmp(&a.height())
+                .then(b.width().cmp(&a.width()))
+                .then(na.cmp(nb))
+        });
+
+        let mut pages: Vec<PageShelves> = vec![];
+        let mut regions = BTreeMap::new();
+        for idx in order {
+            let (name, image) = &self.images[idx];
+            let (page, x, y) = self.place(&mut pages, image.width(), image.height());
+            regions.insert(
+                name.clone(),
+                AtlasRegion {
+                    page,
+                    x,
+                    y,
+                    w: image.width(),
+                    h: image.height(),
+                },
+            );
+        }
+
+        let mut images: Vec<RgbaImage> = pages
+            .iter()
+            .map(|p| RgbaImage::new(p.w.max(1), p.h.max(1)))
+            .collect();
+        for (name, image) in &self.images {
+            let region = &regions[name];
+            image::imageops::replace(&mut images[region.page], image, region.x, region.y);
+        }
+
+        let layout = AtlasLayout {
+            pages: images
+                .iter()
+                .enumerate()
+                .map(|(i, img)| AtlasPage {
+                    sprite: page_name(atlas, i),
+                    w: img.width(),
+                    h: img.height(),
+                })
+                .collect(),
+            regions,
+        };
+        (layout, images)
+    }
+
+    /// Find a place for a sprite. Returns the page and the position.
+    fn place(&self, pages: &mut Vec<PageShelves>, w: u32, h: u32) -> (usize, u32, u32) {
+        let (pw, ph) = (w + self.padding, h + self.padding);
+        if pw > self.max_size || ph > self.max_size {
+            // too big, it gets its own page.
+            pages.push(PageShelves {
+                shelves: vec![],
+                w,
+                h,
+            });
+            return (pages.len() - 1, 0, 0);
+        }
+
+        for (i, page) in pages.iter_mut().enumerate() {
+            let position = Self::place_in_page(page, self.max_size, pw, ph);
+            if let Some((x, y)) = position {
+                page.w = page.w.max(x + w);
+                page.h = page.h.max(y + h);
+                return (i, x, y);
+            }
+        }
+
+        let mut page = PageShelves::default();
+        let (x, y) = Self::place_in_page(&mut page, self.max_size, pw, ph).unwrap();
+        page.w = w;
+        page.h = h;
+        pages.push(page);
+        (pages.len() - 1, x, y)
+    }
+
+    fn place_in_page(page: &mut PageShelves, max_size: u32, w: u32, h: u32) -> Option<(u32, u32)> {
+        // a page that holds a sprite that was too big has no shelves and no room left.
+        if page.shelves.is_empty() && page.w > 0 {
+            return None;
+        }
+
+        if let Some(shelf) = page
+            .shelves
+            .iter_mut()
+            .find(|shelf| shelf.h >= h && shelf.x + w <= max_size)
+        {
+            let x = shelf.x;
+            shelf.x += w;
+            return Some((x, shelf.y));
+        }
+
+        let y = page.shelves.last().map(|s| s.y + s.h).unwrap_or(0);
+        if y + h > max_size {
+            return None;
+        }
+        page.shelves.push(Shelf { y, h, x: w });
+        Some((0, y))
+    }
+}
+
+/// An atlas as loaded by the workers: its layout, and its pages when it was built at runtime.
+#[derive(Default)]
+pub struct AtlasBuild {
+    pub layout: AtlasLayout,
+    /// Flipped and ready to upload. Empty when the pages were built by `pack_assets`, as they are
+    /// loaded like the other sprites.
+    pub pages: Vec<RgbaImage>,
+    pub sampler: Sampler,
+}
+
+pub type AtlasManager<S> = AssetManager<S, AtlasBuild>;
+
+/// Read the layout of an atlas that was built by `pack_assets`, or build the atlas from its spec.
+pub fn load_atlas(source: &AssetSource, atlas: &str) -> Result<AtlasBuild, AssetError> {
+    let atlases = source.join("atlases");
+    let layout_name = layout_name(atlas);
+    if atlases.exists(&layout_name) {
+        let layout: AtlasLayout = serde_json::from_str(&atlases.read_to_string(&layout_name)?)?;
+        return Ok(AtlasBuild {
+            layout,
+            ..AtlasBuild::default()
+        });
+    }
+
+    let spec: AtlasSpec =
+        serde_json::from_str(&atlases.read_to_string(&format!("{}.json", atlas))?)?;
+    let (layout, pages) = AtlasBuilder::from_spec(&spec, &source.join("sprites"))?.build(atlas);
+    info!(
+        "Built atlas {} with {} sprites in {} pages",
+        atlas,
+        layout.regions.len(),
+        pages.len()
+    );
+    Ok(AtlasBuild {
+        layout,
+        pages: pages.iter().map(image::imageops::flip_vertical).collect(),
+        sampler: spec.sampler().to_sampler(),
+    })
+}
+
+/// Decode and pack the sprites of an atlas. This is done by the workers as there can be a lot of
+/// sprites.
+pub struct AtlasLoader {
+    source: AssetSource,
+}
+
+impl AtlasLoader {
+    /// The source is the asset folder, as atlases need both `atlases` and `sprites`.
+    pub fn new(source: AssetSource) -> Self {
+        Self { source }
+    }
+}
+
+impl<S> Loader<S, AtlasBuild, String> for AtlasLoader
+where
+    S: GraphicsContext<Backend = GL33>,
+{
+    fn load_job(&mut self, asset_name: String) -> LoadJob<AtlasBuild> {
+        let source = self.source.clone();
+        Box::new(move || {
+            info!("Will load atlas {}", asset_name);
+            let atlas = load_atlas(&source, &asset_name).map_err(|e| {
+                error!("Cannot load atlas {} = {}", asset_name, e);
+                e
+            })?;
+            let finish: Finish<AtlasBuild> = Box::new(move || Ok(atlas));
+            Ok(finish)
+        })
+    }
+}
+
+/// Atlases that are loaded, to find the page and the texture coordinates of a sprite.
+#[derive(Default)]
+pub struct Atlases {
+    /// Sprite -> page and part of the page.
+    regions: HashMap<String, (String, UvRect)>,
+    atlases: HashMap<String, AtlasLayout>,
+    /// Pages built at runtime. They cannot be loaded again from a file so they stay in the sprite
+    /// manager as long as their atlas is there.
+    built_pages: HashMap<String, Vec<StrongHandle>>,
+    /// Atlases that are loading, see `update`.
+    waiting: Vec<String>,
+}
+
+impl Atlases {
+    /// Page and texture coordinates of a sprite that is in an atlas.
+    pub fn resolve(&self, sprite: &str) -> Option<(&str, UvRect)> {
+        self.regions
+            .get(sprite)
+            .map(|(page, uv)| (page.as_str(), *uv))
+    }
+
+    /// Name of the texture that contains the sprite in the sprite manager.
+    pub fn texture_of<'a>(&'a self, sprite: &'a str) -> &'a str {
+        self.resolve(sprite).map(|(page, _)| page).unwrap_or(sprite)
+    }
+
+    pub fn is_loaded(&self, atlas: &str) -> bool {
+        self.atlases.contains_key(atlas)
+    }
+
+    /// True between `load` and the `update` that adds the atlas.
+    pub fn is_loading(&self, atlas: &str) -> bool {
+        self.waiting.iter().any(|a| a == atlas)
+    }
+
+    /// Atlas that contains the sprite.
+    pub fn atlas_of(&self, sprite: &str) -> Option<&str> {
+        self.atlases
+            .iter()
+            .find(|(_, layout)| layout.regions.contains_key(sprite))
+            .map(|(atlas, _)| atlas.as_str())
+    }
+
+    /// Names of the pages of an atlas in the sprite manager.
+    pub fn pages(&self, atlas: &str) -> Vec<String> {
+        self.atlases
+            .get(atlas)
+            .map(|layout| layout.pages.iter().map(|p| p.sprite.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Use the regions of a layout. They replace the ones of the previous version of the atlas.
+    pub fn add(&mut self, atlas: &str, layout: &AtlasLayout) {
+        self.remove_regions(atlas);
+        for (sprite, region) in &layout.regions {
+            let page = layout.pages[region.page].sprite.clone();
+            self.regions
+                .insert(sprite.clone(), (page, layout.uv(region)));
+        }
+        self.atlases.insert(atlas.to_string(), layout.clone());
+    }
+
+    /// The sprites of the atlas will be loaded from their own file again.
+    pub fn remove(&mut self, atlas: &str) {
+        self.remove_regions(atlas);
+        self.built_pages.remove(atlas);
+    }
+
+    fn remove_regions(&mut self, atlas: &str) {
+        if let Some(layout) = self.atlases.remove(atlas) {
+            for sprite in layout.regions.keys() {
+                self.regions.remove(sprite);
+            }
+        }
+    }
+
+    /// Start loading an atlas on the workers, or loading it again if it is already there. The
+    /// previous version is used until `update` gets the new one.
+    pub fn load<S>(&mut self, atlas: &str, atlas_manager: &mut AtlasManager<S>) -> Handle
+    where
+        S: GraphicsContext<Backend = GL33> + 'static,
+    {
+        if !self.is_loading(atlas) {
+            self.waiting.push(atlas.to_string());
+        }
+        if self.is_loaded(atlas) {
+            atlas_manager.reload(atlas.to_string())
+        } else {
+            atlas_manager.load(atlas.to_string())
+        }
+    }
+
+    /// Add the atlases that have finished loading and give their pages to the sprite manager.
+    /// If an atlas cannot be loaded, the previous version is kept.
+    pub fn update<S>(
+        &mut self,
+        atlas_manager: &AtlasManager<S>,
+        sprite_manager: &mut AssetManager<S, SpriteAsset<S>>,
+    ) where
+        S: GraphicsContext<Backend = GL33> + 'static,
+    {
+        if self.waiting.is_empty() {
+            return;
+        }
+
+        for atlas in std::mem::take(&mut self.waiting) {
+            let handle = Handle(atlas.clone());
+            if atlas_manager.is_pending(&handle) {
+                self.waiting.push(atlas);
+                continue;
+            }
+
+            let build = atlas_manager.get(&handle).and_then(|asset| {
+                // the pages are not needed there once they are in the sprite manager.
+                asset.execute_mut(|build| {
+                    let pages = std::mem::take(&mut build.pages);
+                    (build.layout.clone(), pages, build.sampler)
+                })
+            });
+            let (layout, pages, sampler) = match build {
+                Some(build) => build,
+                None => {
+                    // the error is logged by the loader, and a cancelled load is just dropped.
+                    continue;
+                }
+            };
+
+            if pages.is_empty() {
+                // built by `pack_assets` so the pages are in the sprites folder, or the previous
+                // version that is already in the sprite manager.
+                for page in &layout.pages {
+                    sprite_manager.load(page.sprite.clone());
+                }
+            } else {
+                let handles = layout
+                    .pages
+                    .iter()
+                    .zip(pages)
+                    .filter_map(|(page, image)| {
+                        let handle = sprite_manager.insert(
+                            page.sprite.clone(),
+                            SpriteAsset::Loading(page.w, page.h, image.into_raw(), sampler),
+                        );
+                        sprite_manager.acquire(&handle)
+                    })
+                    .collect();
+                self.built_pages.insert(atlas.clone(), handles);
+            }
+            self.add(&atlas, &layout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::pack::{Compression, Pack, PackWriter};
+    use crate::assets::sprite::SpriteSyncLoader;
+    use crate::assets::Budget;
+    use image::Rgba;
+    use luminance_glfw::GlfwSurface;
+    use std::sync::Arc;
+
+    fn square(size: u32, color: u8) -> RgbaImage {
+        RgbaImage::from_pixel(size, size, Rgba([color, color, color, 255]))
+    }
+
+    fn overlaps(a: &AtlasRegion, b: &AtlasRegion, padding: u32) -> bool {
+        a.page == b.page
+            && a.x < b.x + b.w + padding
+            && b.x < a.x + a.w + padding
+            && a.y < b.y + b.h + padding
+            && b.y < a.y + a.h + padding
+    }
+
+    #[test]
+    fn small_sprites_share_a_page() {
+        let mut builder = AtlasBuilder::new(64, 2);
+        for i in 0..4 {
+            builder.add(format!("{}.png", i), square(10 + i, 50 * i as u8));
+        }
+        let (layout, pages) = builder.build("test");
+
+        assert_eq!(1, pages.len());
+        assert_eq!(page_name("test", 0), layout.pages[0].sprite);
+        let regions: Vec<_> = layout.regions.values().collect();
+        for (i, a) in regions.iter().enumerate() {
+            for b in &regions[i + 1..] {
+                assert!(!overlaps(a, b, 2), "{:?} and {:?} overlap", a, b);
+            }
+        }
+        // the pixels are copied at their region.
+        for i in 0..4u32 {
+            let region = &layout.regions[&format!("{}.png", i)];
+            assert_eq!(10 + i, region.w);
+            let pixel = pages[0].get_pixel(region.x + region.w - 1, region.y + region.h - 1);
+            assert_eq!(50 * i as u8, pixel[0]);
+        }
+    }
+
+    #[test]
+    fn full_page_starts_a_new_one() {
+        let mut builder = AtlasBuilder::new(32, 0);
+        for i in 0..5 {
+            builder.add(format!("{}.png", i), square(16, 0));
+        }
+        let (layout, pages) = builder.build("test");
+
+        assert_eq!(2, pages.len());
+        assert_eq!((32, 32), pages[0].dimensions());
+        assert_eq!((16, 16), pages[1].dimensions());
+        assert_eq!(4, layout.regions.values().filter(|r| r.page == 0).count());
+    }
+
+    #[test]
+    fn big_sprite_gets_its_own_page() {
+        let mut builder = AtlasBuilder::new(64, 2);
+        builder.add("big.png".to_string(), square(100, 0));
+        builder.add("small.png".to_string(), square(8, 0));
+        let (layout, pages) = builder.build("test");
+
+        assert_eq!(2, pages.len());
+        let big = &layout.regions["big.png"];
+        assert_eq!((0, 0), (big.x, big.y));
+        assert_eq!((100, 100), pages[big.page].dimensions());
+        assert_ne!(big.page, layout.regions["small.png"].page);
+    }
+
+    #[test]
+    fn layout_does_not_depend_on_the_order() {
+        let images = vec![("a.png", 12), ("b.png", 20), ("c.png", 12), ("d.png", 5)];
+        let build = |images: &[(&str, u32)]| {
+            let mut builder = AtlasBuilder::new(32, 1);
+            for (name, size) in images {
+                builder.add(name.to_string(), square(*size, 0));
+            }
+            serde_json::to_string(&builder.build("test").0).unwrap()
+        };
+
+        let mut reversed = images.clone();
+        reversed.reverse();
+        assert_eq!(build(&images), build(&reversed));
+    }
+
+    #[test]
+    fn uv_starts_at_the_bottom() {
+        let layout = AtlasLayout {
+            pages: vec![AtlasPage {
+                sprite: page_name("test", 0),
+                w: 64,
+                h: 32,
+            }],
+            regions: BTreeMap::new(),
+        };
+        let region = AtlasRegion {
+            page: 0,
+            x: 16,
+            y: 0,
+            w: 16,
+            h: 8,
+        };
+        let uv = layout.uv(&region);
+        assert_eq!([0.25, 0.75], uv.offset);
+        assert_eq!([0.25, 0.25], uv.size);
+    }
+
+    /// An atlas spec and its sprites in a pack, so that nothing is read from the asset folder.
+    fn source() -> AssetSource {
+        let mut writer = PackWriter::new();
+        writer.add(
+            "atlases/test.json",
+            br#"{ "sprites": ["a.png", "b.png"], "padding": 0 }"#,
+            Compression::None,
+        );
+        for (name, color) in &[("sprites/a.png", 0), ("sprites/b.png", 255)] {
+            let mut png = vec![];
+            image::DynamicImage::ImageRgba8(square(4, *color))
+                .write_to(&mut png, image::ImageOutputFormat::Png)
+                .unwrap();
+            writer.add(name, &png, Compression::None);
+        }
+        let mut bytes = vec![];
+        writer.write(&mut bytes).unwrap();
+        let pack = Pack::from_static(Box::leak(bytes.into_boxed_slice())).unwrap();
+        AssetSource::with_pack(Arc::new(pack), "does_not_exist")
+    }
+
+    #[test]
+    fn built_pages_stay_loaded() {
+        let source = source();
+        let mut atlas_manager: AtlasManager<GlfwSurface> =
+            AssetManager::from_loader(Box::new(AtlasLoader::new(source.clone())));
+        let mut sprite_manager: AssetManager<GlfwSurface, SpriteAsset<GlfwSurface>> =
+            AssetManager::from_loader(Box::new(SpriteSyncLoader::new(source.join("sprites"))));
+        sprite_manager.set_budget(Some(Budget::assets(0)));
+        let mut atlases = Atlases::default();
+
+        atlases.load("test", &mut atlas_manager);
+        assert!(atlases.is_loading("test"));
+        atlas_manager.skip_upload();
+        atlases.update(&atlas_manager, &mut sprite_manager);
+
+        assert!(atlases.is_loaded("test"));
+        let (page, uv) = atlases.resolve("b.png").unwrap();
+        assert_eq!(page_name("test", 0), page);
+        assert_eq!([0.5, 1.0], uv.size);
+
+        // over budget and not used, but the page cannot be loaded again.
+        for _ in 0..3 {
+            sprite_manager.skip_upload();
+        }
+        assert_eq!(0, sprite_manager.unload_unused());
+        assert!(sprite_manager.is_loaded(&Handle(page_name("test", 0))));
+
+        atlases.remove("test");
+        sprite_manager.skip_upload();
+        assert!(!sprite_manager.is_loaded(&Handle(page_name("test", 0))));
+    }
+}
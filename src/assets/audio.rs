@@ -1,9 +1,7 @@
-use crate::assets::{Asset, Loader};
+use crate::assets::source::AssetSource;
+use crate::assets::{Finish, LoadJob, Loader};
 use luminance::context::GraphicsContext;
 use luminance_gl::GL33;
-use std::fs::File;
-use std::io::Read;
-use std::path::{Path, PathBuf};
 
 pub enum Audio {
     Empty,
@@ -17,15 +15,12 @@ impl Default for Audio {
 }
 
 pub struct AudioSyncLoader {
-    base_path: PathBuf,
+    source: AssetSource,
 }
 
 impl AudioSyncLoader {
-    pub fn new<P: AsRef<Path>>(base_path: P) -> Self {
-        let base_path = base_path.as_ref();
-        Self {
-            base_path: base_path.to_path_buf(),
-        }
+    pub fn new(source: AssetSource) -> Self {
+        Self { source }
     }
 }
 
@@ -33,31 +28,25 @@ impl<S> Loader<S, Audio, String> for AudioSyncLoader
 where
     S: GraphicsContext<Backend = GL33>,
 {
-    fn load(&mut self, asset_name: String) -> Asset<Audio> {
-        let mut asset = Asset::new();
-        let path = self.base_path.join(asset_name);
-        info!("Will load audio at {:?}", path);
-
-        match File::open(path) {
-            Ok(mut file) => {
-                let mut content = vec![];
-                match file.read_to_end(&mut content) {
-                    Ok(_) => {
-                        info!("Finished loading");
-                        asset.set_loaded(Audio::File(content))
-                    }
-                    Err(e) => {
-                        error!("Error while loading file");
-                        asset.set_error(e.into())
-                    }
-                }
-            }
-            Err(e) => {
+    fn load_job(&mut self, asset_name: String) -> LoadJob<Audio> {
+        let source = self.source.clone();
+        Box::new(move || {
+            info!("Will load audio at {:?}", source.path(&asset_name));
+            let content = source.read(&asset_name).map_err(|e| {
                 error!("Error while loading file");
-                asset.set_error(e.into())
-            }
-        }
+                e
+            })?;
 
-        asset
+            info!("Finished loading");
+            let finish: Finish<Audio> = Box::new(move || Ok(Audio::File(content)));
+            Ok(finish)
+        })
+    }
+
+    fn asset_size(&self, inner: &Audio) -> usize {
+        match inner {
+            Audio::Empty => 0,
+            Audio::File(content) => content.len(),
+        }
     }
 }
@@ -0,0 +1,381 @@
+//! Good for development. Will listen to the asset folder and reload the files that changed.
+//!
+//! A changed file is mapped back to the asset manager that owns it so only that asset is reloaded:
+//! - `shaders/*`: the shader programs that use the file,
+//! - `sprites/*`: the sprite, or the sprite that uses the metadata file. If the sprite is in an
+//!   atlas, the atlas is built again,
+//! - `atlases/*.json`: the atlas is built again,
+//! - `sheets/*.json`: the sprite sheet. Entities animated with it get its new animations,
+//! - `prefab/*.json`: the prefab. Entities spawned from it are patched (see `Prefab::patch`),
+//! - `particle/*.json`: the emitter. The running `ParticleEffect`s get the new version,
+//! - `stages/*.json`: the stage description. The main scene restarts the stage when it is loaded,
+//! - config files: the resource is replaced (see `HotReloader::watch_config`),
+//! - anything else: the audio with that path.
+//!
+//! An `AssetReloaded` event is also sent for every changed file, so that the systems that use it
+//! can update (e.g. the main scene restarts the current stage).
+//!
+//! If the new version of an asset cannot be loaded, the error is logged and the previous version
+//! is kept.
+use crate::assets::atlas::{AtlasManager, Atlases};
+use crate::assets::audio::Audio;
+use crate::assets::particle::ParticleManager;
+use crate::assets::prefab::{PrefabInstance, PrefabManager};
+use crate::assets::shader::ShaderManager;
+use crate::assets::sheet::SpriteSheets;
+use crate::assets::sprite::SpriteAsset;
+use crate::assets::stage::StageManager;
+use crate::assets::{AssetManager, Handle};
+use crate::config::{load_config, AudioConfig, PlayerConfig};
+use crate::core::animation::{AnimationController, AnimationSheet};
+use crate::event::AssetReloaded;
+use crate::gameplay::level::difficulty::DifficultyConfig;
+use crate::paths::get_assets_path;
+use crate::render::particle::{ParticleEffect, ParticleEmitter};
+use crate::resources::Resources;
+use hecs::World;
+use luminance::context::GraphicsContext;
+use luminance_gl::GL33;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::de::DeserializeOwned;
+use std::any::Any;
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+
+/// Load a config file again and replace its resource.
+type ConfigReloader = Box<dyn Fn(&Resources, &Path)>;
+
+pub struct HotReloader<S>
+where
+    S: GraphicsContext<Backend = GL33>,
+{
+    base_path: PathBuf,
+    rx: Receiver<Result<notify::Event, notify::Error>>,
+    _watcher: RecommendedWatcher,
+
+    /// Config files (relative to the asset folder) and how to reload them.
+    configs: Vec<(PathBuf, ConfigReloader)>,
+
+    /// Prefabs that are reloading. Their instances are patched when the new version is ready.
+    prefabs_to_patch: Vec<Handle>,
+    /// If false, the entities already spawned keep the previous version of their prefab.
+    pub patch_instances: bool,
+    /// Particles that are reloading. The running effects get the new version when it is ready.
+    particles_to_refresh: Vec<Handle>,
+    /// Sprite sheets that were reloaded. The controllers of their entities are created again.
+    sheets_to_refresh: Vec<String>,
+
+    _phantom: PhantomData<S>,
+}
+
+impl<S> Default for HotReloader<S>
+where
+    S: GraphicsContext<Backend = GL33> + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> HotReloader<S>
+where
+    S: GraphicsContext<Backend = GL33> + 'static,
+{
+    pub fn new() -> Self {
+        let base_path = get_assets_path();
+        // notify gives absolute paths.
+        let base_path = base_path.canonicalize().unwrap_or(base_path);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        // Add a path to be watched. All files and directories at that path and
+        // below will be monitored for changes.
+
+        // Automatically select the best implementation for your platform.
+        // You can also access each implementation directly e.g. INotifyWatcher.
+        let mut watcher: RecommendedWatcher =
+            Watcher::new_immediate(move |res| tx.send(res).unwrap()).unwrap();
+
+        watcher
+            .watch(base_path.clone(), RecursiveMode::Recursive)
+            .unwrap();
+
+        let mut reloader = Self {
+            base_path,
+            rx,
+            _watcher: watcher,
+            configs: vec![],
+            prefabs_to_patch: vec![],
+            patch_instances: true,
+            particles_to_refresh: vec![],
+            sheets_to_refresh: vec![],
+            _phantom: PhantomData,
+        };
+        reloader.watch_config::<PlayerConfig, _>("config/player_controller.json");
+        reloader.watch_config::<DifficultyConfig, _>("config/difficulty.json");
+        reloader.watch_config::<AudioConfig, _>("config/audio.json");
+        reloader
+    }
+
+    /// Replace the resource `T` when the config file changes. The path is relative to the asset
+    /// folder.
+    pub fn watch_config<T, P>(&mut self, path: P)
+    where
+        T: DeserializeOwned + Any,
+        P: AsRef<Path>,
+    {
+        let reload: ConfigReloader = Box::new(|resources, path| match load_config::<T, _>(path) {
+            Ok(config) => match resources.fetch_mut::<T>() {
+                Some(mut current) => {
+                    *current = config;
+                    info!("Reloaded {}", path.display());
+                }
+                None => debug!("No {} to replace", std::any::type_name::<T>()),
+            },
+            Err(e) => error!(
+                "Cannot reload {}, will keep the previous version = {}",
+                path.display(),
+                e
+            ),
+        });
+        self.configs.push((path.as_ref().to_path_buf(), reload));
+    }
+
+    /// Will check if there are files that have changed and will reload the corresponding assets.
+    pub fn update(&mut self, world: &mut World, resources: &Resources) {
+        let changed = self.changed_files();
+        for path in &changed {
+            self.reload(resources, path);
+        }
+        if !changed.is_empty() {
+            resources.write_events(changed.into_iter().map(AssetReloaded).collect());
+        }
+
+        self.patch_prefab_instances(world, resources);
+        self.refresh_sheet_instances(world);
+        self.refresh_particle_effects(world, resources);
+    }
+
+    /// Files that changed since the last update, relative to the asset folder.
+    fn changed_files(&self) -> Vec<PathBuf> {
+        // an editor usually sends several events when saving a file.
+        let mut changed = BTreeSet::new();
+        for res in self.rx.try_iter() {
+            match res {
+                Ok(Event {
+                    kind: EventKind::Modify(..) | EventKind::Create(..),
+                    paths,
+                    ..
+                }) => {
+                    for path in paths {
+                        let path = path.canonicalize().unwrap_or(path);
+                        if let Ok(relative) = path.strip_prefix(&self.base_path) {
+                            changed.insert(relative.to_path_buf());
+                        }
+                    }
+                }
+                Ok(_) => (),
+                Err(e) => error!("Error while watching the asset folder = {:?}", e),
+            }
+        }
+        changed.into_iter().collect()
+    }
+
+    fn reload(&mut self, resources: &Resources, path: &Path) {
+        debug!("Should reload {:?}", path);
+        if let Some((_, reload)) = self.configs.iter().find(|(p, _)| p == path) {
+            reload(resources, &self.base_path.join(path));
+            return;
+        }
+
+        let mut components = path.components();
+        let folder = components.next().and_then(|c| c.as_os_str().to_str());
+        let file = components.as_path();
+        match folder {
+            Some("shaders") => {
+                let mut shader_manager = resources.fetch_mut::<ShaderManager<S>>().unwrap();
+                reload_matching(&mut shader_manager, |(vs, fs)| {
+                    Path::new(vs) == file || Path::new(fs) == file
+                });
+            }
+            Some("sprites") => {
+                let mut sprite_manager = resources
+                    .fetch_mut::<AssetManager<S, SpriteAsset<S>>>()
+                    .unwrap();
+                reload_matching(&mut sprite_manager, |name| {
+                    let name = Path::new(name);
+                    name == file || name.with_extension("json") == file
+                });
+
+                let mut atlases = resources.fetch_mut::<Atlases>().unwrap();
+                let sprite = file.to_string_lossy().replace('\\', "/");
+                if let Some(atlas) = atlases.atlas_of(&sprite).map(|a| a.to_string()) {
+                    info!("Will reload atlas {}", atlas);
+                    atlases.load(
+                        &atlas,
+                        &mut resources.fetch_mut::<AtlasManager<S>>().unwrap(),
+                    );
+                }
+            }
+            Some("atlases") => {
+                let mut atlases = resources.fetch_mut::<Atlases>().unwrap();
+                let atlas = file.with_extension("").to_string_lossy().into_owned();
+                if atlases.is_loaded(&atlas) {
+                    info!("Will reload atlas {}", atlas);
+                    atlases.load(
+                        &atlas,
+                        &mut resources.fetch_mut::<AtlasManager<S>>().unwrap(),
+                    );
+                }
+            }
+            Some("sheets") => {
+                let mut sheets = resources.fetch_mut::<SpriteSheets>().unwrap();
+                let sheet = file.to_string_lossy().replace('\\', "/");
+                if sheets.is_loaded(&sheet) {
+                    info!("Will reload sprite sheet {}", sheet);
+                    let mut atlases = resources.fetch_mut::<Atlases>().unwrap();
+                    match sheets.reload(&sheet, &mut atlases) {
+                        Ok(()) => {
+                            // the image can be another one.
+                            let image = sheets.get(&sheet).unwrap().image().to_string();
+                            resources
+                                .fetch_mut::<AssetManager<S, SpriteAsset<S>>>()
+                                .unwrap()
+                                .load(image);
+                            self.sheets_to_refresh.push(sheet);
+                        }
+                        Err(e) => error!(
+                            "Cannot reload sprite sheet {}, will keep the previous version = {}",
+                            sheet, e
+                        ),
+                    }
+                }
+            }
+            Some("particle") => {
+                let mut particle_manager = resources.fetch_mut::<ParticleManager<S>>().unwrap();
+                let reloaded = reload_matching(&mut particle_manager, |n| Path::new(n) == file);
+                self.particles_to_refresh.extend(reloaded);
+            }
+            Some("stages") => {
+                let mut stage_manager = resources.fetch_mut::<StageManager<S>>().unwrap();
+                reload_matching(&mut stage_manager, |n| Path::new(n) == file);
+            }
+            Some("prefab") => {
+                let mut prefab_manager = resources.fetch_mut::<PrefabManager<S>>().unwrap();
+                let name = file.with_extension("");
+                let reloaded = reload_matching(&mut prefab_manager, |n| Path::new(n) == name);
+                self.prefabs_to_patch.extend(reloaded);
+            }
+            _ => {
+                let mut audio_manager = resources.fetch_mut::<AssetManager<S, Audio>>().unwrap();
+                reload_matching(&mut audio_manager, |name| Path::new(name) == path);
+            }
+        }
+    }
+
+    /// Patch the entities spawned from the prefabs that have finished reloading.
+    fn patch_prefab_instances(&mut self, world: &mut World, resources: &Resources) {
+        if self.prefabs_to_patch.is_empty() {
+            return;
+        }
+
+        let prefab_manager = resources.fetch::<PrefabManager<S>>().unwrap();
+        let patch_instances = self.patch_instances;
+        self.prefabs_to_patch.retain(|handle| {
+            let is_ready = prefab_manager.is_loaded(handle) || prefab_manager.is_error(handle);
+            if prefab_manager.is_pending(handle) || !is_ready {
+                return true;
+            }
+
+            if patch_instances {
+                let instances: Vec<_> = world
+                    .query::<&PrefabInstance>()
+                    .iter()
+                    .filter(|(_, instance)| instance.0 == handle.0)
+                    .map(|(e, _)| e)
+                    .collect();
+                if let Some(asset) = prefab_manager.get(handle) {
+                    asset.execute(|prefab| {
+                        for &e in &instances {
+                            prefab.patch(world, e);
+                        }
+                    });
+                }
+                info!("Patched {} instances of {}", instances.len(), handle.0);
+            }
+            false
+        });
+    }
+
+    /// Give the new definition to the effects of the particles that have finished reloading.
+    fn refresh_particle_effects(&mut self, world: &mut World, resources: &Resources) {
+        if self.particles_to_refresh.is_empty() {
+            return;
+        }
+
+        let particle_manager = resources.fetch::<ParticleManager<S>>().unwrap();
+        self.particles_to_refresh.retain(|handle| {
+            let is_ready = particle_manager.is_loaded(handle) || particle_manager.is_error(handle);
+            if particle_manager.is_pending(handle) || !is_ready {
+                return true;
+            }
+
+            let definition = particle_manager
+                .get(handle)
+                .and_then(|asset| asset.execute(|definition| definition.clone()));
+            if let Some(definition) = definition {
+                let mut nb_refreshed = 0;
+                for (_, (effect, emitter)) in world
+                    .query::<(&ParticleEffect, &mut ParticleEmitter)>()
+                    .iter()
+                {
+                    if effect.handle == *handle {
+                        emitter.set_definition(&definition);
+                        nb_refreshed += 1;
+                    }
+                }
+                info!("Refreshed {} effects of {}", nb_refreshed, handle.0);
+            }
+            false
+        });
+    }
+
+    /// Remove the controllers made from the reloaded sheets. The animation system will add the new
+    /// ones.
+    fn refresh_sheet_instances(&mut self, world: &mut World) {
+        if self.sheets_to_refresh.is_empty() {
+            return;
+        }
+
+        let entities: Vec<_> = world
+            .query::<&AnimationSheet>()
+            .iter()
+            .filter(|(_, sheet)| self.sheets_to_refresh.contains(&sheet.0))
+            .map(|(e, _)| e)
+            .collect();
+        for e in entities {
+            let _ = world.remove_one::<AnimationController>(e);
+        }
+        self.sheets_to_refresh.clear();
+    }
+}
+
+/// Reload the assets whose name matches. Returns their handles.
+fn reload_matching<S, T, H, F>(manager: &mut AssetManager<S, T, H>, matches: F) -> Vec<Handle<H>>
+where
+    S: GraphicsContext<Backend = GL33>,
+    T: Default + 'static,
+    H: Clone + Eq + PartialEq + Hash + Send + Debug + 'static,
+    F: Fn(&H) -> bool,
+{
+    let handles: Vec<_> = manager.keys().filter(|h| matches(&h.0)).cloned().collect();
+    for handle in &handles {
+        info!("Will reload {:?}", handle);
+        manager.reload(handle.0.clone());
+    }
+    handles
+}
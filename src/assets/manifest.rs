@@ -0,0 +1,571 @@
+//! List of the assets a scene or a stage needs, so that they can be loaded before it starts.
+//!
+//! Manifests are JSON files in `assets/manifests`. All the fields are optional:
+//!
+//! ```json
+//! {
+//!     "sprites": ["bullet.png"],
+//!     "prefabs": ["player"],
+//!     "sounds": ["sounds/explosion.wav"],
+//!     "shaders": [["simple-vs.glsl", "simple-fs.glsl"]],
+//!     "particles": ["explosion.json"],
+//!     "atlases": ["explosions"],
+//!     "sheets": ["explosion.json"],
+//!     "stages": ["stage1.json"]
+//! }
+//! ```
+//!
+//! Prefabs bring their own dependencies (see `Prefab::dependencies`), stages bring the prefabs of
+//! their waves (see `StageDescription::manifest`) and particles bring their texture, so a manifest
+//! only needs to list the stages.
+use crate::assets::atlas::{AtlasManager, Atlases};
+use crate::assets::audio::Audio;
+use crate::assets::particle::ParticleManager;
+use crate::assets::prefab::PrefabManager;
+use crate::assets::shader::{ShaderHandle, ShaderManager};
+use crate::assets::sheet::SpriteSheets;
+use crate::assets::source::AssetSource;
+use crate::assets::sprite::SpriteAsset;
+use crate::assets::stage::StageManager;
+use crate::assets::worker::{Priority, Ticket};
+use crate::assets::{AssetError, AssetManager, Handle, StrongHandle};
+use crate::render::particle::{ParticleEmitter, ParticleShape};
+use crate::resources::Resources;
+use luminance::context::GraphicsContext;
+use luminance_gl::GL33;
+use serde_derive::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssetManifest {
+    /// Relative to the sprites folder.
+    pub sprites: Vec<String>,
+    /// Name of the prefab, without extension.
+    pub prefabs: Vec<String>,
+    /// Relative to the asset folder.
+    pub sounds: Vec<String>,
+    /// Vertex and fragment shaders, relative to the shaders folder.
+    pub shaders: Vec<ShaderHandle>,
+    /// Relative to the particle folder.
+    pub particles: Vec<String>,
+    /// Name of the atlases, see `assets::atlas`. Their sprites are not loaded from their own file.
+    pub atlases: Vec<String>,
+    /// Relative to the sheets folder, see `assets::sheet`. Their image is loaded with the sprites.
+    pub sheets: Vec<String>,
+    /// Relative to the stages folder.
+    pub stages: Vec<String>,
+}
+
+impl AssetManifest {
+    /// Load a manifest from the manifests folder.
+    pub fn from_file(name: &str) -> Result<Self, AssetError> {
+        let source = AssetSource::new().join("manifests");
+        let content = source.read_to_string(name).map_err(|e| {
+            error!(
+                "Cannot read manifest {} = {}",
+                source.path(name).display(),
+                e
+            );
+            e
+        })?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn with_prefabs<I: IntoIterator<Item = String>>(mut self, prefabs: I) -> Self {
+        for prefab in prefabs {
+            self.add_prefab(prefab);
+        }
+        self
+    }
+
+    /// Add everything from another manifest.
+    pub fn merge(&mut self, other: &AssetManifest) {
+        other
+            .sprites
+            .iter()
+            .for_each(|s| self.add_sprite(s.clone()));
+        other
+            .prefabs
+            .iter()
+            .for_each(|p| self.add_prefab(p.clone()));
+        other.sounds.iter().for_each(|s| self.add_sound(s.clone()));
+        other
+            .shaders
+            .iter()
+            .for_each(|s| self.add_shader(s.clone()));
+        other
+            .particles
+            .iter()
+            .for_each(|p| self.add_particle(p.clone()));
+        other.atlases.iter().for_each(|a| self.add_atlas(a.clone()));
+        other.sheets.iter().for_each(|s| self.add_sheet(s.clone()));
+        other.stages.iter().for_each(|s| self.add_stage(s.clone()));
+    }
+
+    pub fn merged(mut self, other: &AssetManifest) -> Self {
+        self.merge(other);
+        self
+    }
+
+    pub fn add_sprite(&mut self, sprite: String) {
+        push_unique(&mut self.sprites, sprite);
+    }
+
+    pub fn add_prefab(&mut self, prefab: String) {
+        push_unique(&mut self.prefabs, prefab);
+    }
+
+    pub fn add_sound(&mut self, sound: String) {
+        push_unique(&mut self.sounds, sound);
+    }
+
+    pub fn add_shader(&mut self, shader: ShaderHandle) {
+        push_unique(&mut self.shaders, shader);
+    }
+
+    pub fn add_particle(&mut self, particle: String) {
+        push_unique(&mut self.particles, particle);
+    }
+
+    pub fn add_atlas(&mut self, atlas: String) {
+        push_unique(&mut self.atlases, atlas);
+    }
+
+    pub fn add_sheet(&mut self, sheet: String) {
+        push_unique(&mut self.sheets, sheet);
+    }
+
+    pub fn add_stage(&mut self, stage: String) {
+        push_unique(&mut self.stages, stage);
+    }
+
+    /// Add the texture of a particle emitter.
+    pub fn add_emitter(&mut self, emitter: &ParticleEmitter) {
+        if let ParticleShape::Texture(ref id) = emitter.shape {
+            self.add_sprite(id.clone());
+        }
+    }
+
+    /// Add the dependencies of the prefabs that are loaded. Returns true if something new was
+    /// added, in which case it should be loaded too.
+    pub fn add_prefab_dependencies<S>(&mut self, prefab_manager: &PrefabManager<S>) -> bool
+    where
+        S: GraphicsContext<Backend = GL33>,
+    {
+        let before = self.clone();
+        for name in before.prefabs.iter() {
+            if let Some(asset) = prefab_manager.get(&Handle(name.clone())) {
+                asset.execute(|prefab| prefab.dependencies(self));
+            }
+        }
+        *self != before
+    }
+
+    /// Add the textures of the particles that are loaded. Returns true if something new was added,
+    /// in which case it should be loaded too.
+    pub fn add_particle_dependencies<S>(&mut self, particle_manager: &ParticleManager<S>) -> bool
+    where
+        S: GraphicsContext<Backend = GL33>,
+    {
+        let before = self.clone();
+        for name in before.particles.iter() {
+            if let Some(asset) = particle_manager.get(&Handle(name.clone())) {
+                asset.execute(|emitter| self.add_emitter(emitter));
+            }
+        }
+        *self != before
+    }
+
+    /// Add the pages of the atlases that are loaded. Returns true if something new was added, in
+    /// which case it should be loaded too.
+    pub fn add_atlas_dependencies(&mut self, atlases: &Atlases) -> bool {
+        let before = self.sprites.len();
+        for atlas in self.atlases.clone() {
+            atlases
+                .pages(&atlas)
+                .into_iter()
+                .for_each(|page| self.add_sprite(page));
+        }
+        self.sprites.len() != before
+    }
+
+    /// Add the dependencies of the stages, prefabs, particles and atlases that are loaded.
+    /// Returns true if something new was added, in which case it should be loaded too.
+    pub fn add_loaded_dependencies<S>(&mut self, resources: &Resources) -> bool
+    where
+        S: GraphicsContext<Backend = GL33> + 'static,
+    {
+        let stage_manager = resources.fetch::<StageManager<S>>().unwrap();
+        let prefab_manager = resources.fetch::<PrefabManager<S>>().unwrap();
+        let particle_manager = resources.fetch::<ParticleManager<S>>().unwrap();
+        let new_stage_dependencies = self.add_stage_dependencies(&*stage_manager);
+        let new_prefab_dependencies = self.add_prefab_dependencies(&*prefab_manager);
+        let new_particle_dependencies = self.add_particle_dependencies(&*particle_manager);
+        let new_atlas_dependencies = self.add_atlas_dependencies(&resources.fetch().unwrap());
+        new_stage_dependencies
+            || new_prefab_dependencies
+            || new_particle_dependencies
+            || new_atlas_dependencies
+    }
+
+    /// Add the prefabs of the stages that are loaded. Returns true if something new was added, in
+    /// which case it should be loaded too.
+    pub fn add_stage_dependencies<S>(&mut self, stage_manager: &StageManager<S>) -> bool
+    where
+        S: GraphicsContext<Backend = GL33>,
+    {
+        let before = self.clone();
+        for name in before.stages.iter() {
+            if let Some(asset) = stage_manager.get(&Handle(name.clone())) {
+                asset.execute(|stage| self.merge(&stage.manifest()));
+            }
+        }
+        *self != before
+    }
+
+    /// Start loading everything. The assets that are already there are not loaded again. The
+    /// handles keep the assets in their manager, so they are not unloaded while the scene that
+    /// holds them needs them.
+    pub fn load<S>(&self, resources: &Resources, priority: Priority) -> ManifestHandles
+    where
+        S: GraphicsContext<Backend = GL33> + 'static,
+    {
+        let mut handles = ManifestHandles::default();
+        self.load_into::<S>(resources, priority, &mut handles);
+        handles
+    }
+
+    /// Same as `load`, for the assets that are not in `handles` yet. Used when the manifest gets
+    /// new dependencies while it is loading.
+    pub fn load_into<S>(
+        &self,
+        resources: &Resources,
+        priority: Priority,
+        handles: &mut ManifestHandles,
+    ) where
+        S: GraphicsContext<Backend = GL33> + 'static,
+    {
+        let mut sprite_manager = resources
+            .fetch_mut::<AssetManager<S, SpriteAsset<S>>>()
+            .unwrap();
+        let mut prefab_manager = resources.fetch_mut::<PrefabManager<S>>().unwrap();
+        let mut audio_manager = resources.fetch_mut::<AssetManager<S, Audio>>().unwrap();
+        let mut shader_manager = resources.fetch_mut::<ShaderManager<S>>().unwrap();
+        let mut particle_manager = resources.fetch_mut::<ParticleManager<S>>().unwrap();
+        let mut stage_manager = resources.fetch_mut::<StageManager<S>>().unwrap();
+        let mut atlas_manager = resources.fetch_mut::<AtlasManager<S>>().unwrap();
+        let mut atlases = resources.fetch_mut::<Atlases>().unwrap();
+        let mut sheets = resources.fetch_mut::<SpriteSheets>().unwrap();
+
+        for atlas in &self.atlases {
+            let handle = Handle(atlas.clone());
+            if atlases.is_loaded(atlas) || contains(&handles.atlases, &handle) {
+                continue;
+            }
+            if !atlases.is_loading(atlas) {
+                atlases.load(atlas, &mut atlas_manager);
+                // this load can be cancelled, see `ManifestHandles::cancel`.
+                handles.started.extend(atlas_manager.ticket(&handle));
+            }
+            handles.atlases.extend(atlas_manager.acquire(&handle));
+        }
+        let mut sprites = vec![];
+        for sheet in &self.sheets {
+            match sheets.load(sheet, &mut atlases) {
+                Ok(sheet) => push_unique(&mut sprites, sheet.image().to_string()),
+                Err(e) => error!("Cannot load sprite sheet {} = {}", sheet, e),
+            }
+        }
+        // the sprites that are in an atlas are replaced by their page, so they wait for the
+        // atlases. `add_atlas_dependencies` adds the pages once they are built.
+        if self.atlases.iter().all(|atlas| atlases.is_loaded(atlas)) {
+            for sprite in &self.sprites {
+                push_unique(&mut sprites, atlases.texture_of(sprite).to_string());
+            }
+        }
+
+        macro_rules! load_all {
+            ($manager:expr, $names:expr, $handles:expr) => {
+                for name in $names.iter() {
+                    let handle = Handle(name.clone());
+                    if contains(&$handles, &handle) {
+                        continue;
+                    }
+                    let is_new = $manager.get(&handle).is_none();
+                    $manager.load_with_priority(name.clone(), priority);
+                    if is_new {
+                        // this load can be cancelled, see `ManifestHandles::cancel`.
+                        handles.started.extend($manager.ticket(&handle));
+                    }
+                    $handles.extend($manager.acquire(&handle));
+                }
+            };
+        }
+
+        load_all!(sprite_manager, sprites, handles.sprites);
+        load_all!(prefab_manager, self.prefabs, handles.prefabs);
+        load_all!(audio_manager, self.sounds, handles.sounds);
+        load_all!(shader_manager, self.shaders, handles.shaders);
+        load_all!(particle_manager, self.particles, handles.particles);
+        load_all!(stage_manager, self.stages, handles.stages);
+    }
+
+    /// Same as `load` but the manifest also gets the dependencies of the stages and prefabs that
+    /// are loaded. Without workers they are loaded right away, so everything is found.
+    pub fn load_with_dependencies<S>(
+        &mut self,
+        resources: &Resources,
+        priority: Priority,
+    ) -> ManifestHandles
+    where
+        S: GraphicsContext<Backend = GL33> + 'static,
+    {
+        let mut handles = ManifestHandles::default();
+        loop {
+            self.load_into::<S>(resources, priority, &mut handles);
+            if !self.add_loaded_dependencies::<S>(resources) {
+                return handles;
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+            && self.prefabs.is_empty()
+            && self.sounds.is_empty()
+            && self.shaders.is_empty()
+            && self.particles.is_empty()
+            && self.atlases.is_empty()
+            && self.sheets.is_empty()
+            && self.stages.is_empty()
+    }
+}
+
+fn push_unique<T: PartialEq>(v: &mut Vec<T>, elem: T) {
+    if !v.contains(&elem) {
+        v.push(elem);
+    }
+}
+
+fn contains<H: PartialEq>(handles: &[StrongHandle<H>], handle: &Handle<H>) -> bool {
+    handles.iter().any(|h| **h == *handle)
+}
+
+/// How many of the assets are done.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct LoadingProgress {
+    pub loaded: usize,
+    pub failed: usize,
+    pub total: usize,
+}
+
+impl LoadingProgress {
+    /// Between 0 and 1.
+    pub fn ratio(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.loaded as f32 / self.total as f32
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.loaded == self.total
+    }
+
+    fn add<S, T, H>(&mut self, manager: &AssetManager<S, T, H>, handles: &[StrongHandle<H>])
+    where
+        S: GraphicsContext<Backend = GL33>,
+        T: Default,
+        H: Clone + Eq + std::hash::Hash,
+    {
+        self.total += handles.len();
+        for h in handles {
+            self.loaded += manager.is_loaded(h) as usize;
+            self.failed += manager.is_error(h) as usize;
+        }
+    }
+}
+
+/// Add the errors of the assets that have failed loading.
+fn add_errors<S, T, H>(
+    errors: &mut Vec<String>,
+    manager: &AssetManager<S, T, H>,
+    handles: &[StrongHandle<H>],
+) where
+    S: GraphicsContext<Backend = GL33>,
+    T: Default,
+    H: Clone + Eq + std::hash::Hash + std::fmt::Debug,
+{
+    for h in handles {
+        if let Some(e) = manager.get(h).and_then(|asset| asset.error()) {
+            errors.push(format!("{:?} = {}", h.0, e));
+        }
+    }
+}
+
+/// Assets of a manifest. They stay in their manager until the handles are dropped, so the scene
+/// that needs them should keep the handles (see `scene::loading::WithAssets`).
+#[derive(Debug, Default, Clone)]
+pub struct ManifestHandles {
+    pub sprites: Vec<StrongHandle>,
+    pub prefabs: Vec<StrongHandle>,
+    pub sounds: Vec<StrongHandle>,
+    pub shaders: Vec<StrongHandle<ShaderHandle>>,
+    pub particles: Vec<StrongHandle>,
+    pub stages: Vec<StrongHandle>,
+    pub atlases: Vec<StrongHandle>,
+    /// Loads started by the manifest. The others were already there or loading for someone else.
+    started: Vec<Arc<Ticket>>,
+}
+
+impl ManifestHandles {
+    pub fn progress<S>(&self, resources: &Resources) -> LoadingProgress
+    where
+        S: GraphicsContext<Backend = GL33> + 'static,
+    {
+        let mut progress = LoadingProgress::default();
+        progress.add(
+            &*resources
+                .fetch::<AssetManager<S, SpriteAsset<S>>>()
+                .unwrap(),
+            &self.sprites,
+        );
+        progress.add(
+            &*resources.fetch::<PrefabManager<S>>().unwrap(),
+            &self.prefabs,
+        );
+        progress.add(
+            &*resources.fetch::<AssetManager<S, Audio>>().unwrap(),
+            &self.sounds,
+        );
+        progress.add(
+            &*resources.fetch::<ShaderManager<S>>().unwrap(),
+            &self.shaders,
+        );
+        progress.add(
+            &*resources.fetch::<ParticleManager<S>>().unwrap(),
+            &self.particles,
+        );
+        progress.add(
+            &*resources.fetch::<StageManager<S>>().unwrap(),
+            &self.stages,
+        );
+        progress.add(
+            &*resources.fetch::<AtlasManager<S>>().unwrap(),
+            &self.atlases,
+        );
+        progress
+    }
+
+    /// Errors of the assets that have failed loading, to show to the player.
+    pub fn errors<S>(&self, resources: &Resources) -> Vec<String>
+    where
+        S: GraphicsContext<Backend = GL33> + 'static,
+    {
+        let mut errors = vec![];
+        add_errors(
+            &mut errors,
+            &*resources
+                .fetch::<AssetManager<S, SpriteAsset<S>>>()
+                .unwrap(),
+            &self.sprites,
+        );
+        add_errors(
+            &mut errors,
+            &*resources.fetch::<PrefabManager<S>>().unwrap(),
+            &self.prefabs,
+        );
+        add_errors(
+            &mut errors,
+            &*resources.fetch::<AssetManager<S, Audio>>().unwrap(),
+            &self.sounds,
+        );
+        add_errors(
+            &mut errors,
+            &*resources.fetch::<ShaderManager<S>>().unwrap(),
+            &self.shaders,
+        );
+        add_errors(
+            &mut errors,
+            &*resources.fetch::<ParticleManager<S>>().unwrap(),
+            &self.particles,
+        );
+        add_errors(
+            &mut errors,
+            &*resources.fetch::<StageManager<S>>().unwrap(),
+            &self.stages,
+        );
+        add_errors(
+            &mut errors,
+            &*resources.fetch::<AtlasManager<S>>().unwrap(),
+            &self.atlases,
+        );
+        errors
+    }
+
+    /// Stop loading what is left. The assets that are already loaded are kept, and so are the
+    /// loads that were not started by the manifest or that someone else asked for since.
+    pub fn cancel<S>(&self, resources: &Resources)
+    where
+        S: GraphicsContext<Backend = GL33> + 'static,
+    {
+        cancel_started(
+            &mut *resources
+                .fetch_mut::<AssetManager<S, SpriteAsset<S>>>()
+                .unwrap(),
+            &self.sprites,
+            &self.started,
+        );
+        cancel_started(
+            &mut *resources.fetch_mut::<PrefabManager<S>>().unwrap(),
+            &self.prefabs,
+            &self.started,
+        );
+        cancel_started(
+            &mut *resources.fetch_mut::<AssetManager<S, Audio>>().unwrap(),
+            &self.sounds,
+            &self.started,
+        );
+        cancel_started(
+            &mut *resources.fetch_mut::<ShaderManager<S>>().unwrap(),
+            &self.shaders,
+            &self.started,
+        );
+        cancel_started(
+            &mut *resources.fetch_mut::<ParticleManager<S>>().unwrap(),
+            &self.particles,
+            &self.started,
+        );
+        cancel_started(
+            &mut *resources.fetch_mut::<StageManager<S>>().unwrap(),
+            &self.stages,
+            &self.started,
+        );
+        cancel_started(
+            &mut *resources.fetch_mut::<AtlasManager<S>>().unwrap(),
+            &self.atlases,
+            &self.started,
+        );
+    }
+}
+
+/// Cancel the loads of the handles that are in `started`.
+fn cancel_started<S, T, H>(
+    manager: &mut AssetManager<S, T, H>,
+    handles: &[StrongHandle<H>],
+    started: &[Arc<Ticket>],
+) where
+    S: GraphicsContext<Backend = GL33>,
+    T: Default,
+    H: Clone + Eq + std::hash::Hash,
+{
+    for h in handles {
+        if let Some(ticket) = manager.ticket(h) {
+            if started.iter().any(|t| Arc::ptr_eq(t, &ticket)) {
+                manager.cancel_started(h, &ticket);
+            }
+        }
+    }
+}
@@ -1,88 +1,380 @@
+use crate::assets::atlas::{AtlasManager, Atlases};
 use crate::assets::audio::Audio;
+use crate::assets::particle::ParticleManager;
 use crate::assets::prefab::PrefabManager;
 use crate::assets::shader::ShaderManager;
+use crate::assets::source::AssetSource;
 use crate::assets::sprite::SpriteAsset;
-use crate::paths::get_assets_path;
+use crate::assets::stage::StageManager;
+use crate::assets::worker::{panic_message, Priority, Ticket, WorkerPool};
 use crate::resources::Resources;
-use bitflags::_core::marker::PhantomData;
 use log::debug;
 use luminance::context::GraphicsContext;
 use luminance_gl::GL33;
-use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use std::collections::hash_map::Keys;
+use serde_derive::{Deserialize, Serialize};
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::hash::Hash;
-use std::sync::mpsc::Receiver;
+use std::ops::Deref;
+use std::panic::AssertUnwindSafe;
+use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
 
+pub mod atlas;
 pub mod audio;
+#[cfg(feature = "hot-reload")]
+pub mod hot_reload;
+pub mod manifest;
+pub mod pack;
+pub mod particle;
 pub mod prefab;
 pub mod shader;
+pub mod sheet;
+pub mod source;
 pub mod sprite;
+pub mod stage;
+pub mod worker;
+
+#[cfg(feature = "hot-reload")]
+pub use hot_reload::HotReloader;
 
-pub fn create_asset_managers<S>(_surface: &mut S, resources: &mut Resources)
+/// Add the asset managers to the resources. No GPU access is needed at this point so this can be
+/// used without a window.
+pub fn create_asset_managers<S>(resources: &mut Resources)
 where
     S: GraphicsContext<Backend = GL33> + 'static,
 {
-    let base_path = get_assets_path();
+    // the pack if there is one, and the asset folder.
+    let source = AssetSource::new();
 
-    #[cfg(not(feature = "packed"))]
     let sprite_manager: AssetManager<S, SpriteAsset<S>> = AssetManager::from_loader(Box::new(
-        sprite::SpriteSyncLoader::new(base_path.join("sprites")),
-    ));
-
-    #[cfg(feature = "packed")]
-    let sprite_manager: AssetManager<S, SpriteAsset<S>> = AssetManager::from_loader(Box::new(
-        sprite::SpritePackLoader::new(base_path.join("sprites")),
+        sprite::SpriteSyncLoader::new(source.join("sprites")),
     ));
 
     let prefab_loader: PrefabManager<S> = AssetManager::from_loader(Box::new(
-        prefab::PrefabSyncLoader::new(base_path.join("prefab")),
+        prefab::PrefabSyncLoader::new(source.join("prefab")),
     ));
 
-    let audio_loader: AssetManager<S, Audio> = AssetManager::from_loader(Box::new(
-        audio::AudioSyncLoader::new(base_path.clone()),
-    ));
+    let audio_loader: AssetManager<S, Audio> =
+        AssetManager::from_loader(Box::new(audio::AudioSyncLoader::new(source.clone())));
 
-    let shader_loader: ShaderManager<S> = AssetManager::from_loader(Box::new(
-        shader::ShaderLoader::new(base_path.join("shaders")),
+    let shader_loader: ShaderManager<S> =
+        AssetManager::from_loader(Box::new(shader::ShaderLoader::new(source.join("shaders"))));
+
+    let particle_loader: ParticleManager<S> = AssetManager::from_loader(Box::new(
+        particle::ParticleLoader::new(source.join("particle")),
     ));
+
+    let stage_loader: StageManager<S> =
+        AssetManager::from_loader(Box::new(stage::StageLoader::new(source.join("stages"))));
+
+    let atlas_loader: AtlasManager<S> =
+        AssetManager::from_loader(Box::new(atlas::AtlasLoader::new(source)));
     resources.insert(sprite_manager);
+    resources.insert(atlas::Atlases::default());
+    resources.insert(atlas_loader);
+    resources.insert(sheet::SpriteSheets::default());
     resources.insert(prefab_loader);
     resources.insert(audio_loader);
     resources.insert(shader_loader);
+    resources.insert(particle_loader);
+    resources.insert(stage_loader);
+}
+
+/// Load the assets on background threads from now on. Without this, the assets are loaded
+/// synchronously which is needed to get the same result every time when running headless.
+pub fn start_asset_workers<S>(resources: &Resources, nb_threads: usize)
+where
+    S: GraphicsContext<Backend = GL33> + 'static,
+{
+    let workers = Arc::new(WorkerPool::new(nb_threads));
+    resources
+        .fetch_mut::<AssetManager<S, SpriteAsset<S>>>()
+        .unwrap()
+        .set_workers(Some(Arc::clone(&workers)));
+    resources
+        .fetch_mut::<PrefabManager<S>>()
+        .unwrap()
+        .set_workers(Some(Arc::clone(&workers)));
+    resources
+        .fetch_mut::<AssetManager<S, Audio>>()
+        .unwrap()
+        .set_workers(Some(Arc::clone(&workers)));
+    resources
+        .fetch_mut::<ShaderManager<S>>()
+        .unwrap()
+        .set_workers(Some(Arc::clone(&workers)));
+    resources
+        .fetch_mut::<ParticleManager<S>>()
+        .unwrap()
+        .set_workers(Some(Arc::clone(&workers)));
+    resources
+        .fetch_mut::<StageManager<S>>()
+        .unwrap()
+        .set_workers(Some(Arc::clone(&workers)));
+    resources
+        .fetch_mut::<AtlasManager<S>>()
+        .unwrap()
+        .set_workers(Some(workers));
 }
 
 pub fn update_asset_managers<S>(surface: &mut S, resources: &Resources)
 where
     S: GraphicsContext<Backend = GL33> + 'static,
 {
+    {
+        // the pages of the atlases that are ready are uploaded with the other sprites.
+        let mut atlas_manager = resources.fetch_mut::<AtlasManager<S>>().unwrap();
+        atlas_manager.process_jobs();
+        atlas_manager.upload_all(surface);
+        resources.fetch_mut::<Atlases>().unwrap().update(
+            &*atlas_manager,
+            &mut *resources
+                .fetch_mut::<AssetManager<S, SpriteAsset<S>>>()
+                .unwrap(),
+        );
+    }
     {
         let mut sprite_manager = resources
             .fetch_mut::<AssetManager<S, SpriteAsset<S>>>()
             .unwrap();
+        sprite_manager.process_jobs();
         sprite_manager.upload_all(surface);
     }
 
     {
         let mut prefab_loader = resources.fetch_mut::<PrefabManager<S>>().unwrap();
+        prefab_loader.process_jobs();
         prefab_loader.upload_all(surface);
     }
     {
         let mut audio_loader = resources.fetch_mut::<AssetManager<S, Audio>>().unwrap();
+        audio_loader.process_jobs();
         audio_loader.upload_all(surface);
     }
 
     {
         let mut shader_loader = resources.fetch_mut::<ShaderManager<S>>().unwrap();
+        shader_loader.process_jobs();
         shader_loader.upload_all(surface);
     }
+    {
+        let mut particle_loader = resources.fetch_mut::<ParticleManager<S>>().unwrap();
+        particle_loader.process_jobs();
+        particle_loader.upload_all(surface);
+    }
+    {
+        let mut stage_loader = resources.fetch_mut::<StageManager<S>>().unwrap();
+        stage_loader.process_jobs();
+        stage_loader.upload_all(surface);
+    }
+}
+
+/// Stats of each asset manager, with the name of its asset kind.
+pub fn asset_stats<S>(resources: &Resources) -> Vec<(&'static str, AssetStats)>
+where
+    S: GraphicsContext<Backend = GL33> + 'static,
+{
+    vec![
+        (
+            "sprites",
+            resources
+                .fetch::<AssetManager<S, SpriteAsset<S>>>()
+                .unwrap()
+                .stats(),
+        ),
+        (
+            "prefabs",
+            resources.fetch::<PrefabManager<S>>().unwrap().stats(),
+        ),
+        (
+            "audio",
+            resources.fetch::<AssetManager<S, Audio>>().unwrap().stats(),
+        ),
+        (
+            "shaders",
+            resources.fetch::<ShaderManager<S>>().unwrap().stats(),
+        ),
+        (
+            "particles",
+            resources.fetch::<ParticleManager<S>>().unwrap().stats(),
+        ),
+        (
+            "stages",
+            resources.fetch::<StageManager<S>>().unwrap().stats(),
+        ),
+        (
+            "atlases",
+            resources.fetch::<AtlasManager<S>>().unwrap().stats(),
+        ),
+    ]
 }
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+/// Remove the assets that nothing needs anymore (see `AssetManager::unload_unused`), e.g. when
+/// the previous scene is gone.
+pub fn unload_unused_assets<S>(resources: &Resources)
+where
+    S: GraphicsContext<Backend = GL33> + 'static,
+{
+    let unloaded = resources
+        .fetch_mut::<AssetManager<S, SpriteAsset<S>>>()
+        .unwrap()
+        .unload_unused()
+        + resources
+            .fetch_mut::<PrefabManager<S>>()
+            .unwrap()
+            .unload_unused()
+        + resources
+            .fetch_mut::<AssetManager<S, Audio>>()
+            .unwrap()
+            .unload_unused()
+        + resources
+            .fetch_mut::<ShaderManager<S>>()
+            .unwrap()
+            .unload_unused()
+        + resources
+            .fetch_mut::<ParticleManager<S>>()
+            .unwrap()
+            .unload_unused()
+        + resources
+            .fetch_mut::<StageManager<S>>()
+            .unwrap()
+            .unload_unused()
+        + resources
+            .fetch_mut::<AtlasManager<S>>()
+            .unwrap()
+            .unload_unused();
+    debug!("Unloaded {} unused assets", unloaded);
+}
+
+/// Same as `update_asset_managers` but without a graphic context. Loaded assets become ready
+/// without being uploaded, so sprites and shaders will never have a texture or program. This is
+/// used when running the game headless.
+pub fn finish_asset_managers<S>(resources: &Resources)
+where
+    S: GraphicsContext<Backend = GL33> + 'static,
+{
+    {
+        let mut atlas_manager = resources.fetch_mut::<AtlasManager<S>>().unwrap();
+        atlas_manager.process_jobs();
+        atlas_manager.skip_upload();
+        resources.fetch_mut::<Atlases>().unwrap().update(
+            &*atlas_manager,
+            &mut *resources
+                .fetch_mut::<AssetManager<S, SpriteAsset<S>>>()
+                .unwrap(),
+        );
+    }
+    {
+        let mut sprite_manager = resources
+            .fetch_mut::<AssetManager<S, SpriteAsset<S>>>()
+            .unwrap();
+        sprite_manager.process_jobs();
+        sprite_manager.skip_upload();
+    }
+    {
+        let mut prefab_loader = resources.fetch_mut::<PrefabManager<S>>().unwrap();
+        prefab_loader.process_jobs();
+        prefab_loader.skip_upload();
+    }
+    {
+        let mut audio_loader = resources.fetch_mut::<AssetManager<S, Audio>>().unwrap();
+        audio_loader.process_jobs();
+        audio_loader.skip_upload();
+    }
+    {
+        let mut shader_loader = resources.fetch_mut::<ShaderManager<S>>().unwrap();
+        shader_loader.process_jobs();
+        shader_loader.skip_upload();
+    }
+    {
+        let mut particle_loader = resources.fetch_mut::<ParticleManager<S>>().unwrap();
+        particle_loader.process_jobs();
+        particle_loader.skip_upload();
+    }
+    {
+        let mut stage_loader = resources.fetch_mut::<StageManager<S>>().unwrap();
+        stage_loader.process_jobs();
+        stage_loader.skip_upload();
+    }
+}
+
+/// Name of an asset in its manager. It does not keep the asset loaded, see `StrongHandle` for that.
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Handle<H = String>(pub H);
 
+/// Handle that keeps the asset in its manager: `unload_unused` and the budget will not remove it
+/// while a clone of this handle exists. Get one with `AssetManager::acquire`.
+#[derive(Debug, Clone)]
+pub struct StrongHandle<H = String> {
+    handle: Handle<H>,
+    refs: Arc<()>,
+}
+
+impl<H> StrongHandle<H> {
+    /// Number of strong handles to the same asset, this one included.
+    pub fn nb_refs(&self) -> usize {
+        // the manager holds one.
+        Arc::strong_count(&self.refs) - 1
+    }
+}
+
+impl<H> Deref for StrongHandle<H> {
+    type Target = Handle<H>;
+
+    fn deref(&self) -> &Handle<H> {
+        &self.handle
+    }
+}
+
+/// Limits for an asset manager. When it is over budget, the least recently used assets that are
+/// not referenced are unloaded.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Budget {
+    /// Approximate memory of the assets, see `Loader::asset_size`.
+    pub max_bytes: Option<usize>,
+    /// Number of assets, e.g. number of textures for the sprites.
+    pub max_assets: Option<usize>,
+}
+
+impl Budget {
+    pub fn bytes(max_bytes: usize) -> Self {
+        Self {
+            max_bytes: Some(max_bytes),
+            max_assets: None,
+        }
+    }
+
+    pub fn assets(max_assets: usize) -> Self {
+        Self {
+            max_bytes: None,
+            max_assets: Some(max_assets),
+        }
+    }
+
+    fn is_exceeded(&self, stats: &AssetStats) -> bool {
+        let too_many_bytes = self.max_bytes.map(|max| stats.bytes > max);
+        let too_many_assets = self.max_assets.map(|max| stats.loaded > max);
+        too_many_bytes.unwrap_or(false) || too_many_assets.unwrap_or(false)
+    }
+}
+
+/// What an asset manager holds at the moment.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct AssetStats {
+    /// Assets that are ready to use.
+    pub loaded: usize,
+    /// Approximate memory of the assets, see `Loader::asset_size`.
+    pub bytes: usize,
+    /// Kept by strong handles, e.g. the assets of the scenes in the stack.
+    pub referenced: usize,
+    /// Waiting for a worker or being loaded by one.
+    pub pending: usize,
+    /// Assets unloaded because of the budget since the manager was created.
+    pub evicted: usize,
+}
+
 #[derive(Debug, Error)]
 pub enum AssetError {
     #[error(transparent)]
@@ -102,8 +394,30 @@ pub enum AssetError {
 
     #[error("Cannot find {0} in packed data")]
     PackedError(String),
+
+    #[error("Invalid asset pack: {0}")]
+    InvalidPack(String),
+
+    #[error("Invalid sprite sheet {0}: {1}")]
+    InvalidSheet(String, String),
+
+    #[error("Invalid stage {0}: {1}")]
+    InvalidStage(String, String),
+
+    #[error("Loading panicked: {0}")]
+    Panicked(String),
 }
 
+/// Second half of a load, run on the main thread to build the asset from the decoded data. Assets
+/// that hold GPU resources cannot be sent between threads but their data can.
+pub type Finish<T> = Box<dyn FnOnce() -> Result<T, AssetError> + Send>;
+
+/// First half of a load: read and decode the files. Run on a worker thread when there is one.
+pub type LoadJob<T> = Box<dyn FnOnce() -> Result<Finish<T>, AssetError> + Send>;
+
+/// Result of a load job, sent back to the asset manager.
+type JobResult<T, H> = (Handle<H>, Arc<Ticket>, Result<Finish<T>, AssetError>);
+
 pub struct Asset<T> {
     asset: Arc<Mutex<LoadingStatus<T, AssetError>>>,
 }
@@ -165,6 +479,24 @@ impl<T> Asset<T> {
         }
     }
 
+    /// Returns true if there is a version of the asset, even if it is not uploaded yet.
+    fn has_value(&self) -> bool {
+        let asset = &*self.asset.lock().unwrap();
+        matches!(asset, LoadingStatus::Ready(_) | LoadingStatus::Loaded(_))
+    }
+
+    /// Take the status of another asset. The clones of this asset see the change.
+    fn replace_with(&self, other: Asset<T>) {
+        let status = std::mem::replace(&mut *other.asset.lock().unwrap(), LoadingStatus::Loading);
+        *self.asset.lock().unwrap() = status;
+    }
+
+    /// Returns true if the asset is waiting for its data.
+    pub fn is_loading(&self) -> bool {
+        let asset = &*self.asset.lock().unwrap();
+        matches!(asset, LoadingStatus::Loading)
+    }
+
     /// Returns true if the asset has failed loading.
     pub fn is_error(&self) -> bool {
         let asset = &*self.asset.lock().unwrap();
@@ -175,6 +507,16 @@ impl<T> Asset<T> {
         }
     }
 
+    /// Message of the error if the asset has failed loading.
+    pub fn error(&self) -> Option<String> {
+        let asset = &*self.asset.lock().unwrap();
+        if let LoadingStatus::Error(ref e) = asset {
+            Some(e.to_string())
+        } else {
+            None
+        }
+    }
+
     /// Execute a function only if the asset is loaded.
     pub fn execute<F, Ret>(&self, mut f: F) -> Option<Ret>
     where
@@ -232,14 +574,52 @@ impl<T: Default, E> LoadingStatus<T, E> {
     }
 }
 
+/// An asset and what the manager needs to know to unload it.
+struct Entry<T> {
+    asset: Asset<T>,
+    /// Shared with the strong handles.
+    refs: Arc<()>,
+    /// Frame of the last `get`, for the LRU eviction.
+    last_used: Cell<u64>,
+    /// Computed when the asset becomes ready.
+    bytes: usize,
+}
+
+impl<T> Entry<T> {
+    fn new(asset: Asset<T>, frame: u64) -> Self {
+        Self {
+            asset,
+            refs: Arc::new(()),
+            last_used: Cell::new(frame),
+            bytes: 0,
+        }
+    }
+
+    fn nb_refs(&self) -> usize {
+        Arc::strong_count(&self.refs) - 1
+    }
+}
+
 pub struct AssetManager<S, T: Default, H = String>
 where
     S: GraphicsContext<Backend = GL33>,
     H: Clone,
 {
-    // might want to use a LRU instead...
-    store: HashMap<Handle<H>, Asset<T>>,
+    store: HashMap<Handle<H>, Entry<T>>,
     loader: Box<dyn Loader<S, T, H>>,
+
+    /// If set, the least recently used assets are unloaded when over budget.
+    budget: Option<Budget>,
+    /// Incremented on every upload, to know which assets were used recently.
+    frame: u64,
+    evicted: usize,
+
+    /// Assets are loaded by these threads if set. Otherwise they are loaded synchronously.
+    workers: Option<Arc<WorkerPool>>,
+    /// Loads that are running on the workers.
+    pending: HashMap<Handle<H>, Arc<Ticket>>,
+    tx: Sender<JobResult<T, H>>,
+    rx: Receiver<JobResult<T, H>>,
 }
 
 impl<S, T: Default, H> AssetManager<S, T, H>
@@ -248,33 +628,125 @@ where
     H: Clone + Eq + PartialEq + Hash,
 {
     pub fn from_loader(loader: Box<dyn Loader<S, T, H>>) -> Self {
+        let (tx, rx) = std::sync::mpsc::channel();
         Self {
             store: HashMap::new(),
             loader,
+            budget: None,
+            frame: 0,
+            evicted: 0,
+            workers: None,
+            pending: HashMap::new(),
+            tx,
+            rx,
         }
     }
 
-    pub fn load(&mut self, asset_name: H) -> Handle<H> {
-        let handle = Handle(asset_name.clone());
-        if self.store.contains_key(&handle) {
-            return handle;
+    /// Load on these threads from now on. None to load synchronously.
+    pub fn set_workers(&mut self, workers: Option<Arc<WorkerPool>>) {
+        self.workers = workers;
+    }
+
+    /// Change the priority of an asset that is waiting for a worker. Returns false if the asset
+    /// is not loading.
+    pub fn set_priority(&self, handle: &Handle<H>, priority: Priority) -> bool {
+        match self.pending.get(handle) {
+            Some(ticket) => {
+                ticket.set_priority(priority);
+                true
+            }
+            None => false,
         }
-        let asset = self.loader.load(asset_name);
-        self.store.insert(handle.clone(), asset);
-        handle
     }
 
-    pub fn reload(&mut self, asset_name: H) -> Handle<H> {
-        let handle = Handle(asset_name.clone());
-        let asset = self.loader.load(asset_name);
-        self.store.insert(handle.clone(), asset);
-        handle
+    /// Stop loading an asset. If it was not loaded before (not a reload), it is removed from the
+    /// manager. Returns false if the asset is not loading.
+    pub fn cancel(&mut self, handle: &Handle<H>) -> bool {
+        match self.pending.remove(handle) {
+            Some(ticket) => {
+                ticket.cancel();
+                let never_loaded = self
+                    .store
+                    .get(handle)
+                    .map(|entry| entry.asset.is_loading())
+                    .unwrap_or(false);
+                if never_loaded {
+                    self.store.remove(handle);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cancel a load that was started with this ticket, unless the asset was asked for again
+    /// since then (see `Ticket::share`). Returns false if the load was not cancelled.
+    pub fn cancel_started(&mut self, handle: &Handle<H>, ticket: &Arc<Ticket>) -> bool {
+        let is_same_load = self
+            .pending
+            .get(handle)
+            .map(|t| Arc::ptr_eq(t, ticket))
+            .unwrap_or(false);
+        if is_same_load && !ticket.is_shared() {
+            self.cancel(handle)
+        } else {
+            false
+        }
+    }
+
+    /// Ticket of the load that is running, if any.
+    pub fn ticket(&self, handle: &Handle<H>) -> Option<Arc<Ticket>> {
+        self.pending.get(handle).cloned()
+    }
+
+    /// Returns true if the asset is waiting for a worker or being loaded by one.
+    pub fn is_pending(&self, handle: &Handle<H>) -> bool {
+        self.pending.contains_key(handle)
+    }
+
+    /// Number of assets that are waiting for a worker or being loaded by one.
+    pub fn nb_pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Get the results of the workers. The assets become `Loaded` and are ready after the next
+    /// `upload_all`.
+    pub fn process_jobs(&mut self) {
+        while let Ok((handle, ticket, res)) = self.rx.try_recv() {
+            let is_current = self
+                .pending
+                .get(&handle)
+                .map(|t| Arc::ptr_eq(t, &ticket))
+                .unwrap_or(false);
+            if !is_current || ticket.is_cancelled() {
+                continue;
+            }
+            self.pending.remove(&handle);
+
+            let asset = match self.store.get_mut(&handle) {
+                Some(entry) => &mut entry.asset,
+                None => continue,
+            };
+            match res.and_then(|finish| finish()) {
+                Ok(value) => asset.set_loaded(value),
+                Err(e) if asset.has_value() => {
+                    error!(
+                        "Error while reloading asset, will keep the previous version = {:?}",
+                        e
+                    );
+                }
+                Err(e) => {
+                    error!("Error while loading asset = {:?}", e);
+                    asset.set_error(e);
+                }
+            }
+        }
     }
 
     pub fn upload_all(&mut self, ctx: &mut S) {
         // once every now and then, check the resources ready to be uploaded by the current thread.
-        for asset in self.store.values() {
-            let asset = &mut *asset.asset.lock().unwrap();
+        for entry in self.store.values_mut() {
+            let asset = &mut *entry.asset.asset.lock().unwrap();
 
             let mut has_error = Ok(());
             let mut to_process = false;
@@ -288,120 +760,467 @@ where
                 if let Err(e) = has_error {
                     error!("Error when uploading to GPU = {:?}", e);
                     *asset = LoadingStatus::Error(e);
+                    entry.bytes = 0;
                 } else {
                     asset.move_to_read();
+                    if let LoadingStatus::Ready(ref t) = asset {
+                        entry.bytes = self.loader.asset_size(t);
+                    }
                 }
             }
         }
+        self.end_frame();
+    }
+
+    /// Move the loaded assets to ready without calling `upload_to_gpu`. Used when there is no
+    /// graphic context.
+    pub fn skip_upload(&mut self) {
+        for entry in self.store.values_mut() {
+            let asset = &mut *entry.asset.asset.lock().unwrap();
+            if let LoadingStatus::Loaded(ref t) = asset {
+                entry.bytes = self.loader.asset_size(t);
+                asset.move_to_read();
+            }
+        }
+        self.end_frame();
+    }
+
+    fn end_frame(&mut self) {
+        self.enforce_budget();
+        self.frame += 1;
     }
 
     pub fn get(&self, handle: &Handle<H>) -> Option<&Asset<T>> {
-        self.store.get(handle)
+        self.store.get(handle).map(|entry| {
+            entry.last_used.set(self.frame);
+            &entry.asset
+        })
     }
 
     pub fn get_mut(&mut self, handle: &Handle<H>) -> Option<&mut Asset<T>> {
-        self.store.get_mut(handle)
+        let frame = self.frame;
+        self.store.get_mut(handle).map(|entry| {
+            entry.last_used.set(frame);
+            &mut entry.asset
+        })
     }
 
     pub fn is_loaded(&self, handle: &Handle<H>) -> bool {
         self.store
             .get(handle)
-            .map(|asset| asset.is_loaded())
+            .map(|entry| entry.asset.is_loaded())
             .unwrap_or(false)
     }
 
     pub fn is_error(&self, handle: &Handle<H>) -> bool {
         self.store
             .get(handle)
-            .map(|asset| asset.is_error())
+            .map(|entry| entry.asset.is_error())
             .unwrap_or(false)
     }
 
     /// Return the assets that are currently managed
-    pub fn keys(&self) -> Keys<Handle<H>, Asset<T>> {
+    pub fn keys(&self) -> impl Iterator<Item = &Handle<H>> {
         self.store.keys()
     }
+
+    /// Get a handle that keeps the asset in the manager. None if the asset is not in the manager.
+    pub fn acquire(&self, handle: &Handle<H>) -> Option<StrongHandle<H>> {
+        self.store.get(handle).map(|entry| StrongHandle {
+            handle: handle.clone(),
+            refs: Arc::clone(&entry.refs),
+        })
+    }
+
+    /// Number of strong handles to the asset.
+    pub fn nb_refs(&self, handle: &Handle<H>) -> usize {
+        self.store.get(handle).map(Entry::nb_refs).unwrap_or(0)
+    }
+
+    /// Remove an asset from the manager, even if it is referenced. Its loading is
+    /// cancelled if it is not finished. Clones of the asset keep their data. Returns false if the
+    /// asset was not in the manager.
+    pub fn unload(&mut self, handle: &Handle<H>) -> bool {
+        if let Some(ticket) = self.pending.remove(handle) {
+            ticket.cancel();
+        }
+        self.store.remove(handle).is_some()
+    }
+
+    /// Remove the assets that are not referenced by a strong handle and not loading.
+    /// Returns the number of assets removed.
+    pub fn unload_unused(&mut self) -> usize {
+        let before = self.store.len();
+        let pending = &self.pending;
+        self.store
+            .retain(|handle, entry| !Self::is_unused(handle, entry, pending));
+        before - self.store.len()
+    }
+
+    fn is_unused(
+        handle: &Handle<H>,
+        entry: &Entry<T>,
+        pending: &HashMap<Handle<H>, Arc<Ticket>>,
+    ) -> bool {
+        entry.nb_refs() == 0 && !pending.contains_key(handle) && !entry.asset.is_loading()
+    }
+
+    /// None to keep every asset.
+    pub fn set_budget(&mut self, budget: Option<Budget>) {
+        self.budget = budget;
+    }
+
+    pub fn budget(&self) -> Option<Budget> {
+        self.budget
+    }
+
+    pub fn stats(&self) -> AssetStats {
+        AssetStats {
+            loaded: self
+                .store
+                .values()
+                .filter(|entry| entry.asset.is_loaded())
+                .count(),
+            bytes: self.store.values().map(|entry| entry.bytes).sum(),
+            referenced: self
+                .store
+                .values()
+                .filter(|entry| entry.nb_refs() > 0)
+                .count(),
+            pending: self.pending.len(),
+            evicted: self.evicted,
+        }
+    }
+
+    /// Unload the least recently used assets until the manager is within its budget. The assets
+    /// used during the current frame are kept, so a budget that is too small does not make the
+    /// same assets load again and again.
+    fn enforce_budget(&mut self) {
+        let budget = match self.budget {
+            Some(budget) => budget,
+            None => return,
+        };
+        let mut stats = self.stats();
+        if !budget.is_exceeded(&stats) {
+            return;
+        }
+
+        let mut candidates: Vec<(u64, Handle<H>)> = self
+            .store
+            .iter()
+            .filter(|(handle, entry)| {
+                entry.last_used.get() < self.frame && Self::is_unused(handle, entry, &self.pending)
+            })
+            .map(|(handle, entry)| (entry.last_used.get(), handle.clone()))
+            .collect();
+        candidates.sort_by_key(|(last_used, _)| *last_used);
+
+        for (_, handle) in candidates {
+            if !budget.is_exceeded(&stats) {
+                break;
+            }
+            if let Some(entry) = self.store.remove(&handle) {
+                if entry.asset.is_loaded() {
+                    stats.loaded -= 1;
+                }
+                stats.bytes -= entry.bytes;
+                self.evicted += 1;
+            }
+        }
+
+        if budget.is_exceeded(&stats) {
+            debug!("Asset budget exceeded by assets in use = {:?}", stats);
+        }
+    }
 }
 
-pub trait Loader<S, T, H = String>
+impl<S, T: Default + 'static, H> AssetManager<S, T, H>
 where
     S: GraphicsContext<Backend = GL33>,
-    H: Clone,
+    H: Clone + Eq + PartialEq + Hash + Send + 'static,
 {
-    /// Get an asset from an handle
-    fn load(&mut self, asset_name: H) -> Asset<T>;
+    /// Start loading an asset if it is not already there. With workers, the asset is `Loading`
+    /// until `process_jobs` gets the result.
+    pub fn load(&mut self, asset_name: H) -> Handle<H> {
+        self.load_with_priority(asset_name, Priority::Normal)
+    }
 
-    fn upload_to_gpu(&self, _ctx: &mut S, _inner: &mut T) -> Result<(), AssetError> {
-        Ok(())
+    /// Same as `load`. If the asset is still waiting for a worker, its priority is raised.
+    pub fn load_with_priority(&mut self, asset_name: H, priority: Priority) -> Handle<H> {
+        let handle = Handle(asset_name.clone());
+        if self.store.contains_key(&handle) {
+            if let Some(ticket) = self.pending.get(&handle) {
+                // whoever started the load should not cancel it now.
+                ticket.share();
+                if ticket.priority() < priority {
+                    ticket.set_priority(priority);
+                }
+            }
+            return handle;
+        }
+        self.start_load(handle.clone(), asset_name, priority);
+        handle
+    }
+
+    /// Add an asset that was not loaded by the loader, e.g. an atlas page built at runtime. It is
+    /// uploaded like the other assets and replaces the current version if there is one.
+    pub fn insert(&mut self, asset_name: H, value: T) -> Handle<H> {
+        let handle = Handle(asset_name);
+        if let Some(ticket) = self.pending.remove(&handle) {
+            ticket.cancel();
+        }
+        let mut asset = Asset::new();
+        asset.set_loaded(value);
+        match self.store.get(&handle) {
+            Some(previous) => previous.asset.replace_with(asset),
+            None => {
+                self.store
+                    .insert(handle.clone(), Entry::new(asset, self.frame));
+            }
+        }
+        handle
+    }
+
+    /// Load the asset again. With workers, the current version is kept until the new one is
+    /// loaded. If the new version cannot be loaded, the current version is kept.
+    pub fn reload(&mut self, asset_name: H) -> Handle<H> {
+        let handle = Handle(asset_name.clone());
+        self.start_load(handle.clone(), asset_name, Priority::Normal);
+        handle
+    }
+
+    fn start_load(&mut self, handle: Handle<H>, asset_name: H, priority: Priority) {
+        let workers = match self.workers {
+            Some(ref workers) => workers,
+            None => {
+                let asset = self.loader.load(asset_name);
+                match self.store.get(&handle) {
+                    Some(previous) if asset.is_error() && previous.asset.has_value() => {
+                        error!("Cannot reload asset, will keep the previous version")
+                    }
+                    // update in place so that the clones of the asset get the new version.
+                    Some(previous) => previous.asset.replace_with(asset),
+                    None => {
+                        self.store.insert(handle, Entry::new(asset, self.frame));
+                    }
+                }
+                return;
+            }
+        };
+
+        // a previous load of the same asset is not needed anymore.
+        let ticket = Arc::new(Ticket::new(priority));
+        if let Some(previous) = self.pending.insert(handle.clone(), Arc::clone(&ticket)) {
+            previous.cancel();
+        }
+        let frame = self.frame;
+        self.store
+            .entry(handle.clone())
+            .or_insert_with(|| Entry::new(Asset::new(), frame));
+
+        let job = self.loader.load_job(asset_name);
+        let tx = self.tx.clone();
+        workers.submit(Arc::clone(&ticket), move || {
+            // without a result, the asset would be loading forever.
+            let res = std::panic::catch_unwind(AssertUnwindSafe(job))
+                .unwrap_or_else(|e| Err(AssetError::Panicked(panic_message(&*e))));
+            // the manager might be gone already.
+            let _ = tx.send((handle, ticket, res));
+        });
     }
 }
 
-/// Good for development. Will listen to the asset folder and ask the asset managers to reload their
-/// data if needed
-#[cfg(feature = "hot-reload")]
-pub struct HotReloader<S>
+impl<S, T: Default, H> Drop for AssetManager<S, T, H>
 where
     S: GraphicsContext<Backend = GL33>,
+    H: Clone,
 {
-    rx: Receiver<Result<notify::Event, notify::Error>>,
-    _watcher: RecommendedWatcher,
-    _phantom: PhantomData<S>,
+    fn drop(&mut self) {
+        // no need to keep the workers busy.
+        for ticket in self.pending.values() {
+            ticket.cancel();
+        }
+    }
 }
 
-#[cfg(feature = "hot-reload")]
-impl<S> HotReloader<S>
+pub trait Loader<S, T, H = String>
 where
-    S: GraphicsContext<Backend = GL33> + 'static,
+    S: GraphicsContext<Backend = GL33>,
+    H: Clone,
 {
-    pub fn new() -> Self {
-        let base_path = get_assets_path();
+    /// Prepare the loading of an asset. The job can be run on another thread.
+    fn load_job(&mut self, asset_name: H) -> LoadJob<T>;
 
-        let (tx, rx) = std::sync::mpsc::channel();
+    /// Get an asset from an handle, synchronously.
+    fn load(&mut self, asset_name: H) -> Asset<T> {
+        let job = self.load_job(asset_name);
+        let mut asset = Asset::new();
+        match job().and_then(|finish| finish()) {
+            Ok(value) => asset.set_loaded(value),
+            Err(e) => asset.set_error(e),
+        }
+        asset
+    }
+
+    fn upload_to_gpu(&self, _ctx: &mut S, _inner: &mut T) -> Result<(), AssetError> {
+        Ok(())
+    }
+
+    /// Approximate memory used by an asset, in bytes. Used for the budget and the stats.
+    fn asset_size(&self, _inner: &T) -> usize {
+        0
+    }
+}
 
-        // Add a path to be watched. All files and directories at that path and
-        // below will be monitored for changes.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use luminance_glfw::GlfwSurface;
+    use std::sync::mpsc::channel;
+    use std::time::{Duration, Instant};
 
-        // Automatically select the best implementation for your platform.
-        // You can also access each implementation directly e.g. INotifyWatcher.
-        let mut watcher: RecommendedWatcher =
-            Watcher::new_immediate(move |res| tx.send(res).unwrap()).unwrap();
+    /// Loads the name of the asset. "panic" panics.
+    struct NameLoader;
 
-        watcher
-            .watch(base_path.clone(), RecursiveMode::Recursive)
-            .unwrap();
-        Self {
-            rx,
-            _watcher: watcher,
-            _phantom: PhantomData::default(),
-        }
-    }
-
-    /// Will check if there is a file that has changed and will reload the corresponding resource.
-    ///
-    /// WIP, currently just reload all the shaders :D
-    pub fn update(&mut self, resources: &Resources) {
-        let mut should_reload = false;
-        for res in &self.rx.try_recv() {
-            match res {
-                Ok(Event {
-                    kind: EventKind::Modify(..),
-                    paths,
-                    ..
-                }) => {
-                    debug!("Should reload {:?}", paths);
-                    should_reload = true
+    impl Loader<GlfwSurface, String> for NameLoader {
+        fn load_job(&mut self, asset_name: String) -> LoadJob<String> {
+            Box::new(move || {
+                if asset_name == "panic" {
+                    panic!("cannot load {}", asset_name);
                 }
-                _ => (),
-            }
+                let finish: Finish<String> = Box::new(move || Ok(asset_name));
+                Ok(finish)
+            })
         }
+    }
 
-        if should_reload {
-            if let Some(mut shader_manager) = resources.fetch_mut::<ShaderManager<S>>() {
-                let keys = { shader_manager.keys().map(|k| k.clone()).collect::<Vec<_>>() };
-                for k in keys {
-                    shader_manager.reload(k.0);
-                }
-            }
+    fn manager(workers: &Arc<WorkerPool>) -> AssetManager<GlfwSurface, String> {
+        let mut manager = AssetManager::from_loader(Box::new(NameLoader));
+        manager.set_workers(Some(Arc::clone(workers)));
+        manager
+    }
+
+    fn wait_for_jobs(manager: &mut AssetManager<GlfwSurface, String>) {
+        let start = Instant::now();
+        while manager.nb_pending() > 0 {
+            assert!(start.elapsed() < Duration::from_secs(5), "jobs are stuck");
+            std::thread::sleep(Duration::from_millis(1));
+            manager.process_jobs();
         }
+        manager.skip_upload();
+    }
+
+    #[test]
+    fn load_on_workers() {
+        let workers = Arc::new(WorkerPool::new(1));
+        let mut manager = manager(&workers);
+        let handle = manager.load("ship".to_string());
+        wait_for_jobs(&mut manager);
+
+        assert!(manager.is_loaded(&handle));
+        let name = manager.get(&handle).unwrap().execute(|name| name.clone());
+        assert_eq!(name.as_deref(), Some("ship"));
+    }
+
+    #[test]
+    fn panicking_load_is_an_error() {
+        let workers = Arc::new(WorkerPool::new(1));
+        let mut manager = manager(&workers);
+        let handle = manager.load("panic".to_string());
+        wait_for_jobs(&mut manager);
+
+        assert!(manager.is_error(&handle));
+        let error = manager.get(&handle).unwrap().error().unwrap();
+        assert!(error.contains("cannot load panic"), "{}", error);
+    }
+
+    #[test]
+    fn cancel_started_keeps_shared_loads() {
+        let workers = Arc::new(WorkerPool::new(1));
+        // the jobs of the manager stay in the queue until the gate is dropped.
+        let (gate, wait) = channel::<()>();
+        workers.submit(Arc::new(Ticket::new(Priority::High)), move || {
+            let _ = wait.recv();
+        });
+        let mut manager = manager(&workers);
+
+        let shared = manager.load("shared".to_string());
+        let shared_ticket = manager.ticket(&shared).unwrap();
+        manager.load("shared".to_string());
+        let alone = manager.load("alone".to_string());
+        let alone_ticket = manager.ticket(&alone).unwrap();
+
+        assert!(!manager.cancel_started(&shared, &shared_ticket));
+        assert!(manager.is_pending(&shared));
+        assert!(manager.cancel_started(&alone, &alone_ticket));
+        assert!(manager.get(&alone).is_none());
+
+        drop(gate);
+        wait_for_jobs(&mut manager);
+        assert!(manager.is_loaded(&shared));
+    }
+
+    #[test]
+    fn cancel_started_ignores_other_loads() {
+        let workers = Arc::new(WorkerPool::new(1));
+        let (gate, wait) = channel::<()>();
+        workers.submit(Arc::new(Ticket::new(Priority::High)), move || {
+            let _ = wait.recv();
+        });
+        let mut manager = manager(&workers);
+
+        let handle = manager.load("ship".to_string());
+        let first = manager.ticket(&handle).unwrap();
+        // the reload replaces the load that was started first.
+        manager.reload("ship".to_string());
+        assert!(!manager.cancel_started(&handle, &first));
+        assert!(manager.is_pending(&handle));
+        drop(gate);
+    }
+
+    #[test]
+    fn unload_unused_keeps_strong_handles() {
+        let mut manager: AssetManager<GlfwSurface, String> =
+            AssetManager::from_loader(Box::new(NameLoader));
+        let ship = manager.load("ship".to_string());
+        let bullet = manager.load("bullet".to_string());
+        manager.skip_upload();
+
+        let strong = manager.acquire(&ship).unwrap();
+        let other = strong.clone();
+        assert_eq!(manager.nb_refs(&ship), 2);
+        assert_eq!(manager.unload_unused(), 1);
+        assert!(manager.is_loaded(&ship));
+        assert!(manager.get(&bullet).is_none());
+
+        // each owner only releases its own reference.
+        drop(strong);
+        assert_eq!(manager.unload_unused(), 0);
+        assert_eq!(manager.stats().referenced, 1);
+        drop(other);
+        assert_eq!(manager.unload_unused(), 1);
+        assert!(manager.get(&ship).is_none());
+    }
+
+    #[test]
+    fn stats_only_count_ready_assets() {
+        let workers = Arc::new(WorkerPool::new(1));
+        let (gate, wait) = channel::<()>();
+        workers.submit(Arc::new(Ticket::new(Priority::High)), move || {
+            let _ = wait.recv();
+        });
+        let mut manager = manager(&workers);
+        manager.load("ship".to_string());
+        manager.load("panic".to_string());
+
+        let stats = manager.stats();
+        assert_eq!(stats.loaded, 0);
+        assert_eq!(stats.pending, 2);
+
+        drop(gate);
+        wait_for_jobs(&mut manager);
+        let stats = manager.stats();
+        assert_eq!(stats.loaded, 1);
+        assert_eq!(stats.pending, 0);
     }
 }
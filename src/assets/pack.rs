@@ -0,0 +1,393 @@
+//! Archive that contains the assets, so that the game can be shipped without the asset folder.
+//! Packs are created with the `pack_assets` binary.
+//!
+//! Layout of a pack:
+//! - `PACK_MAGIC`,
+//! - version (u32, little endian),
+//! - size of the table of contents (u64, little endian),
+//! - table of contents (bincode). Path of each entry relative to the asset folder -> `PackEntry`,
+//! - data of the entries, one after the other.
+//!
+//! Each entry is compressed on its own so that an asset can be read without the rest of the pack.
+use crate::assets::AssetError;
+use bincode::Options;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+pub const PACK_MAGIC: &[u8; 4] = b"SGPK";
+/// Increment when the layout changes. Older packs have to be created again.
+pub const PACK_VERSION: u32 = 1;
+
+/// Size of the magic, version and table of contents size.
+const HEADER_SIZE: u64 = 4 + 4 + 8;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AssetKind {
+    Sprite,
+    Audio,
+    Prefab,
+    Stage,
+    Particle,
+    Shader,
+    Config,
+    Other,
+}
+
+impl AssetKind {
+    /// Guess the kind from the folder of the asset.
+    pub fn from_path(path: &str) -> Self {
+        let folder = path.split('/').next().unwrap_or("");
+        match folder {
+            "sprites" => AssetKind::Sprite,
+            "music" | "sounds" => AssetKind::Audio,
+            "prefab" => AssetKind::Prefab,
+            "stages" => AssetKind::Stage,
+            "particle" => AssetKind::Particle,
+            "shaders" => AssetKind::Shader,
+            "config" => AssetKind::Config,
+            _ => AssetKind::Other,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Compression {
+    None,
+    /// Level between 0 and 10.
+    Deflate(u8),
+}
+
+impl Compression {
+    /// PNG, OGG and MP3 are already compressed so deflate would only make them slower to read.
+    pub fn for_path(path: &str, level: u8) -> Self {
+        let ext = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase());
+        match ext.as_deref() {
+            Some("png") | Some("ogg") | Some("mp3") => Compression::None,
+            _ if level == 0 => Compression::None,
+            _ => Compression::Deflate(level),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackEntry {
+    pub kind: AssetKind,
+    pub compression: Compression,
+    /// Position of the data, from the end of the table of contents.
+    pub offset: u64,
+    /// Size of the data in the pack.
+    pub size: u64,
+    /// Size of the data once uncompressed.
+    pub raw_size: u64,
+}
+
+/// Build a pack in memory then write it.
+#[derive(Default)]
+pub struct PackWriter {
+    entries: BTreeMap<String, PackEntry>,
+    data: Vec<u8>,
+}
+
+impl PackWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file. The path is relative to the asset folder. If compressing does not make the
+    /// file smaller, it is stored as is.
+    pub fn add(&mut self, path: &str, content: &[u8], compression: Compression) -> &PackEntry {
+        let compressed = match compression {
+            Compression::None => None,
+            Compression::Deflate(level) => {
+                Some(miniz_oxide::deflate::compress_to_vec(content, level))
+                    .filter(|compressed| compressed.len() < content.len())
+            }
+        };
+        let (compression, data) = match compressed {
+            Some(ref compressed) => (compression, compressed.as_slice()),
+            None => (Compression::None, content),
+        };
+
+        let entry = PackEntry {
+            kind: AssetKind::from_path(path),
+            compression,
+            offset: self.data.len() as u64,
+            size: data.len() as u64,
+            raw_size: content.len() as u64,
+        };
+        self.data.extend_from_slice(data);
+        self.entries.insert(path.to_string(), entry);
+        &self.entries[path]
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&String, &PackEntry)> {
+        self.entries.iter()
+    }
+
+    pub fn write<W: Write>(&self, mut w: W) -> Result<(), AssetError> {
+        let toc = bincode::serialize(&self.entries)
+            .map_err(|e| AssetError::InvalidPack(e.to_string()))?;
+        w.write_all(PACK_MAGIC)?;
+        w.write_all(&PACK_VERSION.to_le_bytes())?;
+        w.write_all(&(toc.len() as u64).to_le_bytes())?;
+        w.write_all(&toc)?;
+        w.write_all(&self.data)?;
+        Ok(())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), AssetError> {
+        let file = File::create(path)?;
+        self.write(std::io::BufWriter::new(file))
+    }
+}
+
+/// Where the data of the entries is.
+enum PackData {
+    /// Included in the binary.
+    Embedded(&'static [u8]),
+    /// Read from the file when needed. The mutex is needed because the asset workers share the
+    /// file.
+    File(Mutex<File>),
+}
+
+/// Pack opened for reading. Only the table of contents is kept in memory.
+pub struct Pack {
+    entries: BTreeMap<String, PackEntry>,
+    /// Position of the first entry from the start of the pack.
+    data_start: u64,
+    data: PackData,
+}
+
+impl Pack {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, AssetError> {
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let (entries, data_start) = read_toc(&mut file, len)?;
+        Ok(Self {
+            entries,
+            data_start,
+            data: PackData::File(Mutex::new(file)),
+        })
+    }
+
+    pub fn from_static(bytes: &'static [u8]) -> Result<Self, AssetError> {
+        let (entries, data_start) = read_toc(&mut std::io::Cursor::new(bytes), bytes.len() as u64)?;
+        Ok(Self {
+            entries,
+            data_start,
+            data: PackData::Embedded(bytes),
+        })
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&String, &PackEntry)> {
+        self.entries.iter()
+    }
+
+    pub fn get(&self, path: &str) -> Option<&PackEntry> {
+        self.entries.get(path)
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    /// Read and decompress an entry. The path is relative to the asset folder.
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, AssetError> {
+        let entry = self
+            .entries
+            .get(path)
+            .ok_or_else(|| AssetError::PackedError(path.to_string()))?;
+
+        // checked when the pack was opened.
+        let (start, end) = entry_range(self.data_start, entry)
+            .ok_or_else(|| AssetError::InvalidPack(format!("{} is truncated", path)))?;
+        let data = match self.data {
+            PackData::Embedded(bytes) => bytes
+                .get(start as usize..end as usize)
+                .ok_or_else(|| AssetError::InvalidPack(format!("{} is truncated", path)))?
+                .to_vec(),
+            PackData::File(ref file) => {
+                let mut file = file.lock().unwrap();
+                let mut data = vec![0; entry.size as usize];
+                file.seek(SeekFrom::Start(start))?;
+                file.read_exact(&mut data)?;
+                data
+            }
+        };
+
+        match entry.compression {
+            Compression::None => Ok(data),
+            Compression::Deflate(_) => miniz_oxide::inflate::decompress_to_vec(&data)
+                .map_err(|e| AssetError::InvalidPack(format!("Cannot inflate {} = {:?}", path, e))),
+        }
+    }
+}
+
+/// Start and end of the data of an entry, from the start of the pack. None if it overflows.
+fn entry_range(data_start: u64, entry: &PackEntry) -> Option<(u64, u64)> {
+    let start = data_start.checked_add(entry.offset)?;
+    let end = start.checked_add(entry.size)?;
+    Some((start, end))
+}
+
+/// Read the table of contents. `len` is the size of the whole pack: the sizes in the pack are not
+/// trusted so that a corrupt pack is an error instead of a huge allocation.
+fn read_toc<R: Read>(
+    r: &mut R,
+    len: u64,
+) -> Result<(BTreeMap<String, PackEntry>, u64), AssetError> {
+    let mut header = [0u8; HEADER_SIZE as usize];
+    r.read_exact(&mut header)?;
+    if &header[0..4] != PACK_MAGIC {
+        return Err(AssetError::InvalidPack("Not an asset pack".to_string()));
+    }
+
+    let mut version = [0u8; 4];
+    version.copy_from_slice(&header[4..8]);
+    let version = u32::from_le_bytes(version);
+    if version != PACK_VERSION {
+        return Err(AssetError::InvalidPack(format!(
+            "Version {} is not supported (expected {}), the pack should be created again",
+            version, PACK_VERSION
+        )));
+    }
+
+    let mut toc_size = [0u8; 8];
+    toc_size.copy_from_slice(&header[8..16]);
+    let toc_size = u64::from_le_bytes(toc_size);
+    if toc_size > len.saturating_sub(HEADER_SIZE) {
+        return Err(AssetError::InvalidPack(format!(
+            "Table of contents of {} bytes does not fit in a pack of {} bytes",
+            toc_size, len
+        )));
+    }
+    let mut toc = vec![0; toc_size as usize];
+    r.read_exact(&mut toc)?;
+    // same encoding as `bincode::serialize`, without reading more than the table of contents.
+    let entries: BTreeMap<String, PackEntry> = bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(toc_size)
+        .deserialize(&toc)
+        .map_err(|e| AssetError::InvalidPack(e.to_string()))?;
+
+    let data_start = HEADER_SIZE + toc_size;
+    for (path, entry) in &entries {
+        match entry_range(data_start, entry) {
+            Some((_, end)) if end <= len => (),
+            _ => {
+                return Err(AssetError::InvalidPack(format!(
+                    "{} is outside of the pack",
+                    path
+                )))
+            }
+        }
+    }
+    Ok((entries, data_start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack_bytes() -> Vec<u8> {
+        let mut writer = PackWriter::new();
+        writer.add("stages/stage1.json", &[b'a'; 200], Compression::Deflate(6));
+        writer.add("sprites/ship.png", b"not really a png", Compression::None);
+        let mut bytes = vec![];
+        writer.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn open(bytes: Vec<u8>) -> Result<Pack, AssetError> {
+        Pack::from_static(Box::leak(bytes.into_boxed_slice()))
+    }
+
+    fn is_invalid<T>(res: Result<T, AssetError>) -> bool {
+        matches!(res, Err(AssetError::InvalidPack(_)))
+    }
+
+    #[test]
+    fn write_then_read() {
+        let pack = open(pack_bytes()).unwrap();
+        assert_eq!(pack.entries().count(), 2);
+
+        let stage = pack.get("stages/stage1.json").unwrap();
+        assert_eq!(stage.kind, AssetKind::Stage);
+        assert_eq!(stage.compression, Compression::Deflate(6));
+        assert!(stage.size < stage.raw_size);
+        assert_eq!(pack.read("stages/stage1.json").unwrap(), vec![b'a'; 200]);
+
+        assert_eq!(
+            pack.get("sprites/ship.png").unwrap().compression,
+            Compression::None
+        );
+        assert_eq!(pack.read("sprites/ship.png").unwrap(), b"not really a png");
+
+        match pack.read("sprites/other.png") {
+            Err(AssetError::PackedError(path)) => assert_eq!(path, "sprites/other.png"),
+            _ => panic!("sprites/other.png should not be in the pack"),
+        }
+    }
+
+    #[test]
+    fn write_then_open_file() {
+        let path = std::env::temp_dir().join(format!("spacegame-pack-{}.bin", std::process::id()));
+        std::fs::write(&path, pack_bytes()).unwrap();
+        let pack = Pack::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            pack.unwrap().read("stages/stage1.json").unwrap(),
+            vec![b'a'; 200]
+        );
+    }
+
+    #[test]
+    fn not_a_pack() {
+        let mut bytes = pack_bytes();
+        bytes[0] = b'X';
+        assert!(is_invalid(open(bytes)));
+
+        let mut bytes = pack_bytes();
+        bytes[4..8].copy_from_slice(&(PACK_VERSION + 1).to_le_bytes());
+        assert!(is_invalid(open(bytes)));
+    }
+
+    #[test]
+    fn truncated_toc() {
+        // a huge size must not be allocated.
+        let mut bytes = pack_bytes();
+        bytes[8..16].copy_from_slice(&(u64::MAX >> 16).to_le_bytes());
+        assert!(is_invalid(open(bytes)));
+
+        // the pack stops in the middle of the table of contents.
+        let bytes = pack_bytes()[..HEADER_SIZE as usize + 4].to_vec();
+        assert!(is_invalid(open(bytes)));
+    }
+
+    #[test]
+    fn truncated_data() {
+        let mut bytes = pack_bytes();
+        bytes.truncate(bytes.len() - 1);
+        assert!(is_invalid(open(bytes)));
+    }
+
+    #[test]
+    fn entry_range_overflow() {
+        let entry = PackEntry {
+            kind: AssetKind::Other,
+            compression: Compression::None,
+            offset: u64::MAX - 1,
+            size: 10,
+            raw_size: 10,
+        };
+        assert_eq!(entry_range(HEADER_SIZE, &entry), None);
+    }
+}
@@ -0,0 +1,47 @@
+//! Particle emitters from `assets/particle`. The manager keeps the definitions, and the entities
+//! get their own copy through a `ParticleEffect` (see `render::particle::update_effects`).
+use crate::assets::source::AssetSource;
+use crate::assets::{AssetError, AssetManager, Finish, LoadJob, Loader};
+use crate::render::particle::ParticleEmitter;
+use luminance::context::GraphicsContext;
+use luminance_gl::GL33;
+
+pub type ParticleManager<S> = AssetManager<S, ParticleEmitter>;
+
+/// Read an emitter from the particle folder of the source. Its pool is empty, see
+/// `ParticleEmitter::instantiate`.
+pub fn load_particle(source: &AssetSource, name: &str) -> Result<ParticleEmitter, AssetError> {
+    let content = source.read_to_string(name).map_err(|e| {
+        error!("Cannot read particle {} = {}", name, e);
+        e
+    })?;
+    serde_json::from_str(&content).map_err(|e| {
+        error!("Cannot parse particle {} = {}", name, e);
+        e.into()
+    })
+}
+
+pub struct ParticleLoader {
+    source: AssetSource,
+}
+
+impl ParticleLoader {
+    pub fn new(source: AssetSource) -> Self {
+        Self { source }
+    }
+}
+
+impl<S> Loader<S, ParticleEmitter, String> for ParticleLoader
+where
+    S: GraphicsContext<Backend = GL33>,
+{
+    fn load_job(&mut self, asset_name: String) -> LoadJob<ParticleEmitter> {
+        let source = self.source.clone();
+        Box::new(move || {
+            info!("Will load particle at {:?}", source.path(&asset_name));
+            let emitter = load_particle(&source, &asset_name)?;
+            let finish: Finish<ParticleEmitter> = Box::new(move || Ok(emitter));
+            Ok(finish)
+        })
+    }
+}
@@ -1,10 +1,11 @@
-use crate::assets::{Asset, AssetManager, Loader};
+use crate::assets::manifest::AssetManifest;
+use crate::assets::source::AssetSource;
+use crate::assets::{AssetManager, Finish, Handle, LoadJob, Loader};
 use crate::core::transform::Transform;
 use hecs::{Entity, World};
 use luminance::context::GraphicsContext;
 use luminance_gl::GL33;
 use serde_derive::{Deserialize, Serialize};
-use std::path::{Path, PathBuf};
 
 pub type PrefabManager<S> = AssetManager<S, Box<dyn Prefab>>;
 
@@ -29,6 +30,49 @@ pub trait Prefab: std::fmt::Debug {
 
         e
     }
+
+    /// Update an entity that was spawned from a previous version of this prefab, after a hot
+    /// reload. The state of the entity (position, health...) should be kept. Does nothing by
+    /// default.
+    fn patch(&self, _world: &mut hecs::World, _entity: hecs::Entity) {}
+
+    /// Add the assets needed by the spawned entities (sprites, sounds, prefabs they spawn...) to
+    /// the manifest, so that they can be loaded before the prefab is used. Nothing by default.
+    fn dependencies(&self, _manifest: &mut AssetManifest) {}
+}
+
+/// Name of the prefab an entity was spawned from. Used to patch the entity when the prefab is
+/// hot reloaded.
+#[derive(Debug, Clone)]
+pub struct PrefabInstance(pub String);
+
+impl<S> PrefabManager<S>
+where
+    S: GraphicsContext<Backend = GL33>,
+{
+    /// Spawn a prefab that is loaded. Returns None if it is not.
+    pub fn spawn(&self, name: &str, world: &mut World) -> Option<Entity> {
+        let e = self
+            .get(&Handle(name.to_string()))?
+            .execute(|prefab| prefab.spawn(world))?;
+        tag_instance(world, e, name);
+        Some(e)
+    }
+
+    /// Spawn a prefab that is loaded and set its position. Returns None if it is not loaded.
+    pub fn spawn_at_pos(&self, name: &str, world: &mut World, pos: glam::Vec2) -> Option<Entity> {
+        let e = self
+            .get(&Handle(name.to_string()))?
+            .execute(|prefab| prefab.spawn_at_pos(world, pos))?;
+        tag_instance(world, e, name);
+        Some(e)
+    }
+}
+
+fn tag_instance(world: &mut World, e: Entity, name: &str) {
+    if let Err(err) = world.insert_one(e, PrefabInstance(name.to_string())) {
+        error!("Cannot tag prefab instance = {:?}", err);
+    }
 }
 
 impl Default for Box<dyn Prefab> {
@@ -49,15 +93,12 @@ impl Prefab for EmptyPrefab {
 }
 
 pub struct PrefabSyncLoader {
-    base_path: PathBuf,
+    source: AssetSource,
 }
 
 impl PrefabSyncLoader {
-    pub fn new<P: AsRef<Path>>(base_path: P) -> Self {
-        let base_path = base_path.as_ref();
-        Self {
-            base_path: base_path.to_path_buf(),
-        }
+    pub fn new(source: AssetSource) -> Self {
+        Self { source }
     }
 }
 
@@ -65,31 +106,31 @@ impl<S> Loader<S, Box<dyn Prefab>, String> for PrefabSyncLoader
 where
     S: GraphicsContext<Backend = GL33>,
 {
-    fn load(&mut self, asset_name: String) -> Asset<Box<dyn Prefab>> {
-        let mut asset = Asset::new();
-        let asset_path = self.base_path.join(&asset_name).with_extension("json");
-        info!("Will load at path = {}", asset_path.display());
-
-        match std::fs::read_to_string(asset_path) {
-            Ok(asset_str) => {
-                let res: Result<Box<dyn Prefab>, _> = serde_json::from_str(&asset_str);
-                match res {
-                    Ok(val) => {
-                        info!("Finished loading {}", asset_name);
-                        asset.set_loaded(val)
-                    }
-                    Err(e) => {
-                        error!("Error while converting prefab from json = {:?}", e);
-                        asset.set_error(e.into())
-                    }
-                }
-            }
-            Err(e) => {
+    fn load_job(&mut self, asset_name: String) -> LoadJob<Box<dyn Prefab>> {
+        let source = self.source.clone();
+        Box::new(move || {
+            let file_name = format!("{}.json", asset_name);
+            info!("Will load at path = {}", source.path(&file_name).display());
+            let asset_str = source.read_to_string(&file_name).map_err(|e| {
                 error!("Error while reading from file = {:?}", e);
-                asset.set_error(e.into())
-            }
-        }
+                e
+            })?;
+
+            // Prefabs cannot be sent between threads so only the JSON parsing is done here.
+            let value: serde_json::Value = serde_json::from_str(&asset_str).map_err(|e| {
+                error!("Error while parsing prefab json = {:?}", e);
+                e
+            })?;
 
-        asset
+            let finish: Finish<Box<dyn Prefab>> = Box::new(move || {
+                let prefab: Box<dyn Prefab> = serde_json::from_value(value).map_err(|e| {
+                    error!("Error while converting prefab from json = {:?}", e);
+                    e
+                })?;
+                info!("Finished loading {}", asset_name);
+                Ok(prefab)
+            });
+            Ok(finish)
+        })
     }
 }
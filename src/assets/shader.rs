@@ -1,9 +1,9 @@
-use crate::assets::{Asset, AssetError, AssetManager, Loader};
+use crate::assets::source::AssetSource;
+use crate::assets::{AssetError, AssetManager, Finish, LoadJob, Loader};
 use crate::render::mesh::{ShaderUniform, VertexSemantics};
 use luminance::context::GraphicsContext;
 use luminance::shader::Program;
 use luminance_gl::GL33;
-use std::path::{Path, PathBuf};
 
 /// Load with this handle. Filenames for the vertex and fragment shaders
 pub type ShaderHandle = (String, String);
@@ -34,19 +34,13 @@ where
 }
 
 pub struct ShaderLoader {
-    base_path: PathBuf,
+    source: AssetSource,
 }
 
 impl ShaderLoader {
-    pub fn new<P: AsRef<Path>>(base_path: P) -> Self {
-        println!(
-            "WILL CREATE SHADER LOADER WITH PATH {:?}",
-            base_path.as_ref().display()
-        );
-        let base_path = base_path.as_ref();
-        Self {
-            base_path: base_path.to_path_buf(),
-        }
+    pub fn new(source: AssetSource) -> Self {
+        debug!("Shader loader path = {}", source.path("").display());
+        Self { source }
     }
 }
 
@@ -54,36 +48,36 @@ impl<S> Loader<S, ShaderAsset<S>, ShaderHandle> for ShaderLoader
 where
     S: GraphicsContext<Backend = GL33>,
 {
-    fn load(&mut self, asset_name: (String, String)) -> Asset<ShaderAsset<S>> {
-        info!("Will load {:?}", asset_name);
-        let vertex_shader_filename = self.base_path.join(asset_name.0);
-        let fragment_shader_filename = self.base_path.join(asset_name.1);
-
-        let mut asset = Asset::new();
-
-        match (
-            std::fs::read_to_string(&vertex_shader_filename),
-            std::fs::read_to_string(&fragment_shader_filename),
-        ) {
-            (Ok(vertex_shader), Ok(fragment_shader)) => {
-                info!("Ok loading shader");
-                asset.set_loaded(ShaderAsset {
-                    vertex_shader,
-                    fragment_shader,
-                    shader: None,
-                })
+    fn load_job(&mut self, asset_name: (String, String)) -> LoadJob<ShaderAsset<S>> {
+        let source = self.source.clone();
+        Box::new(move || {
+            info!("Will load {:?}", asset_name);
+            match (
+                source.read_to_string(&asset_name.0),
+                source.read_to_string(&asset_name.1),
+            ) {
+                (Ok(vertex_shader), Ok(fragment_shader)) => {
+                    info!("Ok loading shader");
+                    let finish: Finish<ShaderAsset<S>> = Box::new(move || {
+                        Ok(ShaderAsset {
+                            vertex_shader,
+                            fragment_shader,
+                            shader: None,
+                        })
+                    });
+                    Ok(finish)
+                }
+                (Err(e), _) | (_, Err(e)) => {
+                    error!(
+                        "Error while loading shader({}/{}) = {:?}",
+                        source.path(&asset_name.0).display(),
+                        source.path(&asset_name.1).display(),
+                        e
+                    );
+                    Err(e)
+                }
             }
-            (Err(e), _) | (_, Err(e)) => {
-                error!(
-                    "Error while loading shader({}/{}) = {:?}",
-                    vertex_shader_filename.display(),
-                    fragment_shader_filename.display(),
-                    e
-                );
-                asset.set_error(e.into());
-            }
-        }
-        asset
+        })
     }
 
     fn upload_to_gpu(&self, ctx: &mut S, inner: &mut ShaderAsset<S>) -> Result<(), AssetError> {
@@ -94,4 +88,8 @@ where
         inner.shader = Some(shader);
         Ok(())
     }
+
+    fn asset_size(&self, inner: &ShaderAsset<S>) -> usize {
+        inner.vertex_shader.len() + inner.fragment_shader.len()
+    }
 }
@@ -0,0 +1,437 @@
+//! Sprite sheets exported by Aseprite or TexturePacker, as JSON (hash or array of frames).
+//!
+//! The sheets are in `assets/sheets` and their image (`meta.image`) is relative to the sprites
+//! folder. A sheet is used like an atlas: the frames are regions of the image, named
+//! `<sheet>#<frame>` (see `frame_sprite`), so they can be used as the id of a `Sprite`.
+//!
+//! Each Aseprite tag becomes an animation. The sheets without tags (e.g. TexturePacker) get one
+//! animation per frame name without its number, so `walk_01.png`, `walk_02.png`... become `walk`.
+//! The duration of the frames is in milliseconds, 100 if not in the sheet.
+use crate::assets::atlas::{AtlasLayout, AtlasPage, AtlasRegion, Atlases};
+use crate::assets::source::AssetSource;
+use crate::assets::AssetError;
+use crate::core::animation::{Animation, AnimationController};
+use crate::runner::DEFAULT_STEP_RATE;
+use serde_derive::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+const DEFAULT_DURATION_MS: u32 = 100;
+
+#[derive(Debug, Deserialize)]
+struct RectJson {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct SizeJson {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct FrameJson {
+    /// Only in the array format.
+    #[serde(default)]
+    filename: Option<String>,
+    frame: RectJson,
+    #[serde(default)]
+    rotated: bool,
+    #[serde(default)]
+    duration: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum FramesJson {
+    Array(Vec<FrameJson>),
+    Hash(BTreeMap<String, FrameJson>),
+}
+
+#[derive(Debug, Deserialize)]
+struct TagJson {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetaJson {
+    image: String,
+    size: SizeJson,
+    #[serde(default, rename = "frameTags")]
+    frame_tags: Vec<TagJson>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SheetJson {
+    frames: FramesJson,
+    meta: MetaJson,
+}
+
+/// Name of a frame of a sheet, to use as the id of a `Sprite`.
+pub fn frame_sprite(sheet: &str, frame: &str) -> String {
+    format!("{}#{}", sheet, frame)
+}
+
+/// Frame names with their numbers compared as numbers, so that `frame 2` is before `frame 10`.
+/// The frames of the hash format are not in order once parsed.
+fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    fn chunks(s: &str) -> Vec<(bool, &str)> {
+        let mut chunks = vec![];
+        let mut start = 0;
+        let bytes = s.as_bytes();
+        for i in 1..=bytes.len() {
+            if i == bytes.len() || bytes[i].is_ascii_digit() != bytes[start].is_ascii_digit() {
+                chunks.push((bytes[start].is_ascii_digit(), &s[start..i]));
+                start = i;
+            }
+        }
+        chunks
+    }
+
+    let (ca, cb) = (chunks(a), chunks(b));
+    for ((da, a), (db, b)) in ca.iter().zip(cb.iter()) {
+        let ord = if *da && *db {
+            a.parse::<u64>()
+                .unwrap_or(0)
+                .cmp(&b.parse::<u64>().unwrap_or(0))
+        } else {
+            a.cmp(b)
+        };
+        if ord != std::cmp::Ordering::Equal {
+            return ord;
+        }
+    }
+    ca.len().cmp(&cb.len())
+}
+
+/// Name of the animation of a frame when there are no tags: without extension and number.
+fn frame_group(frame: &str) -> String {
+    let name = match frame.rfind('.') {
+        Some(dot) => &frame[..dot],
+        None => frame,
+    };
+    let group =
+        name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '_' || c == '-' || c == ' ');
+    if group.is_empty() {
+        name.to_string()
+    } else {
+        group.to_string()
+    }
+}
+
+/// Number of updates for a keyframe. The animation system stays on a keyframe for one more update
+/// than its value.
+fn duration_to_updates(duration_ms: u32) -> usize {
+    let updates = (duration_ms as f32 * DEFAULT_STEP_RATE as f32 / 1000.0).round() as usize;
+    updates.max(1) - 1
+}
+
+#[derive(Debug, Clone)]
+pub struct SpriteSheet {
+    /// One page (the image of the sheet) and the frames as regions.
+    pub layout: AtlasLayout,
+    /// Frames in order, with their duration in milliseconds.
+    pub frames: Vec<(String, u32)>,
+    pub animations: HashMap<String, Animation>,
+    /// First tag of the sheet, or first animation by name.
+    pub default_animation: Option<String>,
+}
+
+impl SpriteSheet {
+    /// Parse a sheet. The name is used for the regions of the frames.
+    pub fn from_json(name: &str, content: &str) -> Result<Self, AssetError> {
+        let json: SheetJson = serde_json::from_str(content)?;
+
+        let mut frames: Vec<(String, FrameJson)> = match json.frames {
+            FramesJson::Array(frames) => frames
+                .into_iter()
+                .enumerate()
+                .map(|(i, f)| (f.filename.clone().unwrap_or_else(|| i.to_string()), f))
+                .collect(),
+            FramesJson::Hash(frames) => frames.into_iter().collect(),
+        };
+        if let Some((rotated, _)) = frames.iter().find(|(_, f)| f.rotated) {
+            return Err(AssetError::InvalidSheet(
+                name.to_string(),
+                format!(
+                    "frame {} is rotated, rotated frames are not supported",
+                    rotated
+                ),
+            ));
+        }
+        // the array is already in order.
+        if frames.iter().all(|(_, f)| f.filename.is_none()) {
+            frames.sort_by(|(a, _), (b, _)| natural_cmp(a, b));
+        }
+
+        let layout = AtlasLayout {
+            pages: vec![AtlasPage {
+                sprite: json.meta.image.clone(),
+                w: json.meta.size.w,
+                h: json.meta.size.h,
+            }],
+            regions: frames
+                .iter()
+                .map(|(frame, f)| {
+                    let region = AtlasRegion {
+                        page: 0,
+                        x: f.frame.x,
+                        y: f.frame.y,
+                        w: f.frame.w,
+                        h: f.frame.h,
+                    };
+                    (frame_sprite(name, frame), region)
+                })
+                .collect(),
+        };
+        let frames: Vec<(String, u32)> = frames
+            .into_iter()
+            .map(|(frame, f)| (frame, f.duration.unwrap_or(DEFAULT_DURATION_MS)))
+            .collect();
+
+        let keyframe = |i: usize| {
+            let (frame, duration) = &frames[i];
+            (frame_sprite(name, frame), duration_to_updates(*duration))
+        };
+
+        let mut animations = HashMap::new();
+        let mut default_animation = None;
+        for tag in &json.meta.frame_tags {
+            if tag.from > tag.to || tag.to >= frames.len() {
+                return Err(AssetError::InvalidSheet(
+                    name.to_string(),
+                    format!("tag {} is out of the frames", tag.name),
+                ));
+            }
+            let forward = tag.from..=tag.to;
+            let indices: Vec<usize> = match tag.direction.as_deref() {
+                Some("reverse") => forward.rev().collect(),
+                // back without repeating the first and last frames.
+                Some("pingpong") => forward
+                    .clone()
+                    .chain((tag.from + 1..tag.to).rev())
+                    .collect(),
+                _ => forward.collect(),
+            };
+            let keyframes = indices.into_iter().map(keyframe).collect();
+            animations.insert(tag.name.clone(), Animation::new(keyframes));
+            default_animation.get_or_insert_with(|| tag.name.clone());
+        }
+
+        if animations.is_empty() {
+            let mut groups: BTreeMap<String, Vec<(String, usize)>> = BTreeMap::new();
+            for (i, (frame, _)) in frames.iter().enumerate() {
+                groups
+                    .entry(frame_group(frame))
+                    .or_default()
+                    .push(keyframe(i));
+            }
+            default_animation = groups.keys().next().cloned();
+            animations = groups
+                .into_iter()
+                .map(|(group, keyframes)| (group, Animation::new(keyframes)))
+                .collect();
+        }
+
+        Ok(Self {
+            layout,
+            frames,
+            animations,
+            default_animation,
+        })
+    }
+
+    /// Read a sheet from the sheets folder.
+    pub fn load(name: &str) -> Result<Self, AssetError> {
+        let content = AssetSource::new()
+            .join("sheets")
+            .read_to_string(name)
+            .map_err(|e| {
+                error!("Cannot read sprite sheet {} = {}", name, e);
+                e
+            })?;
+        Self::from_json(name, &content)
+    }
+
+    /// Controller that plays the default animation.
+    pub fn controller(&self) -> AnimationController {
+        AnimationController {
+            animations: self.animations.clone(),
+            current_animation: self.default_animation.clone(),
+            delete_on_finished: false,
+        }
+    }
+
+    /// Name of the image of the sheet in the sprite manager.
+    pub fn image(&self) -> &str {
+        &self.layout.pages[0].sprite
+    }
+}
+
+/// Sheets that are loaded. Their frames are added to the atlases so that the sprites can be
+/// rendered.
+#[derive(Default)]
+pub struct SpriteSheets {
+    sheets: HashMap<String, SpriteSheet>,
+}
+
+impl SpriteSheets {
+    pub fn get(&self, name: &str) -> Option<&SpriteSheet> {
+        self.sheets.get(name)
+    }
+
+    pub fn is_loaded(&self, name: &str) -> bool {
+        self.sheets.contains_key(name)
+    }
+
+    /// Read the sheet if it is not loaded yet.
+    pub fn load(&mut self, name: &str, atlases: &mut Atlases) -> Result<&SpriteSheet, AssetError> {
+        if !self.sheets.contains_key(name) {
+            self.reload(name, atlases)?;
+        }
+        Ok(&self.sheets[name])
+    }
+
+    /// Read the sheet again. If it cannot be read, the previous version is kept.
+    pub fn reload(&mut self, name: &str, atlases: &mut Atlases) -> Result<(), AssetError> {
+        let sheet = SpriteSheet::load(name)?;
+        atlases.add(name, &sheet.layout);
+        self.sheets.insert(name.to_string(), sheet);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(animation: &Animation) -> Vec<&str> {
+        animation
+            .keyframes
+            .iter()
+            .map(|(sprite, _)| sprite.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn aseprite_hash_with_tags() {
+        let content = r#"{
+            "frames": {
+                "ship 10.aseprite": { "frame": { "x": 0, "y": 32, "w": 16, "h": 16 }, "duration": 200 },
+                "ship 2.aseprite": { "frame": { "x": 16, "y": 0, "w": 16, "h": 16 }, "duration": 100 },
+                "ship 1.aseprite": { "frame": { "x": 0, "y": 0, "w": 16, "h": 16 }, "duration": 100 }
+            },
+            "meta": {
+                "image": "ship.png",
+                "size": { "w": 32, "h": 48 },
+                "frameTags": [
+                    { "name": "idle", "from": 0, "to": 2, "direction": "pingpong" },
+                    { "name": "back", "from": 0, "to": 1, "direction": "reverse" }
+                ]
+            }
+        }"#;
+        let sheet = SpriteSheet::from_json("ship.json", content).unwrap();
+
+        // frame numbers are compared as numbers.
+        let frames: Vec<&str> = sheet.frames.iter().map(|(f, _)| f.as_str()).collect();
+        assert_eq!(
+            frames,
+            vec!["ship 1.aseprite", "ship 2.aseprite", "ship 10.aseprite"]
+        );
+        assert_eq!(sheet.image(), "ship.png");
+        assert_eq!(sheet.layout.regions.len(), 3);
+        assert_eq!(sheet.default_animation.as_deref(), Some("idle"));
+
+        let idle = &sheet.animations["idle"];
+        assert_eq!(
+            names(idle),
+            vec![
+                "ship.json#ship 1.aseprite",
+                "ship.json#ship 2.aseprite",
+                "ship.json#ship 10.aseprite",
+                "ship.json#ship 2.aseprite",
+            ]
+        );
+        // 200 ms at 60 updates per second.
+        assert_eq!(idle.keyframes[2].1, 11);
+        assert_eq!(
+            names(&sheet.animations["back"]),
+            vec!["ship.json#ship 2.aseprite", "ship.json#ship 1.aseprite"]
+        );
+    }
+
+    #[test]
+    fn aseprite_array_keeps_order() {
+        let content = r#"{
+            "frames": [
+                { "filename": "b", "frame": { "x": 0, "y": 0, "w": 8, "h": 8 } },
+                { "filename": "a", "frame": { "x": 8, "y": 0, "w": 8, "h": 8 } }
+            ],
+            "meta": {
+                "image": "letters.png",
+                "size": { "w": 16, "h": 8 },
+                "frameTags": [{ "name": "all", "from": 0, "to": 1 }]
+            }
+        }"#;
+        let sheet = SpriteSheet::from_json("letters.json", content).unwrap();
+        assert_eq!(
+            names(&sheet.animations["all"]),
+            vec!["letters.json#b", "letters.json#a"]
+        );
+        // no duration in the sheet.
+        assert_eq!(sheet.frames[0].1, DEFAULT_DURATION_MS);
+    }
+
+    #[test]
+    fn texture_packer_groups_frames_by_name() {
+        let content = r#"{
+            "frames": {
+                "walk_02.png": { "frame": { "x": 8, "y": 0, "w": 8, "h": 8 }, "rotated": false },
+                "walk_01.png": { "frame": { "x": 0, "y": 0, "w": 8, "h": 8 }, "rotated": false },
+                "jump.png": { "frame": { "x": 16, "y": 0, "w": 8, "h": 8 }, "rotated": false }
+            },
+            "meta": { "image": "hero.png", "size": { "w": 24, "h": 8 } }
+        }"#;
+        let sheet = SpriteSheet::from_json("hero.json", content).unwrap();
+        assert_eq!(sheet.animations.len(), 2);
+        assert_eq!(
+            names(&sheet.animations["walk"]),
+            vec!["hero.json#walk_01.png", "hero.json#walk_02.png"]
+        );
+        assert_eq!(names(&sheet.animations["jump"]), vec!["hero.json#jump.png"]);
+        assert_eq!(sheet.default_animation.as_deref(), Some("jump"));
+    }
+
+    #[test]
+    fn rotated_frames_are_rejected() {
+        let content = r#"{
+            "frames": {
+                "a.png": { "frame": { "x": 0, "y": 0, "w": 8, "h": 8 }, "rotated": true }
+            },
+            "meta": { "image": "a.png", "size": { "w": 8, "h": 8 } }
+        }"#;
+        match SpriteSheet::from_json("a.json", content) {
+            Err(AssetError::InvalidSheet(name, _)) => assert_eq!(name, "a.json"),
+            other => panic!("expected an invalid sheet, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn tag_out_of_the_frames() {
+        let content = r#"{
+            "frames": [{ "frame": { "x": 0, "y": 0, "w": 8, "h": 8 } }],
+            "meta": {
+                "image": "a.png",
+                "size": { "w": 8, "h": 8 },
+                "frameTags": [{ "name": "bad", "from": 0, "to": 3 }]
+            }
+        }"#;
+        assert!(SpriteSheet::from_json("a.json", content).is_err());
+    }
+}
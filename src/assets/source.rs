@@ -0,0 +1,165 @@
+//! Where the loaders read the asset files from.
+//!
+//! If there is an asset pack, the files are read from it. The files that are not in the pack are
+//! read from the asset folder, so that new assets can be tried without packing everything again.
+//! The pack is either:
+//! - included in the binary with the `packed` feature,
+//! - or read at runtime from `PACK_PATH`. Without it the game only uses the asset folder, even if
+//!   `pack_assets` left a `packed.bin` around.
+//!
+//! Hot reload watches the asset folder, so it only sees the changes if there is no pack.
+use crate::assets::pack::Pack;
+use crate::assets::AssetError;
+use crate::paths::get_assets_path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[cfg(feature = "packed")]
+const EMBEDDED_PACK: &[u8] = include_bytes!("../../packed.bin");
+
+lazy_static::lazy_static! {
+    /// Opened only once as all the loaders share it.
+    static ref PACK: Option<Arc<Pack>> = open_default_pack();
+}
+
+fn default_pack() -> Option<Arc<Pack>> {
+    PACK.clone()
+}
+
+/// The embedded pack, or the one at `PACK_PATH`. If it cannot be opened, the asset folder is used.
+fn open_default_pack() -> Option<Arc<Pack>> {
+    #[cfg(feature = "packed")]
+    let pack = Pack::from_static(EMBEDDED_PACK).map(Some);
+
+    #[cfg(not(feature = "packed"))]
+    let pack = match crate::paths::get_pack_path() {
+        Some(pack_path) => {
+            info!("Will read the assets from {}", pack_path.display());
+            Pack::open(pack_path).map(Some)
+        }
+        None => Ok(None),
+    };
+
+    pack.map(|p| p.map(Arc::new)).unwrap_or_else(|e| {
+        error!(
+            "Cannot open the asset pack, will use the asset folder = {}",
+            e
+        );
+        None
+    })
+}
+
+/// Read files from the pack, or from the asset folder if they are not packed.
+#[derive(Clone)]
+pub struct AssetSource {
+    pack: Option<Arc<Pack>>,
+    base_path: PathBuf,
+    /// Folder of the source, relative to the asset folder. Empty or ending with '/'.
+    prefix: String,
+}
+
+impl Default for AssetSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AssetSource {
+    /// The asset folder and the default pack.
+    pub fn new() -> Self {
+        Self {
+            pack: default_pack(),
+            base_path: get_assets_path(),
+            prefix: String::new(),
+        }
+    }
+
+    /// Only the files in the folder.
+    pub fn loose<P: AsRef<Path>>(base_path: P) -> Self {
+        Self {
+            pack: None,
+            base_path: base_path.as_ref().to_path_buf(),
+            prefix: String::new(),
+        }
+    }
+
+    pub fn with_pack<P: AsRef<Path>>(pack: Arc<Pack>, base_path: P) -> Self {
+        Self {
+            pack: Some(pack),
+            base_path: base_path.as_ref().to_path_buf(),
+            prefix: String::new(),
+        }
+    }
+
+    /// Source for a sub folder, e.g. `source.join("sprites")`.
+    pub fn join(&self, folder: &str) -> Self {
+        Self {
+            pack: self.pack.clone(),
+            base_path: self.base_path.join(folder),
+            prefix: format!("{}{}/", self.prefix, folder.trim_end_matches('/')),
+        }
+    }
+
+    /// Path of the file in the asset folder, even if it is read from the pack.
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.base_path.join(name)
+    }
+
+    fn pack_key(&self, name: &str) -> String {
+        // the pack always uses '/'.
+        format!("{}{}", self.prefix, name.replace('\\', "/"))
+    }
+
+    /// True if the file will be read from the pack.
+    pub fn is_packed(&self, name: &str) -> bool {
+        self.pack
+            .as_ref()
+            .map(|pack| pack.contains(&self.pack_key(name)))
+            .unwrap_or(false)
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.is_packed(name) || self.path(name).exists()
+    }
+
+    /// Files directly in a folder, from the pack and the asset folder. The folder ends with '/'.
+    pub fn list(&self, folder: &str) -> Vec<String> {
+        let mut files = vec![];
+        if let Some(ref pack) = self.pack {
+            let prefix = self.pack_key(folder);
+            files.extend(pack.entries().filter_map(|(key, _)| {
+                key.strip_prefix(&prefix)
+                    .filter(|name| !name.contains('/'))
+                    .map(|name| name.to_string())
+            }));
+        }
+        if let Ok(entries) = std::fs::read_dir(self.path(folder)) {
+            files.extend(
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.path().is_file())
+                    .filter_map(|entry| entry.file_name().to_str().map(|n| n.to_string())),
+            );
+        }
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, AssetError> {
+        if let Some(ref pack) = self.pack {
+            let key = self.pack_key(name);
+            if pack.contains(&key) {
+                return pack.read(&key);
+            }
+        }
+        Ok(std::fs::read(self.path(name))?)
+    }
+
+    pub fn read_to_string(&self, name: &str) -> Result<String, AssetError> {
+        let content = self.read(name)?;
+        String::from_utf8(content).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, e.utf8_error()).into()
+        })
+    }
+}
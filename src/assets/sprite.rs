@@ -1,7 +1,7 @@
-use super::{Asset, Loader};
+use super::{Finish, LoadJob, Loader};
 
+use crate::assets::source::AssetSource;
 use crate::assets::AssetError;
-use downcast_rs::__std::path::PathBuf;
 use image::ImageError;
 use log::{error, info};
 use luminance::context::GraphicsContext;
@@ -12,9 +12,6 @@ use luminance_gl::GL33;
 use serde_derive::{Deserialize, Serialize};
 use std::path::Path;
 
-mod packed;
-pub use packed::*;
-
 pub enum SpriteAsset<S>
 where
     S: GraphicsContext<Backend = GL33>,
@@ -65,82 +62,122 @@ impl Default for SpriteAssetMetadata {
 }
 
 pub struct SpriteSyncLoader {
-    base_path: PathBuf,
+    source: AssetSource,
 }
 
 impl SpriteSyncLoader {
-    pub fn new<P: AsRef<Path>>(base_path: P) -> Self {
-        let base_path = base_path.as_ref();
-        Self {
-            base_path: base_path.to_path_buf(),
-        }
+    pub fn new(source: AssetSource) -> Self {
+        Self { source }
     }
 }
 
-impl SpriteSyncLoader {
-    fn load_metadata(&self, asset_name: &str) -> SpriteAssetMetadata {
-        let metadata_path = self.base_path.join(asset_name).with_extension("json");
-        info!(
-            "Will load {:?} metadata at {}",
-            asset_name,
-            metadata_path.display()
-        );
-        let metadata_str = std::fs::read_to_string(metadata_path);
-
-        match metadata_str {
-            Ok(metadata_str) => serde_json::from_str::<SpriteAssetMetadata>(&metadata_str)
-                .unwrap_or_else(|e| {
-                    error!(
-                        "Cannot deserialize Metadata file, will use default instead = {:?}",
-                        e
-                    );
-                    SpriteAssetMetadata::default()
-                }),
-            Err(_) => {
-                info!(
-                    "No metadata file for {}, Will use default instead.",
-                    asset_name
+/// Name of the metadata file of a sprite, e.g. `ship.json` for `ship.png`.
+pub fn metadata_name(asset_name: &str) -> String {
+    Path::new(asset_name)
+        .with_extension("json")
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn load_metadata(source: &AssetSource, asset_name: &str) -> SpriteAssetMetadata {
+    let metadata_name = metadata_name(asset_name);
+    info!(
+        "Will load {:?} metadata at {}",
+        asset_name,
+        source.path(&metadata_name).display()
+    );
+    let metadata_str = source.read_to_string(&metadata_name);
+
+    match metadata_str {
+        Ok(metadata_str) => serde_json::from_str::<SpriteAssetMetadata>(&metadata_str)
+            .unwrap_or_else(|e| {
+                error!(
+                    "Cannot deserialize Metadata file, will use default instead = {:?}",
+                    e
                 );
                 SpriteAssetMetadata::default()
-            }
+            }),
+        Err(_) => {
+            info!(
+                "No metadata file for {}, Will use default instead.",
+                asset_name
+            );
+            SpriteAssetMetadata::default()
         }
     }
 }
 
-impl<S> Loader<S, SpriteAsset<S>, String> for SpriteSyncLoader
+/// Texture data that is uploaded to the GPU later.
+pub(crate) fn finish_texels<S>(
+    w: u32,
+    h: u32,
+    data: Vec<u8>,
+    sampler: Sampler,
+) -> Finish<SpriteAsset<S>>
+where
+    S: GraphicsContext<Backend = GL33> + 'static,
+{
+    Box::new(move || Ok(SpriteAsset::Loading(w, h, data, sampler)))
+}
+
+pub(crate) fn upload_texels<S>(ctx: &mut S, inner: &mut SpriteAsset<S>) -> Result<(), AssetError>
 where
     S: GraphicsContext<Backend = GL33>,
 {
-    fn load(&mut self, asset_name: String) -> Asset<SpriteAsset<S>> {
-        let mut asset = Asset::new();
-        let asset_path = self.base_path.join(&asset_name);
-        let metadata = self.load_metadata(&asset_name);
-        let sampler = metadata.sampler.to_sampler();
-
-        match load_texels(asset_path) {
-            Ok((w, h, data)) => asset.set_loaded(SpriteAsset::Loading(w, h, data, sampler)),
-            Err(e) => {
-                error!("Error while loading {} = {}", asset_name, e);
-                asset.set_error(e.into());
-            }
-        }
+    let tex = if let SpriteAsset::Loading(w, h, data, sampler) = inner {
+        let mut tex = Texture::new(ctx, [*w, *h], 0, *sampler)?;
+        tex.upload_raw(GenMipmaps::No, data)?;
+        tex
+    } else {
+        panic!("Expecting Loading variant.")
+    };
+
+    *inner = SpriteAsset::Uploaded(tex);
+
+    Ok(())
+}
 
-        info!("Finished loading texture");
-        asset
+/// RGBA8, so 4 bytes per texel.
+pub(crate) fn texels_size<S>(inner: &SpriteAsset<S>) -> usize
+where
+    S: GraphicsContext<Backend = GL33>,
+{
+    let [w, h] = match inner {
+        SpriteAsset::Uploaded(tex) => tex.size(),
+        SpriteAsset::Loading(w, h, _, _) => [*w, *h],
+    };
+    w as usize * h as usize * 4
+}
+
+impl<S> Loader<S, SpriteAsset<S>, String> for SpriteSyncLoader
+where
+    S: GraphicsContext<Backend = GL33> + 'static,
+{
+    fn load_job(&mut self, asset_name: String) -> LoadJob<SpriteAsset<S>> {
+        let source = self.source.clone();
+        Box::new(move || {
+            let metadata = load_metadata(&source, &asset_name);
+            let sampler = metadata.sampler.to_sampler();
+
+            let (w, h, data) = source
+                .read(&asset_name)
+                .and_then(|content| Ok(load_texels_from_memory(&content)?))
+                .map_err(|e| {
+                    error!("Error while loading {} = {}", asset_name, e);
+                    e
+                })?;
+
+            info!("Finished loading texture");
+            Ok(finish_texels(w, h, data, sampler))
+        })
     }
 
     fn upload_to_gpu(&self, ctx: &mut S, inner: &mut SpriteAsset<S>) -> Result<(), AssetError> {
-        let tex = if let SpriteAsset::Loading(w, h, data, sampler) = inner {
-            let mut tex = Texture::new(ctx, [*w, *h], 0, sampler.clone())?;
-            tex.upload_raw(GenMipmaps::No, data)?;
-            tex
-        } else {
-            panic!("Expecting Loading variant.")
-        };
-
-        *inner = SpriteAsset::Uploaded(tex);
+        upload_texels(ctx, inner)
+    }
 
-        Ok(())
+    fn asset_size(&self, inner: &SpriteAsset<S>) -> usize {
+        texels_size(inner)
     }
 }
 
@@ -161,7 +198,7 @@ pub struct SamplerDef {
 }
 
 impl SamplerDef {
-    fn to_sampler(&self) -> Sampler {
+    pub(crate) fn to_sampler(&self) -> Sampler {
         Sampler {
             depth_comparison: self
                 .depth_comparison
@@ -279,3 +316,10 @@ pub fn load_texels<P: AsRef<Path>>(path: P) -> Result<(u32, u32, Vec<u8>), Image
     let (width, height) = img.dimensions();
     Ok((width, height, img.into_raw()))
 }
+
+/// Same as `load_texels` for an image that is already read, e.g. from the asset pack.
+pub fn load_texels_from_memory(content: &[u8]) -> Result<(u32, u32, Vec<u8>), ImageError> {
+    let img = image::load_from_memory(content).map(|img| img.flipv().to_rgba8())?;
+    let (width, height) = img.dimensions();
+    Ok((width, height, img.into_raw()))
+}
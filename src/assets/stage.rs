@@ -0,0 +1,46 @@
+//! Stage descriptions from `assets/stages`. They are validated when loaded (see
+//! `StageDescription::problems`) so that a broken stage is an error instead of a crash mid-game.
+use crate::assets::source::AssetSource;
+use crate::assets::{AssetError, AssetManager, Finish, LoadJob, Loader};
+use crate::gameplay::level::StageDescription;
+use luminance::context::GraphicsContext;
+use luminance_gl::GL33;
+
+pub type StageManager<S> = AssetManager<S, StageDescription>;
+
+/// Read a stage from the stages folder of the source.
+pub fn load_stage(source: &AssetSource, name: &str) -> Result<StageDescription, AssetError> {
+    let content = source.read_to_string(name).map_err(|e| {
+        error!("Cannot read stage {} = {}", name, e);
+        e
+    })?;
+    StageDescription::from_json(name, &content).map_err(|e| {
+        error!("Cannot load stage {} = {}", name, e);
+        e
+    })
+}
+
+pub struct StageLoader {
+    source: AssetSource,
+}
+
+impl StageLoader {
+    pub fn new(source: AssetSource) -> Self {
+        Self { source }
+    }
+}
+
+impl<S> Loader<S, StageDescription, String> for StageLoader
+where
+    S: GraphicsContext<Backend = GL33>,
+{
+    fn load_job(&mut self, asset_name: String) -> LoadJob<StageDescription> {
+        let source = self.source.clone();
+        Box::new(move || {
+            info!("Will load stage at {:?}", source.path(&asset_name));
+            let stage = load_stage(&source, &asset_name)?;
+            let finish: Finish<StageDescription> = Box::new(move || Ok(stage));
+            Ok(finish)
+        })
+    }
+}
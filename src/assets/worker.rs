@@ -0,0 +1,306 @@
+//! Threads that read and decode the assets so that the game does not freeze while loading.
+//!
+//! The asset managers submit jobs with a priority. The workers always pick the job with the highest
+//! priority (the oldest one first when equal). A job can be cancelled or get a new priority while it
+//! is waiting in the queue through its `Ticket`.
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,
+    Normal,
+    /// E.g. assets that the loading screen is waiting for.
+    High,
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Priority::Normal
+    }
+}
+
+impl Priority {
+    fn from_u8(v: u8) -> Self {
+        match v {
+            0 => Priority::Low,
+            1 => Priority::Normal,
+            _ => Priority::High,
+        }
+    }
+}
+
+/// Shared between a job in the queue and whoever submitted it.
+#[derive(Debug)]
+pub struct Ticket {
+    priority: AtomicU8,
+    cancelled: AtomicBool,
+    /// Someone else than the submitter waits for the result.
+    shared: AtomicBool,
+}
+
+impl Ticket {
+    pub fn new(priority: Priority) -> Self {
+        Self {
+            priority: AtomicU8::new(priority as u8),
+            cancelled: AtomicBool::new(false),
+            shared: AtomicBool::new(false),
+        }
+    }
+
+    pub fn priority(&self) -> Priority {
+        Priority::from_u8(self.priority.load(Ordering::Relaxed))
+    }
+
+    /// Only has an effect if the job has not started yet.
+    pub fn set_priority(&self, priority: Priority) {
+        self.priority.store(priority as u8, Ordering::Relaxed);
+    }
+
+    /// The job will not run if it has not started yet. If it has, its result should be ignored.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Mark the job as needed by someone else than the submitter, so that the submitter does not
+    /// cancel it.
+    pub fn share(&self) {
+        self.shared.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_shared(&self) -> bool {
+        self.shared.load(Ordering::Relaxed)
+    }
+}
+
+struct Task {
+    ticket: Arc<Ticket>,
+    /// Order of submission, to keep FIFO order for the same priority.
+    seq: u64,
+    run: Box<dyn FnOnce() + Send>,
+}
+
+#[derive(Default)]
+struct Queue {
+    tasks: Vec<Task>,
+    next_seq: u64,
+    shutdown: bool,
+}
+
+impl Queue {
+    /// Remove the cancelled tasks and return the next task to run.
+    fn pop(&mut self) -> Option<Task> {
+        self.tasks.retain(|t| !t.ticket.is_cancelled());
+        let idx = self
+            .tasks
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| {
+                a.ticket
+                    .priority()
+                    .cmp(&b.ticket.priority())
+                    .then(b.seq.cmp(&a.seq))
+            })
+            .map(|(i, _)| i)?;
+        Some(self.tasks.swap_remove(idx))
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    queue: Mutex<Queue>,
+    available: Condvar,
+}
+
+pub struct WorkerPool {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    pub fn new(nb_threads: usize) -> Self {
+        let shared = Arc::new(Shared::default());
+        let workers = (0..nb_threads.max(1))
+            .map(|i| {
+                let shared = Arc::clone(&shared);
+                std::thread::Builder::new()
+                    .name(format!("asset-worker-{}", i))
+                    .spawn(move || work(&shared))
+                    .expect("Cannot spawn asset worker")
+            })
+            .collect();
+        Self { shared, workers }
+    }
+
+    /// Leave a core for the game thread. No need for many threads as loading is mostly IO.
+    pub fn default_size() -> usize {
+        num_cpus::get().saturating_sub(1).clamp(1, 4)
+    }
+
+    /// Run a job on a worker. The ticket can be used to cancel it or change its priority.
+    pub fn submit<F>(&self, ticket: Arc<Ticket>, run: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let mut queue = self.shared.queue.lock().unwrap();
+        let seq = queue.next_seq;
+        queue.next_seq += 1;
+        queue.tasks.push(Task {
+            ticket,
+            seq,
+            run: Box::new(run),
+        });
+        self.shared.available.notify_one();
+    }
+
+    /// Number of jobs waiting for a worker.
+    pub fn queued(&self) -> usize {
+        self.shared.queue.lock().unwrap().tasks.len()
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        {
+            let mut queue = self.shared.queue.lock().unwrap();
+            queue.shutdown = true;
+            queue.tasks.clear();
+        }
+        self.shared.available.notify_all();
+        for worker in self.workers.drain(..) {
+            if worker.join().is_err() {
+                error!("Asset worker panicked");
+            }
+        }
+    }
+}
+
+fn work(shared: &Shared) {
+    loop {
+        let task = {
+            let mut queue = shared.queue.lock().unwrap();
+            loop {
+                if queue.shutdown {
+                    return;
+                }
+                if let Some(task) = queue.pop() {
+                    break task;
+                }
+                queue = shared.available.wait(queue).unwrap();
+            }
+        };
+
+        if !task.ticket.is_cancelled() {
+            // a panic should not take the worker down with it.
+            if let Err(e) = std::panic::catch_unwind(AssertUnwindSafe(task.run)) {
+                error!("Asset job panicked = {}", panic_message(&*e));
+            }
+        }
+    }
+}
+
+/// Message of a panic caught with `catch_unwind`.
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{channel, Sender};
+    use std::time::Duration;
+
+    /// Keep the only worker busy until the returned sender is dropped.
+    fn block_worker(pool: &WorkerPool) -> Sender<()> {
+        let (tx, rx) = channel::<()>();
+        let (started_tx, started_rx) = channel();
+        pool.submit(Arc::new(Ticket::new(Priority::High)), move || {
+            started_tx.send(()).unwrap();
+            let _ = rx.recv();
+        });
+        started_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        tx
+    }
+
+    #[test]
+    fn highest_priority_first() {
+        let pool = WorkerPool::new(1);
+        let gate = block_worker(&pool);
+
+        let (tx, rx) = channel();
+        for (name, priority) in &[
+            ("low", Priority::Low),
+            ("high", Priority::High),
+            ("normal", Priority::Normal),
+            ("normal 2", Priority::Normal),
+        ] {
+            let tx = tx.clone();
+            let name = *name;
+            pool.submit(Arc::new(Ticket::new(*priority)), move || {
+                tx.send(name).unwrap();
+            });
+        }
+        drop(gate);
+
+        let order: Vec<&str> = (0..4)
+            .map(|_| rx.recv_timeout(Duration::from_secs(5)).unwrap())
+            .collect();
+        assert_eq!(order, vec!["high", "normal", "normal 2", "low"]);
+    }
+
+    #[test]
+    fn cancelled_task_does_not_run() {
+        let pool = WorkerPool::new(1);
+        let gate = block_worker(&pool);
+
+        let (tx, rx) = channel();
+        let ticket = Arc::new(Ticket::new(Priority::Normal));
+        let cancelled_tx = tx.clone();
+        pool.submit(Arc::clone(&ticket), move || {
+            cancelled_tx.send("cancelled").unwrap();
+        });
+        pool.submit(Arc::new(Ticket::new(Priority::Low)), move || {
+            tx.send("other").unwrap();
+        });
+        ticket.cancel();
+        drop(gate);
+
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), "other");
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+    }
+
+    #[test]
+    fn panicking_task_does_not_stop_the_worker() {
+        let pool = WorkerPool::new(1);
+        pool.submit(Arc::new(Ticket::new(Priority::High)), || {
+            panic!("boom");
+        });
+
+        let (tx, rx) = channel();
+        pool.submit(Arc::new(Ticket::new(Priority::Low)), move || {
+            tx.send(()).unwrap();
+        });
+        assert!(rx.recv_timeout(Duration::from_secs(5)).is_ok());
+    }
+
+    #[test]
+    fn message_of_panic() {
+        let e = std::panic::catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(panic_message(&*e), "static");
+        let e = std::panic::catch_unwind(|| panic!("formatted {}", 1)).unwrap_err();
+        assert_eq!(panic_message(&*e), "formatted 1");
+    }
+}
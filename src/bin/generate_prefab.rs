@@ -12,19 +12,13 @@ use spacegame::gameplay::enemy::{
 use spacegame::gameplay::health::Health;
 use spacegame::gameplay::physics::DynamicBody;
 use spacegame::gameplay::player::{Player, Stats};
-use spacegame::paths::get_assets_path;
 use spacegame::prefab::enemies::EnemyPrefab;
 use spacegame::prefab::player::PlayerPrefab;
-use spacegame::render::particle::ParticleEmitter;
+use spacegame::render::particle::ParticleEffect;
 use spacegame::render::sprite::Sprite;
 
 fn gen_player() {
     let player = {
-        let base_path = get_assets_path();
-        let mut emitter: ParticleEmitter = serde_json::from_str(
-            &std::fs::read_to_string(base_path.join("particle/trail.json")).unwrap(),
-        )
-        .unwrap();
         let scale = 24.0;
         let player_prefab = PlayerPrefab {
             dynamic_body: DynamicBody {
@@ -41,9 +35,7 @@ fn gen_player() {
                 rotation: 0.0,
                 dirty: true,
             },
-            sprite: Sprite {
-                id: "blue_05.png".to_string(),
-            },
+            sprite: Sprite::new("blue_05.png".to_string()),
             bounding_box: BoundingBox {
                 half_extend: 20.0 * glam::Vec2::one(),
                 collision_layer: CollisionLayer::PLAYER,
@@ -51,7 +43,7 @@ fn gen_player() {
             },
             health: Health::new(10.0, Timer::of_seconds(0.5)),
             shield: None,
-            trail: emitter,
+            trail: ParticleEffect::new("trail.json"),
             stats: Stats {
                 dmg: 1.0,
                 crit_percent: 50,
@@ -87,9 +79,7 @@ fn gen_mine() {
                 rotation: 0.0,
                 dirty: false,
             },
-            sprite: Sprite {
-                id: "explosion-05.png".to_string(),
-            },
+            sprite: Sprite::new("explosion-05.png".to_string()),
             bounding_box: BoundingBox {
                 half_extend: scale / 2.0 * glam::Vec2::one(),
                 collision_layer: CollisionLayer::MINE,
@@ -137,6 +127,7 @@ fn gen_mine() {
                 };
                 animation_controller
             }),
+            animation_sheet: None,
         };
 
         let prefab = &enemy_prefab as &dyn Prefab;
@@ -164,9 +155,7 @@ fn gen_mine_lander() {
                 rotation: 0.0,
                 dirty: false,
             },
-            sprite: Sprite {
-                id: "red_03.png".to_string(),
-            },
+            sprite: Sprite::new("red_03.png".to_string()),
             bounding_box: BoundingBox {
                 half_extend: scale / 2.0 * glam::Vec2::one(),
                 collision_layer: CollisionLayer::ENEMY,
@@ -182,6 +171,7 @@ fn gen_mine_lander() {
             },
             trail: None,
             animation: None,
+            animation_sheet: None,
         };
 
         let prefab = &enemy_prefab as &dyn Prefab;
@@ -209,9 +199,7 @@ fn gen_wanderer() {
                 rotation: 0.0,
                 dirty: false,
             },
-            sprite: Sprite {
-                id: "red_04.png".to_string(),
-            },
+            sprite: Sprite::new("red_04.png".to_string()),
             bounding_box: BoundingBox {
                 half_extend: scale / 2.0 * glam::Vec2::one(),
                 collision_layer: CollisionLayer::ENEMY,
@@ -227,6 +215,7 @@ fn gen_wanderer() {
             },
             trail: None,
             animation: None,
+            animation_sheet: None,
         };
 
         let prefab = &enemy_prefab as &dyn Prefab;
@@ -239,11 +228,6 @@ fn gen_wanderer() {
 fn gen_base_enemy() {
     let base_enemy = {
         let scale = 24.0;
-        let base_path = get_assets_path();
-        let mut emitter: ParticleEmitter = serde_json::from_str(
-            &std::fs::read_to_string(base_path.join("particle/enemy_trail.json")).unwrap(),
-        )
-        .unwrap();
         let enemy_prefab = EnemyPrefab {
             dynamic_body: DynamicBody {
                 impulses: vec![],
@@ -259,9 +243,7 @@ fn gen_base_enemy() {
                 rotation: 0.0,
                 dirty: false,
             },
-            sprite: Sprite {
-                id: "darkgrey_02.png".to_string(),
-            },
+            sprite: Sprite::new("darkgrey_02.png".to_string()),
             bounding_box: BoundingBox {
                 half_extend: scale / 2.0 * glam::Vec2::one(),
                 collision_layer: CollisionLayer::ENEMY,
@@ -275,8 +257,9 @@ fn gen_base_enemy() {
                 pickup_drop_percent: 2,
                 movement: MovementBehavior::Follow,
             },
-            trail: Some(emitter),
+            trail: Some(ParticleEffect::new("enemy_trail.json")),
             animation: None,
+            animation_sheet: None,
         };
 
         let prefab = &enemy_prefab as &dyn Prefab;
@@ -289,11 +272,6 @@ fn gen_base_enemy() {
 fn gen_carrier() {
     let base_enemy = {
         let scale = 128.0;
-        let base_path = get_assets_path();
-        let mut emitter: ParticleEmitter = serde_json::from_str(
-            &std::fs::read_to_string(base_path.join("particle/enemy_trail.json")).unwrap(),
-        )
-        .unwrap();
         let enemy_prefab = EnemyPrefab {
             dynamic_body: DynamicBody {
                 impulses: vec![],
@@ -309,9 +287,7 @@ fn gen_carrier() {
                 rotation: 0.0,
                 dirty: false,
             },
-            sprite: Sprite {
-                id: "large_red_01.png".to_string(),
-            },
+            sprite: Sprite::new("large_red_01.png".to_string()),
             bounding_box: BoundingBox {
                 half_extend: scale / 2.0 * glam::Vec2::one(),
                 collision_layer: CollisionLayer::ENEMY,
@@ -328,8 +304,9 @@ fn gen_carrier() {
                 pickup_drop_percent: 70,
                 movement: MovementBehavior::Follow,
             },
-            trail: Some(emitter),
+            trail: Some(ParticleEffect::new("enemy_trail.json")),
             animation: None,
+            animation_sheet: None,
         };
 
         let prefab = &enemy_prefab as &dyn Prefab;
@@ -342,11 +319,6 @@ fn gen_carrier() {
 fn gen_kamikaze() {
     let base_enemy = {
         let scale = 20.0;
-        let base_path = get_assets_path();
-        let mut emitter: ParticleEmitter = serde_json::from_str(
-            &std::fs::read_to_string(base_path.join("particle/enemy_trail.json")).unwrap(),
-        )
-        .unwrap();
         let enemy_prefab = EnemyPrefab {
             dynamic_body: DynamicBody {
                 impulses: vec![],
@@ -362,9 +334,7 @@ fn gen_kamikaze() {
                 rotation: 0.0,
                 dirty: false,
             },
-            sprite: Sprite {
-                id: "metalic_06.png".to_string(),
-            },
+            sprite: Sprite::new("metalic_06.png".to_string()),
             bounding_box: BoundingBox {
                 half_extend: scale / 2.0 * glam::Vec2::one(),
                 collision_layer: CollisionLayer::ENEMY,
@@ -378,8 +348,9 @@ fn gen_kamikaze() {
                 pickup_drop_percent: 2,
                 movement: MovementBehavior::GoToPlayer,
             },
-            trail: Some(emitter),
+            trail: Some(ParticleEffect::new("enemy_trail.json")),
             animation: None,
+            animation_sheet: None,
         };
 
         let prefab = &enemy_prefab as &dyn Prefab;
@@ -392,13 +363,9 @@ fn gen_kamikaze() {
 fn gen_base_enemy_2() {
     let base_enemy = {
         let scale = 24.0;
-        let base_path = get_assets_path();
-        let mut emitter: ParticleEmitter = serde_json::from_str(
-            &std::fs::read_to_string(base_path.join("particle/enemy_trail.json")).unwrap(),
-        )
-        .unwrap();
         let enemy_prefab = EnemyPrefab {
             animation: None,
+            animation_sheet: None,
             dynamic_body: DynamicBody {
                 impulses: vec![],
                 forces: vec![],
@@ -413,9 +380,7 @@ fn gen_base_enemy_2() {
                 rotation: 0.0,
                 dirty: false,
             },
-            sprite: Sprite {
-                id: "metalic_06.png".to_string(),
-            },
+            sprite: Sprite::new("metalic_06.png".to_string()),
             bounding_box: BoundingBox {
                 half_extend: scale / 2.0 * glam::Vec2::one(),
                 collision_layer: CollisionLayer::ENEMY,
@@ -429,7 +394,7 @@ fn gen_base_enemy_2() {
                 pickup_drop_percent: 5,
                 movement: MovementBehavior::Follow,
             },
-            trail: Some(emitter),
+            trail: Some(ParticleEffect::new("enemy_trail.json")),
         };
 
         let prefab = &enemy_prefab as &dyn Prefab;
@@ -442,13 +407,9 @@ fn gen_base_enemy_2() {
 fn gen_base_enemy_3() {
     let base_enemy = {
         let scale = 24.0;
-        let base_path = get_assets_path();
-        let mut emitter: ParticleEmitter = serde_json::from_str(
-            &std::fs::read_to_string(base_path.join("particle/enemy_trail.json")).unwrap(),
-        )
-        .unwrap();
         let enemy_prefab = EnemyPrefab {
             animation: None,
+            animation_sheet: None,
             dynamic_body: DynamicBody {
                 impulses: vec![],
                 forces: vec![],
@@ -463,9 +424,7 @@ fn gen_base_enemy_3() {
                 rotation: 0.0,
                 dirty: false,
             },
-            sprite: Sprite {
-                id: "darkgrey_04.png".to_string(),
-            },
+            sprite: Sprite::new("darkgrey_04.png".to_string()),
             bounding_box: BoundingBox {
                 half_extend: scale / 2.0 * glam::Vec2::one(),
                 collision_layer: CollisionLayer::ENEMY,
@@ -479,7 +438,7 @@ fn gen_base_enemy_3() {
                 pickup_drop_percent: 10,
                 movement: MovementBehavior::Follow,
             },
-            trail: Some(emitter),
+            trail: Some(ParticleEffect::new("enemy_trail.json")),
         };
 
         let prefab = &enemy_prefab as &dyn Prefab;
@@ -494,6 +453,7 @@ fn gen_spammer() {
         let scale = 32.0;
         let enemy_prefab = EnemyPrefab {
             animation: None,
+            animation_sheet: None,
             dynamic_body: DynamicBody {
                 impulses: vec![],
                 forces: vec![],
@@ -508,9 +468,7 @@ fn gen_spammer() {
                 rotation: 0.0,
                 dirty: false,
             },
-            sprite: Sprite {
-                id: "green_04.png".to_string(),
-            },
+            sprite: Sprite::new("green_04.png".to_string()),
             bounding_box: BoundingBox {
                 half_extend: scale / 2.0 * glam::Vec2::one(),
                 collision_layer: CollisionLayer::ENEMY,
@@ -544,6 +502,7 @@ fn gen_last_boss() {
         let scale = 64.0;
         let enemy_prefab = EnemyPrefab {
             animation: None,
+            animation_sheet: None,
             dynamic_body: DynamicBody {
                 impulses: vec![],
                 forces: vec![],
@@ -558,9 +517,7 @@ fn gen_last_boss() {
                 rotation: 0.0,
                 dirty: false,
             },
-            sprite: Sprite {
-                id: "large_purple_01.png".to_string(),
-            },
+            sprite: Sprite::new("large_purple_01.png".to_string()),
             bounding_box: BoundingBox {
                 half_extend: scale / 2.0 * glam::Vec2::one(),
                 collision_layer: CollisionLayer::ENEMY,
@@ -605,6 +562,7 @@ fn main() {
         let scale = 40.0;
         let enemy_prefab = EnemyPrefab {
             animation: None,
+            animation_sheet: None,
             dynamic_body: DynamicBody {
                 impulses: vec![],
                 forces: vec![],
@@ -619,9 +577,7 @@ fn main() {
                 rotation: 0.0,
                 dirty: false,
             },
-            sprite: Sprite {
-                id: "sat.png".to_string(),
-            },
+            sprite: Sprite::new("sat.png".to_string()),
             bounding_box: BoundingBox {
                 half_extend: scale / 2.0 * glam::Vec2::one(),
                 collision_layer: CollisionLayer::ENEMY,
@@ -652,6 +608,7 @@ fn main() {
         let scale = 64.0;
         let enemy_prefab = EnemyPrefab {
             animation: None,
+            animation_sheet: None,
             dynamic_body: DynamicBody {
                 impulses: vec![],
                 forces: vec![],
@@ -666,9 +623,7 @@ fn main() {
                 rotation: 0.0,
                 dirty: false,
             },
-            sprite: Sprite {
-                id: "large_grey_02.png".to_string(),
-            },
+            sprite: Sprite::new("large_grey_02.png".to_string()),
             bounding_box: BoundingBox {
                 half_extend: scale / 2.0 * glam::Vec2::one(),
                 collision_layer: CollisionLayer::ENEMY,
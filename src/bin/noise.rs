@@ -1,92 +1,163 @@
-#![allow(warnings)]
-use rand::prelude::StdRng;
+//! Render a noise generator to a grayscale PNG, to tweak the parameters before using them in game.
+//!
+//! Usage: noise [options]
+//!
+//! --noise perlin|simplex|worley   generator to use (default: perlin)
+//! --size N                        width and height of the image in pixels (default: 512)
+//! --scale F                       number of noise cells across the image (default: 8)
+//! --seed N                        seed of the generator (default: 0)
+//! --z F                           z coordinate, to get a slice of the 3D noise
+//! --metric euclidean|manhattan|chebyshev   worley distance (default: euclidean)
+//! --cells f1|f2|f2-f1             worley value (default: f1)
+//! --fbm N                         sum N octaves
+//! --ridged N                      ridged multifractal with N octaves
+//! --persistence F                 amplitude multiplier between octaves (default: 0.5)
+//! --lacunarity F                  frequency multiplier between octaves (default: 2)
+//! --warp F                        domain warping strength
+//! --tile                          make the image tileable
+//! --out FILE                      output file (default: noise.png)
+use rand::rngs::StdRng;
 use rand::SeedableRng;
-use spacegame::core::noise::perlin::{make_permutation, perlin2d, Perlin};
-fn main() {
-    let imgx = 2048;
-    let imgy = 2048;
-
-    let nb_blocks = [64, 64];
-    let block_size = [32u32; 32];
-    let image_size = [block_size[0] * nb_blocks[0], block_size[1] * nb_blocks[1]];
-
-    // Create a new ImgBuf with width: imgx and height: imgy
-    let mut imgbuf = image::ImageBuffer::new(image_size[0], image_size[1]);
+use spacegame::core::noise::{
+    DistanceMetric, DomainWarp, Fbm, NoiseFn, Perlin, RidgedMulti, Simplex, Tileable, Worley,
+    WorleyValue,
+};
 
-    let mut rnd = StdRng::from_entropy();
-    let perlin = Perlin::new(&mut rnd);
-
-    let mut max = 0.0f32;
-    let mut min = 0.0f32;
+/// Options from the command line.
+struct Args {
+    noise: String,
+    size: u32,
+    scale: f32,
+    seed: u64,
+    z: Option<f32>,
+    metric: DistanceMetric,
+    cells: WorleyValue,
+    fbm: Option<u32>,
+    ridged: Option<u32>,
+    persistence: f32,
+    lacunarity: f32,
+    warp: Option<f32>,
+    tile: bool,
+    out: String,
+}
 
-    let values = {
-        let w = nb_blocks[0];
-        let h = nb_blocks[1];
-        let mut values = vec![];
-        for x in 0..w {
-            for y in 0..h {
-                let xf = x as f32 / w as f32;
-                let yf = y as f32 / h as f32;
+fn parse_args() -> Args {
+    let mut args = Args {
+        noise: "perlin".to_string(),
+        size: 512,
+        scale: 8.0,
+        seed: 0,
+        z: None,
+        metric: DistanceMetric::Euclidean,
+        cells: WorleyValue::F1,
+        fbm: None,
+        ridged: None,
+        persistence: 0.5,
+        lacunarity: 2.0,
+        warp: None,
+        tile: false,
+        out: "noise.png".to_string(),
+    };
 
-                values.push(perlin.octave_perlin(xf, yf, 2, 0.9));
+    let mut it = std::env::args().skip(1);
+    while let Some(arg) = it.next() {
+        let mut value = || {
+            it.next()
+                .unwrap_or_else(|| panic!("Missing value for {}", arg))
+        };
+        match arg.as_str() {
+            "--noise" => args.noise = value(),
+            "--size" => args.size = value().parse().expect("Size should be an integer"),
+            "--scale" => args.scale = value().parse().expect("Scale should be a number"),
+            "--seed" => args.seed = value().parse().expect("Seed should be an integer"),
+            "--z" => args.z = Some(value().parse().expect("z should be a number")),
+            "--metric" => {
+                args.metric = match value().as_str() {
+                    "euclidean" => DistanceMetric::Euclidean,
+                    "manhattan" => DistanceMetric::Manhattan,
+                    "chebyshev" => DistanceMetric::Chebyshev,
+                    m => panic!("Unknown metric {}", m),
+                }
             }
+            "--cells" => {
+                args.cells = match value().as_str() {
+                    "f1" => WorleyValue::F1,
+                    "f2" => WorleyValue::F2,
+                    "f2-f1" => WorleyValue::F2MinusF1,
+                    c => panic!("Unknown worley value {}", c),
+                }
+            }
+            "--fbm" => args.fbm = Some(value().parse().expect("Octaves should be an integer")),
+            "--ridged" => {
+                args.ridged = Some(value().parse().expect("Octaves should be an integer"))
+            }
+            "--persistence" => {
+                args.persistence = value().parse().expect("Persistence should be a number")
+            }
+            "--lacunarity" => {
+                args.lacunarity = value().parse().expect("Lacunarity should be a number")
+            }
+            "--warp" => args.warp = Some(value().parse().expect("Warp should be a number")),
+            "--tile" => args.tile = true,
+            "--out" => args.out = value(),
+            _ => panic!("Unknown argument {}", arg),
         }
-        values
-    };
-
-    assert_eq!(nb_blocks[0] * nb_blocks[1], values.len() as u32);
-
-    for x in 0..image_size[0] {
-        for y in 0..image_size[1] {
-            let mut pixel = imgbuf.get_pixel_mut(x, y);
-            let block_x = x / nb_blocks[0];
-            let block_y = y / nb_blocks[1];
+    }
+    args
+}
 
-            assert!(block_x < nb_blocks[0]);
-            assert!(block_y < nb_blocks[1]);
+fn build_noise(args: &Args, rng: &mut StdRng) -> Box<dyn NoiseFn> {
+    let mut noise: Box<dyn NoiseFn> = match args.noise.as_str() {
+        "perlin" => Box::new(Perlin::new(rng)),
+        "simplex" => Box::new(Simplex::new(rng)),
+        "worley" => Box::new(
+            Worley::new(rng)
+                .with_metric(args.metric)
+                .with_value(args.cells),
+        ),
+        n => panic!("Unknown noise {}", n),
+    };
 
-            //let perlin = perlin.octave_perlin(xf, yf, 2, 0.9);
-            let idx = (block_x + nb_blocks[0] * block_y) as usize;
-            let pixel_value = values[idx];
-            match pixel_value {
-                0.0..=0.15 => *pixel = image::Rgb([255u8, 0, 0]),
-                0.15..=0.35 => *pixel = image::Rgb([255u8, 133, 0]),
-                0.35..=0.55 => *pixel = image::Rgb([0, 255u8, 0]),
-                _ => *pixel = image::Rgb([0, 0, 255u8]),
-            }
-        }
+    if let Some(octaves) = args.fbm {
+        noise = Box::new(
+            Fbm::new(noise, octaves)
+                .with_persistence(args.persistence)
+                .with_lacunarity(args.lacunarity),
+        );
     }
+    if let Some(octaves) = args.ridged {
+        noise = Box::new(
+            RidgedMulti::new(noise, octaves)
+                .with_persistence(args.persistence)
+                .with_lacunarity(args.lacunarity),
+        );
+    }
+    if let Some(strength) = args.warp {
+        let warp = Fbm::new(Simplex::new(rng), 3);
+        noise = Box::new(DomainWarp::new(noise, warp, strength));
+    }
+    if args.tile {
+        noise = Box::new(Tileable::new(noise, args.scale, args.scale));
+    }
+    noise
+}
 
-    // // Iterate over the coordinates and pixels of the image
-    // for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
-    //     let xf = x as f32 / 2048.0;
-    //     let yf = y as f32 / 2048.0;
-    //
-    //     let block_x = x / nb_blocks[0];
-    //     let block_y = x / nb_blocks[1];
-    //
-    //     assert!(block_x < nb_blocks[0]);
-    //     assert!(block_y < nb_blocks[1]);
-    //
-    //     //let perlin = perlin.octave_perlin(xf, yf, 2, 0.9);
-    //     let idx = (block_x + nb_blocks[0] * block_y) as usize;
-    //     let pixel_value = values[idx];
-    //
-    //     match pixel_value {
-    //         0.0..=0.15 => *pixel = image::Rgb([255u8, 0, 0]),
-    //         0.15..=0.35 => *pixel = image::Rgb([255u8, 133, 0]),
-    //         0.35..=0.55 => *pixel = image::Rgb([0, 255u8, 0]),
-    //         _ => *pixel = image::Rgb([0, 0, 255u8]),
-    //     }
-    //
-    //     // max = max.max(perlin);
-    //     // min = min.min(perlin);
-    //     // let pixel_value = (perlin * 255.0) as u8;
-    //     //*pixel = image::Rgb([pixel_value, pixel_value, pixel_value]);
-    // }
+fn main() {
+    let args = parse_args();
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let noise = build_noise(&args, &mut rng);
 
-    // Save the image as “fractal.png”, the format is deduced from the path
-    imgbuf.save("perlin.png").unwrap();
+    let size = args.size.max(1);
+    let imgbuf = image::ImageBuffer::from_fn(size, size, |x, y| {
+        let xf = x as f32 / size as f32 * args.scale;
+        let yf = y as f32 / size as f32 * args.scale;
+        let value = match args.z {
+            Some(z) => noise.noise3(xf, yf, z),
+            None => noise.noise2(xf, yf),
+        };
+        image::Luma([(value.clamp(0.0, 1.0) * 255.0) as u8])
+    });
 
-    println!("{} to {}", min, max);
+    imgbuf.save(&args.out).unwrap();
+    println!("Saved {}", args.out);
 }
@@ -1,106 +1,214 @@
-#![allow(warnings)]
+//! Put the assets in a pack so that the game does not need the asset folder. See
+//! `spacegame::assets::pack` for the format.
+//!
+//! Usage: pack_assets [options] [FOLDER...]
+//!
+//! FOLDER                 folders or files to pack, relative to the asset folder. All the
+//!                        subfolders are packed too (default: the whole asset folder)
+//! --assets DIR           asset folder (default: ASSET_PATH or assets/)
+//! --out FILE             output file (default: packed.bin). The game reads it when `PACK_PATH`
+//!                        points to it, or includes it with the `packed` feature
+//! --level N              deflate level between 0 and 10, 0 to store the files as they are
+//!                        (default: 6)
+//! --exclude NAME         skip the files and folders with this name, can be repeated
+//! --no-atlases           do not build the atlases, they will be built when the game loads them
+//! --list FILE            print the table of contents of a pack and exit
+//!
+//! The atlases in `atlases/` that are packed are built here, see `spacegame::assets::atlas`.
+//! The stages are checked like when the game loads them, so that a broken stage is not shipped.
+use spacegame::assets::atlas::{layout_name, AtlasBuilder, AtlasSpec};
+use spacegame::assets::pack::{Compression, Pack, PackWriter};
+use spacegame::assets::source::AssetSource;
+use spacegame::assets::sprite::{metadata_name, SpriteAssetMetadata};
+use spacegame::gameplay::level::StageDescription;
+use spacegame::paths::get_assets_path;
+use std::path::{Path, PathBuf};
 
-use downcast_rs::__std::ffi::{OsStr, OsString};
-use log::{debug, error, info};
-use spacegame::assets::sprite::{
-    load_texels, Packed, PackedSpriteAsset, SamplerDef, SpriteAssetMetadata,
-};
-use std::collections::HashMap;
-use std::fs::FileType;
-use std::path::PathBuf;
+/// Options from the command line.
+struct Args {
+    folders: Vec<String>,
+    assets: PathBuf,
+    out: String,
+    level: u8,
+    exclude: Vec<String>,
+    atlases: bool,
+    list: Option<String>,
+}
 
-fn load_metadata(base_path: PathBuf, asset_name: &str) -> SpriteAssetMetadata {
-    let metadata_path = base_path.join(asset_name).with_extension("json");
-    info!(
-        "Will load {:?} metadata at {}",
-        asset_name,
-        metadata_path.display()
-    );
-    let metadata_str = std::fs::read_to_string(metadata_path);
+fn parse_args() -> Args {
+    let mut args = Args {
+        folders: vec![],
+        assets: get_assets_path(),
+        out: "packed.bin".to_string(),
+        level: 6,
+        exclude: vec![],
+        atlases: true,
+        list: None,
+    };
+
+    let mut it = std::env::args().skip(1);
+    while let Some(arg) = it.next() {
+        let mut value = || {
+            it.next()
+                .unwrap_or_else(|| panic!("Missing value for {}", arg))
+        };
+        match arg.as_str() {
+            "--assets" => args.assets = PathBuf::from(value()),
+            "--out" => args.out = value(),
+            "--level" => {
+                args.level = value()
+                    .parse::<u8>()
+                    .expect("Level should be an integer")
+                    .min(10)
+            }
+            "--exclude" => args.exclude.push(value()),
+            "--no-atlases" => args.atlases = false,
+            "--list" => args.list = Some(value()),
+            _ if arg.starts_with("--") => panic!("Unknown argument {}", arg),
+            _ => args.folders.push(arg),
+        }
+    }
+    args
+}
+
+/// Files under the path, relative to the asset folder and with '/' as separator. Hidden files are
+/// skipped.
+fn walk(assets: &Path, path: &Path, exclude: &[String], files: &mut Vec<String>) {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let is_skipped = name.starts_with('.') || exclude.iter().any(|e| e == name);
+    // the asset folder itself is never skipped.
+    if is_skipped && path != assets {
+        return;
+    }
 
-    match metadata_str {
-        Ok(metadata_str) => serde_json::from_str::<SpriteAssetMetadata>(&metadata_str)
-            .unwrap_or_else(|e| {
-                error!(
-                    "Cannot deserialize Metadata file, will use default instead = {:?}",
-                    e
-                );
-                SpriteAssetMetadata::default()
-            }),
-        Err(_) => {
-            info!(
-                "No metadata file for {}, Will use default instead.",
-                asset_name
-            );
-            SpriteAssetMetadata::default()
+    if path.is_dir() {
+        let mut children: Vec<_> = std::fs::read_dir(path)
+            .unwrap_or_else(|e| panic!("Cannot read {} = {}", path.display(), e))
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        // same pack every time.
+        children.sort();
+        for child in children {
+            walk(assets, &child, exclude, files);
         }
+    } else if let Ok(relative) = path.strip_prefix(assets) {
+        let relative: Vec<_> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        files.push(relative.join("/"));
     }
 }
 
-fn get_packed_sprite(asset_name: &str) -> PackedSpriteAsset {
-    let base_path = PathBuf::from("assets/sprites");
-    let asset_path = base_path.join(asset_name);
-    let metadata = load_metadata(base_path.clone(), asset_name);
-    let (w, h, data) = load_texels(asset_path).unwrap();
+/// Build the atlas described in `atlases/<name>.json` and add its pages and layout to the pack.
+fn pack_atlas(writer: &mut PackWriter, args: &Args, name: &str) {
+    let spec_path = args
+        .assets
+        .join("atlases")
+        .join(name)
+        .with_extension("json");
+    let spec: AtlasSpec = serde_json::from_str(&std::fs::read_to_string(&spec_path).unwrap())
+        .unwrap_or_else(|e| panic!("Cannot parse {} = {}", spec_path.display(), e));
+    let sprites = AssetSource::loose(args.assets.join("sprites"));
+    let (layout, pages) = AtlasBuilder::from_spec(&spec, &sprites)
+        .unwrap_or_else(|e| panic!("Cannot build atlas {} = {}", name, e))
+        .build(name);
+
+    let metadata = serde_json::to_vec(&SpriteAssetMetadata {
+        sampler: spec.sampler(),
+    })
+    .unwrap();
+    for (page, image) in layout.pages.iter().zip(pages) {
+        let mut png = vec![];
+        image::DynamicImage::ImageRgba8(image)
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+        writer.add(&format!("sprites/{}", page.sprite), &png, Compression::None);
+        writer.add(
+            &format!("sprites/{}", metadata_name(&page.sprite)),
+            &metadata,
+            Compression::for_path("json", args.level),
+        );
+    }
+    let layout_json = serde_json::to_vec(&layout).unwrap();
+    writer.add(
+        &format!("atlases/{}", layout_name(name)),
+        &layout_json,
+        Compression::for_path("json", args.level),
+    );
+    println!(
+        "Built atlas {} with {} sprites in {} pages",
+        name,
+        layout.regions.len(),
+        layout.pages.len()
+    );
+}
 
-    PackedSpriteAsset {
-        w,
-        h,
-        data,
-        sampler: metadata.sampler,
+fn list(path: &str) {
+    let pack = Pack::open(path).unwrap_or_else(|e| panic!("Cannot open {} = {}", path, e));
+    for (name, entry) in pack.entries() {
+        println!(
+            "{:<60} {:?} {:?} {} -> {} bytes",
+            name, entry.kind, entry.compression, entry.raw_size, entry.size
+        );
     }
 }
 
 fn main() {
-    let dirs = vec![
-        // "./assets/sprites",
-        // "./assets/sprites/background2",
-        // "./assets/sprites/background3",
-        // "./assets/sprites/explosion4",
-        // "./assets/sprites/explosion4",
-        // "./assets/sprites/windshield_wiper",
-        "./assets/sprites/spaceships",
-        "./assets/sprites/spaceships/Projectiles",
-    ];
+    let args = parse_args();
+    if let Some(ref path) = args.list {
+        list(path);
+        return;
+    }
 
-    let to_pack = dirs
-        .iter()
-        .flat_map(|d| {
-            let paths = std::fs::read_dir(d).unwrap();
-            paths
-                // only files.
-                .filter(|p| {
-                    let p = p.as_ref().unwrap();
-                    match p.file_type() {
-                        Ok(t) => t.is_file(),
-                        _ => false,
-                    }
-                })
-                // only PNG
-                .filter(|p| {
-                    let p = p.as_ref().unwrap();
-                    match p.path().extension() {
-                        Some(ext) => ext.to_os_string() == OsString::from("png"),
-                        _ => false,
-                    }
-                })
-                .map(|p| {
-                    p.unwrap().path().display().to_string()["./assets/sprites/".len()..].to_string()
-                })
-        })
-        .collect::<Vec<_>>();
+    let roots = if args.folders.is_empty() {
+        vec![args.assets.clone()]
+    } else {
+        args.folders.iter().map(|f| args.assets.join(f)).collect()
+    };
+
+    let mut files = vec![];
+    for root in &roots {
+        if !root.exists() {
+            panic!("{} does not exist", root.display());
+        }
+        walk(&args.assets, root, &args.exclude, &mut files);
+    }
+    // folders given on the command line can overlap.
+    files.sort();
+    files.dedup();
 
-    let content = {
-        let mut m = HashMap::new();
+    let mut writer = PackWriter::new();
+    let (mut raw_size, mut packed_size) = (0, 0);
+    for file in &files {
+        let content = std::fs::read(args.assets.join(file))
+            .unwrap_or_else(|e| panic!("Cannot read {} = {}", file, e));
+        if let Some(stage) = file.strip_prefix("stages/") {
+            StageDescription::from_json(stage, &String::from_utf8_lossy(&content))
+                .unwrap_or_else(|e| panic!("Cannot pack {} = {}", file, e));
+        }
+        let entry = writer.add(file, &content, Compression::for_path(file, args.level));
+        raw_size += entry.raw_size;
+        packed_size += entry.size;
+    }
 
-        for n in &to_pack {
-            m.insert(n.clone(), get_packed_sprite(n.as_str()));
+    if args.atlases {
+        let specs = files.iter().filter_map(|f| {
+            f.strip_prefix("atlases/")
+                .filter(|name| !name.ends_with(".layout.json"))
+                .and_then(|name| name.strip_suffix(".json"))
+        });
+        for name in specs {
+            pack_atlas(&mut writer, &args, name);
         }
-        //  let n = "spaceships/blue_05.png";
-        m
-    };
-    let packed = Packed { content };
+    }
 
-    let res = bincode::serialize(&packed).unwrap();
-    std::fs::write("packed.bin", res).unwrap();
+    writer.save(&args.out).unwrap();
+    println!(
+        "Packed {} files in {} ({} KiB -> {} KiB)",
+        files.len(),
+        args.out,
+        raw_size / 1024,
+        packed_size / 1024
+    );
 }
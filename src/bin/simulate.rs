@@ -0,0 +1,154 @@
+//! Run the game without a window. The player does not do anything so this is mostly useful to check
+//! that the stages can be played without crashing.
+//!
+//! Usage: simulate [number of steps] [seed] [--check] [--record file] [--replay file] [--profile file]
+//!
+//! With `--check`, two runs with the same seed are executed side by side and the world hashes are
+//! compared after every step. The program exits with an error at the first difference.
+//!
+//! `--record` saves the inputs of the run and `--replay` plays back a recording (the seed of the
+//! recording is used).
+//!
+//! `--profile` saves the timings of the last frames to a CSV file.
+use spacegame::config::{load_config, PlayerConfig};
+use spacegame::core::input::replay::InputRecording;
+use spacegame::core::profiler::Profiler;
+use spacegame::core::random::Seed;
+use spacegame::core::scene::Scene;
+use spacegame::gameplay::inventory::Inventory;
+use spacegame::gameplay::level::difficulty::DifficultyConfig;
+use spacegame::gameplay::Action;
+use spacegame::paths::get_assets_path;
+use spacegame::runner::{GameRunner, GameRunnerBuilder};
+use spacegame::save::read_saved_data;
+use spacegame::scene::loading::LoadingScene;
+use spacegame::scene::{main_scene_manifest, MainScene};
+use std::process::exit;
+use std::time::Instant;
+
+/// Options from the command line.
+struct Args {
+    nb_steps: usize,
+    seed: u64,
+    check: bool,
+    record: Option<String>,
+    replay: Option<String>,
+    profile: Option<String>,
+}
+
+fn parse_args() -> Args {
+    let mut args = Args {
+        nb_steps: 60 * 60,
+        seed: 0,
+        check: false,
+        record: None,
+        replay: None,
+        profile: None,
+    };
+    let mut positional = 0;
+    let mut it = std::env::args().skip(1);
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--check" => args.check = true,
+            "--record" => args.record = it.next(),
+            "--replay" => args.replay = it.next(),
+            "--profile" => args.profile = it.next(),
+            _ if positional == 0 => {
+                args.nb_steps = arg.parse().expect("Number of steps should be an integer");
+                positional += 1;
+            }
+            _ => args.seed = arg.parse().expect("Seed should be an integer"),
+        }
+    }
+    args
+}
+
+fn build_runner(seed: Seed, args: &Args) -> GameRunner<Action> {
+    let base_path = get_assets_path();
+    let player_config: PlayerConfig =
+        load_config(base_path.join("config/player_controller.json")).unwrap_or_default();
+    let difficulty_config: DifficultyConfig =
+        load_config(base_path.join("config/difficulty.json")).unwrap_or_default();
+
+    // Nothing is played or rendered when headless.
+    let mut manifest = main_scene_manifest(false);
+    manifest.sounds.clear();
+    manifest.atlases.clear();
+    let scene: Box<dyn Scene<_>> = Box::new(LoadingScene::new(manifest, MainScene::default()));
+
+    let mut builder = GameRunnerBuilder::new()
+        .for_scene(scene)
+        .with_seed(seed)
+        .with_resource(read_saved_data())
+        .with_resource(player_config)
+        .with_resource(difficulty_config)
+        .with_resource(Inventory::default());
+
+    if args.record.is_some() {
+        builder = builder.with_input_recording();
+    }
+    if let Some(ref path) = args.replay {
+        let recording = InputRecording::load(path).unwrap_or_else(|e| {
+            eprintln!("Cannot load recording {} = {}", path, e);
+            exit(1);
+        });
+        builder = builder.with_input_replay(recording);
+    }
+
+    let runner = builder.build();
+    if args.profile.is_some() {
+        // the entities are only counted when needed.
+        let mut profiler = runner.resources().fetch_mut::<Profiler>().unwrap();
+        profiler.count_entities = true;
+    }
+    runner
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+    pretty_env_logger::init();
+
+    let args = parse_args();
+    let start = Instant::now();
+    let mut runner = build_runner(Seed::from_u64(args.seed), &args);
+    if args.check {
+        let mut other = build_runner(Seed::from_u64(args.seed), &args);
+        for _ in 0..args.nb_steps {
+            let running = runner.step();
+            other.step();
+            if runner.world_hash() != other.world_hash() {
+                eprintln!("Runs are different at frame {}", runner.frame());
+                exit(1);
+            }
+
+            if !running {
+                break;
+            }
+        }
+    } else {
+        runner.run_for(args.nb_steps);
+    }
+
+    if let Some(ref path) = args.record {
+        if let Err(e) = runner.save_recording(path) {
+            eprintln!("Cannot save recording = {}", e);
+            exit(1);
+        }
+    }
+
+    if let Some(ref path) = args.profile {
+        let profiler = runner.resources().fetch::<Profiler>().unwrap();
+        if let Err(e) = profiler.export_csv(path) {
+            eprintln!("Cannot save profiler samples = {}", e);
+            exit(1);
+        }
+    }
+
+    println!(
+        "Ran {} steps in {:?}. {} entities alive. World hash = {:x}",
+        runner.frame(),
+        start.elapsed(),
+        runner.world().iter().count(),
+        runner.world_hash(),
+    );
+}
@@ -0,0 +1,568 @@
+//! Check the assets before running the game. Every prefab, stage, particle emitter, manifest,
+//! atlas, sprite sheet and config file is parsed, and the sprites, sounds, prefabs, particles and
+//! stages they refer to should exist.
+//!
+//! Usage: validate_assets [options]
+//!
+//! --assets DIR           asset folder (default: ASSET_PATH or assets/)
+//! --no-unused            do not report the assets that nothing refers to
+//!
+//! Errors are printed as `file:line: error: message` and the exit code is 1 if there are any.
+//! Cycles in the `next_stage` chains are errors. Unused assets are only warnings, as the game
+//! also uses some of them directly from the code.
+use serde::de::DeserializeOwned;
+use spacegame::assets::atlas::AtlasSpec;
+use spacegame::assets::manifest::AssetManifest;
+use spacegame::assets::prefab::Prefab;
+use spacegame::assets::sheet::SpriteSheet;
+use spacegame::assets::source::AssetSource;
+use spacegame::config::{AudioConfig, GameEngineConfig, InputConfig, PlayerConfig};
+use spacegame::gameplay::level::difficulty::DifficultyConfig;
+use spacegame::gameplay::level::{StageDescription, FIRST_STAGE};
+use spacegame::paths::get_assets_path;
+use spacegame::render::particle::ParticleEmitter;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Options from the command line.
+struct Args {
+    assets: PathBuf,
+    unused: bool,
+}
+
+fn parse_args() -> Args {
+    let mut args = Args {
+        assets: get_assets_path(),
+        unused: true,
+    };
+
+    let mut it = std::env::args().skip(1);
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--assets" => {
+                args.assets = PathBuf::from(
+                    it.next()
+                        .unwrap_or_else(|| panic!("Missing value for {}", arg)),
+                )
+            }
+            "--no-unused" => args.unused = false,
+            _ => panic!("Unknown argument {}", arg),
+        }
+    }
+    args
+}
+
+/// What an asset refers to. The name is relative to the folder of its kind.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    Sprite,
+    /// Relative to the asset folder.
+    Sound,
+    Prefab,
+    Particle,
+    Stage,
+    Shader,
+    Atlas,
+    Sheet,
+}
+
+impl Kind {
+    /// Path of the asset, relative to the asset folder.
+    fn path(self, name: &str) -> String {
+        match self {
+            Kind::Sprite => format!("sprites/{}", name),
+            Kind::Sound => name.to_string(),
+            Kind::Prefab => format!("prefab/{}.json", name),
+            Kind::Particle => format!("particle/{}", name),
+            Kind::Stage => format!("stages/{}", name),
+            Kind::Shader => format!("shaders/{}", name),
+            Kind::Atlas => format!("atlases/{}.json", name),
+            Kind::Sheet => format!("sheets/{}", name),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Kind::Sprite => "sprite",
+            Kind::Sound => "sound",
+            Kind::Prefab => "prefab",
+            Kind::Particle => "particle",
+            Kind::Stage => "stage",
+            Kind::Shader => "shader",
+            Kind::Atlas => "atlas",
+            Kind::Sheet => "sprite sheet",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+struct Diagnostic {
+    /// Relative to the asset folder, or between parentheses for the assets used by the code.
+    file: String,
+    line: Option<usize>,
+    message: String,
+}
+
+/// Everything that was found. The files are relative to the asset folder.
+struct Validator {
+    assets: PathBuf,
+    files: BTreeSet<String>,
+    /// Content of the files that were parsed, to find the line of a reference.
+    contents: BTreeMap<String, String>,
+    /// Kind and name of the asset -> files that refer to it.
+    references: BTreeMap<(Kind, String), BTreeSet<String>>,
+    /// Frames of the sprite sheets, they can be used as sprites.
+    sheet_frames: BTreeSet<String>,
+    next_stages: BTreeMap<String, String>,
+    errors: Vec<Diagnostic>,
+    warnings: Vec<Diagnostic>,
+}
+
+/// Files under the path, relative to the asset folder and with '/' as separator.
+fn walk(assets: &Path, path: &Path, files: &mut BTreeSet<String>) {
+    if path.is_dir() {
+        if let Ok(entries) = std::fs::read_dir(path) {
+            for entry in entries.filter_map(|e| e.ok()) {
+                walk(assets, &entry.path(), files);
+            }
+        }
+    } else if let Ok(relative) = path.strip_prefix(assets) {
+        let relative: Vec<_> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        files.insert(relative.join("/"));
+    }
+}
+
+/// First line where the string appears in the JSON.
+fn line_of(content: &str, value: &str) -> Option<usize> {
+    let quoted = format!("\"{}\"", value);
+    content
+        .lines()
+        .position(|line| line.contains(&quoted))
+        .map(|i| i + 1)
+}
+
+/// Stage -> next stage cycles. Each one is reported once, from its smallest stage and back to it.
+fn stage_cycles(next_stages: &BTreeMap<String, String>) -> Vec<Vec<String>> {
+    let mut cycles = vec![];
+    let mut reported = BTreeSet::new();
+    for start in next_stages.keys() {
+        let mut chain = vec![start.clone()];
+        let mut current = start;
+        while let Some(next) = next_stages.get(current) {
+            if let Some(pos) = chain.iter().position(|s| s == next) {
+                let mut cycle = chain[pos..].to_vec();
+                // same cycle found from another stage.
+                let first = cycle.iter().min().unwrap().clone();
+                if reported.insert(first.clone()) {
+                    let offset = cycle.iter().position(|s| *s == first).unwrap();
+                    cycle.rotate_left(offset);
+                    cycle.push(first);
+                    cycles.push(cycle);
+                }
+                break;
+            }
+            chain.push(next.clone());
+            current = next;
+        }
+    }
+    cycles
+}
+
+impl Validator {
+    fn new(assets: PathBuf) -> Self {
+        let mut files = BTreeSet::new();
+        walk(&assets, &assets, &mut files);
+        Self {
+            assets,
+            files,
+            contents: BTreeMap::new(),
+            references: BTreeMap::new(),
+            sheet_frames: BTreeSet::new(),
+            next_stages: BTreeMap::new(),
+            errors: vec![],
+            warnings: vec![],
+        }
+    }
+
+    fn error(&mut self, file: &str, line: Option<usize>, message: String) {
+        self.errors.push(Diagnostic {
+            file: file.to_string(),
+            line,
+            message,
+        });
+    }
+
+    /// Files directly in a folder with this extension.
+    fn files_in(&self, folder: &str, extension: &str) -> Vec<String> {
+        let prefix = format!("{}/", folder);
+        self.files
+            .iter()
+            .filter(|f| {
+                f.strip_prefix(&prefix)
+                    .map(|name| !name.contains('/') && name.ends_with(extension))
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Read a file and keep its content for the diagnostics.
+    fn read(&mut self, file: &str) -> Option<String> {
+        match std::fs::read_to_string(self.assets.join(file)) {
+            Ok(content) => {
+                self.contents.insert(file.to_string(), content.clone());
+                Some(content)
+            }
+            Err(e) => {
+                self.error(file, None, format!("cannot read = {}", e));
+                None
+            }
+        }
+    }
+
+    /// Parse a file, or add an error at the position serde gives.
+    fn parse<T: DeserializeOwned>(&mut self, file: &str) -> Option<T> {
+        let content = self.read(file)?;
+        match serde_json::from_str(&content) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                let line = Some(e.line()).filter(|&l| l > 0);
+                self.error(file, line, format!("cannot parse = {}", e));
+                None
+            }
+        }
+    }
+
+    fn refer(&mut self, from: &str, kind: Kind, name: &str) {
+        self.references
+            .entry((kind, name.to_string()))
+            .or_default()
+            .insert(from.to_string());
+    }
+
+    fn refer_manifest(&mut self, from: &str, manifest: &AssetManifest) {
+        let kinds = [
+            (Kind::Sprite, &manifest.sprites),
+            (Kind::Prefab, &manifest.prefabs),
+            (Kind::Sound, &manifest.sounds),
+            (Kind::Particle, &manifest.particles),
+            (Kind::Atlas, &manifest.atlases),
+            (Kind::Sheet, &manifest.sheets),
+            (Kind::Stage, &manifest.stages),
+        ];
+        for (kind, names) in kinds.iter() {
+            for name in names.iter() {
+                self.refer(from, *kind, name);
+            }
+        }
+        for (vs, fs) in &manifest.shaders {
+            self.refer(from, Kind::Shader, vs);
+            self.refer(from, Kind::Shader, fs);
+        }
+    }
+
+    fn check_prefabs(&mut self) {
+        for file in self.files_in("prefab", ".json") {
+            let prefab = self.parse::<Box<dyn Prefab>>(&file);
+            if let Some(prefab) = prefab {
+                let mut manifest = AssetManifest::default();
+                prefab.dependencies(&mut manifest);
+                self.refer_manifest(&file, &manifest);
+            }
+        }
+    }
+
+    fn check_stage(&mut self, file: &str, stage: &StageDescription) {
+        // same checks as when the game loads the stage.
+        for problem in stage.problems() {
+            // the problems of a wave cannot be found by name.
+            let line = match problem.split(':').next() {
+                Some(field) if !field.contains(' ') => line_of(&self.contents[file], field),
+                _ => None,
+            };
+            self.error(file, line, problem);
+        }
+        self.refer_manifest(file, &stage.manifest());
+        for background in &stage.backgrounds {
+            self.refer(file, Kind::Sprite, background);
+        }
+        if let Some(ref next) = stage.next_stage {
+            self.refer(file, Kind::Stage, next);
+            let name = file.trim_start_matches("stages/").to_string();
+            self.next_stages.insert(name, next.clone());
+        }
+    }
+
+    fn check_stages(&mut self) {
+        for file in self.files_in("stages", ".json") {
+            if let Some(stage) = self.parse::<StageDescription>(&file) {
+                self.check_stage(&file, &stage);
+            }
+        }
+        // used by the game itself.
+        self.refer("(story mode)", Kind::Stage, FIRST_STAGE);
+        self.refer_manifest("(infinite mode)", &StageDescription::infinite().manifest());
+        for background in &StageDescription::infinite().backgrounds {
+            self.refer("(infinite mode)", Kind::Sprite, background);
+        }
+    }
+
+    /// Follow the `next_stage` of every stage. A stage that comes back to itself never ends.
+    fn check_stage_cycles(&mut self) {
+        for cycle in stage_cycles(&self.next_stages) {
+            let first = &cycle[0];
+            let file = format!("stages/{}", first);
+            let line = self
+                .contents
+                .get(&file)
+                .and_then(|c| line_of(c, &self.next_stages[first]));
+            self.errors.push(Diagnostic {
+                file,
+                line,
+                message: format!("stages never end = {}", cycle.join(" -> ")),
+            });
+        }
+    }
+
+    fn check_particles(&mut self) {
+        for file in self.files_in("particle", ".json") {
+            if let Some(emitter) = self.parse::<ParticleEmitter>(&file) {
+                let mut manifest = AssetManifest::default();
+                manifest.add_emitter(&emitter);
+                self.refer_manifest(&file, &manifest);
+            }
+        }
+    }
+
+    fn check_manifests(&mut self) {
+        for file in self.files_in("manifests", ".json") {
+            if let Some(manifest) = self.parse::<AssetManifest>(&file) {
+                self.refer_manifest(&file, &manifest);
+            }
+        }
+    }
+
+    fn check_atlases(&mut self) {
+        let sprites = AssetSource::loose(self.assets.join("sprites"));
+        let specs: Vec<_> = self
+            .files_in("atlases", ".json")
+            .into_iter()
+            .filter(|f| !f.ends_with(".layout.json"))
+            .collect();
+        for file in specs {
+            if let Some(spec) = self.parse::<AtlasSpec>(&file) {
+                for folder in spec.sprites.iter().filter(|s| s.ends_with('/')) {
+                    if !sprites.path(folder).is_dir() {
+                        let line = line_of(&self.contents[&file], folder);
+                        self.error(&file, line, format!("cannot find sprite folder {}", folder));
+                    }
+                }
+                for sprite in spec.sprite_names(&sprites) {
+                    self.refer(&file, Kind::Sprite, &sprite);
+                }
+            }
+        }
+    }
+
+    fn check_sheets(&mut self) {
+        for file in self.files_in("sheets", ".json") {
+            let name = file.trim_start_matches("sheets/").to_string();
+            let sheet = match self.read(&file) {
+                Some(content) => SpriteSheet::from_json(&name, &content),
+                None => continue,
+            };
+            match sheet {
+                Ok(sheet) => {
+                    self.refer(&file, Kind::Sprite, sheet.image());
+                    self.sheet_frames
+                        .extend(sheet.layout.regions.keys().cloned());
+                }
+                Err(e) => self.error(&file, None, format!("cannot parse = {}", e)),
+            }
+        }
+    }
+
+    /// Known config files are parsed with their type, the others only have to be JSON.
+    fn check_configs(&mut self) {
+        for file in self.files_in("config", ".json") {
+            let name = file.trim_start_matches("config/");
+            let _ = match name {
+                "player_controller.json" => self.parse::<PlayerConfig>(&file).map(|_| ()),
+                "engine.json" => self.parse::<GameEngineConfig>(&file).map(|_| ()),
+                "difficulty.json" => self.parse::<DifficultyConfig>(&file).map(|_| ()),
+                "input.json" => self.parse::<InputConfig>(&file).map(|_| ()),
+                "audio.json" => self.parse::<AudioConfig>(&file).map(|_| ()),
+                _ => self.parse::<serde_json::Value>(&file).map(|_| ()),
+            };
+        }
+    }
+
+    fn exists(&self, kind: Kind, name: &str) -> bool {
+        if kind == Kind::Sprite && self.sheet_frames.contains(name) {
+            return true;
+        }
+        self.files.contains(&kind.path(name))
+    }
+
+    fn check_references(&mut self) {
+        let mut missing = vec![];
+        for ((kind, name), from) in &self.references {
+            if self.exists(*kind, name) {
+                continue;
+            }
+            for file in from {
+                let line = self.contents.get(file).and_then(|c| line_of(c, name));
+                missing.push(Diagnostic {
+                    file: file.clone(),
+                    line,
+                    message: format!("cannot find {} {}", kind, name),
+                });
+            }
+        }
+        self.errors.extend(missing);
+    }
+
+    /// Files in the asset folders that nothing refers to.
+    fn check_unused(&mut self) {
+        let is_referenced =
+            |kind: Kind, name: &str| self.references.contains_key(&(kind, name.to_string()));
+        let mut unused = vec![];
+        for file in &self.files {
+            let (folder, name) = match file.find('/') {
+                Some(i) => (&file[..i], &file[i + 1..]),
+                None => continue,
+            };
+            let used = match folder {
+                // the other files of the sprites folder are metadata.
+                "sprites" if name.ends_with(".png") => is_referenced(Kind::Sprite, name),
+                "sounds" | "music" if is_audio(name) => is_referenced(Kind::Sound, file),
+                "prefab" => is_referenced(Kind::Prefab, name.trim_end_matches(".json")),
+                "particle" => is_referenced(Kind::Particle, name),
+                "stages" => is_referenced(Kind::Stage, name),
+                "sheets" => is_referenced(Kind::Sheet, name),
+                _ => true,
+            };
+            if !used {
+                unused.push(Diagnostic {
+                    file: file.clone(),
+                    line: None,
+                    message: "not used by any asset".to_string(),
+                });
+            }
+        }
+        self.warnings.extend(unused);
+    }
+}
+
+fn is_audio(name: &str) -> bool {
+    let ext = Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase());
+    matches!(ext.as_deref(), Some("wav") | Some("ogg") | Some("mp3"))
+}
+
+fn print(diagnostics: &[Diagnostic], level: &str, assets: &Path) {
+    for d in diagnostics {
+        let path = if d.file.starts_with('(') {
+            PathBuf::from(&d.file)
+        } else {
+            assets.join(&d.file)
+        };
+        match d.line {
+            Some(line) => println!("{}:{}: {}: {}", path.display(), line, level, d.message),
+            None => println!("{}: {}: {}", path.display(), level, d.message),
+        }
+    }
+}
+
+fn main() {
+    let args = parse_args();
+    if !args.assets.is_dir() {
+        eprintln!("{} is not a folder", args.assets.display());
+        std::process::exit(2);
+    }
+
+    let mut validator = Validator::new(args.assets.clone());
+    // the sheets first, their frames are sprites for the others.
+    validator.check_sheets();
+    validator.check_prefabs();
+    validator.check_stages();
+    validator.check_stage_cycles();
+    validator.check_particles();
+    validator.check_manifests();
+    validator.check_atlases();
+    validator.check_configs();
+    validator.check_references();
+    if args.unused {
+        validator.check_unused();
+    }
+
+    print(&validator.warnings, "warning", &args.assets);
+    print(&validator.errors, "error", &args.assets);
+    println!(
+        "Checked {} files: {} errors, {} warnings",
+        validator.files.len(),
+        validator.errors.len(),
+        validator.warnings.len()
+    );
+    if !validator.errors.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn next_stages(links: &[(&str, &str)]) -> BTreeMap<String, String> {
+        links
+            .iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn chain_without_cycle() {
+        let links = next_stages(&[
+            ("stage1.json", "stage2.json"),
+            ("stage2.json", "stage3.json"),
+        ]);
+        assert!(stage_cycles(&links).is_empty());
+    }
+
+    #[test]
+    fn stage_that_comes_back_to_itself() {
+        let links = next_stages(&[("stage1.json", "stage1.json")]);
+        assert_eq!(
+            vec![vec!["stage1.json", "stage1.json"]],
+            stage_cycles(&links)
+        );
+    }
+
+    #[test]
+    fn cycle_is_reported_once_from_its_smallest_stage() {
+        // stage0 leads to the cycle without being part of it.
+        let links = next_stages(&[
+            ("stage0.json", "stage2.json"),
+            ("stage2.json", "stage3.json"),
+            ("stage3.json", "stage1.json"),
+            ("stage1.json", "stage2.json"),
+            ("other_a.json", "other_b.json"),
+            ("other_b.json", "other_a.json"),
+        ]);
+        assert_eq!(
+            vec![
+                vec!["other_a.json", "other_b.json", "other_a.json"],
+                vec!["stage1.json", "stage2.json", "stage3.json", "stage1.json"],
+            ],
+            stage_cycles(&links)
+        );
+    }
+}
@@ -1,5 +1,8 @@
+use crate::assets::source::AssetSource;
 use crate::core::input::ser::Input;
+use crate::core::window::ScaleMode;
 use crate::gameplay::Action;
+use crate::paths::get_assets_path;
 use glfw::{Key, MouseButton};
 use serde::de::DeserializeOwned;
 use serde_derive::{Deserialize, Serialize};
@@ -7,11 +10,16 @@ use std::collections::HashMap;
 use std::error::Error;
 use std::path::Path;
 
+/// The configs in the asset folder are read from the asset pack if there is one.
 pub fn load_config<T, P: AsRef<Path>>(path: P) -> Result<T, Box<dyn Error>>
 where
     T: DeserializeOwned,
 {
-    let content = std::fs::read_to_string(path)?;
+    let path = path.as_ref();
+    let content = match path.strip_prefix(get_assets_path()) {
+        Ok(name) => AssetSource::new().read_to_string(&name.to_string_lossy())?,
+        Err(_) => std::fs::read_to_string(path)?,
+    };
     serde_json::from_str(&content).map_err(|e| e.into())
 }
 
@@ -39,6 +47,13 @@ impl PlayerConfig {
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct GameEngineConfig {
     pub show_gizmos: bool,
+    /// How the game is scaled when the window is resized.
+    #[serde(default)]
+    pub scale_mode: ScaleMode,
+    /// Texture memory in MiB before the least recently used sprites are unloaded. The game uses
+    /// its own default if not set.
+    #[serde(default)]
+    pub texture_budget_mb: Option<usize>,
 }
 
 #[derive(Default, Debug, Serialize, Deserialize)]
@@ -60,7 +75,7 @@ impl InputConfig {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AudioConfig {
     pub background_volume: u32,
     pub effects_volume: u32,
@@ -1,10 +1,13 @@
-use crate::event::GameEvent;
+use crate::assets::sheet::SpriteSheets;
+use crate::core::system::System;
+use crate::event::Delete;
 use crate::render::sprite::Sprite;
 use crate::resources::Resources;
 use log::error;
 use serde_derive::{Deserialize, Serialize};
 use shrev::EventChannel;
 use std::collections::HashMap;
+use std::time::Duration;
 
 /// One animation (in one spreadsheet).
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -47,10 +50,57 @@ pub struct AnimationController {
     pub delete_on_finished: bool,
 }
 
+impl AnimationController {
+    /// Sprites of all the keyframes.
+    pub fn sprites(&self) -> impl Iterator<Item = &String> {
+        self.animations
+            .values()
+            .flat_map(|animation| animation.keyframes.iter().map(|(sprite, _)| sprite))
+    }
+}
+
+/// Animate the entity with a sprite sheet from `assets/sheets` (see `assets::sheet`). The
+/// animation system replaces it by the `AnimationController` of the sheet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimationSheet(pub String);
+
 pub struct AnimationSystem;
 
 impl AnimationSystem {
+    /// Add the controllers of the entities that only have a sprite sheet. The sheets are loaded
+    /// with the manifest of the scene or stage (see `AssetManifest::add_sheet`), files are not read
+    /// during the update.
+    fn attach_sheets(&mut self, world: &mut hecs::World, resources: &Resources) {
+        let to_attach: Vec<_> = world
+            .query::<&AnimationSheet>()
+            .without::<AnimationController>()
+            .iter()
+            .map(|(e, sheet)| (e, sheet.0.clone()))
+            .collect();
+        if to_attach.is_empty() {
+            return;
+        }
+
+        let sheets = resources.fetch::<SpriteSheets>().unwrap();
+        for (entity, name) in to_attach {
+            match sheets.get(&name) {
+                Some(sheet) => {
+                    let _ = world.insert_one(entity, sheet.controller());
+                }
+                None => {
+                    error!(
+                        "Sprite sheet {} is not loaded, is it in the manifest?",
+                        name
+                    );
+                    let _ = world.remove_one::<AnimationSheet>(entity);
+                }
+            }
+        }
+    }
+
     pub fn animate(&mut self, world: &mut hecs::World, resources: &Resources) {
+        self.attach_sheets(world, resources);
+
         let mut events = vec![];
         for (e, (controller, sprite)) in world
             .query::<(&mut AnimationController, &mut Sprite)>()
@@ -65,7 +115,7 @@ impl AnimationSystem {
                         animation.elapsed_frame = 0;
 
                         if animation.last_frame() && controller.delete_on_finished {
-                            events.push(GameEvent::Delete(e));
+                            events.push(Delete(e));
                         }
                         animation.current_index =
                             (animation.current_index + 1) % animation.keyframes.len();
@@ -77,8 +127,14 @@ impl AnimationSystem {
         }
 
         {
-            let mut channel = resources.fetch_mut::<EventChannel<GameEvent>>().unwrap();
+            let mut channel = resources.fetch_mut::<EventChannel<Delete>>().unwrap();
             channel.drain_vec_write(&mut events);
         }
     }
 }
+
+impl System for AnimationSystem {
+    fn run(&mut self, world: &mut hecs::World, resources: &Resources, _dt: Duration) {
+        self.animate(world, resources);
+    }
+}
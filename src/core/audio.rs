@@ -1,7 +1,7 @@
 use crate::assets::audio::Audio;
 use crate::assets::{AssetManager, Handle};
 use crate::config::AudioConfig;
-use crate::event::GameEvent;
+use crate::event::{PlayBackgroundMusic, PlaySound};
 use crate::resources::Resources;
 use luminance_glfw::GlfwSurface;
 use shrev::{EventChannel, ReaderId};
@@ -18,7 +18,8 @@ pub struct AudioSystem {
     /// Sinks for sound
     sound_sinks: Vec<rodio::Sink>,
 
-    rdr_id: ReaderId<GameEvent>,
+    music_rdr_id: ReaderId<PlayBackgroundMusic>,
+    sound_rdr_id: ReaderId<PlaySound>,
 
     config: AudioConfig,
 }
@@ -36,7 +37,14 @@ impl AudioSystem {
                 sink
             });
         }
-        let mut channel = resources.fetch_mut::<EventChannel<GameEvent>>().unwrap();
+        let music_rdr_id = resources
+            .fetch_mut::<EventChannel<PlayBackgroundMusic>>()
+            .unwrap()
+            .register_reader();
+        let sound_rdr_id = resources
+            .fetch_mut::<EventChannel<PlaySound>>()
+            .unwrap()
+            .register_reader();
 
         Ok(Self {
             config,
@@ -45,71 +53,94 @@ impl AudioSystem {
             sound_sinks,
             background,
             current_background: None,
-            rdr_id: channel.register_reader(),
+            music_rdr_id,
+            sound_rdr_id,
         })
     }
 
+    /// Apply the `AudioConfig` resource if it changed (e.g. hot reload or options menu).
+    fn apply_config(&mut self, resources: &Resources) {
+        let config = match resources.fetch::<AudioConfig>() {
+            Some(config) if *config != self.config => config.clone(),
+            _ => return,
+        };
+
+        self.background
+            .set_volume(config.background_volume as f32 / 100.0);
+        if config.channel_nb < self.sound_sinks.len() {
+            self.sound_sinks.truncate(config.channel_nb);
+        }
+        while self.sound_sinks.len() < config.channel_nb {
+            match rodio::Sink::try_new(&self.handle) {
+                Ok(sink) => self.sound_sinks.push(sink),
+                Err(e) => {
+                    error!("Cannot create sound sink = {}", e);
+                    break;
+                }
+            }
+        }
+        for sink in &self.sound_sinks {
+            sink.set_volume(config.effects_volume as f32 / 100.0);
+        }
+
+        info!("Applied audio config = {:?}", config);
+        self.config = config;
+    }
+
     pub fn process(&mut self, resources: &Resources) {
-        let channel = resources.fetch::<EventChannel<GameEvent>>().unwrap();
+        self.apply_config(resources);
+
+        let music_channel = resources
+            .fetch::<EventChannel<PlayBackgroundMusic>>()
+            .unwrap();
+        let sound_channel = resources.fetch::<EventChannel<PlaySound>>().unwrap();
         let audio_manager = resources
             .fetch::<AssetManager<GlfwSurface, Audio>>()
             .unwrap();
-        for ev in channel.read(&mut self.rdr_id) {
-            match ev {
-                GameEvent::PlayBackgroundMusic(name) => {
-                    if let Some(asset) = audio_manager.get(&Handle(name.to_string())) {
-                        self.current_background = Some(name.to_string());
-                        if !self.background.empty() {
-                            self.background.stop();
-                            self.background = rodio::Sink::try_new(&self.handle)
-                                .expect("SHould be able to create new sink");
-                            self.background
-                                .set_volume(self.config.background_volume as f32 / 100.0);
-                        }
+        for PlayBackgroundMusic(name) in music_channel.read(&mut self.music_rdr_id) {
+            if let Some(asset) = audio_manager.get(&Handle(name.to_string())) {
+                self.current_background = Some(name.to_string());
+                if !self.background.empty() {
+                    self.background.stop();
+                    self.background = rodio::Sink::try_new(&self.handle)
+                        .expect("SHould be able to create new sink");
+                    self.background
+                        .set_volume(self.config.background_volume as f32 / 100.0);
+                }
 
-                        asset.execute(|audio| {
-                            info!("Could load asset");
+                asset.execute(|audio| {
+                    info!("Could load asset");
 
-                            if let Audio::File(content) = audio {
-                                self.background.append(
-                                    rodio::Decoder::new(BufReader::new(Cursor::new(
-                                        content.clone(),
-                                    )))
-                                    .unwrap(),
-                                );
+                    if let Audio::File(content) = audio {
+                        self.background.append(
+                            rodio::Decoder::new(BufReader::new(Cursor::new(content.clone())))
+                                .unwrap(),
+                        );
 
-                                self.background.play();
-                            }
-                        });
-                    } else {
-                        error!("No asset with name: {}", name);
+                        self.background.play();
                     }
-                }
-                GameEvent::PlaySound(name) => {
-                    if let Some(asset) = audio_manager.get(&Handle(name.to_string())) {
-                        asset.execute(|audio| {
-                            if let Audio::File(content) = audio {
-                                // get the first available channel.
-                                let sink = self
-                                    .sound_sinks
-                                    .iter_mut()
-                                    .filter(|sink| sink.empty())
-                                    .next();
-                                if let Some(s) = sink {
-                                    s.append(
-                                        rodio::Decoder::new(BufReader::new(Cursor::new(
-                                            content.clone(),
-                                        )))
-                                        .unwrap(),
-                                    );
-                                }
-                            }
-                        });
-                    } else {
-                        error!("No asset with name: {}", name);
+                });
+            } else {
+                error!("No asset with name: {}", name);
+            }
+        }
+
+        for PlaySound(name) in sound_channel.read(&mut self.sound_rdr_id) {
+            if let Some(asset) = audio_manager.get(&Handle(name.to_string())) {
+                asset.execute(|audio| {
+                    if let Audio::File(content) = audio {
+                        // get the first available channel.
+                        let sink = self.sound_sinks.iter_mut().find(|sink| sink.empty());
+                        if let Some(s) = sink {
+                            s.append(
+                                rodio::Decoder::new(BufReader::new(Cursor::new(content.clone())))
+                                    .unwrap(),
+                            );
+                        }
                     }
-                }
-                _ => (),
+                });
+            } else {
+                error!("No asset with name: {}", name);
             }
         }
 
@@ -135,11 +166,9 @@ impl AudioSystem {
 }
 
 pub fn play_background_music(resources: &Resources, name: &str) {
-    let mut channel = resources.fetch_mut::<EventChannel<GameEvent>>().unwrap();
-    channel.single_write(GameEvent::PlayBackgroundMusic(name.to_string()));
+    resources.write_event(PlayBackgroundMusic(name.to_string()));
 }
 
 pub fn play_sound(resources: &Resources, name: &str) {
-    let mut channel = resources.fetch_mut::<EventChannel<GameEvent>>().unwrap();
-    channel.single_write(GameEvent::PlaySound(name.to_string()));
+    resources.write_event(PlaySound(name.to_string()));
 }
@@ -1,3 +1,4 @@
+use crate::core::transform::PreviousTransforms;
 use glam::Vec2;
 use hecs::World;
 
@@ -7,6 +8,9 @@ use hecs::World;
 pub struct Camera {
     pub main: bool,
     pub position: glam::Vec2,
+    /// Screen shake. It is only added for the rendering, the gameplay (e.g. aiming) uses the
+    /// position without it.
+    pub shake: glam::Vec2,
 }
 
 impl Camera {
@@ -14,6 +18,7 @@ impl Camera {
         Self {
             main: true,
             position: Vec2::zero(),
+            shake: Vec2::zero(),
         }
     }
 
@@ -21,6 +26,16 @@ impl Camera {
         glam::Mat4::look_at_rh(self.eye(), self.position.extend(0.0), glam::Vec3::unit_y())
     }
 
+    /// Same as `to_view` with the shake.
+    pub fn to_render_view(&self) -> glam::Mat4 {
+        let position = self.position + self.shake;
+        glam::Mat4::look_at_rh(
+            position.extend(1.0),
+            position.extend(0.0),
+            glam::Vec3::unit_y(),
+        )
+    }
+
     pub fn eye(&self) -> glam::Vec3 {
         self.position.extend(1.0)
     }
@@ -34,6 +49,26 @@ pub fn get_view_matrix(world: &World) -> Option<glam::Mat4> {
         .next()
 }
 
+/// View matrix of the main camera, between the previous and the current fixed update.
+pub fn get_interpolated_view_matrix(
+    world: &World,
+    previous: &PreviousTransforms,
+    alpha: f32,
+) -> Option<glam::Mat4> {
+    world
+        .query::<&Camera>()
+        .iter()
+        .map(|(e, c)| {
+            Camera {
+                main: c.main,
+                position: previous.interpolate_camera(e, c.position, alpha),
+                shake: c.shake,
+            }
+            .to_render_view()
+        })
+        .next()
+}
+
 pub fn screen_to_world(
     screen_coords: glam::Vec2,
     projection_matrix: glam::Mat4,
@@ -46,15 +81,53 @@ pub fn screen_to_world(
     glam::vec2(mouse_pos_world.x, mouse_pos_world.y)
 }
 
+/// Orthographic projection. The zoom is done around the center of the screen.
 #[derive(Copy, Clone, Debug)]
-pub struct ProjectionMatrix(pub(crate) glam::Mat4);
+pub struct ProjectionMatrix {
+    matrix: glam::Mat4,
+    width: f32,
+    height: f32,
+    zoom: f32,
+}
 
 impl ProjectionMatrix {
     pub fn new(w: f32, h: f32) -> Self {
-        Self(glam::Mat4::orthographic_rh_gl(0.0, w, 0.0, h, -1.0, 10.0))
+        let mut projection = Self {
+            matrix: glam::Mat4::identity(),
+            width: w,
+            height: h,
+            zoom: 1.0,
+        };
+        projection.update();
+        projection
     }
 
     pub fn resize(&mut self, w: f32, h: f32) {
-        self.0 = glam::Mat4::orthographic_rh_gl(0.0, w, 0.0, h, -1.0, 10.0);
+        self.width = w;
+        self.height = h;
+        self.update();
+    }
+
+    /// More than 1 to zoom in.
+    pub fn set_zoom(&mut self, zoom: f32) {
+        if zoom > 0.0 && (zoom - self.zoom).abs() > f32::EPSILON {
+            self.zoom = zoom;
+            self.update();
+        }
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn matrix(&self) -> glam::Mat4 {
+        self.matrix
+    }
+
+    fn update(&mut self) {
+        let (cx, cy) = (self.width / 2.0, self.height / 2.0);
+        let (hw, hh) = (cx / self.zoom, cy / self.zoom);
+        self.matrix =
+            glam::Mat4::orthographic_rh_gl(cx - hw, cx + hw, cy - hh, cy + hh, -1.0, 10.0);
     }
 }
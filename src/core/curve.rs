@@ -1,6 +1,23 @@
 //! Curve used for interpolation (e.g. gradients)
+//!
+//! A curve is a list of points. Each segment between two points has its own interpolation mode
+//! (linear by default) and the wrap mode tells what happens outside of the first and last point.
+//!
+//! The modes are optional in JSON so the old curves still work:
+//! ```json
+//! {
+//!     "xs": [0.0, 0.5, 1.0],
+//!     "ys": [0.0, 1.0, 0.0],
+//!     "modes": ["Smoothstep", {"Ease": "BackOut"}],
+//!     "wrap": "PingPong"
+//! }
+//! ```
+//!
+//! The points are checked when the curve is created or deserialized, so sampling does not have to.
 use serde_derive::{Deserialize, Serialize};
+use std::convert::TryFrom;
 use std::fmt::Debug;
+use thiserror::Error;
 
 pub trait CurveNode:
     Copy
@@ -12,13 +29,168 @@ pub trait CurveNode:
 {
 }
 
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum CurveError {
+    #[error("Curve has no points")]
+    Empty,
+
+    #[error("Curve has {xs} xs but {ys} ys")]
+    LengthMismatch { xs: usize, ys: usize },
+
+    #[error("Curve xs should be in increasing order (x[{0}] is smaller than the previous one)")]
+    NotSorted(usize),
+}
+
+/// How to go from a point to the next one.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Interpolation {
+    /// Keep the value of the first point until the next one.
+    Step,
+    Linear,
+    Smoothstep,
+    /// Cubic Hermite spline with the tangents of Catmull-Rom, so the curve goes smoothly through
+    /// the points.
+    CatmullRom,
+    Ease(Easing),
+}
+
+impl Default for Interpolation {
+    fn default() -> Self {
+        Interpolation::Linear
+    }
+}
+
+/// The usual easing functions. See https://easings.net
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Easing {
+    QuadIn,
+    QuadOut,
+    CubicIn,
+    CubicOut,
+    ElasticIn,
+    ElasticOut,
+    BackIn,
+    BackOut,
+}
+
+impl Easing {
+    /// Ease s between 0 and 1. Elastic and back go a bit outside of [0, 1].
+    pub fn apply(self, s: f32) -> f32 {
+        use std::f32::consts::PI;
+        const BACK: f32 = 1.70158;
+        match self {
+            Easing::QuadIn => s * s,
+            Easing::QuadOut => 1.0 - (1.0 - s) * (1.0 - s),
+            Easing::CubicIn => s * s * s,
+            Easing::CubicOut => 1.0 - (1.0 - s).powi(3),
+            Easing::ElasticIn => {
+                if s <= 0.0 || s >= 1.0 {
+                    s
+                } else {
+                    -(2.0f32.powf(10.0 * s - 10.0)) * ((s * 10.0 - 10.75) * 2.0 * PI / 3.0).sin()
+                }
+            }
+            Easing::ElasticOut => {
+                if s <= 0.0 || s >= 1.0 {
+                    s
+                } else {
+                    2.0f32.powf(-10.0 * s) * ((s * 10.0 - 0.75) * 2.0 * PI / 3.0).sin() + 1.0
+                }
+            }
+            Easing::BackIn => (BACK + 1.0) * s * s * s - BACK * s * s,
+            Easing::BackOut => {
+                let s = s - 1.0;
+                1.0 + (BACK + 1.0) * s * s * s + BACK * s * s
+            }
+        }
+    }
+}
+
+/// What happens before the first point and after the last point.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WrapMode {
+    /// Keep the value of the first/last point.
+    Clamp,
+    /// Start again from the first point.
+    Loop,
+    /// Go back and forth.
+    PingPong,
+}
+
+impl Default for WrapMode {
+    fn default() -> Self {
+        WrapMode::Clamp
+    }
+}
+
+impl WrapMode {
+    fn is_clamp(&self) -> bool {
+        *self == WrapMode::Clamp
+    }
+
+    /// Bring t back between start and end.
+    fn wrap(self, t: f32, start: f32, end: f32) -> f32 {
+        let span = end - start;
+        if span <= 0.0 {
+            return start;
+        }
+        match self {
+            WrapMode::Clamp => t.max(start).min(end),
+            WrapMode::Loop => start + (t - start).rem_euclid(span),
+            WrapMode::PingPong => {
+                let r = (t - start).rem_euclid(2.0 * span);
+                if r > span {
+                    start + 2.0 * span - r
+                } else {
+                    start + r
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "CurveData<T>")]
 pub struct Curve<T>
 where
     T: CurveNode,
 {
     xs: Vec<f32>,
     ys: Vec<T>,
+    /// Interpolation of each segment. Missing segments are linear.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    modes: Vec<Interpolation>,
+    #[serde(default, skip_serializing_if = "WrapMode::is_clamp")]
+    wrap: WrapMode,
+}
+
+/// Curve as it is in the files, before it is checked.
+#[derive(Deserialize)]
+struct CurveData<T> {
+    xs: Vec<f32>,
+    ys: Vec<T>,
+    #[serde(default)]
+    modes: Vec<Interpolation>,
+    #[serde(default)]
+    wrap: WrapMode,
+}
+
+impl<T> TryFrom<CurveData<T>> for Curve<T>
+where
+    T: CurveNode,
+{
+    type Error = CurveError;
+
+    fn try_from(data: CurveData<T>) -> Result<Self, Self::Error> {
+        let curve = Self {
+            xs: data.xs,
+            ys: data.ys,
+            modes: data.modes,
+            wrap: data.wrap,
+        };
+        curve.validate()?;
+        Ok(curve)
+    }
 }
 
 impl<T> Default for Curve<T>
@@ -29,6 +201,8 @@ where
         Self {
             xs: vec![],
             ys: vec![],
+            modes: vec![],
+            wrap: WrapMode::Clamp,
         }
     }
 }
@@ -37,9 +211,62 @@ impl<T> Curve<T>
 where
     T: CurveNode,
 {
-    pub fn y(&self, t: f32) -> T {
-        // why use a curve otherwise.
-        assert!(self.xs.len() == self.ys.len() && !self.ys.is_empty());
+    /// Create a linear curve. xs should be sorted.
+    pub fn new(xs: Vec<f32>, ys: Vec<T>) -> Result<Self, CurveError> {
+        let curve = Self {
+            xs,
+            ys,
+            ..Self::default()
+        };
+        curve.validate()?;
+        Ok(curve)
+    }
+
+    /// Set the interpolation of all the segments.
+    pub fn with_interpolation(mut self, mode: Interpolation) -> Self {
+        self.modes = vec![mode; self.xs.len().saturating_sub(1)];
+        self
+    }
+
+    /// Set the interpolation between the point `segment` and the next one.
+    pub fn set_segment_interpolation(&mut self, segment: usize, mode: Interpolation) {
+        if self.modes.len() <= segment {
+            self.modes.resize(segment + 1, Interpolation::Linear);
+        }
+        self.modes[segment] = mode;
+    }
+
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Check that the curve can be sampled.
+    pub fn validate(&self) -> Result<(), CurveError> {
+        if self.xs.len() != self.ys.len() {
+            return Err(CurveError::LengthMismatch {
+                xs: self.xs.len(),
+                ys: self.ys.len(),
+            });
+        }
+        if self.xs.is_empty() {
+            return Err(CurveError::Empty);
+        }
+        if let Some(i) = (1..self.xs.len()).find(|&i| self.xs[i] < self.xs[i - 1]) {
+            return Err(CurveError::NotSorted(i));
+        }
+        Ok(())
+    }
+
+    /// Value of the curve at t. The points were checked when the curve was created, so only the
+    /// default (empty) curve cannot be sampled.
+    pub fn y(&self, t: f32) -> Result<T, CurveError> {
+        if self.xs.is_empty() {
+            return Err(CurveError::Empty);
+        }
+
+        let last = self.xs.len() - 1;
+        let t = self.wrap.wrap(t, self.xs[0], self.xs[last]);
 
         // First find the x corresponding to this t. (lower bound)
         let mut idx = 0usize;
@@ -51,21 +278,170 @@ where
             idx = i;
         }
 
-        let lower_y = unsafe { *self.ys.get_unchecked(idx) };
-        if idx == self.ys.len() - 1 {
-            lower_y
+        let lower_y = self.ys[idx];
+        if idx == last {
+            return Ok(lower_y);
+        }
+
+        let lower_t = self.xs[idx];
+        let higher_t = self.xs[idx + 1];
+        let higher_y = self.ys[idx + 1];
+        let s = if higher_t > lower_t {
+            (t - lower_t) / (higher_t - lower_t)
         } else {
-            let lower_t: f32 = *unsafe { self.xs.get_unchecked(idx) };
-            let higher_t: f32 = *unsafe { self.xs.get_unchecked(idx + 1) };
-            let higher_y = unsafe { *self.ys.get_unchecked(idx + 1) };
+            0.0
+        };
 
-            let slope = (higher_y - lower_y) * (1.0 / (higher_t - lower_t));
-            let val = lower_y + slope * (t - lower_t);
+        let mode = self.modes.get(idx).copied().unwrap_or_default();
+        let val = match mode {
+            Interpolation::Step => lower_y,
+            Interpolation::Linear => lerp(lower_y, higher_y, s),
+            Interpolation::Smoothstep => lerp(lower_y, higher_y, s * s * (3.0 - 2.0 * s)),
+            Interpolation::Ease(easing) => lerp(lower_y, higher_y, easing.apply(s)),
+            Interpolation::CatmullRom => {
+                let h = higher_t - lower_t;
+                let m0 = self.tangent(idx) * h;
+                let m1 = self.tangent(idx + 1) * h;
+                let s2 = s * s;
+                let s3 = s2 * s;
+                lower_y * (2.0 * s3 - 3.0 * s2 + 1.0)
+                    + m0 * (s3 - 2.0 * s2 + s)
+                    + higher_y * (-2.0 * s3 + 3.0 * s2)
+                    + m1 * (s3 - s2)
+            }
+        };
+        Ok(val)
+    }
 
-            val
+    /// Slope at the point i, from its neighbours. One sided at the ends of the curve.
+    fn tangent(&self, i: usize) -> T {
+        let prev = i.saturating_sub(1);
+        let next = (i + 1).min(self.xs.len() - 1);
+        let dx = self.xs[next] - self.xs[prev];
+        if dx > 0.0 {
+            (self.ys[next] - self.ys[prev]) * (1.0 / dx)
+        } else {
+            self.ys[i] * 0.0
         }
     }
 }
 
+fn lerp<T: CurveNode>(a: T, b: T, s: f32) -> T {
+    a + (b - a) * s
+}
+
 impl CurveNode for glam::Vec2 {}
 impl CurveNode for f32 {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(expected: f32, actual: f32) {
+        assert!(
+            (expected - actual).abs() < 1e-5,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn old_curves_still_parse() {
+        let curve: Curve<f32> =
+            serde_json::from_str(r#"{"xs": [0.0, 1.0], "ys": [2.0, 4.0]}"#).unwrap();
+        assert_close(3.0, curve.y(0.5).unwrap());
+        assert_close(4.0, curve.y(2.0).unwrap());
+        // and they are written the same way.
+        assert_eq!(
+            r#"{"xs":[0.0,1.0],"ys":[2.0,4.0]}"#,
+            serde_json::to_string(&curve).unwrap()
+        );
+
+        let curve: Curve<glam::Vec2> =
+            serde_json::from_str(r#"{"xs": [0.0, 1.0], "ys": [[0.0, 0.0], [2.0, 4.0]]}"#).unwrap();
+        assert_eq!(glam::vec2(1.0, 2.0), curve.y(0.5).unwrap());
+    }
+
+    #[test]
+    fn invalid_curves_are_rejected_when_deserialized() {
+        let parse = |json: &str| {
+            serde_json::from_str::<Curve<f32>>(json)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(parse(r#"{"xs": [], "ys": []}"#).contains(&CurveError::Empty.to_string()));
+        assert!(parse(r#"{"xs": [0.0, 1.0], "ys": [1.0]}"#)
+            .contains(&CurveError::LengthMismatch { xs: 2, ys: 1 }.to_string()));
+        assert!(parse(r#"{"xs": [0.0, 1.0, 0.5], "ys": [1.0, 2.0, 3.0]}"#)
+            .contains(&CurveError::NotSorted(2).to_string()));
+
+        assert_eq!(
+            Err(CurveError::NotSorted(1)),
+            Curve::new(vec![1.0, 0.0], vec![0.0, 1.0]).map(|_| ())
+        );
+        assert_eq!(Err(CurveError::Empty), Curve::<f32>::default().y(0.0));
+    }
+
+    #[test]
+    fn wrap_modes() {
+        let curve = Curve::new(vec![1.0, 3.0], vec![0.0, 2.0]).unwrap();
+        assert_close(0.0, curve.y(0.0).unwrap());
+        assert_close(2.0, curve.y(4.5).unwrap());
+
+        let looped = curve.clone().with_wrap(WrapMode::Loop);
+        assert_close(0.5, looped.y(3.5).unwrap());
+        assert_close(1.5, looped.y(0.5).unwrap());
+        assert_close(1.0, looped.y(6.0).unwrap());
+
+        let ping_pong = curve.with_wrap(WrapMode::PingPong);
+        assert_close(1.5, ping_pong.y(3.5).unwrap());
+        assert_close(0.5, ping_pong.y(5.5).unwrap());
+        assert_close(0.5, ping_pong.y(0.5).unwrap());
+    }
+
+    #[test]
+    fn interpolations_go_through_the_points() {
+        let xs = vec![0.0, 1.0, 3.0, 4.0];
+        let ys = vec![0.0, 2.0, -1.0, 5.0];
+        let modes = vec![
+            Interpolation::Step,
+            Interpolation::Linear,
+            Interpolation::Smoothstep,
+            Interpolation::CatmullRom,
+            Interpolation::Ease(Easing::QuadIn),
+            Interpolation::Ease(Easing::QuadOut),
+            Interpolation::Ease(Easing::CubicIn),
+            Interpolation::Ease(Easing::CubicOut),
+            Interpolation::Ease(Easing::ElasticIn),
+            Interpolation::Ease(Easing::ElasticOut),
+            Interpolation::Ease(Easing::BackIn),
+            Interpolation::Ease(Easing::BackOut),
+        ];
+        for mode in modes {
+            let curve = Curve::new(xs.clone(), ys.clone())
+                .unwrap()
+                .with_interpolation(mode);
+            for (x, y) in xs.iter().zip(ys.iter()) {
+                let value = curve.y(*x).unwrap();
+                assert!((value - y).abs() < 1e-4, "{:?} at {} = {}", mode, x, value);
+            }
+        }
+    }
+
+    #[test]
+    fn step_and_segment_modes() {
+        let mut curve = Curve::new(vec![0.0, 1.0, 2.0], vec![0.0, 1.0, 3.0]).unwrap();
+        curve.set_segment_interpolation(1, Interpolation::Step);
+        // the first segment is still linear.
+        assert_close(0.5, curve.y(0.5).unwrap());
+        assert_close(1.0, curve.y(1.9).unwrap());
+
+        // catmull-rom is smooth at the peak, so it stays above the straight lines around it.
+        let curve = Curve::new(vec![0.0, 1.0, 2.0], vec![0.0, 1.0, 0.0])
+            .unwrap()
+            .with_interpolation(Interpolation::CatmullRom);
+        assert!(curve.y(0.5).unwrap() > 0.5);
+        assert_close(curve.y(0.5).unwrap(), curve.y(1.5).unwrap());
+    }
+}
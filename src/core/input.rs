@@ -1,11 +1,13 @@
-use crate::{HEIGHT, WIDTH};
+use crate::core::window::WindowDim;
 use glfw::{Key, MouseButton, WindowEvent};
 use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 
+pub mod replay;
 pub mod ser;
-pub trait InputAction: Hash + Eq + PartialEq + Clone + DeserializeOwned {
+pub trait InputAction: Hash + Eq + PartialEq + Clone + Serialize + DeserializeOwned {
     fn get_default_key_mapping() -> HashMap<Key, Self>;
     fn get_default_mouse_mapping() -> HashMap<MouseButton, Self>;
 }
@@ -28,6 +30,8 @@ where
     just_pressed: HashSet<A>,
 
     mouse_pos: glam::Vec2,
+    /// Position from a recording, in normalized device coordinates. Used instead of `mouse_pos`.
+    replayed_mouse: Option<glam::Vec2>,
 
     key_mapping: HashMap<Key, A>,
     mouse_mapping: HashMap<MouseButton, A>,
@@ -42,6 +46,7 @@ where
             action_state: HashMap::default(),
             just_pressed: HashSet::default(),
             mouse_pos: glam::Vec2::zero(),
+            replayed_mouse: None,
             key_mapping,
             mouse_mapping,
         }
@@ -95,10 +100,10 @@ where
         self.just_pressed.contains(&action)
     }
 
-    pub fn mouse_position(&self) -> glam::Vec2 {
-        glam::vec2(
-            (self.mouse_pos.x / WIDTH as f32) * 2.0 - 1.0,
-            ((HEIGHT as f32 - self.mouse_pos.y) / HEIGHT as f32) * 2.0 - 1.0,
-        )
+    /// Position of the mouse in normalized device coordinates (between -1 and 1, y up). Use
+    /// `screen_to_world` to get the position in the world.
+    pub fn mouse_position(&self, window_dim: &WindowDim) -> glam::Vec2 {
+        self.replayed_mouse
+            .unwrap_or_else(|| window_dim.window_to_ndc(self.mouse_pos))
     }
 }
@@ -0,0 +1,213 @@
+//! Record the inputs of a run and play them back.
+//!
+//! For every fixed update, the state of `Input<A>` is saved: the pressed actions, the actions that
+//! were just pressed and the mouse position. The mouse position is saved in normalized device
+//! coordinates so that the replay does not depend on the size of the window. The keys given to
+//! `Scene::process_input` (escape to pause for example) are saved with the update that follows
+//! them. With the seed of the random generator, this is enough to replay the same run.
+//!
+//! The recording starts at the first fixed update of a scene that asks for it (see
+//! `Scene::starts_input_recording`), so the loading and the menus before the game, which do not
+//! take the same number of updates every time, are not part of it.
+//!
+//! The file starts with a magic number and a version, then the recording is serialized with bincode.
+//! Consecutive identical frames are stored only once with a repeat count, so a run where the player
+//! holds the same keys for a while stays small.
+//!
+//! The GUI is not recorded, so the pause menu should be left with escape instead of the buttons.
+use crate::core::input::ser::BasicKey;
+use crate::core::input::{Input, InputAction};
+use crate::core::random::Seed;
+use crate::core::window::WindowDim;
+use glfw::WindowEvent;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// First bytes of a recording file.
+const MAGIC: &[u8; 4] = b"SGIR";
+
+/// Increase when the format of the recording changes.
+pub const RECORDING_VERSION: u16 = 2;
+
+/// Key given to `Scene::process_input`. Only the keys that have a `BasicKey` are recorded.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SceneKey {
+    pub key: BasicKey,
+    pub pressed: bool,
+}
+
+impl SceneKey {
+    /// None if the event is not a key press or release, or if the key cannot be recorded.
+    pub fn from_event(event: &WindowEvent) -> Option<Self> {
+        let (key, pressed) = match *event {
+            WindowEvent::Key(key, _, glfw::Action::Press, _) => (key, true),
+            WindowEvent::Key(key, _, glfw::Action::Release, _) => (key, false),
+            _ => return None,
+        };
+        BasicKey::from_key(key).map(|key| Self { key, pressed })
+    }
+
+    /// Event to give to the scene when replaying.
+    pub fn to_event(self) -> WindowEvent {
+        let action = if self.pressed {
+            glfw::Action::Press
+        } else {
+            glfw::Action::Release
+        };
+        WindowEvent::Key(self.key.into(), 0, action, glfw::Modifiers::empty())
+    }
+}
+
+/// State of the inputs during one fixed update.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "A: InputAction")]
+pub struct InputFrame<A: InputAction> {
+    pub pressed: HashSet<A>,
+    pub just_pressed: HashSet<A>,
+    /// In normalized device coordinates, see `Input::mouse_position`.
+    pub mouse_pos: glam::Vec2,
+    /// Keys given to the scene before this update.
+    pub scene_keys: Vec<SceneKey>,
+}
+
+impl<A: InputAction> Default for InputFrame<A> {
+    fn default() -> Self {
+        Self {
+            pressed: HashSet::default(),
+            just_pressed: HashSet::default(),
+            mouse_pos: glam::Vec2::zero(),
+            scene_keys: vec![],
+        }
+    }
+}
+
+impl<A> Input<A>
+where
+    A: InputAction,
+{
+    /// Current state of the inputs, to be recorded. The scene keys are added by the runner.
+    pub fn snapshot(&self, window_dim: &WindowDim) -> InputFrame<A> {
+        InputFrame {
+            pressed: self
+                .action_state
+                .iter()
+                .filter(|(_, pressed)| **pressed)
+                .map(|(action, _)| action.clone())
+                .collect(),
+            just_pressed: self.just_pressed.clone(),
+            mouse_pos: self.mouse_position(window_dim),
+            scene_keys: vec![],
+        }
+    }
+
+    /// Replace the state of the inputs with a recorded frame.
+    pub fn apply_frame(&mut self, frame: &InputFrame<A>) {
+        self.action_state.clear();
+        for action in &frame.pressed {
+            self.action_state.insert(action.clone(), true);
+        }
+        self.just_pressed = frame.just_pressed.clone();
+        self.replayed_mouse = Some(frame.mouse_pos);
+    }
+}
+
+/// All the inputs of a run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "A: InputAction")]
+pub struct InputRecording<A: InputAction> {
+    /// Seed of the random generator for the run.
+    pub seed: Seed,
+    /// Frames with the number of times they are repeated.
+    frames: Vec<(u32, InputFrame<A>)>,
+}
+
+impl<A: InputAction> InputRecording<A> {
+    pub fn new(seed: Seed) -> Self {
+        Self {
+            seed,
+            frames: vec![],
+        }
+    }
+
+    /// Add the inputs of the next fixed update.
+    pub fn push(&mut self, frame: InputFrame<A>) {
+        if let Some((repeat, last)) = self.frames.last_mut() {
+            if *last == frame {
+                *repeat += 1;
+                return;
+            }
+        }
+        self.frames.push((1, frame));
+    }
+
+    /// Number of fixed updates in the recording.
+    pub fn len(&self) -> usize {
+        self.frames.iter().map(|(repeat, _)| *repeat as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), anyhow::Error> {
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&RECORDING_VERSION.to_le_bytes());
+        data.extend(bincode::serialize(self)?);
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, anyhow::Error> {
+        let data = std::fs::read(path)?;
+        if data.len() < MAGIC.len() + 2 || &data[..MAGIC.len()] != MAGIC {
+            anyhow::bail!("Not an input recording");
+        }
+        let version = u16::from_le_bytes([data[MAGIC.len()], data[MAGIC.len() + 1]]);
+        if version != RECORDING_VERSION {
+            anyhow::bail!(
+                "Recording version is {} but only version {} is supported",
+                version,
+                RECORDING_VERSION
+            );
+        }
+
+        Ok(bincode::deserialize(&data[MAGIC.len() + 2..])?)
+    }
+}
+
+/// Give the frames of a recording one by one.
+pub struct InputReplay<A: InputAction> {
+    recording: InputRecording<A>,
+    /// index in the recording frames.
+    current: usize,
+    /// How many times the current frame has been given already.
+    repeated: u32,
+}
+
+impl<A: InputAction> InputReplay<A> {
+    pub fn new(recording: InputRecording<A>) -> Self {
+        Self {
+            recording,
+            current: 0,
+            repeated: 0,
+        }
+    }
+
+    /// Inputs for the next fixed update. None when the recording is over.
+    pub fn next_frame(&mut self) -> Option<&InputFrame<A>> {
+        let (repeat, _) = self.recording.frames.get(self.current)?;
+        if self.repeated == *repeat {
+            self.current += 1;
+            self.repeated = 0;
+        }
+
+        let (_, frame) = self.recording.frames.get(self.current)?;
+        self.repeated += 1;
+        Some(frame)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.current >= self.recording.frames.len()
+    }
+}
@@ -2,7 +2,7 @@ use glfw::Key;
 use serde_derive::{Deserialize, Serialize};
 
 /// Input keys. Copy of glfw just for serialization.
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BasicKey {
     Space,
 
@@ -45,6 +45,7 @@ pub enum BasicKey {
     Z,
 
     Enter,
+    Escape,
 
     Right,
     Left,
@@ -52,6 +53,64 @@ pub enum BasicKey {
     Up,
 }
 
+impl BasicKey {
+    /// None if the key has no copy.
+    pub fn from_key(key: Key) -> Option<Self> {
+        use BasicKey::*;
+        let key = match key {
+            Key::Space => Space,
+
+            Key::Num0 => Num0,
+            Key::Num1 => Num1,
+            Key::Num2 => Num2,
+            Key::Num3 => Num3,
+            Key::Num4 => Num4,
+            Key::Num5 => Num5,
+            Key::Num6 => Num6,
+            Key::Num7 => Num7,
+            Key::Num8 => Num8,
+            Key::Num9 => Num9,
+
+            Key::A => A,
+            Key::B => B,
+            Key::C => C,
+            Key::D => D,
+            Key::E => E,
+            Key::F => F,
+            Key::G => G,
+            Key::H => H,
+            Key::I => I,
+            Key::J => J,
+            Key::K => K,
+            Key::L => L,
+            Key::M => M,
+            Key::N => N,
+            Key::O => O,
+            Key::P => P,
+            Key::Q => Q,
+            Key::R => R,
+            Key::S => S,
+            Key::T => T,
+            Key::U => U,
+            Key::V => V,
+            Key::W => W,
+            Key::X => X,
+            Key::Y => Y,
+            Key::Z => Z,
+
+            Key::Enter => Enter,
+            Key::Escape => Escape,
+
+            Key::Right => Right,
+            Key::Left => Left,
+            Key::Down => Down,
+            Key::Up => Up,
+            _ => return None,
+        };
+        Some(key)
+    }
+}
+
 impl Into<Key> for BasicKey {
     fn into(self) -> Key {
         use BasicKey::*;
@@ -61,7 +120,7 @@ impl Into<Key> for BasicKey {
             Num0 => Key::Num0,
             Num1 => Key::Num1,
             Num2 => Key::Num2,
-            Num3 => Key::Num4,
+            Num3 => Key::Num3,
             Num4 => Key::Num4,
             Num5 => Key::Num5,
             Num6 => Key::Num6,
@@ -97,6 +156,7 @@ impl Into<Key> for BasicKey {
             Z => Key::Z,
 
             Enter => Key::Enter,
+            Escape => Key::Escape,
 
             Right => Key::Right,
             Left => Key::Left,
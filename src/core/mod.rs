@@ -5,8 +5,11 @@ pub mod colors;
 pub mod curve;
 pub mod input;
 pub mod noise;
+pub mod profiler;
 pub mod random;
 pub mod scene;
+pub mod system;
 pub mod timer;
 pub mod transform;
+pub mod tween;
 pub mod window;
@@ -0,0 +1,274 @@
+//! Generators built on top of other generators.
+use crate::core::noise::NoiseFn;
+
+/// Fractal brownian motion: sum of octaves of the same noise at higher frequencies and lower
+/// amplitudes. Same as `Perlin::octave_perlin` but for any noise.
+pub struct Fbm<N> {
+    source: N,
+    pub octaves: u32,
+    /// Frequency multiplier between two octaves.
+    pub lacunarity: f32,
+    /// Amplitude multiplier between two octaves.
+    pub persistence: f32,
+}
+
+impl<N: NoiseFn> Fbm<N> {
+    pub fn new(source: N, octaves: u32) -> Self {
+        Self {
+            source,
+            octaves: octaves.max(1),
+            lacunarity: 2.0,
+            persistence: 0.5,
+        }
+    }
+
+    pub fn with_lacunarity(mut self, lacunarity: f32) -> Self {
+        self.lacunarity = lacunarity;
+        self
+    }
+
+    pub fn with_persistence(mut self, persistence: f32) -> Self {
+        self.persistence = persistence;
+        self
+    }
+
+    fn sum(&self, sample: impl Fn(f32) -> f32) -> f32 {
+        let mut freq = 1.0;
+        let mut amplitude = 1.0;
+        let mut max_value = 0.0;
+        let mut total = 0.0;
+        for _ in 0..self.octaves {
+            total += sample(freq) * amplitude;
+            max_value += amplitude;
+            amplitude *= self.persistence;
+            freq *= self.lacunarity;
+        }
+        total / max_value
+    }
+}
+
+impl<N: NoiseFn> NoiseFn for Fbm<N> {
+    fn noise2(&self, x: f32, y: f32) -> f32 {
+        self.sum(|freq| self.source.noise2(x * freq, y * freq))
+    }
+
+    fn noise3(&self, x: f32, y: f32, z: f32) -> f32 {
+        self.sum(|freq| self.source.noise3(x * freq, y * freq, z * freq))
+    }
+}
+
+/// Ridged multifractal: like fBm but each octave is folded around the middle value so that it
+/// makes sharp ridges. Good for mountains or lightning-like nebulas.
+pub struct RidgedMulti<N> {
+    source: N,
+    pub octaves: u32,
+    pub lacunarity: f32,
+    pub persistence: f32,
+}
+
+impl<N: NoiseFn> RidgedMulti<N> {
+    pub fn new(source: N, octaves: u32) -> Self {
+        Self {
+            source,
+            octaves: octaves.max(1),
+            lacunarity: 2.0,
+            persistence: 0.5,
+        }
+    }
+
+    pub fn with_lacunarity(mut self, lacunarity: f32) -> Self {
+        self.lacunarity = lacunarity;
+        self
+    }
+
+    pub fn with_persistence(mut self, persistence: f32) -> Self {
+        self.persistence = persistence;
+        self
+    }
+
+    fn sum(&self, sample: impl Fn(f32) -> f32) -> f32 {
+        let mut freq = 1.0;
+        let mut amplitude = 1.0;
+        let mut max_value = 0.0;
+        let mut total = 0.0;
+        // previous octave, so that the ridges get details only where there are ridges already.
+        let mut weight = 1.0;
+        for _ in 0..self.octaves {
+            let signal = 1.0 - (sample(freq) * 2.0 - 1.0).abs();
+            let signal = signal * signal * weight;
+            weight = signal.clamp(0.0, 1.0);
+
+            total += signal * amplitude;
+            max_value += amplitude;
+            amplitude *= self.persistence;
+            freq *= self.lacunarity;
+        }
+        (total / max_value).clamp(0.0, 1.0)
+    }
+}
+
+impl<N: NoiseFn> NoiseFn for RidgedMulti<N> {
+    fn noise2(&self, x: f32, y: f32) -> f32 {
+        self.sum(|freq| self.source.noise2(x * freq, y * freq))
+    }
+
+    fn noise3(&self, x: f32, y: f32, z: f32) -> f32 {
+        self.sum(|freq| self.source.noise3(x * freq, y * freq, z * freq))
+    }
+}
+
+/// Move the input coordinates with another noise before sampling the source. Turns regular
+/// noise into swirly, organic shapes.
+pub struct DomainWarp<N, W> {
+    source: N,
+    warp: W,
+    /// How far the coordinates can move.
+    pub strength: f32,
+}
+
+impl<N: NoiseFn, W: NoiseFn> DomainWarp<N, W> {
+    pub fn new(source: N, warp: W, strength: f32) -> Self {
+        Self {
+            source,
+            warp,
+            strength,
+        }
+    }
+
+    /// Offset between -strength and strength. The components are sampled far from each other so
+    /// that they are not correlated.
+    fn offset(&self, v: f32) -> f32 {
+        (v * 2.0 - 1.0) * self.strength
+    }
+}
+
+impl<N: NoiseFn, W: NoiseFn> NoiseFn for DomainWarp<N, W> {
+    fn noise2(&self, x: f32, y: f32) -> f32 {
+        let dx = self.offset(self.warp.noise2(x, y));
+        let dy = self.offset(self.warp.noise2(x + 5.2, y + 1.3));
+        self.source.noise2(x + dx, y + dy)
+    }
+
+    fn noise3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let dx = self.offset(self.warp.noise3(x, y, z));
+        let dy = self.offset(self.warp.noise3(x + 5.2, y + 1.3, z + 2.8));
+        let dz = self.offset(self.warp.noise3(x + 9.7, y + 4.1, z + 7.4));
+        self.source.noise3(x + dx, y + dy, z + dz)
+    }
+}
+
+/// Noise that repeats every `width` in x and `height` in y, e.g. for scrolling backgrounds.
+///
+/// The source is sampled at the four mirrored positions and blended, so the edges match.
+/// The contrast is a bit lower in the middle of the tile.
+pub struct Tileable<N> {
+    source: N,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl<N: NoiseFn> Tileable<N> {
+    pub fn new(source: N, width: f32, height: f32) -> Self {
+        Self {
+            source,
+            width,
+            height,
+        }
+    }
+
+    fn blend(&self, x: f32, y: f32, sample: impl Fn(f32, f32) -> f32) -> f32 {
+        let x = x.rem_euclid(self.width);
+        let y = y.rem_euclid(self.height);
+        let u = x / self.width;
+        let v = y / self.height;
+
+        let a = sample(x, y);
+        let b = sample(x - self.width, y);
+        let c = sample(x, y - self.height);
+        let d = sample(x - self.width, y - self.height);
+
+        let top = a * (1.0 - u) + b * u;
+        let bottom = c * (1.0 - u) + d * u;
+        top * (1.0 - v) + bottom * v
+    }
+}
+
+impl<N: NoiseFn> NoiseFn for Tileable<N> {
+    fn noise2(&self, x: f32, y: f32) -> f32 {
+        self.blend(x, y, |x, y| self.source.noise2(x, y))
+    }
+
+    fn noise3(&self, x: f32, y: f32, z: f32) -> f32 {
+        self.blend(x, y, |x, y| self.source.noise3(x, y, z))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::noise::Simplex;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn simplex(seed: u64) -> Simplex {
+        Simplex::new(&mut StdRng::seed_from_u64(seed))
+    }
+
+    fn points() -> impl Iterator<Item = (f32, f32)> {
+        (0..500).map(|i| {
+            let i = i as f32;
+            (i * 0.37 - 90.0, i * 0.71 - 150.0)
+        })
+    }
+
+    fn assert_close(expected: f32, actual: f32) {
+        assert!(
+            (expected - actual).abs() < 1e-3,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn tileable_wraps_around() {
+        let (width, height) = (16.0, 8.0);
+        let noise = Tileable::new(simplex(5), width, height);
+        for (x, y) in points() {
+            let value = noise.noise2(x, y);
+            assert!((0.0..=1.0).contains(&value));
+            assert_close(value, noise.noise2(x + width, y));
+            assert_close(value, noise.noise2(x, y - 3.0 * height));
+            assert_close(
+                noise.noise3(x, y, 2.0),
+                noise.noise3(x - width, y + height, 2.0),
+            );
+        }
+        // no seam at the edges of the tile.
+        for i in 0..20 {
+            let y = i as f32 * 0.4;
+            assert_close(noise.noise2(0.0, y), noise.noise2(width - 1e-4, y));
+            let x = i as f32 * 0.8;
+            assert_close(noise.noise2(x, 0.0), noise.noise2(x, height - 1e-4));
+        }
+    }
+
+    #[test]
+    fn fractals_are_deterministic_and_between_0_and_1() {
+        let fbm = |seed| Fbm::new(simplex(seed), 5);
+        let ridged = |seed| RidgedMulti::new(simplex(seed), 5);
+        let warp = |seed| DomainWarp::new(simplex(seed), simplex(seed + 1), 0.4);
+        for (x, y) in points() {
+            for (a, b) in [
+                (fbm(1).noise2(x, y), fbm(1).noise2(x, y)),
+                (ridged(1).noise2(x, y), ridged(1).noise2(x, y)),
+                (warp(1).noise2(x, y), warp(1).noise2(x, y)),
+            ]
+            .iter()
+            {
+                assert_eq!(a, b);
+                assert!((0.0..=1.0).contains(a));
+            }
+        }
+    }
+}
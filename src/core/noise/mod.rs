@@ -1,3 +1,51 @@
+//! Noise functions for procedural generation.
+//!
+//! All the generators implement `NoiseFn` and return values between 0 and 1 (roughly for the
+//! fractal ones). They are created from a random generator so the same seed gives the same noise.
+//!
+//! Generators can be combined:
+//! ```ignore
+//! let base = Simplex::new(random.rng());
+//! let nebula = DomainWarp::new(Fbm::new(base, 5), Simplex::new(random.rng()), 0.4);
+//! let value = nebula.noise2(x, y);
+//! ```
+pub mod fractal;
 pub mod perlin;
+pub mod simplex;
+pub mod worley;
 
-pub fn interpolate() {}
+pub use fractal::{DomainWarp, Fbm, RidgedMulti, Tileable};
+pub use perlin::Perlin;
+pub use simplex::Simplex;
+pub use worley::{DistanceMetric, Worley, WorleyValue};
+
+pub trait NoiseFn {
+    /// Value of the noise at (x, y), between 0 and 1.
+    fn noise2(&self, x: f32, y: f32) -> f32;
+
+    /// Value of the noise at (x, y, z), between 0 and 1. Generators that only exist in 2D ignore
+    /// z.
+    fn noise3(&self, x: f32, y: f32, _z: f32) -> f32 {
+        self.noise2(x, y)
+    }
+}
+
+impl<N: NoiseFn + ?Sized> NoiseFn for Box<N> {
+    fn noise2(&self, x: f32, y: f32) -> f32 {
+        (**self).noise2(x, y)
+    }
+
+    fn noise3(&self, x: f32, y: f32, z: f32) -> f32 {
+        (**self).noise3(x, y, z)
+    }
+}
+
+impl<N: NoiseFn + ?Sized> NoiseFn for &N {
+    fn noise2(&self, x: f32, y: f32) -> f32 {
+        (**self).noise2(x, y)
+    }
+
+    fn noise3(&self, x: f32, y: f32, z: f32) -> f32 {
+        (**self).noise3(x, y, z)
+    }
+}
@@ -1,3 +1,4 @@
+use crate::core::noise::NoiseFn;
 use rand::{seq::SliceRandom, SeedableRng};
 
 ///  6t5-15t4+10t3.
@@ -27,15 +28,24 @@ fn get_constant_vector(hash: usize) -> glam::Vec2 {
 pub struct PermutationTable(Vec<u8>);
 
 impl PermutationTable {
-    fn get1(&self, x: isize) -> usize {
+    pub(crate) fn new<R: SeedableRng + rand::RngCore>(rand: &mut R) -> Self {
+        Self(make_permutation(rand))
+    }
+
+    pub(crate) fn get1(&self, x: isize) -> usize {
         let idx = (x & 0xFF) as usize;
         self.0[idx] as usize
     }
 
-    fn get2(&self, x: isize, y: isize) -> usize {
+    pub(crate) fn get2(&self, x: isize, y: isize) -> usize {
         let y = (y & 0xFF) as usize;
         self.0[self.get1(x) ^ y] as usize
     }
+
+    pub(crate) fn get3(&self, x: isize, y: isize, z: isize) -> usize {
+        let z = (z & 0xFF) as usize;
+        self.0[self.get2(x, y) ^ z] as usize
+    }
 }
 
 pub fn perlin2d(x: f32, y: f32, perm: &PermutationTable) -> f32 {
@@ -107,7 +117,7 @@ pub struct Perlin {
 impl Perlin {
     pub fn new<R: SeedableRng + rand::RngCore>(rand: &mut R) -> Self {
         Self {
-            perm: PermutationTable(make_permutation(rand)),
+            perm: PermutationTable::new(rand),
             repeat: None,
         }
     }
@@ -137,3 +147,9 @@ impl Perlin {
         total / max_value
     }
 }
+
+impl NoiseFn for Perlin {
+    fn noise2(&self, x: f32, y: f32) -> f32 {
+        self.perlin(x, y)
+    }
+}
@@ -0,0 +1,199 @@
+//! Worley (cellular) noise. Each cell of the grid has a feature point and the noise is the
+//! distance to the closest ones. Good for asteroids, cracks and bubbly nebulas.
+use crate::core::noise::perlin::PermutationTable;
+use crate::core::noise::NoiseFn;
+use rand::{Rng, SeedableRng};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DistanceMetric {
+    Euclidean,
+    Manhattan,
+    Chebyshev,
+}
+
+impl DistanceMetric {
+    fn distance(self, d: [f32; 3]) -> f32 {
+        match self {
+            DistanceMetric::Euclidean => (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt(),
+            DistanceMetric::Manhattan => d[0].abs() + d[1].abs() + d[2].abs(),
+            DistanceMetric::Chebyshev => d[0].abs().max(d[1].abs()).max(d[2].abs()),
+        }
+    }
+}
+
+/// Which distance is returned.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WorleyValue {
+    /// Distance to the closest point. Round cells.
+    F1,
+    /// Distance to the second closest point.
+    F2,
+    /// F2 - F1. Bright borders between the cells.
+    F2MinusF1,
+}
+
+pub struct Worley {
+    perm: PermutationTable,
+    /// Position of the feature point in its cell, between 0 and 1.
+    offsets: Vec<[f32; 3]>,
+    metric: DistanceMetric,
+    value: WorleyValue,
+}
+
+impl Worley {
+    pub fn new<R: SeedableRng + rand::RngCore>(rand: &mut R) -> Self {
+        let perm = PermutationTable::new(rand);
+        let offsets = (0..256)
+            .map(|_| [rand.gen::<f32>(), rand.gen::<f32>(), rand.gen::<f32>()])
+            .collect();
+        Self {
+            perm,
+            offsets,
+            metric: DistanceMetric::Euclidean,
+            value: WorleyValue::F1,
+        }
+    }
+
+    pub fn with_metric(mut self, metric: DistanceMetric) -> Self {
+        self.metric = metric;
+        self
+    }
+
+    pub fn with_value(mut self, value: WorleyValue) -> Self {
+        self.value = value;
+        self
+    }
+
+    /// Closest and second closest distances to a feature point. `depth` is 1 to look at the
+    /// neighbouring cells in z, 0 for 2D.
+    fn closest(&self, x: f32, y: f32, z: f32, depth: isize) -> (f32, f32) {
+        let cx = x.floor() as isize;
+        let cy = y.floor() as isize;
+        let cz = z.floor() as isize;
+
+        let mut f1 = f32::MAX;
+        let mut f2 = f32::MAX;
+        for i in cx - 1..=cx + 1 {
+            for j in cy - 1..=cy + 1 {
+                for k in cz - depth..=cz + depth {
+                    let offset = self.offsets[self.perm.get3(i, j, k)];
+                    let d = [
+                        i as f32 + offset[0] - x,
+                        j as f32 + offset[1] - y,
+                        // 2D noise is a slice of the cells at z = 0.
+                        if depth == 0 {
+                            0.0
+                        } else {
+                            k as f32 + offset[2] - z
+                        },
+                    ];
+                    let d = self.metric.distance(d);
+                    if d < f1 {
+                        f2 = f1;
+                        f1 = d;
+                    } else if d < f2 {
+                        f2 = d;
+                    }
+                }
+            }
+        }
+        (f1, f2)
+    }
+
+    fn select(&self, (f1, f2): (f32, f32)) -> f32 {
+        let v = match self.value {
+            WorleyValue::F1 => f1,
+            WorleyValue::F2 => f2,
+            WorleyValue::F2MinusF1 => f2 - f1,
+        };
+        v.clamp(0.0, 1.0)
+    }
+}
+
+impl NoiseFn for Worley {
+    fn noise2(&self, x: f32, y: f32) -> f32 {
+        self.select(self.closest(x, y, 0.0, 0))
+    }
+
+    fn noise3(&self, x: f32, y: f32, z: f32) -> f32 {
+        self.select(self.closest(x, y, z, 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    const VALUES: [WorleyValue; 3] = [WorleyValue::F1, WorleyValue::F2, WorleyValue::F2MinusF1];
+    const METRICS: [DistanceMetric; 3] = [
+        DistanceMetric::Euclidean,
+        DistanceMetric::Manhattan,
+        DistanceMetric::Chebyshev,
+    ];
+
+    fn worley(seed: u64, value: WorleyValue, metric: DistanceMetric) -> Worley {
+        Worley::new(&mut StdRng::seed_from_u64(seed))
+            .with_value(value)
+            .with_metric(metric)
+    }
+
+    fn points() -> impl Iterator<Item = (f32, f32, f32)> {
+        (0..500).map(|i| {
+            let i = i as f32;
+            (i * 0.37 - 90.0, i * 0.71 - 150.0, i * 0.13 - 30.0)
+        })
+    }
+
+    #[test]
+    fn same_seed_same_noise() {
+        for &value in VALUES.iter() {
+            let a = worley(3, value, DistanceMetric::Euclidean);
+            let b = worley(3, value, DistanceMetric::Euclidean);
+            let c = worley(4, value, DistanceMetric::Euclidean);
+            let mut differences = 0;
+            for (x, y, z) in points() {
+                assert_eq!(a.noise2(x, y), b.noise2(x, y));
+                assert_eq!(a.noise3(x, y, z), b.noise3(x, y, z));
+                differences += (a.noise2(x, y) != c.noise2(x, y)) as usize;
+            }
+            assert!(differences > 250, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn every_value_is_between_0_and_1() {
+        for &value in VALUES.iter() {
+            for &metric in METRICS.iter() {
+                let noise = worley(1, value, metric);
+                for (x, y, z) in points() {
+                    let v2 = noise.noise2(x, y);
+                    let v3 = noise.noise3(x, y, z);
+                    assert!(
+                        (0.0..=1.0).contains(&v2),
+                        "{:?} {:?} = {}",
+                        value,
+                        metric,
+                        v2
+                    );
+                    assert!(
+                        (0.0..=1.0).contains(&v3),
+                        "{:?} {:?} = {}",
+                        value,
+                        metric,
+                        v3
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn second_closest_is_further() {
+        let noise = worley(1, WorleyValue::F1, DistanceMetric::Euclidean);
+        for (x, y, z) in points() {
+            let (f1, f2) = noise.closest(x, y, z, 1);
+            assert!(f1 <= f2);
+        }
+    }
+}
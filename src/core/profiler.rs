@@ -0,0 +1,277 @@
+//! Measure where the frame time goes.
+//!
+//! Systems and render passes are timed with `profile` and the results are accumulated for the
+//! current frame. When the frame ends, the sample is pushed into a rolling buffer along with the
+//! number of entities per archetype and the number of alive particles. Counting the entities goes
+//! through the whole world so it is only done while the overlay is shown, or when
+//! `count_entities` is set (e.g. to export them).
+//!
+//! The overlay shows the average and maximum timings over the buffer. Render passes are timed on
+//! the CPU so they measure the time to submit the draw calls, not the time spent on the GPU.
+use crate::core::colors::RgbaColor;
+use crate::render::particle::ParticleEmitter;
+use crate::render::ui::Gui;
+use crate::resources::Resources;
+use hecs::World;
+use std::any::TypeId;
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Number of frames kept by default.
+const DEFAULT_CAPACITY: usize = 300;
+
+/// Everything measured during one frame.
+#[derive(Debug, Clone, Default)]
+pub struct FrameSample {
+    pub frame: u64,
+    /// Time spent in each system or render pass, in the order they were first recorded.
+    pub timings: Vec<(String, Duration)>,
+    /// Number of entities for each archetype.
+    pub archetypes: Vec<(String, usize)>,
+    /// Number of alive particles.
+    pub particles: usize,
+}
+
+pub struct Profiler {
+    current: FrameSample,
+    samples: VecDeque<FrameSample>,
+    capacity: usize,
+    /// Names of the components used to describe archetypes.
+    component_names: HashMap<TypeId, &'static str>,
+    pub show_overlay: bool,
+    /// Count the entities and particles even when the overlay is hidden.
+    pub count_entities: bool,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl Profiler {
+    /// Create a profiler that keeps the last `capacity` frames.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            current: FrameSample::default(),
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            component_names: HashMap::new(),
+            show_overlay: false,
+            count_entities: false,
+        }
+    }
+
+    /// Give a name to a component so that it appears in the archetype names. Components that are
+    /// not registered are only counted.
+    pub fn register_component<T: 'static>(&mut self) {
+        let name = std::any::type_name::<T>();
+        let short_name = name.rsplit("::").next().unwrap_or(name);
+        self.component_names.insert(TypeId::of::<T>(), short_name);
+    }
+
+    pub fn with_component<T: 'static>(mut self) -> Self {
+        self.register_component::<T>();
+        self
+    }
+
+    pub fn toggle_overlay(&mut self) {
+        self.show_overlay = !self.show_overlay;
+    }
+
+    /// Add some time to the current frame. Time recorded several times under the same name during
+    /// a frame (for example with several fixed updates) is summed.
+    pub fn record(&mut self, name: &str, elapsed: Duration) {
+        if let Some((_, total)) = self.current.timings.iter_mut().find(|(n, _)| n == name) {
+            *total += elapsed;
+        } else {
+            self.current.timings.push((name.to_string(), elapsed));
+        }
+    }
+
+    /// Count the entities and particles if needed then store the current frame in the buffer.
+    pub fn end_frame(&mut self, world: &World) {
+        let (archetypes, particles) = if self.show_overlay || self.count_entities {
+            self.count(world)
+        } else {
+            (vec![], 0)
+        };
+
+        let frame = self.current.frame;
+        let mut sample = std::mem::take(&mut self.current);
+        sample.archetypes = archetypes;
+        sample.particles = particles;
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        self.current.frame = frame + 1;
+    }
+
+    /// Number of entities per archetype, and number of alive particles.
+    fn count(&self, world: &World) -> (Vec<(String, usize)>, usize) {
+        let mut archetypes: HashMap<Vec<TypeId>, usize> = HashMap::new();
+        for (_, entity) in world.iter() {
+            let mut types: Vec<TypeId> = entity.component_types().collect();
+            types.sort();
+            *archetypes.entry(types).or_default() += 1;
+        }
+        let mut archetypes: Vec<(String, usize)> = archetypes
+            .into_iter()
+            .map(|(types, count)| (self.archetype_name(&types), count))
+            .collect();
+        archetypes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let particles = world
+            .query::<&ParticleEmitter>()
+            .iter()
+            .map(|(_, emitter)| emitter.alive_particles())
+            .sum();
+        (archetypes, particles)
+    }
+
+    /// Frames in the buffer, from the oldest to the most recent.
+    pub fn samples(&self) -> impl Iterator<Item = &FrameSample> {
+        self.samples.iter()
+    }
+
+    pub fn last_sample(&self) -> Option<&FrameSample> {
+        self.samples.back()
+    }
+
+    /// Write all the frames in the buffer to a CSV file. There is one line per frame and per
+    /// measure: `frame,kind,name,value`. Timings are in milliseconds.
+    pub fn export_csv<P: AsRef<Path>>(&self, path: P) -> Result<(), anyhow::Error> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writeln!(writer, "frame,kind,name,value")?;
+        for sample in &self.samples {
+            for (name, elapsed) in &sample.timings {
+                writeln!(
+                    writer,
+                    "{},timing,{},{:.4}",
+                    sample.frame,
+                    name,
+                    elapsed.as_secs_f64() * 1000.0
+                )?;
+            }
+            for (name, count) in &sample.archetypes {
+                // archetype names contain commas.
+                writeln!(writer, "{},entities,\"{}\",{}", sample.frame, name, count)?;
+            }
+            writeln!(writer, "{},particles,,{}", sample.frame, sample.particles)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Average and maximum time of each measure over the buffer.
+    pub fn timing_stats(&self) -> Vec<(&str, Duration, Duration)> {
+        let mut stats: Vec<(&str, Duration, Duration)> = vec![];
+        for sample in &self.samples {
+            for (name, elapsed) in &sample.timings {
+                if let Some((_, total, max)) = stats.iter_mut().find(|(n, _, _)| n == name) {
+                    *total += *elapsed;
+                    *max = (*max).max(*elapsed);
+                } else {
+                    stats.push((name.as_str(), *elapsed, *elapsed));
+                }
+            }
+        }
+
+        let nb_samples = self.samples.len().max(1) as u32;
+        stats
+            .into_iter()
+            .map(|(name, total, max)| (name, total / nb_samples, max))
+            .collect()
+    }
+
+    /// Draw the timings and counts in the top right corner.
+    pub fn draw_overlay(&self, gui: &mut Gui) {
+        let white = RgbaColor::new(255, 255, 255, 255);
+        let line_height = gui.style.font_size + 4.0;
+        let width = 420.0;
+        let stats = self.timing_stats();
+        let archetypes = self
+            .last_sample()
+            .map(|s| s.archetypes.as_slice())
+            .unwrap_or(&[]);
+        let nb_lines = 3 + stats.len() + archetypes.len();
+
+        let anchor = glam::vec2(gui.window_dim.width as f32 - width - 10.0, 10.0);
+        gui.panel(
+            anchor,
+            glam::vec2(width, line_height * nb_lines as f32 + 10.0),
+            RgbaColor::new(0, 0, 0, 180),
+        );
+
+        let mut pos = anchor + glam::vec2(5.0, 5.0);
+        gui.colored_label(
+            pos,
+            format!("{} frames. avg / max (ms)", self.samples.len()),
+            white,
+        );
+        for (name, avg, max) in stats {
+            pos.y += line_height;
+            gui.colored_label(
+                pos,
+                format!(
+                    "{}: {:.3} / {:.3}",
+                    name,
+                    avg.as_secs_f64() * 1000.0,
+                    max.as_secs_f64() * 1000.0
+                ),
+                white,
+            );
+        }
+
+        pos.y += line_height;
+        gui.colored_label(
+            pos,
+            format!(
+                "Particles: {}",
+                self.last_sample().map(|s| s.particles).unwrap_or(0)
+            ),
+            white,
+        );
+        pos.y += line_height;
+        gui.colored_label(pos, "Entities:".to_string(), white);
+        for (name, count) in archetypes {
+            pos.y += line_height;
+            gui.colored_label(pos, format!("{} x {}", count, name), white);
+        }
+    }
+
+    fn archetype_name(&self, types: &[TypeId]) -> String {
+        let mut names: Vec<&str> = types
+            .iter()
+            .filter_map(|t| self.component_names.get(t).copied())
+            .collect();
+        names.sort_unstable();
+        let unknown = types.len() - names.len();
+
+        let mut name = names.join(", ");
+        if unknown > 0 {
+            if !name.is_empty() {
+                name.push_str(", ");
+            }
+            name.push_str(&format!("{} other", unknown));
+        }
+        if name.is_empty() {
+            name.push_str("(empty)");
+        }
+        name
+    }
+}
+
+/// Run `f` and record the time it took in the `Profiler` resource, if there is one.
+pub fn profile<R>(resources: &Resources, name: &str, f: impl FnOnce() -> R) -> R {
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    if let Some(mut profiler) = resources.fetch_mut::<Profiler>() {
+        profiler.record(name, elapsed);
+    }
+    result
+}
@@ -1,27 +1,58 @@
 use rand::prelude::StdRng;
-use rand::SeedableRng;
+use rand::{RngCore, SeedableRng};
+use serde_derive::{Deserialize, Serialize};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Seed(pub(crate) [u8; 32]);
 
+impl Seed {
+    /// Create a seed from a number. Handy for command line arguments.
+    pub fn from_u64(value: u64) -> Self {
+        let mut seed = [0u8; 32];
+        StdRng::seed_from_u64(value).fill_bytes(&mut seed);
+        Self(seed)
+    }
+
+    /// New random seed. Used when no seed is given so that the run can still be recorded.
+    pub fn random() -> Self {
+        Self(rand::random())
+    }
+}
+
+/// All the randomness of the game should come from here so that a run can be reproduced from its
+/// seed.
+///
+/// There are two streams. The gameplay stream is used by everything that has an effect on the
+/// simulation. The cosmetic stream is for effects such as particles, so that the number of
+/// particles on screen cannot change what happens in the game.
 pub struct RandomGenerator {
     rand: StdRng,
+    cosmetic: StdRng,
 }
 
 impl RandomGenerator {
     pub fn new(seed: Seed) -> Self {
-        Self {
-            rand: StdRng::from_seed(seed.0),
-        }
+        Self::from_root(StdRng::from_seed(seed.0))
     }
 
     pub fn from_entropy() -> Self {
-        Self {
-            rand: StdRng::from_entropy(),
-        }
+        Self::from_root(StdRng::from_entropy())
     }
 
+    fn from_root(mut root: StdRng) -> Self {
+        let rand = StdRng::from_rng(&mut root).expect("StdRng cannot fail");
+        let cosmetic = StdRng::from_rng(&mut root).expect("StdRng cannot fail");
+        Self { rand, cosmetic }
+    }
+
+    /// Random generator for gameplay.
     pub fn rng(&mut self) -> &mut StdRng {
         &mut self.rand
     }
+
+    /// Random generator for visual effects only. It should not be used for anything that has an
+    /// effect on the simulation.
+    pub fn cosmetic(&mut self) -> &mut StdRng {
+        &mut self.cosmetic
+    }
 }
@@ -1,4 +1,3 @@
-use crate::event::GameEvent;
 use crate::render::ui::gui::GuiContext;
 use crate::render::ui::Gui;
 use crate::resources::Resources;
@@ -9,6 +8,9 @@ use std::time::Duration;
 /// The top of the stack will be used for the update loop. The states below
 /// are still kept in memory so to go back to a previous state, you just have
 /// to pop the stack.
+///
+/// A scene can let the scenes below it keep updating or keep drawing their GUI (see
+/// `SceneTransparency`). That way, a pause menu can be drawn on top of the game HUD.
 pub struct SceneStack<I> {
     states: Vec<Box<dyn Scene<I>>>,
 }
@@ -23,11 +25,57 @@ pub enum SceneResult<I> {
     ReplaceScene(Box<dyn Scene<I>>),
     Push(Box<dyn Scene<I>>),
     Pop,
+    /// Pop scenes until the given scene is at the top of the stack. Nothing happens if the scene
+    /// is not in the stack.
+    PopTo(SceneSelector),
+    /// Pop that many scenes.
+    PopN(usize),
     /// Remove all existing scenes and create the new one.
     ReplaceAll(Box<dyn Scene<I>>),
     Noop,
 }
 
+impl<I> SceneResult<I> {
+    /// Pop scenes until a scene of type S is at the top of the stack.
+    pub fn pop_to<S: Scene<I>>() -> Self {
+        SceneResult::PopTo(SceneSelector::Type(std::any::type_name::<S>()))
+    }
+
+    /// Pop scenes until the scene with the given name is at the top of the stack.
+    pub fn pop_to_name(name: &str) -> Self {
+        SceneResult::PopTo(SceneSelector::Name(name.to_string()))
+    }
+}
+
+/// Find a scene in the stack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SceneSelector {
+    /// Scene with the given `Scene::name`.
+    Name(String),
+    /// Scene with the given type name. See `SceneResult::pop_to`.
+    Type(&'static str),
+}
+
+impl SceneSelector {
+    fn matches<I>(&self, scene: &dyn Scene<I>) -> bool {
+        match self {
+            SceneSelector::Name(name) => scene.name() == Some(name.as_str()),
+            SceneSelector::Type(type_name) => scene.type_name() == *type_name,
+        }
+    }
+}
+
+/// What happens to the scenes below a scene.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SceneTransparency {
+    /// Scenes below are not updated and their GUI is hidden.
+    Opaque,
+    /// Scenes below are frozen but their GUI is still drawn.
+    ShowBelow,
+    /// Scenes below keep updating and drawing their GUI.
+    UpdateBelow,
+}
+
 impl<I> SceneStack<I> {
     pub fn apply_result(
         &mut self,
@@ -39,12 +87,22 @@ impl<I> SceneStack<I> {
             SceneResult::ReplaceScene(state) => self.replace(state, world, resources),
             SceneResult::Push(state) => self.push(state, world, resources),
             SceneResult::Pop => {
-                self.pop(world);
+                self.pop(world, resources);
             }
-            SceneResult::ReplaceAll(state) => {
-                while !self.states.is_empty() {
-                    self.pop(world);
+            SceneResult::PopTo(selector) => {
+                if let Some(idx) = self
+                    .states
+                    .iter()
+                    .rposition(|s| selector.matches(s.as_ref()))
+                {
+                    self.pop_n(self.states.len() - idx - 1, world, resources);
+                } else {
+                    warn!("Cannot pop to {:?}, it is not in the stack", selector);
                 }
+            }
+            SceneResult::PopN(n) => self.pop_n(n, world, resources),
+            SceneResult::ReplaceAll(state) => {
+                while self.remove_top(world).is_some() {}
                 self.push(state, world, resources);
             }
             SceneResult::Noop => (),
@@ -61,7 +119,7 @@ impl<I> SceneStack<I> {
         resources: &mut Resources,
     ) {
         if let Some(current) = self.states.last_mut() {
-            current.on_exit();
+            current.on_exit(world, resources);
         }
 
         self.states.push(state);
@@ -71,16 +129,35 @@ impl<I> SceneStack<I> {
     }
 
     /// Remove the current state and execute its exit callback.
-    pub fn pop(&mut self, world: &mut hecs::World) -> Option<Box<dyn Scene<I>>> {
-        if let Some(mut s) = self.states.pop() {
-            s.on_destroy(world);
-            if let Some(current) = self.states.last() {
-                current.on_enter();
-            }
-            Some(s)
-        } else {
-            None
+    pub fn pop(
+        &mut self,
+        world: &mut hecs::World,
+        resources: &mut Resources,
+    ) -> Option<Box<dyn Scene<I>>> {
+        let s = self.remove_top(world)?;
+        if let Some(current) = self.states.last_mut() {
+            current.on_enter(world, resources);
         }
+        Some(s)
+    }
+
+    /// Remove n states. on_enter is only called for the state that ends up at the top.
+    pub fn pop_n(&mut self, n: usize, world: &mut hecs::World, resources: &mut Resources) {
+        if n == 0 {
+            return;
+        }
+        for _ in 0..n {
+            self.remove_top(world);
+        }
+        if let Some(current) = self.states.last_mut() {
+            current.on_enter(world, resources);
+        }
+    }
+
+    fn remove_top(&mut self, world: &mut hecs::World) -> Option<Box<dyn Scene<I>>> {
+        let mut s = self.states.pop()?;
+        s.on_destroy(world);
+        Some(s)
     }
 
     /// Replace the current state.
@@ -104,6 +181,73 @@ impl<I> SceneStack<I> {
     pub fn current_mut(&mut self) -> Option<&mut Box<dyn Scene<I>>> {
         self.states.last_mut()
     }
+
+    /// Index of the lowest scene that has the given transparency or better through all the scenes
+    /// above it.
+    fn lowest_through(&self, min_transparency: &[SceneTransparency]) -> usize {
+        let mut idx = self.states.len().saturating_sub(1);
+        while idx > 0 && min_transparency.contains(&self.states[idx].transparency()) {
+            idx -= 1;
+        }
+        idx
+    }
+
+    /// Update the current state and the states below it that should keep running. Only the result
+    /// of the current state is returned. Results of the states below are ignored.
+    pub fn update(
+        &mut self,
+        dt: Duration,
+        world: &mut World,
+        resources: &Resources,
+    ) -> SceneResult<I> {
+        if self.states.is_empty() {
+            return SceneResult::Noop;
+        }
+
+        let top = self.states.len() - 1;
+        let lowest = self.lowest_through(&[SceneTransparency::UpdateBelow]);
+        for idx in lowest..top {
+            let result = self.states[idx].update(dt, world, resources);
+            if !matches!(result, SceneResult::Noop) {
+                debug!("Ignore result of scene {} below the top", idx);
+            }
+        }
+        self.states[top].update(dt, world, resources)
+    }
+
+    /// GUI of all the visible states, from bottom to top. Only the current state receives the mouse
+    /// clicks.
+    pub fn prepare_gui(
+        &mut self,
+        dt: Duration,
+        world: &mut World,
+        resources: &Resources,
+        gui_context: &GuiContext,
+    ) -> Option<Gui> {
+        if self.states.is_empty() {
+            return None;
+        }
+
+        let top = self.states.len() - 1;
+        let lowest =
+            self.lowest_through(&[SceneTransparency::ShowBelow, SceneTransparency::UpdateBelow]);
+        let mut gui: Option<Gui> = None;
+        let passive_context = gui_context.without_inputs();
+        for idx in lowest..=top {
+            let context = if idx == top {
+                gui_context
+            } else {
+                &passive_context
+            };
+            if let Some(scene_gui) = self.states[idx].prepare_gui(dt, world, resources, context) {
+                match gui {
+                    Some(ref mut gui) => gui.append(scene_gui),
+                    None => gui = Some(scene_gui),
+                }
+            }
+        }
+        gui
+    }
 }
 
 pub trait Scene<I> {
@@ -121,19 +265,44 @@ pub trait Scene<I> {
     /// on stack.pop
     ///
     /// Careful, this is not call on stack.push. Use the on_create callback instead.
-    fn on_enter(&self) {
+    fn on_enter(&mut self, _world: &mut hecs::World, _resources: &mut Resources) {
         info!("Enter state");
     }
 
     /// Will be called when the state becomes inactive. This is called on
     /// stack.push
-    fn on_exit(&self) {
+    fn on_exit(&mut self, _world: &mut hecs::World, _resources: &mut Resources) {
         info!("Exit state");
     }
 
+    /// Whether the states below this one are updated and drawn when this state is at the top of the
+    /// stack.
+    fn transparency(&self) -> SceneTransparency {
+        SceneTransparency::Opaque
+    }
+
+    /// Name used by `SceneResult::PopTo`.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Type of the scene, used by `SceneResult::pop_to`.
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// If true, the inputs are recorded (or replayed) from the first update of this scene. See
+    /// `core::input::replay`.
+    fn starts_input_recording(&self) -> bool {
+        false
+    }
+
     //fn on_new_world(&mut self);
 
     /// Update gameplay systems.
+    ///
+    /// To react to game events, register readers for the event types in on_create and read them
+    /// here.
     fn update(&mut self, dt: Duration, world: &mut World, resources: &Resources) -> SceneResult<I>;
 
     fn prepare_gui(
@@ -146,9 +315,6 @@ pub trait Scene<I> {
         None
     }
 
-    /// React to game events.
-    fn process_event(&mut self, _world: &mut World, _ev: GameEvent, _resources: &Resources) {}
-
     /// Process input from keyboard/mouse
     fn process_input(&mut self, _world: &mut World, _input: I, _resources: &Resources) {}
 }
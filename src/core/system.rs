@@ -0,0 +1,409 @@
+//! Systems and the schedule that runs them.
+//!
+//! A system is some logic that runs every update on the world. Scenes declare the systems they
+//! need in a `Schedule` instead of calling them one by one. Each system is added to a stage
+//! (input, AI, physics...) and stages are run in order. Inside a stage, systems run in the order
+//! they were added unless they have `before`/`after` constraints.
+//!
+//! ```ignore
+//! let mut schedule = Schedule::default();
+//! schedule.add_system(SystemStage::Input, "player", |world: &mut World, resources: &Resources, dt| {
+//!     player::update_player(world, dt, resources)
+//! });
+//! schedule
+//!     .add_system(SystemStage::Input, "camera", |world: &mut World, resources: &Resources, _dt| {
+//!         update_camera(world, resources)
+//!     })
+//!     .after("player");
+//! ```
+use crate::core::profiler::profile;
+use crate::resources::Resources;
+use downcast_rs::{impl_downcast, Downcast};
+use hecs::World;
+use std::collections::HashMap;
+use std::time::Duration;
+use thiserror::Error;
+
+pub trait System: Downcast {
+    /// Called once before the first run. Register the event readers here.
+    fn setup(&mut self, _world: &mut World, _resources: &mut Resources) {}
+
+    /// Update the world.
+    fn run(&mut self, world: &mut World, resources: &Resources, dt: Duration);
+}
+impl_downcast!(System);
+
+/// Simple functions can be used as systems.
+impl<F> System for F
+where
+    F: FnMut(&mut World, &Resources, Duration) + 'static,
+{
+    fn run(&mut self, world: &mut World, resources: &Resources, dt: Duration) {
+        self(world, resources, dt)
+    }
+}
+
+/// Stages are executed in this order.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SystemStage {
+    Input,
+    Ai,
+    Physics,
+    Collision,
+    Damage,
+    Cleanup,
+}
+
+#[derive(Debug, Error)]
+pub enum ScheduleError {
+    #[error("{first} should run before {second} but its stage {first_stage:?} is after {second_stage:?}")]
+    StageConflict {
+        first: String,
+        first_stage: SystemStage,
+        second: String,
+        second_stage: SystemStage,
+    },
+
+    #[error("Cycle in the ordering constraints between {0:?}")]
+    Cycle(Vec<String>),
+}
+
+/// A system with its position in the schedule.
+pub struct SystemEntry {
+    name: String,
+    stage: SystemStage,
+    before: Vec<String>,
+    after: Vec<String>,
+    system: Box<dyn System>,
+}
+
+impl SystemEntry {
+    /// This system should run before the system with the given name.
+    pub fn before(&mut self, name: &str) -> &mut Self {
+        self.before.push(name.to_string());
+        self
+    }
+
+    /// This system should run after the system with the given name.
+    pub fn after(&mut self, name: &str) -> &mut Self {
+        self.after.push(name.to_string());
+        self
+    }
+}
+
+/// Ordered list of systems.
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<SystemEntry>,
+    /// Order of execution. Computed again when the systems change.
+    order: Option<Vec<usize>>,
+}
+
+impl Schedule {
+    /// Add a system at the end of the stage. Constraints can be added to the returned entry.
+    ///
+    /// If a system already has the same name, it is replaced by this one, stage and constraints
+    /// included. The constraints of the other systems that refer to the name still apply. That
+    /// way, a schedule factory can swap a system of the default schedule.
+    pub fn add_system<S: System>(
+        &mut self,
+        stage: SystemStage,
+        name: &str,
+        system: S,
+    ) -> &mut SystemEntry {
+        self.order = None;
+        let entry = SystemEntry {
+            name: name.to_string(),
+            stage,
+            before: vec![],
+            after: vec![],
+            system: Box::new(system),
+        };
+        match self.systems.iter().position(|s| s.name == name) {
+            Some(idx) => {
+                self.systems[idx] = entry;
+                &mut self.systems[idx]
+            }
+            None => {
+                self.systems.push(entry);
+                self.systems.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// Same as `add_system` but without constraints.
+    pub fn with_system<S: System>(mut self, stage: SystemStage, name: &str, system: S) -> Self {
+        self.add_system(stage, name, system);
+        self
+    }
+
+    /// Remove a system from the schedule.
+    pub fn remove_system(&mut self, name: &str) -> Option<Box<dyn System>> {
+        let idx = self.systems.iter().position(|s| s.name == name)?;
+        self.order = None;
+        Some(self.systems.remove(idx).system)
+    }
+
+    /// Replace the system with the given name. The stage and constraints are kept. Returns false if
+    /// there is no such system.
+    ///
+    /// The setup hook is not called on the new system, so it should not need one if the schedule is
+    /// already set up.
+    pub fn replace_system<S: System>(&mut self, name: &str, system: S) -> bool {
+        if let Some(entry) = self.systems.iter_mut().find(|s| s.name == name) {
+            entry.system = Box::new(system);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Get the first system of the given type.
+    pub fn get<S: System>(&self) -> Option<&S> {
+        self.systems
+            .iter()
+            .find_map(|s| s.system.downcast_ref::<S>())
+    }
+
+    /// Get the first system of the given type.
+    pub fn get_mut<S: System>(&mut self) -> Option<&mut S> {
+        self.systems
+            .iter_mut()
+            .find_map(|s| s.system.downcast_mut::<S>())
+    }
+
+    /// Names of the systems in their execution order.
+    pub fn names(&mut self) -> Result<Vec<&str>, ScheduleError> {
+        self.compute_order()?;
+        let (order, systems) = (self.order.as_ref().unwrap(), &self.systems);
+        Ok(order.iter().map(|&i| systems[i].name.as_str()).collect())
+    }
+
+    /// Sort the systems and call their setup hook.
+    pub fn setup(
+        &mut self,
+        world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<(), ScheduleError> {
+        self.compute_order()?;
+        let (order, systems) = (self.order.as_ref().unwrap(), &mut self.systems);
+        for &i in order {
+            systems[i].system.setup(world, resources);
+        }
+        Ok(())
+    }
+
+    /// Run all the systems once. The time spent in each system is recorded by the profiler.
+    /// Nothing runs if the constraints of the systems cannot be satisfied.
+    pub fn run(
+        &mut self,
+        world: &mut World,
+        resources: &Resources,
+        dt: Duration,
+    ) -> Result<(), ScheduleError> {
+        self.compute_order()?;
+        let (order, systems) = (self.order.as_ref().unwrap(), &mut self.systems);
+        for &i in order {
+            let SystemEntry { name, system, .. } = &mut systems[i];
+            trace!("Run system {}", name);
+            profile(resources, name, || system.run(world, resources, dt));
+        }
+        Ok(())
+    }
+
+    /// Compute the execution order if the systems have changed since last time. It is stored in
+    /// `self.order`.
+    fn compute_order(&mut self) -> Result<(), ScheduleError> {
+        if self.order.is_some() {
+            return Ok(());
+        }
+
+        // names are unique, see `add_system`.
+        let indices: HashMap<&str, usize> = self
+            .systems
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.name.as_str(), i))
+            .collect();
+
+        // edges[i] contains the systems that must run after i.
+        let mut edges: Vec<Vec<usize>> = vec![vec![]; self.systems.len()];
+        let mut add_edge = |first: usize, second: usize| -> Result<(), ScheduleError> {
+            let (f, s) = (&self.systems[first], &self.systems[second]);
+            if f.stage > s.stage {
+                return Err(ScheduleError::StageConflict {
+                    first: f.name.clone(),
+                    first_stage: f.stage,
+                    second: s.name.clone(),
+                    second_stage: s.stage,
+                });
+            }
+            edges[first].push(second);
+            Ok(())
+        };
+        for (i, s) in self.systems.iter().enumerate() {
+            for other in &s.before {
+                match indices.get(other.as_str()) {
+                    Some(&j) => add_edge(i, j)?,
+                    None => debug!("{} should run before unknown system {}", s.name, other),
+                }
+            }
+            for other in &s.after {
+                match indices.get(other.as_str()) {
+                    Some(&j) => add_edge(j, i)?,
+                    None => debug!("{} should run after unknown system {}", s.name, other),
+                }
+            }
+        }
+
+        // Topological sort. When several systems are ready, take the one with the lowest stage,
+        // then the one that was added first.
+        let mut nb_incoming = vec![0; self.systems.len()];
+        for next in edges.iter().flatten() {
+            nb_incoming[*next] += 1;
+        }
+        let mut order = Vec::with_capacity(self.systems.len());
+        let mut done = vec![false; self.systems.len()];
+        while order.len() < self.systems.len() {
+            let ready = (0..self.systems.len())
+                .filter(|&i| !done[i] && nb_incoming[i] == 0)
+                .min_by_key(|&i| (self.systems[i].stage, i));
+
+            match ready {
+                Some(i) => {
+                    done[i] = true;
+                    order.push(i);
+                    for &next in &edges[i] {
+                        nb_incoming[next] -= 1;
+                    }
+                }
+                None => {
+                    let in_cycle = (0..self.systems.len())
+                        .filter(|&i| !done[i])
+                        .map(|i| self.systems[i].name.clone())
+                        .collect();
+                    return Err(ScheduleError::Cycle(in_cycle));
+                }
+            }
+        }
+
+        self.order = Some(order);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn noop(_world: &mut World, _resources: &Resources, _dt: Duration) {}
+
+    /// System that writes its name when it runs.
+    fn record(log: &Rc<RefCell<Vec<&'static str>>>, name: &'static str) -> impl System {
+        let log = Rc::clone(log);
+        move |_: &mut World, _: &Resources, _: Duration| log.borrow_mut().push(name)
+    }
+
+    #[test]
+    fn stages_run_in_order() {
+        let mut schedule = Schedule::default()
+            .with_system(SystemStage::Cleanup, "cleanup", noop)
+            .with_system(SystemStage::Physics, "physics", noop)
+            .with_system(SystemStage::Input, "input", noop)
+            .with_system(SystemStage::Physics, "physics2", noop);
+        assert_eq!(
+            vec!["input", "physics", "physics2", "cleanup"],
+            schedule.names().unwrap()
+        );
+    }
+
+    #[test]
+    fn before_and_after_change_the_order_in_a_stage() {
+        let mut schedule = Schedule::default();
+        schedule.add_system(SystemStage::Ai, "a", noop).after("c");
+        schedule.add_system(SystemStage::Ai, "b", noop);
+        schedule.add_system(SystemStage::Ai, "c", noop).after("b");
+        schedule.add_system(SystemStage::Ai, "d", noop).before("b");
+        // unknown systems are ignored.
+        schedule.add_system(SystemStage::Ai, "e", noop).before("x");
+        assert_eq!(vec!["d", "b", "c", "a", "e"], schedule.names().unwrap());
+    }
+
+    #[test]
+    fn constraint_against_the_stage_order() {
+        let mut schedule = Schedule::default();
+        schedule.add_system(SystemStage::Input, "input", noop);
+        schedule
+            .add_system(SystemStage::Cleanup, "cleanup", noop)
+            .before("input");
+        match schedule.names() {
+            Err(ScheduleError::StageConflict {
+                first,
+                first_stage,
+                second,
+                second_stage,
+            }) => {
+                assert_eq!("cleanup", first);
+                assert_eq!(SystemStage::Cleanup, first_stage);
+                assert_eq!("input", second);
+                assert_eq!(SystemStage::Input, second_stage);
+            }
+            other => panic!("expected a stage conflict, got {:?}", other),
+        }
+
+        // the same constraint in the other direction is already true.
+        let mut schedule = Schedule::default();
+        schedule.add_system(SystemStage::Cleanup, "cleanup", noop);
+        schedule
+            .add_system(SystemStage::Input, "input", noop)
+            .before("cleanup");
+        assert_eq!(vec!["input", "cleanup"], schedule.names().unwrap());
+    }
+
+    #[test]
+    fn cycle_is_an_error() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let mut schedule = Schedule::default();
+        schedule.add_system(SystemStage::Ai, "first", record(&log, "first"));
+        schedule
+            .add_system(SystemStage::Ai, "a", record(&log, "a"))
+            .after("b");
+        schedule
+            .add_system(SystemStage::Ai, "b", record(&log, "b"))
+            .after("a");
+
+        match schedule.names() {
+            Err(ScheduleError::Cycle(names)) => assert_eq!(vec!["a", "b"], names),
+            other => panic!("expected a cycle, got {:?}", other),
+        }
+        let mut world = World::new();
+        let resources = Resources::default();
+        assert!(schedule
+            .run(&mut world, &resources, Duration::from_millis(16))
+            .is_err());
+        assert!(log.borrow().is_empty());
+    }
+
+    #[test]
+    fn same_name_replaces_the_system() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let mut schedule = Schedule::default();
+        schedule.add_system(SystemStage::Physics, "stage", record(&log, "old"));
+        schedule
+            .add_system(SystemStage::Physics, "camera", record(&log, "camera"))
+            .after("stage");
+        schedule
+            .add_system(SystemStage::Input, "stage", record(&log, "new"))
+            .before("other");
+
+        let mut world = World::new();
+        let resources = Resources::default();
+        schedule
+            .run(&mut world, &resources, Duration::from_millis(16))
+            .unwrap();
+        assert_eq!(vec!["new", "camera"], *log.borrow());
+        assert_eq!(vec!["stage", "camera"], schedule.names().unwrap());
+    }
+}
@@ -1,7 +1,7 @@
 use glam::{Mat3, Mat4, Quat, Vec2};
 use log::debug;
 use serde_derive::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 
 /// Transform of an element to place it on the screen
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
@@ -35,6 +35,66 @@ impl Transform {
         self.translation += translation;
         self.dirty = true;
     }
+
+    /// Blend between two transforms. Rotation will take the shortest path.
+    pub fn lerp(&self, other: &Transform, alpha: f32) -> Transform {
+        let mut delta_rotation = other.rotation - self.rotation;
+        while delta_rotation > std::f32::consts::PI {
+            delta_rotation -= 2.0 * std::f32::consts::PI;
+        }
+        while delta_rotation < -std::f32::consts::PI {
+            delta_rotation += 2.0 * std::f32::consts::PI;
+        }
+
+        Transform {
+            translation: self.translation.lerp(other.translation, alpha),
+            scale: self.scale.lerp(other.scale, alpha),
+            rotation: self.rotation + delta_rotation * alpha,
+            dirty: other.dirty,
+        }
+    }
+}
+
+/// Transforms as they were before the last fixed update. The renderer blends them with the current
+/// transforms so that movement stays smooth when the screen refreshes faster than the simulation.
+#[derive(Debug, Default)]
+pub struct PreviousTransforms {
+    transforms: HashMap<hecs::Entity, Transform>,
+    cameras: HashMap<hecs::Entity, Vec2>,
+}
+
+impl PreviousTransforms {
+    /// Keep a copy of the current state. Called before each fixed update.
+    pub fn capture(&mut self, world: &hecs::World) {
+        self.transforms.clear();
+        self.transforms
+            .extend(world.query::<&Transform>().iter().map(|(e, t)| (e, *t)));
+
+        self.cameras.clear();
+        self.cameras.extend(
+            world
+                .query::<&crate::core::camera::Camera>()
+                .iter()
+                .map(|(e, c)| (e, c.position)),
+        );
+    }
+
+    /// Transform to draw for the entity. Entities that did not exist before the last update are
+    /// drawn at their current position.
+    pub fn interpolate(&self, e: hecs::Entity, current: &Transform, alpha: f32) -> Transform {
+        match self.transforms.get(&e) {
+            Some(previous) => previous.lerp(current, alpha),
+            None => *current,
+        }
+    }
+
+    /// Same as `interpolate` but for the camera position.
+    pub fn interpolate_camera(&self, e: hecs::Entity, current: Vec2, alpha: f32) -> Vec2 {
+        match self.cameras.get(&e) {
+            Some(previous) => previous.lerp(current, alpha),
+            None => current,
+        }
+    }
 }
 
 /// Transform relative the the parent component.
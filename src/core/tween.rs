@@ -0,0 +1,413 @@
+//! Animate a value over time. E.g. move an entity, fade its color or move the camera.
+//!
+//! ```ignore
+//! world.spawn((
+//!     transform,
+//!     sprite,
+//!     Tween::new(TweenTarget::Scale { from: small, to: big }, 0.5)
+//!         .with_easing(Interpolation::Ease(Easing::BackOut))
+//!         .then(Tween::new(TweenTarget::Tint { from: white, to: transparent }, 1.0))
+//!         .delete_on_finish(),
+//! ));
+//! ```
+//!
+//! Translation, rotation and scale are applied to the `LocalTransform` if the entity has one, so
+//! that children can be animated relative to their parent.
+use crate::core::camera::Camera;
+use crate::core::colors::RgbaColor;
+use crate::core::curve::{Curve, Interpolation};
+use crate::core::transform::{LocalTransform, Transform};
+use crate::event::{Delete, TweenFinished};
+use crate::render::sprite::Tint;
+use crate::resources::Resources;
+use glam::Vec2;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// The property to animate, with its start and end values.
+#[derive(Debug, Clone, Copy)]
+pub enum TweenTarget {
+    Translation { from: Vec2, to: Vec2 },
+    Rotation { from: f32, to: f32 },
+    Scale { from: Vec2, to: Vec2 },
+    Tint { from: RgbaColor, to: RgbaColor },
+    CameraPosition { from: Vec2, to: Vec2 },
+}
+
+/// How many times the tween plays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Repeat {
+    /// Play again n times after the first time.
+    Times(u32),
+    Forever,
+}
+
+#[derive(Debug, Clone)]
+pub struct Tween {
+    /// All the targets are animated at the same time.
+    targets: Vec<TweenTarget>,
+    /// Duration of one play, in seconds.
+    duration: f32,
+    /// Wait before starting, in seconds.
+    delay: f32,
+    /// Maps the progress (between 0 and 1) to the interpolation factor.
+    easing: Curve<f32>,
+    repeat: Repeat,
+    /// If true, every other play goes from `to` back to `from`.
+    yoyo: bool,
+    /// Tweens to play after this one.
+    next: VecDeque<Tween>,
+    /// Name sent with the `TweenFinished` event.
+    name: Option<String>,
+    delete_on_finish: bool,
+
+    elapsed: f32,
+    plays: u32,
+}
+
+impl Tween {
+    pub fn new(target: TweenTarget, duration: f32) -> Self {
+        Self {
+            targets: vec![target],
+            duration,
+            delay: 0.0,
+            easing: linear_easing(),
+            repeat: Repeat::Times(0),
+            yoyo: false,
+            next: VecDeque::new(),
+            name: None,
+            delete_on_finish: false,
+            elapsed: 0.0,
+            plays: 0,
+        }
+    }
+
+    /// Animate another property at the same time.
+    pub fn and(mut self, target: TweenTarget) -> Self {
+        self.targets.push(target);
+        self
+    }
+
+    pub fn with_delay(mut self, delay: f32) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn with_easing(mut self, interpolation: Interpolation) -> Self {
+        self.easing = linear_easing().with_interpolation(interpolation);
+        self
+    }
+
+    /// Use any curve from 0 to 1 as easing.
+    pub fn with_easing_curve(mut self, easing: Curve<f32>) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: Repeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    /// Go back and forth. Each way counts as one play for `Repeat`.
+    pub fn with_yoyo(mut self) -> Self {
+        self.yoyo = true;
+        self
+    }
+
+    pub fn named(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Play the other tween after this one is finished. The event and the deletion only happen
+    /// at the end of the whole sequence.
+    pub fn then(mut self, next: Tween) -> Self {
+        self.next.push_back(next);
+        self
+    }
+
+    /// Delete the entity when the sequence is finished.
+    pub fn delete_on_finish(mut self) -> Self {
+        self.delete_on_finish = true;
+        self
+    }
+
+    /// Whether this tween or one played after it moves the camera. The camera rig does not move
+    /// the camera during such a sequence, see `gameplay::camera::update_camera`.
+    pub fn moves_camera(&self) -> bool {
+        self.targets
+            .iter()
+            .any(|t| matches!(t, TweenTarget::CameraPosition { .. }))
+            || self.next.iter().any(Tween::moves_camera)
+    }
+
+    /// Advance the time. Returns true when the tween and all the tweens after it are finished.
+    fn advance(&mut self, dt: f32) -> bool {
+        self.elapsed += dt;
+        loop {
+            if self.elapsed < self.delay + self.duration {
+                return false;
+            }
+
+            let can_repeat = match self.repeat {
+                Repeat::Times(n) => self.plays < n,
+                Repeat::Forever => self.duration > 0.0,
+            };
+            if can_repeat {
+                // the delay is only before the first play.
+                self.elapsed -= self.duration;
+                self.plays += 1;
+                continue;
+            }
+
+            match self.next.pop_front() {
+                Some(mut next) => {
+                    let leftover = self.elapsed - self.delay - self.duration;
+                    next.next.extend(self.next.drain(..));
+                    next.name = next.name.or_else(|| self.name.take());
+                    next.delete_on_finish |= self.delete_on_finish;
+                    *self = next;
+                    self.elapsed += leftover;
+                }
+                None => return true,
+            }
+        }
+    }
+
+    /// Interpolation factor of the current play.
+    fn factor(&self) -> f32 {
+        let progress = if self.duration > 0.0 {
+            ((self.elapsed - self.delay) / self.duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let progress = if self.yoyo && self.plays % 2 == 1 {
+            1.0 - progress
+        } else {
+            progress
+        };
+        self.easing.y(progress).unwrap_or(progress)
+    }
+}
+
+fn linear_easing() -> Curve<f32> {
+    Curve::new(vec![0.0, 1.0], vec![0.0, 1.0]).expect("valid curve")
+}
+
+fn lerp<T>(from: T, to: T, s: f32) -> T
+where
+    T: Copy
+        + std::ops::Add<Output = T>
+        + std::ops::Sub<Output = T>
+        + std::ops::Mul<f32, Output = T>,
+{
+    from + (to - from) * s
+}
+
+fn apply(world: &hecs::World, e: hecs::Entity, target: TweenTarget, s: f32) {
+    match target {
+        TweenTarget::Translation { from, to } => {
+            let value = lerp(from, to, s);
+            if let Ok(mut local) = world.get_mut::<LocalTransform>(e) {
+                local.translation = value;
+                local.dirty = true;
+            } else if let Ok(mut t) = world.get_mut::<Transform>(e) {
+                t.translation = value;
+                t.dirty = true;
+            }
+        }
+        TweenTarget::Rotation { from, to } => {
+            let value = lerp(from, to, s);
+            if let Ok(mut local) = world.get_mut::<LocalTransform>(e) {
+                local.rotation = value;
+                local.dirty = true;
+            } else if let Ok(mut t) = world.get_mut::<Transform>(e) {
+                t.rotation = value;
+                t.dirty = true;
+            }
+        }
+        TweenTarget::Scale { from, to } => {
+            let value = lerp(from, to, s);
+            if let Ok(mut local) = world.get_mut::<LocalTransform>(e) {
+                local.scale = value;
+                local.dirty = true;
+            } else if let Ok(mut t) = world.get_mut::<Transform>(e) {
+                t.scale = value;
+                t.dirty = true;
+            }
+        }
+        TweenTarget::Tint { from, to } => {
+            if let Ok(mut tint) = world.get_mut::<Tint>(e) {
+                tint.color = lerp(from, to, s);
+            }
+        }
+        TweenTarget::CameraPosition { from, to } => {
+            if let Ok(mut camera) = world.get_mut::<Camera>(e) {
+                camera.position = lerp(from, to, s);
+            }
+        }
+    }
+}
+
+/// Advance all the tweens and update the animated properties. Finished tweens are removed.
+pub fn update_tweens(world: &mut hecs::World, resources: &Resources, dt: Duration) {
+    let mut updates = vec![];
+    let mut finished = vec![];
+    for (e, tween) in world.query::<&mut Tween>().iter() {
+        if tween.advance(dt.as_secs_f32()) {
+            finished.push((e, tween.name.take(), tween.delete_on_finish));
+        }
+        // a tween that has not started yet does not touch the values.
+        if tween.elapsed >= tween.delay {
+            updates.push((e, tween.targets.clone(), tween.factor()));
+        }
+    }
+
+    for (e, targets, s) in updates {
+        for target in targets {
+            apply(world, e, target, s);
+        }
+    }
+
+    let mut events = vec![];
+    let mut deletes = vec![];
+    for (e, name, delete) in finished {
+        let _ = world.remove_one::<Tween>(e);
+        events.push(TweenFinished { entity: e, name });
+        if delete {
+            deletes.push(Delete(e));
+        }
+    }
+    resources.write_events(events);
+    resources.write_events(deletes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shrev::EventChannel;
+
+    fn assert_close(expected: f32, actual: f32) {
+        assert!(
+            (expected - actual).abs() < 1e-5,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    fn rotation(duration: f32) -> Tween {
+        Tween::new(TweenTarget::Rotation { from: 0.0, to: 1.0 }, duration)
+    }
+
+    #[test]
+    fn delay_is_waited_before_the_first_play() {
+        let mut tween = rotation(1.0).with_delay(1.0).with_repeat(Repeat::Times(1));
+        assert!(!tween.advance(0.5));
+        assert!(tween.elapsed < tween.delay);
+        assert!(!tween.advance(1.0));
+        assert_close(0.5, tween.factor());
+
+        // no delay before the second play.
+        assert!(!tween.advance(1.0));
+        assert_eq!(1, tween.plays);
+        assert_close(0.5, tween.factor());
+        assert!(tween.advance(0.5));
+    }
+
+    #[test]
+    fn repeat_plays_again() {
+        let mut tween = rotation(1.0).with_repeat(Repeat::Times(2));
+        assert!(!tween.advance(2.5));
+        assert_eq!(2, tween.plays);
+        assert_close(0.5, tween.factor());
+        assert!(tween.advance(0.5));
+        assert_close(1.0, tween.factor());
+
+        let mut tween = rotation(1.0).with_repeat(Repeat::Forever);
+        assert!(!tween.advance(100.25));
+        assert_close(0.25, tween.factor());
+
+        // would never end otherwise.
+        let mut tween = rotation(0.0).with_repeat(Repeat::Forever);
+        assert!(tween.advance(0.1));
+    }
+
+    #[test]
+    fn yoyo_goes_back_every_other_play() {
+        let mut tween = rotation(1.0).with_yoyo().with_repeat(Repeat::Times(3));
+        let mut factors = vec![];
+        for _ in 0..4 {
+            tween.advance(if factors.is_empty() { 0.25 } else { 1.0 });
+            factors.push(tween.factor());
+        }
+        for (expected, actual) in [0.25, 0.75, 0.25, 0.75].iter().zip(factors) {
+            assert_close(*expected, actual);
+        }
+        assert!(tween.advance(1.0));
+        // ends on the way back.
+        assert_close(0.0, tween.factor());
+    }
+
+    #[test]
+    fn then_carries_the_leftover_time() {
+        let mut tween = rotation(1.0)
+            .named("intro")
+            .delete_on_finish()
+            .then(rotation(2.0))
+            .then(rotation(1.0).named("last"));
+
+        assert!(!tween.advance(1.5));
+        assert_close(2.0, tween.duration);
+        assert_close(0.25, tween.factor());
+        // the settings of the sequence go with it.
+        assert_eq!(Some("intro"), tween.name.as_deref());
+        assert!(tween.delete_on_finish);
+
+        assert!(!tween.advance(2.0));
+        assert_close(0.5, tween.factor());
+        // a tween keeps its own name.
+        assert_eq!(Some("last"), tween.name.as_deref());
+        assert!(tween.delete_on_finish);
+        assert!(tween.next.is_empty());
+
+        assert!(tween.advance(0.5));
+    }
+
+    #[test]
+    fn events_are_sent_at_the_end_of_the_sequence() {
+        let mut world = hecs::World::new();
+        let mut resources = Resources::default();
+        let mut finished_rdr = resources.register_reader::<TweenFinished>();
+        let mut delete_rdr = resources.register_reader::<Delete>();
+        let e = world.spawn((
+            Transform::default(),
+            rotation(1.0)
+                .named("fade")
+                .delete_on_finish()
+                .then(rotation(1.0)),
+        ));
+
+        let dt = Duration::from_millis(600);
+        let mut nb_finished = vec![];
+        let mut nb_deleted = vec![];
+        for _ in 0..4 {
+            update_tweens(&mut world, &resources, dt);
+            let finished = resources.fetch::<EventChannel<TweenFinished>>().unwrap();
+            let finished: Vec<_> = finished.read(&mut finished_rdr).cloned().collect();
+            let deleted = resources.fetch::<EventChannel<Delete>>().unwrap();
+            nb_deleted.push(deleted.read(&mut delete_rdr).count());
+            if let Some(event) = finished.first() {
+                assert_eq!(e, event.entity);
+                assert_eq!(Some("fade"), event.name.as_deref());
+            }
+            nb_finished.push(finished.len());
+        }
+
+        assert_eq!(vec![0, 0, 0, 1], nb_finished);
+        assert_eq!(vec![0, 0, 0, 1], nb_deleted);
+        assert!(world.get::<Tween>(e).is_err());
+        assert_close(1.0, world.get::<Transform>(e).unwrap().rotation);
+    }
+}
@@ -1,20 +1,149 @@
+//! Size of the window and of the virtual canvas.
+//!
+//! The game is laid out on a virtual canvas (`width` x `height`) that does not depend on the size
+//! of the window. The canvas is then scaled to the framebuffer according to the `ScaleMode`.
+//!
+//! The window size and the framebuffer size are not the same on HiDPI screens: the cursor
+//! position is in window coordinates but rendering is done in framebuffer pixels.
+
+use serde_derive::{Deserialize, Serialize};
+
+/// How the virtual canvas is shown in the framebuffer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScaleMode {
+    /// Keep the aspect ratio and add black bars on the sides.
+    Letterbox,
+    /// Fill the whole framebuffer, even if it distorts the image.
+    Stretch,
+    /// The canvas is as big as the framebuffer so a bigger window shows more of the world.
+    Expand,
+}
+
+impl Default for ScaleMode {
+    fn default() -> Self {
+        ScaleMode::Letterbox
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct WindowDim {
+    /// Width of the virtual canvas.
     pub width: u32,
+    /// Height of the virtual canvas.
     pub height: u32,
+
+    /// Size of the canvas when the scale mode is not `Expand`.
+    virtual_size: (u32, u32),
+    /// Size of the window in screen coordinates.
+    window_size: (u32, u32),
+    /// Size of the framebuffer in pixels.
+    framebuffer_size: (u32, u32),
+    scale_mode: ScaleMode,
 }
 
 impl WindowDim {
+    /// Canvas, window and framebuffer of the same size.
     pub fn new(width: u32, height: u32) -> Self {
-        Self { width, height }
+        Self {
+            width,
+            height,
+            virtual_size: (width, height),
+            window_size: (width, height),
+            framebuffer_size: (width, height),
+            scale_mode: ScaleMode::Letterbox,
+        }
     }
 
-    pub fn resize(&mut self, width: u32, height: u32) {
-        self.width = width;
-        self.height = height;
+    pub fn with_scale_mode(mut self, scale_mode: ScaleMode) -> Self {
+        self.set_scale_mode(scale_mode);
+        self
+    }
+
+    pub fn set_scale_mode(&mut self, scale_mode: ScaleMode) {
+        self.scale_mode = scale_mode;
+        self.update_canvas();
+    }
+
+    pub fn scale_mode(&self) -> ScaleMode {
+        self.scale_mode
+    }
+
+    /// The window or the framebuffer changed size.
+    pub fn resize(
+        &mut self,
+        window_width: u32,
+        window_height: u32,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+    ) {
+        self.window_size = (window_width.max(1), window_height.max(1));
+        self.framebuffer_size = (framebuffer_width.max(1), framebuffer_height.max(1));
+        self.update_canvas();
+    }
+
+    pub fn framebuffer_size(&self) -> (u32, u32) {
+        self.framebuffer_size
+    }
+
+    pub fn window_size(&self) -> (u32, u32) {
+        self.window_size
     }
 
     pub fn to_vec2(&self) -> glam::Vec2 {
         glam::vec2(self.width as f32, self.height as f32)
     }
+
+    /// Part of the framebuffer where the canvas is drawn: x, y (from the bottom left), width and
+    /// height in pixels.
+    pub fn viewport(&self) -> (u32, u32, u32, u32) {
+        let (fb_w, fb_h) = self.framebuffer_size;
+        match self.scale_mode {
+            ScaleMode::Stretch | ScaleMode::Expand => (0, 0, fb_w, fb_h),
+            ScaleMode::Letterbox => {
+                let scale = (fb_w as f32 / self.width as f32).min(fb_h as f32 / self.height as f32);
+                let w = ((self.width as f32 * scale).round() as u32).min(fb_w);
+                let h = ((self.height as f32 * scale).round() as u32).min(fb_h);
+                ((fb_w - w) / 2, (fb_h - h) / 2, w, h)
+            }
+        }
+    }
+
+    /// Convert a cursor position (window coordinates, origin at the top left) to the canvas
+    /// (origin at the top left). The position can be outside of the canvas when it is in the black
+    /// bars.
+    pub fn window_to_canvas(&self, cursor: glam::Vec2) -> glam::Vec2 {
+        let (win_w, win_h) = self.window_size;
+        let (fb_w, fb_h) = self.framebuffer_size;
+        let pixel = glam::vec2(
+            cursor.x * fb_w as f32 / win_w as f32,
+            cursor.y * fb_h as f32 / win_h as f32,
+        );
+
+        // viewport origin is at the bottom left.
+        let (vx, vy, vw, vh) = self.viewport();
+        let top = fb_h as f32 - (vy + vh) as f32;
+        glam::vec2(
+            (pixel.x - vx as f32) * self.width as f32 / vw as f32,
+            (pixel.y - top) * self.height as f32 / vh as f32,
+        )
+    }
+
+    /// Convert a cursor position (window coordinates) to normalized device coordinates of the
+    /// canvas, between -1 and 1 with y up.
+    pub fn window_to_ndc(&self, cursor: glam::Vec2) -> glam::Vec2 {
+        let canvas = self.window_to_canvas(cursor);
+        glam::vec2(
+            canvas.x / self.width as f32 * 2.0 - 1.0,
+            1.0 - canvas.y / self.height as f32 * 2.0,
+        )
+    }
+
+    fn update_canvas(&mut self) {
+        let (width, height) = match self.scale_mode {
+            ScaleMode::Expand => self.framebuffer_size,
+            ScaleMode::Letterbox | ScaleMode::Stretch => self.virtual_size,
+        };
+        self.width = width;
+        self.height = height;
+    }
 }
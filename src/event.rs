@@ -1,6 +1,110 @@
+//! Events sent between the systems.
+//!
+//! Each event type has its own `EventChannel` in the resources so a system only reads the events it
+//! cares about. Adding a new event is just adding a new type and registering its channel.
+//!
+//! ```ignore
+//! // when creating the system.
+//! let rdr_id = resources.register_reader::<Hit>();
+//!
+//! // somewhere else.
+//! resources.write_event(Hit { entity, details });
+//!
+//! // in the system.
+//! let chan = resources.fetch::<EventChannel<Hit>>().unwrap();
+//! for hit in chan.read(&mut rdr_id) { ... }
+//! ```
+//!
+//! `GameEvent` is the old enum with all the events. It is kept so that code that still writes it
+//! can be migrated progressively: `GameEvent::write` sends the variant to its typed channel.
 use crate::gameplay::explosion::ExplosionDetails;
 use crate::gameplay::health::HitDetails;
+use crate::resources::Resources;
 
+/// Remove an entity from the world at the end of the frame.
+#[derive(Debug, Clone, Copy)]
+pub struct Delete(pub hecs::Entity);
+
+/// An entity was hit.
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub entity: hecs::Entity,
+    pub details: HitDetails,
+}
+
+/// The player died.
+#[derive(Debug, Clone, Copy)]
+pub struct GameOver;
+
+/// No more stages, you are the boss !
+#[derive(Debug, Clone, Copy)]
+pub struct YouWin;
+
+/// Enemy that dies, its position, the amount of scrap to gain, the % of chance to drop a pickup.
+#[derive(Debug, Clone, Copy)]
+pub struct EnemyDied {
+    pub entity: hecs::Entity,
+    pub position: glam::Vec2,
+    pub scrap: (u32, u32),
+    pub pickup_drop: u8,
+}
+
+/// Some text to display for the player. E.g. Pickup.
+#[derive(Debug, Clone)]
+pub struct InfoText(pub String);
+
+/// Play the background music.
+#[derive(Debug, Clone)]
+pub struct PlayBackgroundMusic(pub String);
+
+/// Play some sound
+#[derive(Debug, Clone)]
+pub struct PlaySound(pub String);
+
+/// Start the next stage.
+#[derive(Debug, Clone)]
+pub struct NextStage(pub String);
+
+/// Something exploded :D
+#[derive(Debug, Clone, Copy)]
+pub struct Exploded {
+    pub entity: hecs::Entity,
+    pub details: ExplosionDetails,
+    pub position: glam::Vec2,
+}
+
+/// A file of the asset folder changed and was reloaded. The path is relative to the asset folder.
+#[derive(Debug, Clone)]
+pub struct AssetReloaded(pub std::path::PathBuf);
+
+/// A tween is finished. See `core::tween`.
+#[derive(Debug, Clone)]
+pub struct TweenFinished {
+    pub entity: hecs::Entity,
+    pub name: Option<String>,
+}
+
+/// Anything can be an event as long as it can be sent between threads.
+pub trait Event: Send + Sync + 'static {}
+impl<T> Event for T where T: Send + Sync + 'static {}
+
+/// Create the channels for all the events of the game.
+pub fn register_game_events(resources: &mut Resources) {
+    resources.register_event::<Delete>();
+    resources.register_event::<Hit>();
+    resources.register_event::<GameOver>();
+    resources.register_event::<YouWin>();
+    resources.register_event::<EnemyDied>();
+    resources.register_event::<InfoText>();
+    resources.register_event::<PlayBackgroundMusic>();
+    resources.register_event::<PlaySound>();
+    resources.register_event::<NextStage>();
+    resources.register_event::<Exploded>();
+    resources.register_event::<TweenFinished>();
+    resources.register_event::<AssetReloaded>();
+}
+
+/// All the events in one enum. Use the typed events instead.
 #[derive(Debug, Clone)]
 pub enum GameEvent {
     Delete(hecs::Entity),
@@ -29,3 +133,35 @@ pub enum GameEvent {
     /// No more stages, you are the boss !
     YouWin,
 }
+
+impl GameEvent {
+    /// Send the event to its typed channel.
+    pub fn write(self, resources: &Resources) {
+        match self {
+            GameEvent::Delete(e) => resources.write_event(Delete(e)),
+            GameEvent::Hit(entity, details) => resources.write_event(Hit { entity, details }),
+            GameEvent::GameOver => resources.write_event(GameOver),
+            GameEvent::TextUpdated => (),
+            GameEvent::EnemyDied(entity, position, scrap, pickup_drop) => {
+                resources.write_event(EnemyDied {
+                    entity,
+                    position,
+                    scrap,
+                    pickup_drop,
+                })
+            }
+            GameEvent::InfoText(text) => resources.write_event(InfoText(text)),
+            GameEvent::PlayBackgroundMusic(name) => {
+                resources.write_event(PlayBackgroundMusic(name))
+            }
+            GameEvent::PlaySound(name) => resources.write_event(PlaySound(name)),
+            GameEvent::NextStage(name) => resources.write_event(NextStage(name)),
+            GameEvent::Explosion(entity, details, position) => resources.write_event(Exploded {
+                entity,
+                details,
+                position,
+            }),
+            GameEvent::YouWin => resources.write_event(YouWin),
+        }
+    }
+}
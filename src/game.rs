@@ -1,31 +1,37 @@
+use crate::assets::sprite::SpriteAsset;
+use crate::assets::worker::WorkerPool;
 #[cfg(feature = "hot-reload")]
 use crate::assets::HotReloader;
+use crate::assets::{AssetManager, Budget};
 use crate::config::AudioConfig;
 use crate::core::audio::AudioSystem;
-use crate::core::camera::{Camera, ProjectionMatrix};
-use crate::core::input::{Input, InputAction};
-use crate::core::random::{RandomGenerator, Seed};
-use crate::core::scene::{Scene, SceneStack};
-use crate::core::transform::update_transforms;
-use crate::core::window::WindowDim;
-use crate::event::GameEvent;
-use crate::gameplay::collision::CollisionWorld;
-use crate::gameplay::delete::GarbageCollector;
-use crate::render::path::debug::DebugQueue;
+use crate::core::camera::ProjectionMatrix;
+use crate::core::input::replay::InputRecording;
+use crate::core::input::InputAction;
+use crate::core::profiler::Profiler;
+use crate::core::random::Seed;
+use crate::core::scene::{Scene, SceneResult};
+use crate::core::window::{ScaleMode, WindowDim};
 use crate::render::ui::gui::GuiContext;
 use crate::render::Renderer;
-use crate::resources::Resources;
+use crate::runner::{GameRunner, GameRunnerBuilder};
 use crate::{HEIGHT, WIDTH};
-use glfw::{Context, Key, MouseButton, WindowEvent};
-use log::info;
+use glfw::{Action, Context, Key, MouseButton, WindowEvent};
+use log::{debug, error, info};
 use luminance_glfw::GlfwSurface;
-use shrev::{EventChannel, ReaderId};
 use std::any::Any;
 use std::collections::HashMap;
-use std::marker::PhantomData;
-use std::thread;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
+pub use crate::runner::TimeStep;
+
+/// Show or hide the profiler overlay.
+const PROFILER_OVERLAY_KEY: Key = Key::F3;
+/// Save the profiler samples to `PROFILER_CSV_FILE`.
+const PROFILER_EXPORT_KEY: Key = Key::F4;
+const PROFILER_CSV_FILE: &str = "profiler.csv";
+
 /// GameBuilder is used to create a new game. Game struct has a lot of members that do not need to be
 /// exposed so gamebuilder provides a simpler way to get started.
 pub struct GameBuilder<'a, A>
@@ -33,13 +39,13 @@ where
     A: InputAction,
 {
     surface: &'a mut GlfwSurface,
-    scene: Option<Box<dyn Scene<WindowEvent>>>,
-    resources: Resources,
-    phantom: PhantomData<A>,
-    seed: Option<Seed>,
-    input_config: Option<(HashMap<Key, A>, HashMap<MouseButton, A>)>,
+    runner: GameRunnerBuilder<A>,
     gui_context: GuiContext,
     audio_config: AudioConfig,
+    recording_path: Option<PathBuf>,
+    scale_mode: ScaleMode,
+    loading_threads: usize,
+    texture_budget: Option<Budget>,
 }
 
 impl<'a, A> GameBuilder<'a, A>
@@ -47,35 +53,21 @@ where
     A: InputAction + 'static,
 {
     pub fn new(surface: &'a mut GlfwSurface) -> Self {
-        // resources will need at least an event channel and an input
-        let mut resources = Resources::default();
-        let chan: EventChannel<GameEvent> = EventChannel::new();
-        resources.insert(chan);
-
-        // and some asset manager;
-        crate::assets::create_asset_managers(surface, &mut resources);
-
-        // the proj matrix.
-        resources.insert(ProjectionMatrix::new(WIDTH as f32, HEIGHT as f32));
-        resources.insert(WindowDim::new(WIDTH, HEIGHT));
-        resources.insert(CollisionWorld::default());
-        resources.insert(DebugQueue::default());
-
         Self {
             gui_context: GuiContext::new(WindowDim::new(WIDTH, HEIGHT)),
             surface,
-            scene: None,
-            resources,
-            input_config: None,
-            phantom: PhantomData::default(),
-            seed: None,
+            runner: GameRunnerBuilder::new(),
             audio_config: AudioConfig::default(),
+            recording_path: None,
+            scale_mode: ScaleMode::default(),
+            loading_threads: WorkerPool::default_size(),
+            texture_budget: None,
         }
     }
 
     /// Set up the first scene.
     pub fn for_scene(mut self, scene: Box<dyn Scene<WindowEvent>>) -> Self {
-        self.scene = Some(scene);
+        self.runner = self.runner.for_scene(scene);
         self
     }
 
@@ -84,7 +76,7 @@ where
         key_map: HashMap<Key, A>,
         btn_map: HashMap<MouseButton, A>,
     ) -> Self {
-        self.input_config = Some((key_map, btn_map));
+        self.runner = self.runner.with_input_config(key_map, btn_map);
         self
     }
 
@@ -94,74 +86,96 @@ where
         self
     }
 
+    /// How the virtual canvas (WIDTH x HEIGHT) is scaled to the window. Default is letterbox.
+    pub fn with_scale_mode(mut self, scale_mode: ScaleMode) -> Self {
+        self.scale_mode = scale_mode;
+        self
+    }
+
+    /// Number of threads that load the assets in the background. With 0, the assets are loaded
+    /// synchronously on the game thread.
+    pub fn with_loading_threads(mut self, loading_threads: usize) -> Self {
+        self.loading_threads = loading_threads;
+        self
+    }
+
+    /// Maximum number of textures or texture memory. The least recently used sprites are unloaded
+    /// when over budget and loaded again when needed. No budget by default.
+    pub fn with_texture_budget(mut self, budget: Budget) -> Self {
+        self.texture_budget = Some(budget);
+        self
+    }
+
     /// Add custom resources.
     pub fn with_resource<T: Any>(mut self, r: T) -> Self {
-        self.resources.insert(r);
+        self.runner = self.runner.with_resource(r);
         self
     }
 
     pub fn with_seed(mut self, seed: Seed) -> Self {
-        self.seed = Some(seed);
+        self.runner = self.runner.with_seed(seed);
         self
     }
 
-    pub fn build(mut self) -> Game<'a, A> {
-        let renderer = Renderer::new(self.surface, &self.gui_context);
-        // Need some input :D
-        let input: Input<A> = {
-            let (key_mapping, btn_mapping) = self
-                .input_config
-                .unwrap_or((A::get_default_key_mapping(), A::get_default_mouse_mapping()));
-            Input::new(key_mapping, btn_mapping)
-        };
-        self.resources.insert(input);
-        let mut world = hecs::World::new();
-
-        // if a seed is provided, let's add it to the resources.
-        if let Some(seed) = self.seed {
-            self.resources.insert(RandomGenerator::new(seed));
-        } else {
-            self.resources.insert(RandomGenerator::from_entropy());
-        }
+    /// Number of fixed updates per second. Default is 60.
+    pub fn with_step_rate(mut self, step_rate: u32) -> Self {
+        self.runner = self.runner.with_step_rate(step_rate);
+        self
+    }
 
-        let scene_stack = {
-            let mut scenes = SceneStack::default();
-            if let Some(scene) = self.scene {
-                scenes.push(scene, &mut world, &mut self.resources);
-            }
-            scenes
-        };
+    /// Maximum number of fixed updates during one frame. Default is 5.
+    pub fn with_max_steps_per_frame(mut self, max_steps: u32) -> Self {
+        self.runner = self.runner.with_max_steps_per_frame(max_steps);
+        self
+    }
 
-        let rdr_id = {
-            let mut chan = self
-                .resources
-                .fetch_mut::<EventChannel<GameEvent>>()
-                .unwrap();
-            chan.register_reader()
-        };
+    /// Record the inputs and save them to the given file when the game exits.
+    pub fn with_input_recording<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.runner = self.runner.with_input_recording();
+        self.recording_path = Some(path.as_ref().to_path_buf());
+        self
+    }
 
-        let garbage_collector = GarbageCollector::new(&mut self.resources);
+    /// Play a recording instead of using the keyboard and mouse.
+    pub fn with_input_replay(mut self, recording: InputRecording<A>) -> Self {
+        self.runner = self.runner.with_input_replay(recording);
+        self
+    }
 
-        // we need a camera :)
-        world.spawn((Camera::new(),));
+    pub fn build(self) -> Game<'a, A> {
+        let renderer = Renderer::new(self.surface, &self.gui_context);
+        let mut runner = self.runner.build();
+        runner
+            .resources
+            .fetch_mut::<WindowDim>()
+            .unwrap()
+            .set_scale_mode(self.scale_mode);
+        runner
+            .resources
+            .fetch_mut::<AssetManager<GlfwSurface, SpriteAsset<GlfwSurface>>>()
+            .unwrap()
+            .set_budget(self.texture_budget);
+        if self.loading_threads > 0 {
+            crate::assets::start_asset_workers::<GlfwSurface>(
+                &runner.resources,
+                self.loading_threads,
+            );
+        }
 
         info!("Finished building game");
 
-        // audio system.
-        let audio_system = AudioSystem::new(&self.resources, self.audio_config)
+        // audio system. The config is also a resource so that it can be changed while playing.
+        runner.resources.insert(self.audio_config.clone());
+        let audio_system = AudioSystem::new(&runner.resources, self.audio_config)
             .expect("Cannot create audio system");
 
         Game {
             surface: self.surface,
             renderer,
-            scene_stack,
-            world,
+            runner,
             audio_system,
-            resources: self.resources,
-            rdr_id,
-            garbage_collector,
-            phantom: self.phantom,
             gui_context: self.gui_context,
+            recording_path: self.recording_path,
             #[cfg(feature = "hot-reload")]
             hot_reloader: HotReloader::new(),
         }
@@ -178,32 +192,24 @@ where
 /// # Generic parameters:
 /// - A: Action that is derived from the inputs. (e.g. Move Left)
 ///
-pub struct Game<'a, A> {
+pub struct Game<'a, A>
+where
+    A: InputAction,
+{
     /// for drawing stuff
     surface: &'a mut GlfwSurface,
     renderer: Renderer<GlfwSurface>,
 
-    /// All the scenes. Current scene will be used in the main loop.
-    scene_stack: SceneStack<WindowEvent>,
+    /// Scenes, world and resources. Updated with fixed time steps.
+    runner: GameRunner<A>,
 
     /// Play music and sound effects
     audio_system: AudioSystem,
 
-    /// Resources (assets, inputs...)
-    resources: Resources,
-
-    /// Current entities.
-    world: hecs::World,
-
-    /// Read events from the systems
-    rdr_id: ReaderId<GameEvent>,
-
-    /// Clean up the dead entities.
-    garbage_collector: GarbageCollector,
-
     gui_context: GuiContext,
 
-    phantom: PhantomData<A>,
+    /// Where to save the inputs when the game exits.
+    recording_path: Option<PathBuf>,
 
     #[cfg(feature = "hot-reload")]
     hot_reloader: HotReloader<GlfwSurface>,
@@ -214,10 +220,18 @@ where
     A: InputAction + 'static,
 {
     /// Run the game. This is the main loop.
+    ///
+    /// The real time elapsed between two frames is added to an accumulator, then the scene is
+    /// updated with a fixed time step until the accumulator is consumed. What is left is used to
+    /// interpolate the transforms when rendering.
     pub fn run(&mut self) {
-        let mut current_time = Instant::now();
-        let dt = Duration::from_millis(16);
+        let time_step = self.runner.time_step;
+        let dt = time_step.dt;
+        let mut previous_time = Instant::now();
+        let mut accumulator = Duration::from_secs(0);
         let mut back_buffer = self.surface.back_buffer().unwrap();
+        // the framebuffer is not the size of the window on HiDPI screens.
+        self.resize();
 
         'app: loop {
             // 1. Poll the events and update the Input resource
@@ -225,79 +239,109 @@ where
             let mut resize = false;
             self.surface.window.glfw.poll_events();
             {
-                let mut input = self.resources.fetch_mut::<Input<A>>().unwrap();
-                input.prepare();
+                let runner = &mut self.runner;
+                // When replaying, the inputs come from the recording.
+                let is_replaying = runner.is_replaying();
                 self.gui_context.reset_inputs();
                 for (_, event) in self.surface.events_rx.try_iter() {
                     match event {
                         WindowEvent::Close => break 'app,
                         WindowEvent::FramebufferSize(_, _) => resize = true,
+                        WindowEvent::Key(PROFILER_OVERLAY_KEY, _, Action::Press, _) => {
+                            if let Some(mut profiler) = runner.resources.fetch_mut::<Profiler>() {
+                                profiler.toggle_overlay();
+                            }
+                        }
+                        WindowEvent::Key(PROFILER_EXPORT_KEY, _, Action::Press, _) => {
+                            if let Some(profiler) = runner.resources.fetch::<Profiler>() {
+                                match profiler.export_csv(PROFILER_CSV_FILE) {
+                                    Ok(_) => {
+                                        info!("Saved profiler samples to {}", PROFILER_CSV_FILE)
+                                    }
+                                    Err(e) => error!("Cannot save profiler samples = {:?}", e),
+                                }
+                            }
+                        }
+                        _ if is_replaying => (),
                         ev => {
                             self.gui_context.process_event(ev.clone());
-                            if let Some(scene) = self.scene_stack.current_mut() {
-                                scene.process_input(&mut self.world, ev.clone(), &self.resources);
-                            }
-                            input.process_event(ev)
+                            runner.process_event(ev);
                         }
                     }
                 }
             }
 
-            // 2. Update the scene.
-            // ------------------------------------------------
-            let scene_result = if let Some(scene) = self.scene_stack.current_mut() {
-                let scene_res = scene.update(dt, &mut self.world, &self.resources);
+            let now = Instant::now();
+            let frame_duration = now - previous_time;
+            previous_time = now;
+            accumulator += frame_duration;
 
-                {
-                    let chan = self.resources.fetch::<EventChannel<GameEvent>>().unwrap();
-                    for ev in chan.read(&mut self.rdr_id) {
-                        scene.process_event(&mut self.world, ev.clone(), &self.resources);
+            // 2. Update the scene with fixed time steps.
+            // ------------------------------------------------
+            let mut scene_result = None;
+            let mut steps = 0;
+            while accumulator >= dt && steps < time_step.max_steps_per_frame {
+                accumulator -= dt;
+                steps += 1;
+
+                match self.runner.fixed_update() {
+                    SceneResult::Noop => (),
+                    // The scene needs to change. Remaining updates will be done by the next scene.
+                    res => {
+                        scene_result = Some(res);
+                        break;
                     }
                 }
+            }
 
-                let maybe_gui =
-                    scene.prepare_gui(dt, &mut self.world, &self.resources, &mut self.gui_context);
-
-                self.renderer.prepare_ui(
-                    self.surface,
-                    maybe_gui,
-                    &self.resources,
-                    &mut *self.gui_context.fonts.borrow_mut(),
-                );
-
-                Some(scene_res)
-            } else {
-                None
-            };
-
-            // Update children transforms:
-            // -----------------------------
-            update_transforms(&mut self.world);
+            // Do not try to catch up forever if the simulation is too slow.
+            if steps == time_step.max_steps_per_frame && accumulator >= dt {
+                debug!("Simulation is late, drop {:?}", accumulator);
+                accumulator = Duration::from_secs(0);
+            }
+            // The loop stops early when the scene changes, so there can be more than one step
+            // left. The transforms are never extrapolated.
+            let alpha = (accumulator.as_secs_f32() / dt.as_secs_f32()).clamp(0.0, 1.0);
 
-            // 3. Clean up dead entities.
+            // 3. Prepare the GUI for this frame.
             // ------------------------------------------------
-            self.garbage_collector
-                .collect(&mut self.world, &self.resources);
+            let runner = &mut self.runner;
+            let mut maybe_gui = runner.scene_stack.prepare_gui(
+                frame_duration,
+                &mut runner.world,
+                &runner.resources,
+                &self.gui_context,
+            );
+            if let Some(profiler) = runner.resources.fetch::<Profiler>() {
+                if profiler.show_overlay {
+                    let gui_context = &self.gui_context;
+                    let gui = maybe_gui.get_or_insert_with(|| gui_context.new_frame());
+                    profiler.draw_overlay(gui);
+                }
+            }
+            self.renderer.prepare_ui(
+                self.surface,
+                maybe_gui,
+                &runner.resources,
+                &mut self.gui_context.fonts.borrow_mut(),
+            );
 
             // 4. Render to screen
             // ------------------------------------------------
             log::debug!("RENDER");
-            self.renderer
-                .update(self.surface, &self.world, dt, &self.resources);
             if resize {
                 back_buffer = self.surface.back_buffer().unwrap();
-                let new_size = back_buffer.size();
-                let mut proj = self.resources.fetch_mut::<ProjectionMatrix>().unwrap();
-                proj.resize(new_size[0] as f32, new_size[1] as f32);
-
-                let mut dim = self.resources.fetch_mut::<WindowDim>().unwrap();
-                dim.resize(new_size[0], new_size[1]);
-                self.gui_context.window_dim = *dim;
+                self.resize();
             }
 
-            let render =
-                self.renderer
-                    .render(self.surface, &mut back_buffer, &self.world, &self.resources);
+            let runner = &mut self.runner;
+            let render = self.renderer.render(
+                self.surface,
+                &mut back_buffer,
+                &runner.world,
+                &runner.resources,
+                alpha,
+            );
             if render.is_ok() {
                 self.surface.window.swap_buffers();
             } else {
@@ -305,33 +349,49 @@ where
             }
 
             // Play music :)
-            self.audio_system.process(&self.resources);
-
-            // Update collision world for collision queries.
-            {
-                let mut collisions = self.resources.fetch_mut::<CollisionWorld>().unwrap();
-                collisions.synchronize(&self.world);
-            }
+            self.audio_system.process(&runner.resources);
 
             // Either clean up or load new resources.
-            crate::assets::update_asset_managers(self.surface, &self.resources);
+            crate::assets::update_asset_managers(self.surface, &runner.resources);
             #[cfg(feature = "hot-reload")]
-            self.hot_reloader.update(&self.resources);
+            self.hot_reloader
+                .update(&mut runner.world, &runner.resources);
 
             // Now, if need to switch scenes, do it.
             if let Some(res) = scene_result {
-                self.scene_stack
-                    .apply_result(res, &mut self.world, &mut self.resources);
+                runner.apply_scene_result(res);
             }
+            runner.end_profiler_frame();
+        }
 
-            let now = Instant::now();
-            let frame_duration = now - current_time;
-            if frame_duration < dt {
-                thread::sleep(dt - frame_duration);
+        if let Some(ref path) = self.recording_path {
+            if let Err(e) = self.runner.save_recording(path) {
+                error!("Cannot save input recording = {:?}", e);
             }
-            current_time = now;
         }
 
         info!("Bye bye.");
     }
+
+    /// Update the canvas, the projection and the GUI with the current size of the window and of
+    /// the framebuffer.
+    fn resize(&mut self) {
+        let (window_width, window_height) = self.surface.window.get_size();
+        let (fb_width, fb_height) = self.surface.window.get_framebuffer_size();
+
+        let mut dim = self.runner.resources.fetch_mut::<WindowDim>().unwrap();
+        dim.resize(
+            window_width.max(0) as u32,
+            window_height.max(0) as u32,
+            fb_width.max(0) as u32,
+            fb_height.max(0) as u32,
+        );
+        let mut proj = self
+            .runner
+            .resources
+            .fetch_mut::<ProjectionMatrix>()
+            .unwrap();
+        proj.resize(dim.width as f32, dim.height as f32);
+        self.gui_context.window_dim = *dim;
+    }
 }
@@ -1,7 +1,7 @@
 use crate::core::colors::RgbaColor;
 use crate::core::transform::Transform;
 use crate::core::window::WindowDim;
-use crate::event::GameEvent;
+use crate::event::Delete;
 use crate::gameplay::collision::{BoundingBox, CollisionLayer};
 use crate::gameplay::health::HitDetails;
 use crate::gameplay::physics::DynamicBody;
@@ -12,6 +12,8 @@ use hecs::{Entity, World};
 use log::trace;
 use shrev::EventChannel;
 
+pub const MISSILE_SPRITE: &str = "missile-01.png";
+
 #[derive(Debug, Copy, Clone)]
 pub enum BulletType {
     Small,
@@ -23,7 +25,7 @@ pub enum BulletType {
 
 impl BulletType {
     /// Get the name of the sprite that this bullet is representing
-    fn get_sprite_name(&self) -> String {
+    pub fn get_sprite_name(&self) -> String {
         match *self {
             BulletType::Small => "small_bullet.png",
             BulletType::Fast => "fast_bullet.png",
@@ -100,12 +102,12 @@ pub fn process_missiles(world: &World, resources: &Resources) {
             || t.translation.y > max_height
             || t.translation.y < -max_height
         {
-            to_despawn.push(GameEvent::Delete(e));
+            to_despawn.push(Delete(e));
         }
     }
 
     {
-        let mut channel = resources.fetch_mut::<EventChannel<GameEvent>>().unwrap();
+        let mut channel = resources.fetch_mut::<EventChannel<Delete>>().unwrap();
         channel.drain_vec_write(&mut to_despawn)
     }
 }
@@ -128,12 +130,12 @@ pub fn process_bullets(world: &World, resources: &Resources) {
             || t.translation.y > max_height
             || t.translation.y < -max_height
         {
-            to_despawn.push(GameEvent::Delete(e));
+            to_despawn.push(Delete(e));
         }
     }
 
     {
-        let mut channel = resources.fetch_mut::<EventChannel<GameEvent>>().unwrap();
+        let mut channel = resources.fetch_mut::<EventChannel<Delete>>().unwrap();
         channel.drain_vec_write(&mut to_despawn)
     }
     trace!("finished process_bullets");
@@ -155,9 +157,7 @@ pub fn spawn_player_bullet(
             alive: true,
             details: hit_details,
         },
-        Sprite {
-            id: bullet_type.get_sprite_name(),
-        },
+        Sprite::new(bullet_type.get_sprite_name()),
         Transform {
             translation: initial_position,
             rotation: angle,
@@ -202,9 +202,7 @@ pub fn spawn_enemy_bullet(
             alive: true,
             details: hit_details,
         },
-        Sprite {
-            id: bullet_type.get_sprite_name(),
-        },
+        Sprite::new(bullet_type.get_sprite_name()),
         Transform {
             translation: initial_position,
             rotation: angle,
@@ -231,10 +229,7 @@ pub fn spawn_missile(
         Missile {
             home_to_entity: Some(target),
         },
-        Sprite {
-            //id: "fast_bullet.png".to_string(),
-            id: "missile-01.png".to_string(),
-        },
+        Sprite::new(MISSILE_SPRITE.to_string()),
         Transform {
             translation: initial_position,
             rotation: angle,
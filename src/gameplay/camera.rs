@@ -1,24 +1,242 @@
-use crate::core::camera::Camera;
+//! Move the camera around the player.
+//!
+//! The `CameraRig` is attached to the camera entity. The camera follows a point in front of the
+//! player (look-ahead) with some damping, and only moves when that point leaves the dead zone.
+//! Explosions and hits add trauma which makes the screen shake. The shake is only used for the
+//! rendering (see `Camera::shake`). The center of the camera is kept inside the arena given by
+//! the stage description. A `Tween` that moves the camera takes over from the rig until it is
+//! finished.
+use crate::core::camera::{Camera, ProjectionMatrix};
+use crate::core::random::RandomGenerator;
 use crate::core::transform::Transform;
+use crate::core::tween::Tween;
 use crate::core::window::WindowDim;
+use crate::gameplay::physics::DynamicBody;
 use crate::gameplay::player::Player;
 use crate::resources::Resources;
+use glam::Vec2;
 use hecs::World;
+use rand::Rng;
+use serde_derive::{Deserialize, Serialize};
+use std::time::Duration;
 
-pub fn update_camera(world: &mut World, resources: &Resources) {
+/// Where the center of the camera can go.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct ArenaBounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Default for ArenaBounds {
+    fn default() -> Self {
+        Self {
+            min: glam::vec2(-800.0, -450.0),
+            max: glam::vec2(800.0, 450.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CameraRig {
+    /// How fast the camera catches up with the target. Higher is snappier.
+    pub stiffness: f32,
+    /// Half size of the area around the center where the target can move without moving the
+    /// camera.
+    pub dead_zone: Vec2,
+    /// The camera looks at where the target will be in that many seconds.
+    pub look_ahead: f32,
+    /// Between 0 and 1. The shake is proportional to trauma².
+    pub trauma: f32,
+    /// Trauma lost per second.
+    pub trauma_decay: f32,
+    /// Offset of the camera when trauma is 1.
+    pub max_shake: Vec2,
+    /// More than 1 to zoom in.
+    pub zoom: f32,
+    pub bounds: ArenaBounds,
+
+    /// Center of the camera without the shake.
+    focus: Option<Vec2>,
+}
+
+impl Default for CameraRig {
+    fn default() -> Self {
+        Self {
+            stiffness: 8.0,
+            dead_zone: glam::vec2(40.0, 30.0),
+            look_ahead: 0.25,
+            trauma: 0.0,
+            trauma_decay: 1.2,
+            max_shake: glam::vec2(20.0, 20.0),
+            zoom: 1.0,
+            bounds: ArenaBounds::default(),
+            focus: None,
+        }
+    }
+}
+
+impl CameraRig {
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+
+    /// Move the focus towards the target.
+    fn follow(&mut self, target: Vec2, dt: f32) -> Vec2 {
+        let focus = *self.focus.get_or_insert(target);
+
+        // only move enough to keep the target at the edge of the dead zone.
+        let offset = target - focus;
+        let outside = offset - offset.max(-self.dead_zone).min(self.dead_zone);
+        let desired = focus + outside;
+
+        let t = 1.0 - (-self.stiffness * dt).exp();
+        let focus = focus + (desired - focus) * t;
+        let focus = focus.max(self.bounds.min).min(self.bounds.max);
+        self.focus = Some(focus);
+        focus
+    }
+
+    fn shake(&mut self, random: &mut RandomGenerator, dt: f32) -> Vec2 {
+        if self.trauma <= 0.0 {
+            return Vec2::zero();
+        }
+        let amount = self.trauma * self.trauma;
+        self.trauma = (self.trauma - self.trauma_decay * dt).max(0.0);
+
+        // screen shake is only visual.
+        let rng = random.cosmetic();
+        glam::vec2(
+            self.max_shake.x * amount * rng.gen_range(-1.0, 1.0),
+            self.max_shake.y * amount * rng.gen_range(-1.0, 1.0),
+        )
+    }
+}
+
+pub fn update_camera(world: &mut World, resources: &Resources, dt: Duration) {
     let dim = resources.fetch::<WindowDim>().unwrap();
-    let pos = world
-        .query::<(&Transform, &Player)>()
+    let target = world
+        .query::<(&Transform, &Player, Option<&DynamicBody>)>()
         .iter()
-        .map(|(_, (t, _p))| t.translation)
+        .map(|(_, (t, _p, body))| (t.translation, body.map(|b| b.velocity)))
         .next();
-    if let Some(pos) = pos {
-        if let Some((_, t)) = world.query::<&mut Camera>().iter().next() {
-            t.position = pos; // - glam::vec2(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0);
-            t.position
-                .x = (t.position.x.max(-800.0)).min(800.0) - dim.width as f32 / 2.0;
-            t.position
-                .y = t.position.y.max(-450.0).min(450.0) - dim.height as f32 / 2.0;
+    if let Some((pos, velocity)) = target {
+        if let Some((_, (camera, rig, tween))) = world
+            .query::<(&mut Camera, &mut CameraRig, Option<&Tween>)>()
+            .iter()
+            .next()
+        {
+            let dt = dt.as_secs_f32();
+            // the camera position is the bottom left corner of the screen.
+            let half_screen = glam::vec2(dim.width as f32 / 2.0, dim.height as f32 / 2.0);
+            if tween.map(Tween::moves_camera).unwrap_or(false) {
+                // e.g. the intro of a boss. The rig follows the player again from where the
+                // tween leaves the camera.
+                rig.focus = Some(camera.position + half_screen);
+            } else {
+                let target = pos + velocity.unwrap_or_else(Vec2::zero) * rig.look_ahead;
+                let focus = rig.follow(target, dt);
+                camera.position = focus - half_screen;
+            }
+            let shake = {
+                let mut random = resources.fetch_mut::<RandomGenerator>().unwrap();
+                rig.shake(&mut random, dt)
+            };
+            camera.shake = shake;
+
+            if let Some(mut projection) = resources.fetch_mut::<ProjectionMatrix>() {
+                projection.set_zoom(rig.zoom);
+            }
+        }
+    }
+}
+
+/// Shake all the cameras.
+pub fn add_trauma(world: &World, amount: f32) {
+    for (_, rig) in world.query::<&mut CameraRig>().iter() {
+        rig.add_trauma(amount);
+    }
+}
+
+/// Set the bounds of the arena. A rig is added to the cameras that do not have one.
+pub fn set_arena_bounds(world: &mut World, bounds: ArenaBounds) {
+    let cameras: Vec<_> = world.query::<&Camera>().iter().map(|(e, _)| e).collect();
+    for e in cameras {
+        if let Ok(mut rig) = world.get_mut::<CameraRig>(e) {
+            rig.bounds = bounds;
+            // new stage, no need to move smoothly from the previous position.
+            rig.focus = None;
+            continue;
+        }
+        let rig = CameraRig {
+            bounds,
+            ..CameraRig::default()
+        };
+        if let Err(err) = world.insert_one(e, rig) {
+            error!("Cannot add camera rig = {:?}", err);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::random::Seed;
+    use crate::core::tween::TweenTarget;
+    use crate::gameplay::player::{Stats, Weapon};
+
+    fn setup() -> (World, Resources, hecs::Entity) {
+        let mut world = World::new();
+        world.spawn((
+            Transform {
+                translation: glam::vec2(300.0, 0.0),
+                scale: glam::vec2(1.0, 1.0),
+                rotation: 0.0,
+                dirty: true,
+            },
+            Player {
+                weapon: Weapon::Simple,
+                direction: Vec2::zero(),
+                stats: Stats::default(),
+            },
+        ));
+        let camera = world.spawn((Camera::new(),));
+        set_arena_bounds(&mut world, ArenaBounds::default());
+
+        let mut resources = Resources::default();
+        resources.insert(WindowDim::new(800, 600));
+        resources.insert(RandomGenerator::new(Seed::from_u64(1)));
+        (world, resources, camera)
+    }
+
+    #[test]
+    fn rig_follows_the_player() {
+        let (mut world, resources, camera) = setup();
+        update_camera(&mut world, &resources, Duration::from_millis(16));
+        let position = world.get::<Camera>(camera).unwrap().position;
+        assert_eq!(glam::vec2(300.0 - 400.0, -300.0), position);
+    }
+
+    #[test]
+    fn tween_moves_the_camera_instead_of_the_rig() {
+        let (mut world, resources, camera) = setup();
+        let from = glam::vec2(-400.0, -100.0);
+        world.get_mut::<Camera>(camera).unwrap().position = from;
+        // the camera only moves in the second part of the sequence.
+        let fade = TweenTarget::Tint {
+            from: Default::default(),
+            to: Default::default(),
+        };
+        let intro = TweenTarget::CameraPosition { from, to: from };
+        let tween = Tween::new(fade, 1.0).then(Tween::new(intro, 1.0));
+        world.insert_one(camera, tween).unwrap();
+
+        update_camera(&mut world, &resources, Duration::from_millis(16));
+        assert_eq!(from, world.get::<Camera>(camera).unwrap().position);
+
+        // then the rig starts again from there, smoothly.
+        world.remove_one::<Tween>(camera).unwrap();
+        update_camera(&mut world, &resources, Duration::from_millis(16));
+        let position = world.get::<Camera>(camera).unwrap().position;
+        assert!(position.x > from.x && position.x < 300.0 - 400.0);
+    }
+}
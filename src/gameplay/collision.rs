@@ -1,6 +1,6 @@
 use crate::core::colors::RgbaColor;
 use crate::core::transform::Transform;
-use crate::event::GameEvent;
+use crate::event::{Delete, Exploded, Hit};
 use crate::gameplay::bullet::{Bullet, Missile};
 use crate::gameplay::explosion::{ExplosionDetails, ExplosionType};
 use crate::gameplay::health::Health;
@@ -11,7 +11,6 @@ use glam::Vec2;
 use hecs::{Entity, World};
 use log::{debug, trace};
 use serde_derive::{Deserialize, Serialize};
-use shrev::EventChannel;
 use std::mem::swap;
 
 #[derive(Debug)]
@@ -337,8 +336,9 @@ pub fn process_collisions(
 ) {
     trace!("process_collisions, IN = {:?}", collision_pairs);
 
-    let mut ev_channel = resources.fetch_mut::<EventChannel<GameEvent>>().unwrap();
-    let mut events = vec![];
+    let mut deletes = vec![];
+    let mut hits = vec![];
+    let mut explosions = vec![];
     for (e1, e2) in collision_pairs {
         if e1 == e2 {
             continue;
@@ -353,16 +353,26 @@ pub fn process_collisions(
             let e1_bullet = world.get::<Bullet>(e1).is_ok();
             let e2_bullet = world.get::<Bullet>(e2).is_ok();
             match (e1_health, e2_bullet, e2_health, e1_bullet) {
-                (true, true, _, _) => events.append(&mut process_bullet_collision(world, e1, e2)),
-                (_, _, true, true) => events.append(&mut process_bullet_collision(world, e2, e1)),
+                (true, true, _, _) => {
+                    if let Some((delete, hit)) = process_bullet_collision(world, e1, e2) {
+                        deletes.push(delete);
+                        hits.push(hit);
+                    }
+                }
+                (_, _, true, true) => {
+                    if let Some((delete, hit)) = process_bullet_collision(world, e2, e1) {
+                        deletes.push(delete);
+                        hits.push(hit);
+                    }
+                }
                 (false, true, _, _) => {
                     if let Some(ev) = delete_bullet(world, e2) {
-                        events.push(ev)
+                        deletes.push(ev)
                     }
                 }
                 (_, _, _, true) => {
                     if let Some(ev) = delete_bullet(world, e1) {
-                        events.push(ev)
+                        deletes.push(ev)
                     }
                 }
                 _ => (),
@@ -378,32 +388,32 @@ pub fn process_collisions(
             has_missile = e1_missile || e2_missile;
             match (e2_missile, e1_missile) {
                 (true, _) => {
-                    events.push(GameEvent::Delete(e2));
+                    deletes.push(Delete(e2));
                     let e2_transform = world
                         .get::<Transform>(e2)
                         .expect("Missile should have a transform");
-                    events.push(GameEvent::Explosion(
-                        e2,
-                        ExplosionDetails {
+                    explosions.push(Exploded {
+                        entity: e2,
+                        details: ExplosionDetails {
                             radius: 100.0,
                             ty: ExplosionType::Second,
                         },
-                        e2_transform.translation,
-                    ));
+                        position: e2_transform.translation,
+                    });
                 }
                 (_, true) => {
-                    events.push(GameEvent::Delete(e1));
+                    deletes.push(Delete(e1));
                     let e1_transform = world
                         .get::<Transform>(e1)
                         .expect("Missile should have a transform");
-                    events.push(GameEvent::Explosion(
-                        e1,
-                        ExplosionDetails {
+                    explosions.push(Exploded {
+                        entity: e1,
+                        details: ExplosionDetails {
                             radius: 100.0,
                             ty: ExplosionType::Second,
                         },
-                        e1_transform.translation,
-                    ));
+                        position: e1_transform.translation,
+                    });
                 }
                 _ => (),
             }
@@ -438,9 +448,14 @@ pub fn process_collisions(
         }
     }
 
-    if !events.is_empty() {
-        debug!("Will publish {:?}", events);
-        ev_channel.drain_vec_write(&mut events);
+    if !deletes.is_empty() || !hits.is_empty() || !explosions.is_empty() {
+        debug!(
+            "Will publish {:?}, {:?} and {:?}",
+            deletes, hits, explosions
+        );
+        resources.write_events(deletes);
+        resources.write_events(hits);
+        resources.write_events(explosions);
     }
 
     trace!("Finished process_collisions");
@@ -450,28 +465,30 @@ fn process_bullet_collision(
     world: &mut World,
     health_entity: hecs::Entity,
     bullet_entity: hecs::Entity,
-) -> Vec<GameEvent> {
-    let mut events = vec![];
-
-    if let Ok(mut b) = world.get_mut::<Bullet>(bullet_entity) {
-        // if bullet is not alive, let's not process the rest.
-        if b.alive {
-            b.alive = false;
-            events.push(GameEvent::Delete(bullet_entity));
-            events.push(GameEvent::Hit(health_entity, b.details));
-        }
+) -> Option<(Delete, Hit)> {
+    let mut b = world.get_mut::<Bullet>(bullet_entity).ok()?;
+    // if bullet is not alive, let's not process the rest.
+    if b.alive {
+        b.alive = false;
+        Some((
+            Delete(bullet_entity),
+            Hit {
+                entity: health_entity,
+                details: b.details,
+            },
+        ))
+    } else {
+        None
     }
-
-    events
 }
 
-fn delete_bullet(world: &mut World, bullet_entity: hecs::Entity) -> Option<GameEvent> {
+fn delete_bullet(world: &mut World, bullet_entity: hecs::Entity) -> Option<Delete> {
     let mut b = world.get_mut::<Bullet>(bullet_entity).unwrap();
     // if bullet is not alive, let's not process the rest.
     if !b.alive {
         None
     } else {
         b.alive = false;
-        Some(GameEvent::Delete(bullet_entity))
+        Some(Delete(bullet_entity))
     }
 }
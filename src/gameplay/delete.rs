@@ -1,35 +1,180 @@
 //! Clean entities the right way. Done at the end of a frame.
+//!
+//! Deleting an entity also deletes all its children (see `HasChildren`), unless the entity has
+//! the `OrphanChildren` component. In that case, the children are detached and stay where they
+//! are in the world.
 
-use crate::event::GameEvent;
+use crate::core::transform::{HasChildren, HasParent, LocalTransform};
+use crate::event::Delete;
 use crate::resources::Resources;
 use log::{debug, info};
 use shrev::{EventChannel, ReaderId};
 
+/// Add this to an entity to keep its children alive when it is deleted.
+pub struct OrphanChildren;
+
 /// ahahaha what a confusing name.
 pub struct GarbageCollector {
-    rdr_id: ReaderId<GameEvent>,
+    rdr_id: ReaderId<Delete>,
 }
 
 impl GarbageCollector {
     pub fn new(resources: &mut Resources) -> Self {
-        let mut chan = resources.fetch_mut::<EventChannel<GameEvent>>().unwrap();
-        let rdr_id = chan.register_reader();
+        let rdr_id = resources.register_reader::<Delete>();
         Self { rdr_id }
     }
 
     pub fn collect(&mut self, world: &mut hecs::World, resources: &Resources) {
-        let chan = resources.fetch::<EventChannel<GameEvent>>().unwrap();
-        for ev in chan.read(&mut self.rdr_id) {
-            if let GameEvent::Delete(e) = ev {
-                log::debug!("Will delete {:?}", e);
-
-                // remove from world
-                if let Err(e) = world.despawn(*e) {
-                    info!("Entity was already deleted (or does not exist?) = {}", e);
-                } else {
-                    debug!("Entity successfully deleted.");
-                }
+        let chan = resources.fetch::<EventChannel<Delete>>().unwrap();
+        for Delete(e) in chan.read(&mut self.rdr_id) {
+            log::debug!("Will delete {:?}", e);
+
+            // remove from world
+            if let Err(e) = despawn_recursive(world, *e) {
+                info!("Entity was already deleted (or does not exist?) = {}", e);
+            } else {
+                debug!("Entity successfully deleted.");
             }
         }
     }
 }
+
+/// Despawn an entity and all its descendants. The entity is removed from its parent's children.
+/// If the entity has `OrphanChildren`, its children are detached instead of despawned.
+pub fn despawn_recursive(
+    world: &mut hecs::World,
+    entity: hecs::Entity,
+) -> Result<(), hecs::NoSuchEntity> {
+    if !world.contains(entity) {
+        return Err(hecs::NoSuchEntity);
+    }
+    detach(world, entity);
+
+    let orphan_children = world.get::<OrphanChildren>(entity).is_ok();
+    let mut to_despawn = vec![entity];
+    let mut to_visit = children_of(world, entity);
+    if orphan_children {
+        for child in to_visit.drain(..) {
+            detach(world, child);
+        }
+    }
+
+    while let Some(child) = to_visit.pop() {
+        to_visit.extend(children_of(world, child));
+        to_despawn.push(child);
+    }
+
+    for e in to_despawn {
+        if let Err(err) = world.despawn(e) {
+            debug!("Child {:?} was already deleted = {}", e, err);
+        }
+    }
+    Ok(())
+}
+
+/// Remove the entity from its parent's children. Its global transform is kept so it stays at
+/// the same place in the world.
+pub fn detach(world: &mut hecs::World, child: hecs::Entity) {
+    let parent = match world.remove_one::<HasParent>(child) {
+        Ok(HasParent { entity }) => entity,
+        Err(_) => return,
+    };
+    let _ = world.remove_one::<LocalTransform>(child);
+
+    if let Ok(mut children) = world.get_mut::<HasChildren>(parent) {
+        children.children.retain(|&c| c != child);
+    }
+}
+
+fn children_of(world: &hecs::World, entity: hecs::Entity) -> Vec<hecs::Entity> {
+    world
+        .get::<HasChildren>(entity)
+        .map(|children| children.children.clone())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::transform::Transform;
+
+    /// root -> (a -> a1), b. Returns [root, a, a1, b].
+    fn hierarchy(world: &mut hecs::World) -> [hecs::Entity; 4] {
+        let root = world.spawn((Transform::default(),));
+        let child = |world: &mut hecs::World, parent, x| {
+            let transform = Transform {
+                translation: glam::vec2(x, 0.0),
+                ..Transform::default()
+            };
+            let local = LocalTransform::new(glam::vec2(10.0, 0.0), 0.0, glam::vec2(1.0, 1.0));
+            world.spawn((transform, local, HasParent { entity: parent }))
+        };
+        let a = child(world, root, 10.0);
+        let a1 = child(world, a, 20.0);
+        let b = child(world, root, 10.0);
+        world
+            .insert_one(
+                root,
+                HasChildren {
+                    children: vec![a, b],
+                },
+            )
+            .unwrap();
+        world
+            .insert_one(a, HasChildren { children: vec![a1] })
+            .unwrap();
+        [root, a, a1, b]
+    }
+
+    #[test]
+    fn delete_the_subtree() {
+        let mut world = hecs::World::new();
+        let [root, a, a1, b] = hierarchy(&mut world);
+
+        despawn_recursive(&mut world, a).unwrap();
+        assert!(!world.contains(a));
+        assert!(!world.contains(a1));
+        assert!(world.contains(b));
+        // the parent does not refer to the deleted child anymore.
+        assert_eq!(vec![b], world.get::<HasChildren>(root).unwrap().children);
+
+        despawn_recursive(&mut world, root).unwrap();
+        assert!(!world.contains(b));
+        assert!(despawn_recursive(&mut world, root).is_err());
+    }
+
+    #[test]
+    fn orphan_children_stay_where_they_are() {
+        let mut world = hecs::World::new();
+        let [root, a, a1, b] = hierarchy(&mut world);
+        world.insert_one(root, OrphanChildren).unwrap();
+
+        despawn_recursive(&mut world, root).unwrap();
+        assert!(!world.contains(root));
+        for child in [a, b].iter() {
+            assert!(world.get::<HasParent>(*child).is_err());
+            assert!(world.get::<LocalTransform>(*child).is_err());
+            assert_eq!(
+                glam::vec2(10.0, 0.0),
+                world.get::<Transform>(*child).unwrap().translation
+            );
+        }
+        // the grand children are still attached to their parent.
+        assert_eq!(a, world.get::<HasParent>(a1).unwrap().entity);
+    }
+
+    #[test]
+    fn delete_event_is_collected() {
+        let mut world = hecs::World::new();
+        let mut resources = Resources::default();
+        let mut collector = GarbageCollector::new(&mut resources);
+        let [root, a, a1, b] = hierarchy(&mut world);
+
+        resources.write_event(Delete(a));
+        // deleted twice, or with its parent, is fine.
+        resources.write_event(Delete(a1));
+        collector.collect(&mut world, &resources);
+        assert!(world.contains(root) && world.contains(b));
+        assert!(!world.contains(a) && !world.contains(a1));
+    }
+}
@@ -1,12 +1,12 @@
+use crate::assets::manifest::AssetManifest;
 use crate::assets::prefab::PrefabManager;
-use crate::assets::Handle;
 use crate::core::animation::AnimationController;
 use crate::core::colors;
 use crate::core::random::RandomGenerator;
 use crate::core::timer::Timer;
 use crate::core::transform::Transform;
-use crate::event::GameEvent;
-use crate::gameplay::bullet::{spawn_enemy_bullet, spawn_missile, BulletType};
+use crate::event::{Delete, EnemyDied, Exploded, PlaySound};
+use crate::gameplay::bullet::{spawn_enemy_bullet, spawn_missile, BulletType, MISSILE_SPRITE};
 use crate::gameplay::collision::CollisionLayer;
 use crate::gameplay::explosion::{ExplosionDetails, ExplosionType};
 use crate::gameplay::health::HitDetails;
@@ -22,9 +22,12 @@ use log::{debug, trace};
 use luminance_glfw::GlfwSurface;
 use rand::Rng;
 use serde_derive::{Deserialize, Serialize};
-use shrev::EventChannel;
 use std::time::Duration;
 
+/// Sound when most enemies shoot.
+const SHOT_SOUND: &str = "sounds/scifi_kit/Laser/Laser_04.wav";
+const BOSS_SHOT_SOUND: &str = "sounds/scifi_kit/Laser/Laser_03.wav";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Enemy {
     pub enemy_type: EnemyType,
@@ -46,6 +49,36 @@ impl Default for Enemy {
     }
 }
 
+impl Enemy {
+    /// Sounds, bullets and prefabs used when this enemy attacks.
+    pub fn dependencies(&self, manifest: &mut AssetManifest) {
+        let (sounds, bullets, prefabs): (&[&str], &[BulletType], &[&str]) = match self.enemy_type {
+            EnemyType::FollowPlayer(_) => (&[SHOT_SOUND], &[BulletType::Round2], &[]),
+            EnemyType::Satellite(_) => (&[], &[], &[]),
+            EnemyType::Boss1(_) => (&[BOSS_SHOT_SOUND], &[BulletType::Round2], &[]),
+            EnemyType::Carrier { .. } => (&[], &[], &["kamikaze"]),
+            EnemyType::MineLander(_) => (&[], &[], &["mine"]),
+            EnemyType::Wanderer(_) | EnemyType::Spammer(_) => {
+                (&[SHOT_SOUND], &[BulletType::Round1], &[])
+            }
+            EnemyType::Mine { .. } | EnemyType::Kamikaze => (&[], &[], &[]),
+            EnemyType::LastBoss(_) => (&[SHOT_SOUND], &[BulletType::Fast], &[]),
+        };
+        sounds
+            .iter()
+            .for_each(|s| manifest.add_sound(s.to_string()));
+        bullets
+            .iter()
+            .for_each(|b| manifest.add_sprite(b.get_sprite_name()));
+        prefabs
+            .iter()
+            .for_each(|p| manifest.add_prefab(p.to_string()));
+        if let EnemyType::Satellite(_) = self.enemy_type {
+            manifest.add_sprite(MISSILE_SPRITE.to_string());
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MovementBehavior {
     /// Move toward the player. Avoid basic obstacles
@@ -257,13 +290,13 @@ pub fn update_enemies(world: &mut World, resources: &Resources, dt: Duration) {
     }
     let player = maybe_player.unwrap();
 
-    let mut ev_channel = resources.fetch_mut::<EventChannel<GameEvent>>().unwrap();
-
     // prefabs to spawn.
     let mut to_spawn: Vec<(String, glam::Vec2)> = vec![];
     let mut spaceship_to_spawn = vec![];
     let mut bullets = vec![];
     let mut to_remove = vec![];
+    let mut explosions = vec![];
+    let mut deaths = vec![];
     let mut missiles = vec![];
 
     let maybe_player = world
@@ -289,21 +322,21 @@ pub fn update_enemies(world: &mut World, resources: &Resources, dt: Duration) {
             match enemy.enemy_type {
                 EnemyType::Kamikaze => {
                     if (t.translation - player_position).length() < 60.0 {
-                        to_remove.push(GameEvent::Delete(e));
-                        to_remove.push(GameEvent::Explosion(
-                            e,
-                            ExplosionDetails {
+                        to_remove.push(Delete(e));
+                        explosions.push(Exploded {
+                            entity: e,
+                            details: ExplosionDetails {
                                 radius: 100.0,
                                 ty: ExplosionType::First,
                             },
-                            t.translation,
-                        ));
-                        to_remove.push(GameEvent::EnemyDied(
-                            e,
-                            t.translation,
-                            enemy.scrap_drop,
-                            enemy.pickup_drop_percent,
-                        ));
+                            position: t.translation,
+                        });
+                        deaths.push(EnemyDied {
+                            entity: e,
+                            position: t.translation,
+                            scrap: enemy.scrap_drop,
+                            pickup_drop: enemy.pickup_drop_percent,
+                        });
                     }
                 }
                 EnemyType::Carrier {
@@ -335,9 +368,7 @@ pub fn update_enemies(world: &mut World, resources: &Resources, dt: Duration) {
                                     BulletType::Fast,
                                 ));
                             }
-                            ev_channel.single_write(GameEvent::PlaySound(
-                                "sounds/scifi_kit/Laser/Laser_04.wav".to_string(),
-                            ));
+                            resources.write_event(PlaySound(SHOT_SOUND.to_string()));
 
                             boss.current_shot += 1;
                         }
@@ -378,9 +409,7 @@ pub fn update_enemies(world: &mut World, resources: &Resources, dt: Duration) {
                                 glam::Mat2::from_angle(std::f32::consts::FRAC_PI_3) * d,
                                 BulletType::Round1,
                             ));
-                            ev_channel.single_write(GameEvent::PlaySound(
-                                "sounds/scifi_kit/Laser/Laser_04.wav".to_string(),
-                            ));
+                            resources.write_event(PlaySound(SHOT_SOUND.to_string()));
 
                             spammer.current_shot += 1;
                         }
@@ -401,9 +430,7 @@ pub fn update_enemies(world: &mut World, resources: &Resources, dt: Duration) {
                             // shoot.
                             let to_spawn = (t.translation, dir.normalize(), BulletType::Round2);
                             bullets.push(to_spawn);
-                            ev_channel.single_write(GameEvent::PlaySound(
-                                "sounds/scifi_kit/Laser/Laser_03.wav".to_string(),
-                            ));
+                            resources.write_event(PlaySound(BOSS_SHOT_SOUND.to_string()));
 
                             boss1.current_shot += 1;
                         }
@@ -437,9 +464,7 @@ pub fn update_enemies(world: &mut World, resources: &Resources, dt: Duration) {
                         if shoot_timer.finished() {
                             shoot_timer.reset();
                             let to_spawn = (t.translation, dir.normalize(), BulletType::Round2);
-                            ev_channel.single_write(GameEvent::PlaySound(
-                                "sounds/scifi_kit/Laser/Laser_04.wav".to_string(),
-                            ));
+                            resources.write_event(PlaySound(SHOT_SOUND.to_string()));
                             bullets.push(to_spawn);
                         }
                     }
@@ -468,9 +493,7 @@ pub fn update_enemies(world: &mut World, resources: &Resources, dt: Duration) {
                             glam::Mat2::from_angle(3.0 * std::f32::consts::FRAC_PI_2) * d,
                             BulletType::Round1,
                         ));
-                        ev_channel.single_write(GameEvent::PlaySound(
-                            "sounds/scifi_kit/Laser/Laser_04.wav".to_string(),
-                        ));
+                        resources.write_event(PlaySound(SHOT_SOUND.to_string()));
                     }
                 }
                 EnemyType::MineLander(ref mut timer) => {
@@ -494,15 +517,15 @@ pub fn update_enemies(world: &mut World, resources: &Resources, dt: Duration) {
                         explosion_timer.tick(dt);
                         if explosion_timer.finished() {
                             // badaboum
-                            to_remove.push(GameEvent::Delete(e));
-                            to_remove.push(GameEvent::Explosion(
-                                e,
-                                ExplosionDetails {
+                            to_remove.push(Delete(e));
+                            explosions.push(Exploded {
+                                entity: e,
+                                details: ExplosionDetails {
                                     radius: trigger_distance,
                                     ty: ExplosionType::First,
                                 },
-                                t.translation,
-                            ));
+                                position: t.translation,
+                            });
                         }
                         debug::stroke_circle(
                             resources,
@@ -531,11 +554,7 @@ pub fn update_enemies(world: &mut World, resources: &Resources, dt: Duration) {
     {
         let prefab_manager = resources.fetch_mut::<PrefabManager<GlfwSurface>>().unwrap();
         for (prefab, pos) in to_spawn {
-            if let Some(prefab) = prefab_manager.get(&Handle(prefab)) {
-                prefab.execute(|prefab| {
-                    prefab.spawn_at_pos(world, pos);
-                });
-            }
+            prefab_manager.spawn_at_pos(&prefab, world, pos);
         }
     }
 
@@ -568,21 +587,19 @@ pub fn update_enemies(world: &mut World, resources: &Resources, dt: Duration) {
         let mut random = resources.fetch_mut::<RandomGenerator>().unwrap();
 
         for (_e, pos, nb) in spaceship_to_spawn {
-            if let Some(asset) = prefab_manager.get(&Handle("kamikaze".to_string())) {
-                for _ in 0..nb {
-                    asset.execute(|prefab| {
-                        let e = prefab.spawn_at_pos(world, pos);
-                        if let Ok(mut body) = world.get_mut::<DynamicBody>(e) {
-                            let angle = random.rng().gen_range(0.0, std::f32::consts::PI * 2.0);
-                            let impulse =
-                                500.0 * glam::Mat2::from_angle(angle) * glam::Vec2::unit_y();
-                            body.add_impulse(impulse);
-                        }
-                    });
+            for _ in 0..nb {
+                if let Some(e) = prefab_manager.spawn_at_pos("kamikaze", world, pos) {
+                    if let Ok(mut body) = world.get_mut::<DynamicBody>(e) {
+                        let angle = random.rng().gen_range(0.0, std::f32::consts::PI * 2.0);
+                        let impulse = 500.0 * glam::Mat2::from_angle(angle) * glam::Vec2::unit_y();
+                        body.add_impulse(impulse);
+                    }
                 }
             }
         }
     }
-    ev_channel.drain_vec_write(&mut to_remove);
+    resources.write_events(to_remove);
+    resources.write_events(explosions);
+    resources.write_events(deaths);
     trace!("Finished update_enemies")
 }
@@ -2,8 +2,10 @@
 
 use crate::core::animation::{Animation, AnimationController};
 use crate::core::colors;
+use crate::core::system::System;
 use crate::core::transform::Transform;
-use crate::event::GameEvent;
+use crate::event::{Exploded, Hit, PlaySound};
+use crate::gameplay::camera;
 use crate::gameplay::collision::CollisionWorld;
 use crate::gameplay::health::HitDetails;
 use crate::gameplay::physics::DynamicBody;
@@ -13,6 +15,7 @@ use crate::resources::Resources;
 use serde_derive::{Deserialize, Serialize};
 use shrev::{EventChannel, ReaderId};
 use std::collections::HashMap;
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Explosive;
@@ -32,34 +35,31 @@ pub struct ExplosionDetails {
     pub ty: ExplosionType,
 }
 
+#[derive(Default)]
 pub struct ExplosionSystem {
-    rdr_id: ReaderId<GameEvent>,
+    /// Registered when the system is set up.
+    rdr_id: Option<ReaderId<Exploded>>,
 }
 
 impl ExplosionSystem {
-    pub fn new(resources: &mut Resources) -> Self {
-        let mut channel = resources.fetch_mut::<EventChannel<GameEvent>>().unwrap();
-
-        Self {
-            rdr_id: channel.register_reader(),
-        }
-    }
-
     pub fn update(&mut self, world: &mut hecs::World, resources: &Resources) {
-        let mut channel = resources.fetch_mut::<EventChannel<GameEvent>>().unwrap();
+        let channel = resources.fetch::<EventChannel<Exploded>>().unwrap();
         let collision_world = resources.fetch::<CollisionWorld>().unwrap();
 
         let mut explosions = vec![];
-        for ev in channel.read(&mut self.rdr_id) {
-            if let GameEvent::Explosion(e, explosion, pos) = ev {
-                explosions.push((*e, *explosion, *pos));
-            }
+        let rdr_id = self
+            .rdr_id
+            .as_mut()
+            .expect("ExplosionSystem should be set up before running");
+        for ev in channel.read(rdr_id) {
+            explosions.push((ev.entity, ev.details, ev.position));
         }
 
-        let mut events = vec![];
+        let mut hits = vec![];
         for (entity, explosion, pos) in explosions {
             // play the sound, show the animation, then query who is hit by this explosion.
-            events.push(GameEvent::PlaySound("sounds/explosion.wav".to_string()));
+            resources.write_event(PlaySound("sounds/explosion.wav".to_string()));
+            camera::add_trauma(world, 0.5);
             match explosion.ty {
                 ExplosionType::First => {
                     spawn_explosion(world, pos, explosion.radius * glam::Vec2::one())
@@ -94,20 +94,20 @@ impl ExplosionSystem {
                     );
                     body.add_impulse(force / body.mass);
 
-                    events.push(GameEvent::Hit(
-                        e,
-                        HitDetails {
+                    hits.push(Hit {
+                        entity: e,
+                        details: HitDetails {
                             hit_points: 2.0,
                             is_crit: false,
                         },
-                    ));
+                    });
                 } else {
                     info!("No transform and body for entity");
                 }
             }
         }
 
-        channel.drain_vec_write(&mut events);
+        resources.write_events(hits);
 
         //ev_channel.single_write(GameEvent::PlaySound(
         //                                 "sounds/explosion.wav".to_string(),
@@ -115,6 +115,16 @@ impl ExplosionSystem {
     }
 }
 
+impl System for ExplosionSystem {
+    fn setup(&mut self, _world: &mut hecs::World, resources: &mut Resources) {
+        self.rdr_id = Some(resources.register_reader::<Exploded>());
+    }
+
+    fn run(&mut self, world: &mut hecs::World, resources: &Resources, _dt: Duration) {
+        self.update(world, resources);
+    }
+}
+
 pub fn spawn_explosion(world: &mut hecs::World, position: glam::Vec2, scale: glam::Vec2) {
     let mut builder = hecs::EntityBuilder::new();
 
@@ -155,9 +165,7 @@ pub fn spawn_explosion(world: &mut hecs::World, position: glam::Vec2, scale: gla
     };
 
     builder.add(animation_controller);
-    builder.add(Sprite {
-        id: String::from("explosion4/k2_0001.png"),
-    });
+    builder.add(Sprite::new(String::from("explosion4/k2_0001.png")));
 
     world.spawn(builder.build());
 }
@@ -202,9 +210,7 @@ pub fn spawn_explosion_2(world: &mut hecs::World, position: glam::Vec2, scale: g
     };
 
     builder.add(animation_controller);
-    builder.add(Sprite {
-        id: String::from("explosion4/h_0001.png"),
-    });
+    builder.add(Sprite::new(String::from("explosion4/h_0001.png")));
 
     world.spawn(builder.build());
 }
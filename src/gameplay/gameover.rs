@@ -1,25 +1,20 @@
-use crate::event::GameEvent;
+use crate::event;
 use crate::resources::Resources;
 use shrev::{EventChannel, ReaderId};
 
 pub struct GameOver {
-    rdr_id: ReaderId<GameEvent>,
+    rdr_id: ReaderId<event::GameOver>,
 }
 
 impl GameOver {
     pub fn new(resources: &mut Resources) -> Self {
-        let mut chan = resources.fetch_mut::<EventChannel<GameEvent>>().unwrap();
-        let rdr_id = chan.register_reader();
+        let rdr_id = resources.register_reader::<event::GameOver>();
         Self { rdr_id }
     }
 
     pub fn game_over(&mut self, resources: &Resources) -> bool {
-        let chan = resources.fetch::<EventChannel<GameEvent>>().unwrap();
-        for ev in chan.read(&mut self.rdr_id) {
-            if let GameEvent::GameOver = ev {
-                return true;
-            }
-        }
-        return false;
+        let chan = resources.fetch::<EventChannel<event::GameOver>>().unwrap();
+        let game_over = chan.read(&mut self.rdr_id).next().is_some();
+        game_over
     }
 }
@@ -1,13 +1,16 @@
+use crate::assets::particle::ParticleManager;
+use crate::core::system::System;
 use crate::core::timer::Timer;
 use crate::core::transform::Transform;
-use crate::event::GameEvent;
+use crate::event::{Delete, EnemyDied, GameOver, Hit};
+use crate::gameplay::camera;
 use crate::gameplay::enemy::{Enemy, EnemyType};
 use crate::gameplay::player::Player;
-use crate::paths::get_assets_path;
-use crate::render::particle::ParticleEmitter;
+use crate::render::particle::ParticleEffect;
 use crate::render::sprite::Blink;
 use crate::resources::Resources;
 use log::{debug, trace};
+use luminance_glfw::GlfwSurface;
 use serde_derive::{Deserialize, Serialize};
 use shrev::{EventChannel, ReaderId};
 use std::time::Duration;
@@ -90,140 +93,136 @@ pub struct HitDetails {
     pub is_crit: bool,
 }
 
-pub struct HealthSystem {
-    rdr_id: ReaderId<GameEvent>,
+/// Particles when something dies, in the particle folder.
+pub const EXPLOSION_PARTICLE: &str = "explosion.json";
 
-    /// TODO put somewhere else.
-    explosion: ParticleEmitter,
+pub struct HealthSystem {
+    /// Registered when the system is set up.
+    rdr_id: Option<ReaderId<Hit>>,
 }
 
 impl HealthSystem {
-    pub fn new(resources: &mut Resources) -> Self {
-        let base_path = get_assets_path();
-        let mut emitter: ParticleEmitter = serde_json::from_str(
-            &std::fs::read_to_string(base_path.join("particle/explosion.json"))
-                .unwrap(),
-        )
-        .unwrap();
-        emitter.init_pool();
-
-        let mut chan = resources.fetch_mut::<EventChannel<GameEvent>>().unwrap();
-        let rdr_id = chan.register_reader();
-        Self {
-            rdr_id,
-            explosion: emitter,
-        }
+    pub fn new() -> Self {
+        Self { rdr_id: None }
     }
 
     pub fn update(&mut self, world: &mut hecs::World, resources: &Resources, dt: Duration) {
         trace!("Update HealthSystem");
-        let mut chan = resources.fetch_mut::<EventChannel<GameEvent>>().unwrap();
-        let mut death_events = vec![];
+        let chan = resources.fetch::<EventChannel<Hit>>().unwrap();
+        let mut death_events = DeathEvents::default();
 
         // FIRST, PROCESS ALL EVENTS TO SEE IF ANYBODY GOT HIT
         // ----------------------------------------------------
-        for ev in chan.read(&mut self.rdr_id) {
-            if let GameEvent::Hit(e, hit_details) = ev {
-                debug!("Process HIT event for {:?}", e);
-                let mut hit_points = hit_details.hit_points;
-                let mut explosion = false;
-                let mut insert_blink = false;
-                {
-                    let invulnerable = world.get::<Invulnerable>(*e);
-                    if invulnerable.is_ok() {
-                        continue;
-                    }
+        let rdr_id = self
+            .rdr_id
+            .as_mut()
+            .expect("HealthSystem should be set up before running");
+        for Hit {
+            entity: e,
+            details: hit_details,
+        } in chan.read(rdr_id)
+        {
+            debug!("Process HIT event for {:?}", e);
+            let mut hit_points = hit_details.hit_points;
+            let mut explosion = false;
+            let mut insert_blink = false;
+            {
+                let invulnerable = world.get::<Invulnerable>(*e);
+                if invulnerable.is_ok() {
+                    continue;
+                }
 
-                    let health = world.get_mut::<Health>(*e);
-                    let shield = world.get_mut::<Shield>(*e);
-                    let t = world.get::<Transform>(*e);
-                    if t.is_err() {
-                        continue;
+                let health = world.get_mut::<Health>(*e);
+                let shield = world.get_mut::<Shield>(*e);
+                let t = world.get::<Transform>(*e);
+                if t.is_err() {
+                    continue;
+                }
+                let t = t.unwrap();
+
+                let enemy_drop = if let Ok(mut enemy) = world.get_mut::<Enemy>(*e) {
+                    // if should explode on contact. BOUM
+                    if let EnemyType::Mine {
+                        ref mut explosion_timer,
+                        ..
+                    } = enemy.enemy_type
+                    {
+                        explosion_timer.start();
                     }
-                    let t = t.unwrap();
-
-                    let enemy_drop = if let Ok(mut enemy) = world.get_mut::<Enemy>(*e) {
-                        // if should explode on contact. BOUM
-                        if let EnemyType::Mine {
-                            ref mut explosion_timer,
-                            ..
-                        } = enemy.enemy_type
-                        {
-                            explosion_timer.start();
-                        }
 
-                        Some((t.translation, enemy.scrap_drop, enemy.pickup_drop_percent))
-                    } else {
-                        None
-                    };
-
-                    if let Ok(mut shield) = shield {
-                        // reset shield timer. Shield cannot recharge until elapsed.
-                        shield.timer_until_replenish.reset();
-                        shield.timer_until_replenish.start();
-                        if shield.current != 0.0 {
-                            if shield.current > hit_points {
-                                shield.current -= hit_points;
-                                hit_points = 0.0;
-                            } else {
-                                hit_points -= shield.current;
-                                shield.current = 0.0;
-                            }
+                    Some((t.translation, enemy.scrap_drop, enemy.pickup_drop_percent))
+                } else {
+                    None
+                };
+
+                if let Ok(mut shield) = shield {
+                    // reset shield timer. Shield cannot recharge until elapsed.
+                    shield.timer_until_replenish.reset();
+                    shield.timer_until_replenish.start();
+                    if shield.current != 0.0 {
+                        if shield.current > hit_points {
+                            shield.current -= hit_points;
+                            hit_points = 0.0;
+                        } else {
+                            hit_points -= shield.current;
+                            shield.current = 0.0;
                         }
                     }
+                }
 
-                    // if no shield, then we can hit the health.
-                    if hit_points > 0.0 {
-                        if let Ok(mut health) = health {
-                            if !health.hittable {
-                                continue;
-                            }
-
-                            health.current -= hit_points;
-                            if health.is_dead() {
-                                debug!("{:?} is dead ({:?}", e, *health);
-                                Self::add_death_events(&mut death_events, world, *e, enemy_drop);
-                                explosion = true;
-                            } else {
-                                // start invulnerability frames.
-                                health.hittable = false;
-                                health.invulnerability_timer.reset();
-                                health.invulnerability_timer.start();
-                                insert_blink = true;
-                            }
-                        } else {
-                            // no shield, no health,  you're dead boy.
+                // if no shield, then we can hit the health.
+                if hit_points > 0.0 {
+                    if let Ok(mut health) = health {
+                        if !health.hittable {
+                            continue;
+                        }
+
+                        health.current -= hit_points;
+                        if health.is_dead() {
+                            debug!("{:?} is dead ({:?}", e, *health);
                             Self::add_death_events(&mut death_events, world, *e, enemy_drop);
                             explosion = true;
+                        } else {
+                            // start invulnerability frames.
+                            health.hittable = false;
+                            health.invulnerability_timer.reset();
+                            health.invulnerability_timer.start();
+                            insert_blink = true;
                         }
+                    } else {
+                        // no shield, no health,  you're dead boy.
+                        Self::add_death_events(&mut death_events, world, *e, enemy_drop);
+                        explosion = true;
                     }
                 }
+            }
 
-                if explosion {
-                    let transform = { world.get::<Transform>(*e).unwrap().translation }; // no sense if no transform..
-                    self.make_explosion(world, transform);
-                }
+            if explosion {
+                let transform = { world.get::<Transform>(*e).unwrap().translation }; // no sense if no transform..
+                Self::make_explosion(world, resources, transform);
+            }
 
-                if insert_blink {
-                    debug!("WIll insert blink");
-                    world
-                        .insert(
-                            *e,
-                            (Blink {
-                                color: [1.0, 0.0, 0.0, 1.0],
-                                amplitude: 10.0,
-                            },),
-                        )
-                        .unwrap();
-                }
+            if world.get::<Player>(*e).is_ok() {
+                camera::add_trauma(world, 0.3);
             }
-        }
 
-        if !death_events.is_empty() {
-            debug!("WIll publish {:?}", death_events);
-            chan.drain_vec_write(&mut death_events);
+            if insert_blink {
+                debug!("WIll insert blink");
+                world
+                    .insert(
+                        *e,
+                        (Blink {
+                            color: [1.0, 0.0, 0.0, 1.0],
+                            amplitude: 10.0,
+                        },),
+                    )
+                    .unwrap();
+            }
         }
 
+        debug!("WIll publish {:?}", death_events);
+        death_events.write(resources);
+
         // THEN, UPDATE INVULNERABILY TIMERS.
         // ----------------------------------------------------
         let mut remove_blink = vec![];
@@ -255,32 +254,75 @@ impl HealthSystem {
     }
 
     fn add_death_events(
-        death_events: &mut Vec<GameEvent>,
+        death_events: &mut DeathEvents,
         world: &hecs::World,
         entity: hecs::Entity,
         is_enemy: Option<(glam::Vec2, (u32, u32), u8)>,
     ) {
         // no shield, no health,  you're dead boy.
         if world.get::<Player>(entity).is_ok() {
-            death_events.push(GameEvent::GameOver);
+            death_events.game_over = true;
         } else {
-            death_events.push(GameEvent::Delete(entity));
+            death_events.deletes.push(Delete(entity));
         }
 
         if let Some(drop) = is_enemy {
-            death_events.push(GameEvent::EnemyDied(entity, drop.0, drop.1, drop.2));
+            death_events.enemies.push(EnemyDied {
+                entity,
+                position: drop.0,
+                scrap: drop.1,
+                pickup_drop: drop.2,
+            });
         }
     }
 
-    fn make_explosion(&self, world: &mut hecs::World, pos: glam::Vec2) {
-        world.spawn((
-            Transform {
-                translation: pos,
-                rotation: 0.0,
-                scale: glam::Vec2::one(),
-                dirty: false,
-            },
-            self.explosion.clone(),
-        ));
+    fn make_explosion(world: &mut hecs::World, resources: &Resources, pos: glam::Vec2) {
+        let transform = Transform {
+            translation: pos,
+            rotation: 0.0,
+            scale: glam::Vec2::one(),
+            dirty: false,
+        };
+        let effect = ParticleEffect::new(EXPLOSION_PARTICLE);
+        // the explosion is in the main scene manifest so it should already be loaded.
+        let particle_manager = resources.fetch::<ParticleManager<GlfwSurface>>().unwrap();
+        match effect.emitter(&*particle_manager) {
+            Some(emitter) => world.spawn((transform, effect, emitter)),
+            None => world.spawn((transform, effect)),
+        };
+    }
+}
+
+/// What happens when entities die during a frame.
+#[derive(Debug, Default)]
+struct DeathEvents {
+    deletes: Vec<Delete>,
+    enemies: Vec<EnemyDied>,
+    game_over: bool,
+}
+
+impl DeathEvents {
+    fn write(self, resources: &Resources) {
+        if self.game_over {
+            resources.write_event(GameOver);
+        }
+        resources.write_events(self.deletes);
+        resources.write_events(self.enemies);
+    }
+}
+
+impl Default for HealthSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl System for HealthSystem {
+    fn setup(&mut self, _world: &mut hecs::World, resources: &mut Resources) {
+        self.rdr_id = Some(resources.register_reader::<Hit>());
+    }
+
+    fn run(&mut self, world: &mut hecs::World, resources: &Resources, dt: Duration) {
+        self.update(world, resources, dt);
     }
 }
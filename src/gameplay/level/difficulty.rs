@@ -1,3 +1,4 @@
+use crate::core::curve::Curve;
 use crate::core::random::RandomGenerator;
 use crate::prefab::enemies::{ENEMY_STR_1, ENEMY_STR_2, ENEMY_STR_3};
 use rand::seq::SliceRandom;
@@ -60,6 +61,8 @@ impl WaveDifficulty {
 pub enum DifficultyCurve {
     Linear(f32, f32),
     Constant(f32),
+    /// Any curve, sampled with the wave number.
+    Curve(Curve<f32>),
 }
 
 impl DifficultyCurve {
@@ -67,6 +70,10 @@ impl DifficultyCurve {
         match self {
             Self::Linear(origin, slope) => origin + slope * x,
             Self::Constant(v) => *v,
+            Self::Curve(curve) => curve.y(x).unwrap_or_else(|e| {
+                error!("Invalid difficulty curve = {}", e);
+                0.0
+            }),
         }
     }
 }
@@ -1,7 +1,11 @@
+use crate::assets::manifest::AssetManifest;
+use crate::assets::AssetError;
 use crate::core::noise::perlin::Perlin;
 use crate::core::random::RandomGenerator;
+use crate::core::system::System;
 use crate::core::timer::Timer;
 use crate::core::transform::Transform;
+use crate::gameplay::camera::{set_arena_bounds, ArenaBounds};
 use crate::gameplay::collision::{BoundingBox, CollisionLayer};
 use crate::gameplay::physics::DynamicBody;
 use crate::gameplay::pickup::spawn_pickup;
@@ -15,16 +19,19 @@ use std::time::Duration;
 const NB_BLOCKS_X: u32 = 80;
 const NB_BLOCKS_Y: u32 = 50;
 
+/// Stage the story mode starts with, in the stages folder.
+pub const FIRST_STAGE: &str = "stage1.json";
+
 pub mod difficulty;
 pub mod wave;
-use crate::event::GameEvent;
+use crate::event::{NextStage, YouWin};
 use crate::gameplay::explosion::Explosion;
 use crate::gameplay::health::Invulnerable;
 use crate::gameplay::level::difficulty::DifficultyConfig;
-use shrev::EventChannel;
+use crate::prefab::enemies::ENEMY_PREFABS;
 use wave::{Wave, WaveDescription};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct StageDescription {
     pub waves: Vec<WaveDescription>,
     pub nb_pickups: usize,
@@ -35,6 +42,10 @@ pub struct StageDescription {
     pub next_stage: Option<String>,
 
     pub backgrounds: Vec<String>,
+
+    /// Where the camera can go.
+    #[serde(default)]
+    pub arena: ArenaBounds,
 }
 
 impl StageDescription {
@@ -65,7 +76,62 @@ impl StageDescription {
             .iter()
             .map(|s| s.to_string())
             .collect(),
+            arena: ArenaBounds::default(),
+        }
+    }
+
+    /// Parse and validate a stage. The name is only used for the errors.
+    pub fn from_json(name: &str, content: &str) -> Result<Self, AssetError> {
+        let stage: Self = serde_json::from_str(content)?;
+        let problems = stage.problems();
+        if problems.is_empty() {
+            Ok(stage)
+        } else {
+            Err(AssetError::InvalidStage(
+                name.to_string(),
+                problems.join(", "),
+            ))
+        }
+    }
+
+    /// What would make the stage fail while playing it, with the wave and field at fault.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if !self.is_infinite && self.waves.is_empty() {
+            problems.push("waves: there should be at least one wave".to_string());
+        }
+        for (i, wave) in self.waves.iter().enumerate() {
+            if wave.to_instantiate.is_empty() {
+                problems.push(format!("wave {}: to_instantiate is empty", i));
+            }
+            for (j, prefab) in wave.to_instantiate.iter().enumerate() {
+                if prefab.is_empty() {
+                    problems.push(format!("wave {}: to_instantiate[{}] is empty", i, j));
+                }
+            }
         }
+        if let Some("") = self.next_stage.as_deref() {
+            problems.push("next_stage: should be a stage file".to_string());
+        }
+        if self.arena.min.x > self.arena.max.x || self.arena.min.y > self.arena.max.y {
+            problems.push("arena: min should be lower than max".to_string());
+        }
+        problems
+    }
+
+    /// Prefabs of the waves. The infinite mode can spawn any enemy. The sprites and sounds used by
+    /// the prefabs are added with `AssetManifest::add_prefab_dependencies` once the prefabs are
+    /// loaded. Only one background is used so they are loaded when needed.
+    pub fn manifest(&self) -> AssetManifest {
+        let prefabs: Vec<String> = if self.is_infinite {
+            ENEMY_PREFABS.iter().map(|p| p.to_string()).collect()
+        } else {
+            self.waves
+                .iter()
+                .flat_map(|w| w.to_instantiate.iter().cloned())
+                .collect()
+        };
+        AssetManifest::default().with_prefabs(prefabs)
     }
 }
 
@@ -86,6 +152,8 @@ pub struct Stage {
 
     finished: bool,
     next_stage: Option<String>,
+    /// The assets of the waves are not all loaded yet. The next wave waits for them.
+    loading_assets: bool,
 
     pub is_infinite: bool,
     pub wave_number: usize,
@@ -114,10 +182,12 @@ impl Stage {
                         rotation: 0.0,
                         dirty: false,
                     },
-                    Sprite { id: background },
+                    Sprite::new(background),
                 ))
             });
 
+        set_arena_bounds(world, stage_desc.arena);
+
         // 2. GENERATE ASTEROIDS!
         // -------------------------------
         let (asteroids, no_asteroids) = generate_terrain(world, &mut *random, 15);
@@ -148,6 +218,7 @@ impl Stage {
             timer_between_waves: Timer::of_seconds(5.0),
             timer_between_stages: Timer::of_seconds(10.0),
             next_stage: stage_desc.next_stage,
+            loading_assets: false,
             is_infinite: stage_desc.is_infinite,
         }
     }
@@ -173,6 +244,11 @@ impl Stage {
             .collect()
     }
 
+    /// Hold back the next wave while its assets are loading.
+    pub fn set_loading_assets(&mut self, loading_assets: bool) {
+        self.loading_assets = loading_assets;
+    }
+
     pub fn enemy_died(&mut self, entity: Entity) {
         if let Some(wave) = self.current_wave {
             if let Some(wave) = self.waves.get_mut(wave) {
@@ -184,23 +260,22 @@ impl Stage {
     pub fn update(&mut self, world: &mut hecs::World, resources: &Resources, dt: Duration) {
         match (self.current_wave, self.next_wave) {
             (None, None) => {
-                let mut channel = resources.fetch_mut::<EventChannel<GameEvent>>().unwrap();
                 if let Some(next_stage) = self.next_stage.as_ref() {
                     // In this case, the stage is over !
                     self.finished = true;
                     self.timer_between_stages.tick(dt);
                     if self.timer_between_stages.finished() {
-                        channel.single_write(GameEvent::NextStage(next_stage.clone()));
+                        resources.write_event(NextStage(next_stage.clone()));
                     }
                 } else {
                     // no more stages, the game is finished !
-                    channel.single_write(GameEvent::YouWin);
+                    resources.write_event(YouWin);
                 }
             }
             (None, Some(next_wave)) => {
                 // Tick the timer between waves.
                 self.timer_between_waves.tick(dt);
-                if self.timer_between_waves.finished() {
+                if self.timer_between_waves.finished() && !self.loading_assets {
                     self.wave_number += 1;
                     self.timer_between_waves.stop();
                     self.timer_between_waves.reset();
@@ -244,6 +319,10 @@ impl Stage {
         if self.finished {
             return Some("Brace for next stage".to_string());
         }
+        if self.current_wave.is_none() && self.loading_assets && self.timer_between_waves.finished()
+        {
+            return Some("Loading the next wave...".to_string());
+        }
         if let None = self.current_wave {
             Some(format!(
                 "Next wave will start in {:02}",
@@ -297,6 +376,12 @@ impl Stage {
     }
 }
 
+impl System for Stage {
+    fn run(&mut self, world: &mut hecs::World, resources: &Resources, dt: Duration) {
+        self.update(world, resources, dt);
+    }
+}
+
 //
 pub fn generate_terrain(
     world: &mut hecs::World,
@@ -357,9 +442,7 @@ pub fn generate_terrain(
                 rotation: 0.0,
                 dirty: false,
             },
-            Sprite {
-                id: "asteroid.png".to_string(),
-            },
+            Sprite::new("asteroid.png".to_string()),
             DynamicBody {
                 impulses: vec![],
                 forces: vec![],
@@ -391,9 +474,7 @@ pub fn generate_terrain(
                 rotation: 0.0,
                 dirty: false,
             },
-            Sprite {
-                id: "asteroid.png".to_string(),
-            },
+            Sprite::new("asteroid.png".to_string()),
             DynamicBody {
                 impulses: vec![],
                 forces: vec![],
@@ -441,3 +522,85 @@ fn pick_positions(
         .map(|p| *p)
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage(waves: &[&[&str]]) -> StageDescription {
+        StageDescription {
+            waves: waves
+                .iter()
+                .map(|w| WaveDescription {
+                    to_instantiate: w.iter().map(|p| p.to_string()).collect(),
+                })
+                .collect(),
+            nb_pickups: 1,
+            is_infinite: false,
+            next_stage: Some("stage2.json".to_string()),
+            backgrounds: vec!["back.png".to_string()],
+            arena: ArenaBounds::default(),
+        }
+    }
+
+    #[test]
+    fn valid_stage_has_no_problem() {
+        assert!(stage(&[&["base_enemy"], &["boss1", "kamikaze"]])
+            .problems()
+            .is_empty());
+        assert!(StageDescription::infinite().problems().is_empty());
+    }
+
+    #[test]
+    fn problems_name_the_wave_and_the_field() {
+        assert_eq!(
+            vec!["waves: there should be at least one wave"],
+            stage(&[]).problems()
+        );
+        assert_eq!(
+            vec![
+                "wave 1: to_instantiate is empty",
+                "wave 2: to_instantiate[1] is empty"
+            ],
+            stage(&[&["base_enemy"], &[], &["base_enemy", ""]]).problems()
+        );
+
+        let mut desc = stage(&[&["base_enemy"]]);
+        desc.next_stage = Some("".to_string());
+        desc.arena.min.x = 1000.0;
+        assert_eq!(
+            vec![
+                "next_stage: should be a stage file",
+                "arena: min should be lower than max"
+            ],
+            desc.problems()
+        );
+    }
+
+    #[test]
+    fn invalid_stage_is_an_error() {
+        let content =
+            r#"{ "nb_pickups": 1, "waves": [{ "to_instantiate": [] }], "backgrounds": [] }"#;
+        match StageDescription::from_json("broken.json", content) {
+            Err(e @ AssetError::InvalidStage(..)) => assert_eq!(
+                "Invalid stage broken.json: wave 0: to_instantiate is empty",
+                e.to_string()
+            ),
+            other => panic!("expected an invalid stage, got {:?}", other.map(|_| ())),
+        }
+
+        for name in &["stage1.json", "stage2.json", "stage3.json"] {
+            let content = std::fs::read_to_string(format!(
+                "{}/assets/stages/{}",
+                env!("CARGO_MANIFEST_DIR"),
+                name
+            ))
+            .unwrap();
+            assert!(
+                StageDescription::from_json(name, &content).is_ok(),
+                "{}",
+                name
+            );
+        }
+    }
+}
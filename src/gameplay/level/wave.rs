@@ -1,5 +1,4 @@
 use crate::assets::prefab::PrefabManager;
-use crate::assets::Handle;
 use crate::core::random::RandomGenerator;
 use crate::prefab::enemies::ENEMY_PREFABS;
 use crate::resources::Resources;
@@ -55,12 +54,9 @@ impl Wave {
         let prefab_manager = resources.fetch_mut::<PrefabManager<GlfwSurface>>().unwrap();
         for prefab_name in to_instantiate {
             let pos = no_asteroids.choose(random.rng());
-            if let Some(prefab) = prefab_manager.get(&Handle(prefab_name.clone())) {
-                prefab.execute(|prefab| {
-                    info!("Will spawn = {:?}", prefab);
-
-                    enemies.push(prefab.spawn_at_pos(world, *pos.unwrap()));
-                });
+            if let Some(e) = prefab_manager.spawn_at_pos(prefab_name, world, *pos.unwrap()) {
+                info!("Spawned {} = {:?}", prefab_name, e);
+                enemies.push(e);
             } else {
                 error!(
                     "Prefab {} should have been loaded in the loading scene",
@@ -1,4 +1,5 @@
 use crate::core::colors;
+use crate::core::system::System;
 use crate::core::transform::Transform;
 use crate::render::path::debug;
 use crate::resources::Resources;
@@ -104,3 +105,9 @@ impl PhysicSystem {
         }
     }
 }
+
+impl System for PhysicSystem {
+    fn run(&mut self, world: &mut World, resources: &Resources, dt: Duration) {
+        self.update(world, dt, resources);
+    }
+}
@@ -2,7 +2,7 @@ use crate::core::input::Input;
 use crate::core::random::RandomGenerator;
 use crate::core::timer::Timer;
 use crate::core::transform::Transform;
-use crate::event::GameEvent;
+use crate::event::{Delete, InfoText, PlaySound};
 use crate::gameplay::collision::{aabb_intersection, BoundingBox, CollisionLayer};
 use crate::gameplay::health::{Health, Invulnerable, Shield};
 use crate::gameplay::inventory::Inventory;
@@ -14,7 +14,6 @@ use crate::resources::Resources;
 use rand::distributions::{Distribution, Standard};
 use rand::Rng;
 use serde_derive::{Deserialize, Serialize};
-use shrev::EventChannel;
 
 pub struct Pickup {
     pub item: Items,
@@ -34,9 +33,7 @@ pub fn spawn_pickup(
             rotation: 0.0,
             dirty: false,
         },
-        Sprite {
-            id: "capsule.png".to_string(),
-        },
+        Sprite::new("capsule.png".to_string()),
         Pickup { item },
         BoundingBox {
             half_extend: glam::vec2(10.0, 10.0),
@@ -51,10 +48,10 @@ pub fn is_pickup(world: &hecs::World, entity: hecs::Entity) -> bool {
 }
 
 pub fn process_pickups(world: &mut hecs::World, resources: &Resources) {
-    let mut channel = resources.fetch_mut::<EventChannel<GameEvent>>().unwrap();
     let input = resources.fetch::<Input<Action>>().unwrap();
     let mut inventory = resources.fetch_mut::<Inventory>().unwrap();
     let mut to_delete = vec![];
+    let mut info_texts = vec![];
 
     let maybe_player = {
         let mut query = world.query::<(&Transform, &BoundingBox, &Player)>();
@@ -69,9 +66,9 @@ pub fn process_pickups(world: &mut hecs::World, resources: &Resources) {
             {
                 if let Ok(()) = inventory.remove_scratch(50) {
                     //
-                    channel.single_write(GameEvent::PlaySound("sounds/powerUp2.mp3".to_string()));
-                    to_delete.push(GameEvent::Delete(e));
-                    to_delete.push(GameEvent::InfoText(pickup.item.info_text()));
+                    resources.write_event(PlaySound("sounds/powerUp2.mp3".to_string()));
+                    to_delete.push(Delete(e));
+                    info_texts.push(InfoText(pickup.item.info_text()));
                     picked_up.push(pickup.item);
                 }
             }
@@ -82,7 +79,8 @@ pub fn process_pickups(world: &mut hecs::World, resources: &Resources) {
         }
     }
 
-    channel.drain_vec_write(&mut to_delete);
+    resources.write_events(to_delete);
+    resources.write_events(info_texts);
 }
 
 pub fn aabb_intersection2(
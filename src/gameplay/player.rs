@@ -1,12 +1,14 @@
 use super::bullet;
+use crate::assets::manifest::AssetManifest;
 use crate::config::PlayerConfig;
 use crate::core::audio;
-use crate::core::camera::screen_to_world;
+use crate::core::camera::{screen_to_world, ProjectionMatrix};
 use crate::core::input::{Axis, Input};
 use crate::core::random::RandomGenerator;
 use crate::core::timer::Timer;
 use crate::core::transform::Transform;
-use crate::gameplay::bullet::spawn_missile;
+use crate::core::window::WindowDim;
+use crate::gameplay::bullet::{spawn_missile, BulletType, MISSILE_SPRITE};
 use crate::gameplay::collision::CollisionLayer;
 use crate::gameplay::enemy::Enemy;
 use crate::gameplay::health::HitDetails;
@@ -14,7 +16,6 @@ use crate::gameplay::physics::DynamicBody;
 use crate::gameplay::trail::Trail;
 use crate::gameplay::{steering, Action};
 use crate::resources::Resources;
-use crate::{HEIGHT, WIDTH};
 use bitflags::_core::time::Duration;
 use hecs::{Entity, World};
 #[allow(unused_imports)]
@@ -22,11 +23,22 @@ use log::{info, trace};
 use rand::Rng;
 use serde_derive::{Deserialize, Serialize};
 
+const SHOT_SOUND: &str = "sounds/scifi_kit/Laser/Laser_09.wav";
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Weapon {
     Simple,
 }
 
+impl Player {
+    /// Sounds and bullets used when the player shoots.
+    pub fn dependencies(manifest: &mut AssetManifest) {
+        manifest.add_sound(SHOT_SOUND.to_string());
+        manifest.add_sprite(BulletType::Twin.get_sprite_name());
+        manifest.add_sprite(MISSILE_SPRITE.to_string());
+    }
+}
+
 /// Tag to tell the ECS that the entity is a player.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Player {
@@ -113,8 +125,8 @@ pub fn update_player(world: &mut World, dt: Duration, resources: &Resources) {
     let mut random = resources.fetch_mut::<RandomGenerator>().unwrap();
     let player_controller_conf = resources.fetch::<PlayerConfig>().unwrap();
 
-    let projection_matrix =
-        glam::Mat4::orthographic_rh_gl(0.0, WIDTH as f32, 0.0, HEIGHT as f32, -1.0, 10.0);
+    let projection_matrix = resources.fetch::<ProjectionMatrix>().unwrap().matrix();
+    let window_dim = resources.fetch::<WindowDim>().unwrap();
 
     let mut bullets = vec![];
     let mut missiles = vec![];
@@ -138,7 +150,7 @@ pub fn update_player(world: &mut World, dt: Duration, resources: &Resources) {
         trail.should_display = delta_y.max(0.0) > 0.0;
 
         // DESIRED VELOCITY IF FORWARD TO THE MOUSE CURSOR
-        let target = screen_to_world(input.mouse_position(), projection_matrix, world);
+        let target = screen_to_world(input.mouse_position(&window_dim), projection_matrix, world);
 
         let steering_force = steering::seek(
             transform.translation,
@@ -189,11 +201,11 @@ pub fn update_player(world: &mut World, dt: Duration, resources: &Resources) {
                 }
             }
 
-            audio::play_sound(resources, "sounds/scifi_kit/Laser/Laser_09.wav");
+            audio::play_sound(resources, SHOT_SOUND);
             bullets = vec![(
                 initial_pos,
                 dir,
-                bullet::BulletType::Twin,
+                BulletType::Twin,
                 HitDetails {
                     hit_points: dmg,
                     is_crit,
@@ -1,3 +1,4 @@
+use crate::core::random::RandomGenerator;
 use crate::core::transform::Transform;
 use crate::gameplay::collision::{CollisionLayer, CollisionWorld, Ray};
 use hecs::Entity;
@@ -21,9 +22,13 @@ pub fn seek(
 }
 
 /// Random movements that do not look too weird
-pub fn wander(velocity: glam::Vec2, wander_strength: f32) -> glam::Vec2 {
+pub fn wander(
+    velocity: glam::Vec2,
+    wander_strength: f32,
+    random: &mut RandomGenerator,
+) -> glam::Vec2 {
     let circle_center = velocity.normalize() * 20.0; // TODO Circle distance somewhere else.
-    let mut rng = rand::thread_rng();
+    let rng = random.rng();
     let displacement = glam::Mat2::from_angle(rng.gen_range(0.0, 2.0 * std::f32::consts::PI))
         * glam::Vec2::unit_x()
         * wander_strength;
@@ -16,6 +16,7 @@ pub mod paths;
 pub mod prefab;
 pub mod render;
 pub mod resources;
+pub mod runner;
 pub mod save;
 pub mod scene;
 pub mod ui;
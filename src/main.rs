@@ -6,7 +6,11 @@ use std::process::exit;
 
 use spacegame::game::{Game, GameBuilder};
 
+use spacegame::assets::manifest::AssetManifest;
+use spacegame::assets::Budget;
 use spacegame::config::{load_config, AudioConfig, GameEngineConfig, InputConfig, PlayerConfig};
+use spacegame::core::input::replay::InputRecording;
+use spacegame::core::scene::Scene;
 use spacegame::gameplay::inventory::Inventory;
 use spacegame::gameplay::level::difficulty::DifficultyConfig;
 use spacegame::gameplay::Action;
@@ -17,9 +21,41 @@ use spacegame::scene::loading::LoadingScene;
 use spacegame::scene::main_menu::MainMenu;
 #[allow(unused_imports)]
 use spacegame::scene::particle_scene::ParticleScene;
+use spacegame::scene::{main_scene_manifest, MainScene};
 use spacegame::DIMENSIONS;
+use std::path::PathBuf;
+
+/// Texture memory in MiB, unless the engine config says otherwise.
+const DEFAULT_TEXTURE_BUDGET_MB: usize = 256;
+
+/// Options from the command line.
+///
+/// --record file: save the inputs to the file when exiting.
+/// --replay file: play back the inputs saved in the file.
+#[derive(Default)]
+struct Args {
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+}
+
+fn parse_args() -> Args {
+    let mut args = Args::default();
+    let mut it = std::env::args().skip(1);
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--record" => args.record = it.next().map(PathBuf::from),
+            "--replay" => args.replay = it.next().map(PathBuf::from),
+            _ => {
+                eprintln!("Unknown argument {}", arg);
+                exit(1);
+            }
+        }
+    }
+    args
+}
 
 fn main() {
+    let args = parse_args();
     let surface = GlfwSurface::new_gl33(
         "EverFight",
         WindowOpt::default()
@@ -28,7 +64,7 @@ fn main() {
     );
 
     match surface {
-        Ok(surface) => main_loop(surface),
+        Ok(surface) => main_loop(surface, args),
         Err(e) => {
             eprintln!("Error = {}", e);
             exit(1);
@@ -36,7 +72,7 @@ fn main() {
     }
 }
 
-fn main_loop(mut surface: GlfwSurface) {
+fn main_loop(mut surface: GlfwSurface, args: Args) {
     dotenv::dotenv().ok();
     pretty_env_logger::init();
 
@@ -67,28 +103,32 @@ fn main_loop(mut surface: GlfwSurface) {
 
     let saved_data = read_saved_data();
 
+    let boot_manifest = AssetManifest::from_file("boot.json").unwrap_or_else(|e| {
+        log::error!("Cannot read boot manifest = {}", e);
+        AssetManifest::default()
+    });
+
+    // Menus are driven by the GUI which is not recorded, so a recorded run starts directly in the
+    // game.
+    let first_scene: Box<dyn Scene<_>> = if args.record.is_some() || args.replay.is_some() {
+        Box::new(LoadingScene::new(
+            boot_manifest.merged(&main_scene_manifest(false)),
+            MainScene::default(),
+        ))
+    } else {
+        Box::new(LoadingScene::new(boot_manifest, MainMenu::default()))
+    };
+
+    let texture_budget_mb = engine_config
+        .texture_budget_mb
+        .unwrap_or(DEFAULT_TEXTURE_BUDGET_MB);
+
     let mut builder: GameBuilder<Action> = GameBuilder::new(&mut surface)
-        .for_scene(Box::new(LoadingScene::new(
-            vec![],
-            vec![
-                "music/spacelifeNo14.ogg".to_string(),
-                "music/Finding-Flora.wav".to_string(),
-                "sounds/scifi_kit/Laser/Laser_09.wav".to_string(),
-                "sounds/scifi_kit/Laser/Laser_01.wav".to_string(),
-                "sounds/scifi_kit/Laser/Laser_02.wav".to_string(),
-                "sounds/scifi_kit/Laser/Laser_03.wav".to_string(),
-                "sounds/scifi_kit/Laser/Laser_04.wav".to_string(),
-                "sounds/scifi_kit/Laser/Laser_05.wav".to_string(),
-                "sounds/scifi_kit/Laser/Laser_06.wav".to_string(),
-                "sounds/scifi_kit/Laser/Laser_07.wav".to_string(),
-                "sounds/scifi_kit/Laser/Laser_08.wav".to_string(),
-                "sounds/explosion.wav".to_string(),
-                "sounds/powerUp2.mp3".to_string(),
-            ],
-            MainMenu::default(),
-        )))
+        .for_scene(first_scene)
         .with_resource(saved_data)
         .with_resource(player_config)
+        .with_scale_mode(engine_config.scale_mode)
+        .with_texture_budget(Budget::bytes(texture_budget_mb * 1024 * 1024))
         .with_resource(engine_config)
         .with_resource(difficulty_config)
         .with_resource(Inventory::default());
@@ -102,6 +142,20 @@ fn main_loop(mut surface: GlfwSurface) {
         builder = builder.with_audio_config(audio_config);
     }
 
+    if let Some(path) = args.record {
+        builder = builder.with_input_recording(path);
+    }
+
+    if let Some(path) = args.replay {
+        match InputRecording::load(&path) {
+            Ok(recording) => builder = builder.with_input_replay(recording),
+            Err(e) => {
+                eprintln!("Cannot load recording {} = {}", path.display(), e);
+                exit(1);
+            }
+        }
+    }
+
     let mut game: Game<Action> = builder.build();
     game.run();
 }
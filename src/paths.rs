@@ -5,6 +5,12 @@ pub fn get_assets_path() -> PathBuf {
     PathBuf::from(std::env::var("ASSET_PATH").unwrap_or("assets/".to_string()))
 }
 
+/// Asset pack read at runtime, see `assets::source`. None if `PACK_PATH` is not set, so that a pack
+/// left in the working directory does not hide the asset folder.
+pub fn get_pack_path() -> Option<PathBuf> {
+    std::env::var("PACK_PATH").ok().map(PathBuf::from)
+}
+
 pub fn get_save_path() -> PathBuf {
     if let Some(mut save_dir) = data_dir() {
         save_dir.push("everfight");
@@ -1,12 +1,13 @@
+use crate::assets::manifest::AssetManifest;
 use crate::assets::prefab::Prefab;
-use crate::core::animation::AnimationController;
+use crate::core::animation::{AnimationController, AnimationSheet};
 use crate::core::transform::Transform;
 use crate::gameplay::collision::{BoundingBox, CollisionLayer};
 use crate::gameplay::enemy::Enemy;
 use crate::gameplay::health::{Health, Shield};
 use crate::gameplay::physics::DynamicBody;
 use crate::gameplay::trail::Trail;
-use crate::render::particle::ParticleEmitter;
+use crate::render::particle::ParticleEffect;
 use crate::render::sprite::Sprite;
 use hecs::EntityBuilder;
 use serde_derive::{Deserialize, Serialize};
@@ -39,8 +40,13 @@ pub struct EnemyPrefab {
     pub health: Option<Health>,
     pub shield: Option<Shield>,
     pub enemy: Enemy,
-    pub trail: Option<ParticleEmitter>,
+    /// Emitter in `assets/particle`.
+    pub trail: Option<ParticleEffect>,
     pub animation: Option<AnimationController>,
+    /// Sprite sheet in `assets/sheets` to animate the enemy with, e.g. `"explosion.json"`. Its
+    /// animations are used when `animation` is not set.
+    #[serde(default)]
+    pub animation_sheet: Option<String>,
 }
 
 #[typetag::serde]
@@ -58,9 +64,8 @@ impl Prefab for EnemyPrefab {
             components.add(s);
         }
         components.add(self.enemy.clone());
-        if let Some(mut particles) = self.trail.clone() {
-            particles.init_pool();
-            components.add(particles);
+        if let Some(trail) = self.trail.clone() {
+            components.add(trail);
             components.add(Trail {
                 should_display: true,
                 offset: 0.0,
@@ -68,9 +73,51 @@ impl Prefab for EnemyPrefab {
         }
         if let Some(animation) = self.animation.clone() {
             components.add(animation);
+        } else if let Some(ref sheet) = self.animation_sheet {
+            components.add(AnimationSheet(sheet.clone()));
         }
         world.spawn(components.build())
     }
+
+    fn dependencies(&self, manifest: &mut AssetManifest) {
+        manifest.add_sprite(self.sprite.id.clone());
+        if let Some(ref trail) = self.trail {
+            manifest.add_particle(trail.handle.0.clone());
+        }
+        if let Some(ref animation) = self.animation {
+            animation
+                .sprites()
+                .for_each(|sprite| manifest.add_sprite(sprite.clone()));
+        }
+        if let Some(ref sheet) = self.animation_sheet {
+            manifest.add_sheet(sheet.clone());
+        }
+        self.enemy.dependencies(manifest);
+    }
+
+    fn patch(&self, world: &mut hecs::World, entity: hecs::Entity) {
+        super::patch_common(
+            world,
+            entity,
+            &self.dynamic_body,
+            &self.transform,
+            &self.sprite,
+            self.bounding_box,
+            self.health.as_ref(),
+            self.trail.as_ref(),
+        );
+        if let Some(animation) = self.animation.clone() {
+            if let Err(e) = world.insert_one(entity, animation) {
+                error!("Cannot patch animation = {:?}", e);
+            }
+        } else if let Some(ref sheet) = self.animation_sheet {
+            // the animation system creates the controller again from the sheet.
+            let _ = world.remove_one::<AnimationController>(entity);
+            if let Err(e) = world.insert_one(entity, AnimationSheet(sheet.clone())) {
+                error!("Cannot patch animation sheet = {:?}", e);
+            }
+        }
+    }
 }
 
 impl Default for EnemyPrefab {
@@ -85,7 +132,7 @@ impl Default for EnemyPrefab {
                 max_force: 500.0,
             },
             transform: Transform::default(),
-            sprite: Sprite { id: String::new() },
+            sprite: Sprite::new(String::new()),
             bounding_box: BoundingBox {
                 half_extend: Default::default(),
                 collision_layer: CollisionLayer::NOTHING,
@@ -96,6 +143,7 @@ impl Default for EnemyPrefab {
             enemy: Enemy::default(),
             trail: None,
             animation: None,
+            animation_sheet: None,
         }
     }
 }
@@ -1,2 +1,55 @@
+use crate::core::transform::Transform;
+use crate::gameplay::collision::BoundingBox;
+use crate::gameplay::health::Health;
+use crate::gameplay::physics::DynamicBody;
+use crate::render::particle::{ParticleEffect, ParticleEmitter};
+use crate::render::sprite::Sprite;
+
 pub mod enemies;
 pub mod player;
+
+/// Patch what enemies and player have in common after their prefab is hot reloaded. The current
+/// velocity, position and health are kept.
+#[allow(clippy::too_many_arguments)]
+fn patch_common(
+    world: &mut hecs::World,
+    entity: hecs::Entity,
+    dynamic_body: &DynamicBody,
+    transform: &Transform,
+    sprite: &Sprite,
+    bounding_box: BoundingBox,
+    health: Option<&Health>,
+    trail: Option<&ParticleEffect>,
+) {
+    if let Ok(mut body) = world.get_mut::<DynamicBody>(entity) {
+        body.max_velocity = dynamic_body.max_velocity;
+        body.mass = dynamic_body.mass;
+        body.max_force = dynamic_body.max_force;
+    }
+    if let Ok(mut t) = world.get_mut::<Transform>(entity) {
+        t.scale = transform.scale;
+        t.dirty = true;
+    }
+    if let (Some(new), Ok(mut current)) = (health, world.get_mut::<Health>(entity)) {
+        current.max = new.max;
+        current.current = current.current.min(new.max);
+    }
+
+    let res = match trail {
+        Some(effect) => {
+            // the emitter of another effect is created again by `update_effects`.
+            let is_same_effect = world
+                .get::<ParticleEffect>(entity)
+                .map(|current| *current == *effect)
+                .unwrap_or(false);
+            if !is_same_effect {
+                let _ = world.remove_one::<ParticleEmitter>(entity);
+            }
+            world.insert(entity, (sprite.clone(), bounding_box, effect.clone()))
+        }
+        None => world.insert(entity, (sprite.clone(), bounding_box)),
+    };
+    if let Err(e) = res {
+        error!("Cannot patch prefab instance = {:?}", e);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::assets::manifest::AssetManifest;
 use crate::assets::prefab::Prefab;
 use crate::core::transform::Transform;
 use crate::gameplay::collision::BoundingBox;
@@ -5,12 +6,12 @@ use crate::gameplay::health::{Health, Shield};
 use crate::gameplay::physics::DynamicBody;
 use crate::gameplay::player::{Player, Stats, Weapon};
 use crate::gameplay::trail::Trail;
-use crate::render::particle::ParticleEmitter;
+use crate::render::particle::ParticleEffect;
 use crate::render::sprite::Sprite;
 use hecs::{Entity, EntityBuilder, World};
 use serde_derive::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PlayerPrefab {
     pub dynamic_body: DynamicBody,
     pub transform: Transform,
@@ -18,7 +19,8 @@ pub struct PlayerPrefab {
     pub bounding_box: BoundingBox,
     pub health: Health,
     pub shield: Option<Shield>,
-    pub trail: ParticleEmitter,
+    /// Emitter in `assets/particle`.
+    pub trail: ParticleEffect,
     pub stats: Stats,
 }
 
@@ -34,9 +36,7 @@ impl Prefab for PlayerPrefab {
         if let Some(s) = self.shield.clone() {
             components.add(s);
         }
-        let mut particles = self.trail.clone();
-        particles.init_pool();
-        components.add(particles);
+        components.add(self.trail.clone());
         components.add(Trail {
             should_display: true,
             offset: 20.0,
@@ -49,4 +49,23 @@ impl Prefab for PlayerPrefab {
 
         world.spawn(components.build())
     }
+
+    fn dependencies(&self, manifest: &mut AssetManifest) {
+        manifest.add_sprite(self.sprite.id.clone());
+        manifest.add_particle(self.trail.handle.0.clone());
+        Player::dependencies(manifest);
+    }
+
+    fn patch(&self, world: &mut World, entity: Entity) {
+        super::patch_common(
+            world,
+            entity,
+            &self.dynamic_body,
+            &self.transform,
+            &self.sprite,
+            self.bounding_box,
+            Some(&self.health),
+            Some(&self.trail),
+        );
+    }
 }
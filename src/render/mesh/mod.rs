@@ -1,7 +1,7 @@
 use crate::assets::shader::ShaderManager;
 use crate::assets::Handle;
 use crate::core::colors::RgbaColor;
-use crate::core::transform::Transform;
+use crate::core::transform::{PreviousTransforms, Transform};
 use luminance::blending::{Blending, Equation, Factor};
 use luminance::context::GraphicsContext;
 use luminance::pipeline::{Pipeline, PipelineError, TextureBinding};
@@ -100,7 +100,7 @@ pub struct MeshRender {
 
 impl<S> MeshRenderer<S>
 where
-    S: GraphicsContext<Backend = GL33>,
+    S: GraphicsContext<Backend = GL33> + 'static,
 {
     pub fn new(surface: &mut S) -> Self {
         let color = RgbaColor::new(255, 0, 0, 255).to_normalized();
@@ -144,6 +144,7 @@ where
             creation_time: Instant::now(),
         }
     }
+    #[allow(clippy::too_many_arguments)]
     pub fn render(
         &mut self,
         _pipeline: &Pipeline<S::Backend>,
@@ -152,6 +153,8 @@ where
         view: &glam::Mat4,
         world: &hecs::World,
         shader_manager: &mut ShaderManager<S>,
+        previous: &PreviousTransforms,
+        alpha: f32,
     ) -> Result<(), PipelineError> {
         // let handle = Handle(("simple-vs.glsl".to_string(), "simple-fs.glsl".to_string()));
 
@@ -171,7 +174,7 @@ where
             );
         let elapsed = self.creation_time.elapsed().as_secs_f32();
 
-        for (_, (t, render)) in world.query::<(&Transform, &MeshRender)>().iter() {
+        for (e, (t, render)) in world.query::<(&Transform, &MeshRender)>().iter() {
             if !render.enabled {
                 continue;
             }
@@ -181,7 +184,7 @@ where
                 ref fragment_shader_id,
             } = render.material
             {
-                let model = t.to_model();
+                let model = previous.interpolate(e, t, alpha).to_model();
 
                 let handle = Handle((vertex_shader_id.clone(), fragment_shader_id.clone()));
                 if let Some(shader) = shader_manager.get_mut(&handle) {
@@ -1,7 +1,11 @@
+use crate::assets::atlas::Atlases;
 use crate::assets::shader::ShaderManager;
 use crate::assets::sprite::SpriteAsset;
 use crate::assets::AssetManager;
 use crate::core::camera::ProjectionMatrix;
+use crate::core::profiler::profile;
+use crate::core::transform::PreviousTransforms;
+use crate::core::window::WindowDim;
 use crate::render::mesh::MeshRenderer;
 use crate::render::particle::ParticleSystem;
 use crate::render::path::PathRenderer;
@@ -11,10 +15,9 @@ use crate::resources::Resources;
 use glyph_brush::GlyphBrush;
 use luminance::context::GraphicsContext;
 use luminance::framebuffer::Framebuffer;
-use luminance::pipeline::{PipelineError, PipelineState, Render};
+use luminance::pipeline::{PipelineError, PipelineState, Render, Viewport};
 use luminance::texture::Dim2;
 use luminance_gl::GL33;
-use std::time::Duration;
 
 pub mod mesh;
 pub mod particle;
@@ -63,73 +66,104 @@ where
         resources: &Resources,
         fonts: &mut GlyphBrush<'static, text::Instance>,
     ) {
-        self.ui_renderer.prepare(surface, gui, resources, fonts);
-        self.path_renderer.prepare(surface, resources);
+        let ui_renderer = &mut self.ui_renderer;
+        profile(resources, "render/prepare_ui", || {
+            ui_renderer.prepare(surface, gui, resources, fonts)
+        });
+        let path_renderer = &mut self.path_renderer;
+        profile(resources, "render/prepare_paths", || {
+            path_renderer.prepare(surface, resources)
+        });
     }
 
+    /// Draw the world. `alpha` is how far we are between the previous fixed update and the
+    /// current one (0 to 1). It is used to interpolate the transforms.
+    ///
+    /// Each pass is timed by the profiler.
     pub fn render(
         &mut self,
         surface: &mut S,
         back_buffer: &mut Framebuffer<S::Backend, Dim2, (), ()>,
         world: &hecs::World,
         resources: &Resources,
+        alpha: f32,
     ) -> Render<PipelineError> {
-        let projection_matrix = resources.fetch::<ProjectionMatrix>().unwrap().0;
-        let view = crate::core::camera::get_view_matrix(world).unwrap();
+        let projection_matrix = resources.fetch::<ProjectionMatrix>().unwrap().matrix();
+        let (x, y, width, height) = resources.fetch::<WindowDim>().unwrap().viewport();
+        let previous = resources.fetch::<PreviousTransforms>().unwrap();
+        let view =
+            crate::core::camera::get_interpolated_view_matrix(world, &previous, alpha).unwrap();
 
         let mut textures = resources
             .fetch_mut::<AssetManager<S, SpriteAsset<S>>>()
             .unwrap();
+        let atlases = resources.fetch::<Atlases>().unwrap();
         let mut shaders = resources.fetch_mut::<ShaderManager<S>>().unwrap();
         surface
             .new_pipeline_gate()
             .pipeline(
                 back_buffer,
-                &PipelineState::default().set_clear_color([0.0, 0.0, 0.0, 1.0]),
+                &PipelineState::default()
+                    .set_clear_color([0.0, 0.0, 0.0, 1.0])
+                    .set_viewport(Viewport::Specific {
+                        x,
+                        y,
+                        width,
+                        height,
+                    }),
                 |pipeline, mut shd_gate| {
-                    self.sprite_renderer.render(
-                        &pipeline,
-                        &mut shd_gate,
-                        &projection_matrix,
-                        &view,
-                        &world,
-                        &mut *textures,
-                    )?;
+                    let sprite_renderer = &mut self.sprite_renderer;
+                    profile(resources, "render/sprites", || {
+                        sprite_renderer.render(
+                            &pipeline,
+                            &mut shd_gate,
+                            &projection_matrix,
+                            &view,
+                            world,
+                            &mut *textures,
+                            &atlases,
+                            &previous,
+                            alpha,
+                        )
+                    })?;
 
-                    self.mesh_renderer.render(
-                        &pipeline,
-                        &mut shd_gate,
-                        &projection_matrix,
-                        &view,
-                        &world,
-                        &mut *shaders,
-                    )?;
+                    let mesh_renderer = &mut self.mesh_renderer;
+                    profile(resources, "render/meshes", || {
+                        mesh_renderer.render(
+                            &pipeline,
+                            &mut shd_gate,
+                            &projection_matrix,
+                            &view,
+                            world,
+                            &mut *shaders,
+                            &previous,
+                            alpha,
+                        )
+                    })?;
 
-                    self.particle_renderer.render(
-                        &pipeline,
-                        &mut shd_gate,
-                        &projection_matrix,
-                        &view,
-                        world,
-                        &mut *textures,
-                    )?;
+                    let particle_renderer = &mut self.particle_renderer;
+                    profile(resources, "render/particles", || {
+                        particle_renderer.render(
+                            &pipeline,
+                            &mut shd_gate,
+                            &projection_matrix,
+                            &view,
+                            world,
+                            &mut *textures,
+                            &atlases,
+                        )
+                    })?;
 
-                    self.ui_renderer.render(&pipeline, &mut shd_gate)?;
-                    self.path_renderer
-                        .render(&projection_matrix, &view, &mut shd_gate)
+                    let ui_renderer = &mut self.ui_renderer;
+                    profile(resources, "render/ui", || {
+                        ui_renderer.render(&pipeline, &mut shd_gate)
+                    })?;
+                    let path_renderer = &mut self.path_renderer;
+                    profile(resources, "render/paths", || {
+                        path_renderer.render(&projection_matrix, &view, &mut shd_gate)
+                    })
                 },
             )
             .assume()
     }
-
-    pub fn update(
-        &mut self,
-        _surface: &mut S,
-        world: &hecs::World,
-        dt: Duration,
-        resources: &Resources,
-    ) {
-        // update particle systems.
-        self.particle_renderer.update(world, dt, resources);
-    }
 }
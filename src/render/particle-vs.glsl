@@ -14,6 +14,8 @@ uniform vec4 color;
 uniform mat4 projection;
 uniform mat4 model;
 uniform mat4 view;
+// offset and size of the sprite in the texture, for atlases. Only used with a texture.
+uniform vec4 uv_rect;
 
 out vec2 v_uv;
 out vec4 v_color;
@@ -23,5 +25,5 @@ void main() {
     v_color = color;
     vec2 p = QUAD_POS[gl_VertexID];
     gl_Position = projection * view *  model  * vec4(p, 1.0, 1.0);
-    v_uv = p * .5 + .5; // transform the position of the vertex into UV space
+    v_uv = uv_rect.xy + (p * .5 + .5) * uv_rect.zw; // transform the position of the vertex into UV space
 }
\ No newline at end of file
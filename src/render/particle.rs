@@ -1,9 +1,13 @@
+use crate::assets::atlas::{Atlases, UvRect};
+use crate::assets::particle::ParticleManager;
 use crate::assets::sprite::SpriteAsset;
 use crate::assets::{AssetManager, Handle};
 use crate::core::colors::RgbaColor;
 use crate::core::curve::Curve;
+use crate::core::random::RandomGenerator;
 use crate::core::transform::Transform;
-use crate::event::GameEvent;
+use crate::event::Delete;
+use crate::gameplay::trail::Trail;
 use crate::resources::Resources;
 use hecs::World;
 use luminance::blending::{Blending, Equation, Factor};
@@ -20,7 +24,6 @@ use luminance_gl::GL33;
 use rand::Rng;
 use serde_derive::{Deserialize, Serialize};
 use shrev::EventChannel;
-use std::path::Path;
 use std::time::Duration;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -77,14 +80,16 @@ impl Particle {
 
     fn color(&self) -> RgbaColor {
         let t = self.t();
-        self.colors.y(t)
+        // a broken curve should not crash the game.
+        self.colors
+            .y(t)
+            .unwrap_or(RgbaColor::new(255, 255, 255, 255))
     }
 
     fn scale(&self) -> glam::Vec2 {
-        if let Some(curve) = &self.scale_over_lifetime {
-            self.scale * curve.y(self.t())
-        } else {
-            self.scale
+        match self.scale_over_lifetime.as_ref().map(|c| c.y(self.t())) {
+            Some(Ok(s)) => self.scale * s,
+            _ => self.scale,
         }
     }
 }
@@ -124,6 +129,10 @@ impl ParticlePool {
     fn all_dead(&self) -> bool {
         self.particles.len() == self.free.len()
     }
+
+    fn nb_alive(&self) -> usize {
+        self.particles.len() - self.free.len()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -214,13 +223,6 @@ impl Default for ParticleEmitter {
 }
 
 impl ParticleEmitter {
-    pub fn load_from_path<P: AsRef<Path>>(p: P) -> Result<Self, anyhow::Error> {
-        let content = std::fs::read_to_string(p)?;
-        let mut emitter: Self = serde_json::from_str(&content)?;
-        emitter.init_pool();
-        Ok(emitter)
-    }
-
     pub fn enable(&mut self) {
         self.enabled = true;
     }
@@ -229,6 +231,26 @@ impl ParticleEmitter {
         self.enabled = false;
     }
 
+    /// Number of particles currently on screen.
+    pub fn alive_particles(&self) -> usize {
+        self.particles.nb_alive()
+    }
+
+    /// Copy of a definition (e.g. from the `ParticleManager`) with its own particles.
+    pub fn instantiate(&self) -> Self {
+        let mut emitter = self.clone();
+        emitter.init_pool();
+        emitter
+    }
+
+    /// Use a new definition, after a hot reload. The particles start again but the emitter stays
+    /// enabled or disabled.
+    pub fn set_definition(&mut self, definition: &ParticleEmitter) {
+        let enabled = self.enabled;
+        *self = definition.instantiate();
+        self.enabled = enabled;
+    }
+
     /// Necessary when getting the emitter from a file.
     pub fn init_pool(&mut self) {
         let frame_needed = if self.burst {
@@ -241,8 +263,9 @@ impl ParticleEmitter {
 
     /// Update the position and velocity of all particles. If a particle is dead, respawn it :)
     /// Return true if should despawn the particle emitter.
-    fn update(&mut self, position: glam::Vec2, dt: f32) -> bool {
-        let mut rng = rand::thread_rng();
+    fn update(&mut self, position: glam::Vec2, dt: f32, random: &mut RandomGenerator) -> bool {
+        // particles are only visual so they do not use the gameplay random stream.
+        let rng = random.cosmetic();
 
         // emit particles.
         trace!(
@@ -275,7 +298,7 @@ impl ParticleEmitter {
 
                         particle.respawn(
                             self.particle_life,
-                            self.source.spawn_position(position, &mut rng) + self.position_offset,
+                            self.source.spawn_position(position, rng) + self.position_offset,
                             rotation * (speed * glam::Vec2::unit_x()),
                             scale,
                             self.scale_over_lifetime.clone(),
@@ -359,6 +382,8 @@ pub struct TextureParticleShaderInterface {
 
     /// Texture for the sprite.
     tex: Uniform<TextureBinding<Dim2, NormUnsigned>>,
+    /// Part of the texture to use, see `UvRect`.
+    uv_rect: Uniform<[f32; 4]>,
 }
 
 pub struct ParticleSystem<S>
@@ -370,9 +395,91 @@ where
     texture_shader: Program<S::Backend, (), (), TextureParticleShaderInterface>,
 }
 
+/// Emitter defined in `assets/particle`. The entity gets its `ParticleEmitter` from the
+/// `ParticleManager` once the definition is loaded, and a new one when the file is hot reloaded.
+///
+/// In prefabs, it is just the name of the file, e.g. `"trail": "trail.json"`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ParticleEffect {
+    pub handle: Handle,
+}
+
+impl ParticleEffect {
+    pub fn new(name: &str) -> Self {
+        Self {
+            handle: Handle(name.to_string()),
+        }
+    }
+
+    /// New emitter for this effect, if the definition is loaded.
+    pub fn emitter<S>(&self, particle_manager: &ParticleManager<S>) -> Option<ParticleEmitter>
+    where
+        S: GraphicsContext<Backend = GL33>,
+    {
+        particle_manager
+            .get(&self.handle)?
+            .execute(|definition| definition.instantiate())
+    }
+}
+
+/// Give an emitter to the effects that do not have one yet. When the definition cannot be loaded,
+/// a trail is removed from its entity, and the entities that are only an effect (explosions...)
+/// are deleted.
+pub fn update_effects<S>(world: &mut World, resources: &Resources)
+where
+    S: GraphicsContext<Backend = GL33> + 'static,
+{
+    let waiting: Vec<_> = world
+        .query::<&ParticleEffect>()
+        .without::<ParticleEmitter>()
+        .iter()
+        .map(|(e, effect)| (e, effect.clone()))
+        .collect();
+    if waiting.is_empty() {
+        return;
+    }
+
+    let mut particle_manager = resources.fetch_mut::<ParticleManager<S>>().unwrap();
+    for (e, effect) in waiting {
+        if particle_manager.is_error(&effect.handle) {
+            error!(
+                "Cannot load particle {}, will remove the effect",
+                effect.handle.0
+            );
+            if world.get::<Trail>(e).is_ok() {
+                let _ = world.remove_one::<ParticleEffect>(e);
+            } else {
+                resources
+                    .fetch_mut::<EventChannel<Delete>>()
+                    .unwrap()
+                    .single_write(Delete(e));
+            }
+        } else if let Some(emitter) = effect.emitter(&*particle_manager) {
+            let _ = world.insert_one(e, emitter);
+        } else {
+            // does nothing if it is already loading.
+            particle_manager.load(effect.handle.0);
+        }
+    }
+}
+
+/// Update the particles of all emitters. Emitters that are finished will be deleted.
+///
+/// This does not need the GPU so it runs with the rest of the simulation.
+pub fn update_emitters(world: &World, dt: Duration, resources: &Resources) {
+    let mut chan = resources.fetch_mut::<EventChannel<Delete>>().unwrap();
+    let mut random = resources.fetch_mut::<RandomGenerator>().unwrap();
+    for (e, (t, emitter)) in world.query::<(&Transform, &mut ParticleEmitter)>().iter() {
+        if !emitter.update(t.translation, dt.as_secs_f32(), &mut random) {
+            chan.single_write(Delete(e));
+        }
+    }
+}
+
 impl<S> ParticleSystem<S>
 where
-    S: GraphicsContext<Backend = GL33>,
+    S: GraphicsContext<Backend = GL33> + 'static,
 {
     pub fn new(surface: &mut S) -> Self {
         let tess = surface
@@ -388,17 +495,7 @@ where
         }
     }
 
-    pub fn update(&mut self, world: &World, dt: Duration, resources: &Resources) {
-        let mut chan = resources.fetch_mut::<EventChannel<GameEvent>>().unwrap();
-        let mut remove_events = vec![];
-        for (e, (t, emitter)) in world.query::<(&Transform, &mut ParticleEmitter)>().iter() {
-            if !emitter.update(t.translation, dt.as_secs_f32()) {
-                chan.single_write(GameEvent::Delete(e));
-            }
-        }
-        chan.drain_vec_write(&mut remove_events);
-    }
-
+    #[allow(clippy::too_many_arguments)]
     pub fn render(
         &mut self,
         pipeline: &Pipeline<S::Backend>,
@@ -408,6 +505,7 @@ where
         world: &World,
 
         textures: &mut AssetManager<S, SpriteAsset<S>>,
+        atlases: &Atlases,
     ) -> Result<(), PipelineError> {
         let tess = &self.tess;
         let render_st = RenderState::default()
@@ -447,7 +545,8 @@ where
                     })?;
                 }
                 ParticleShape::Texture(id) => {
-                    if let Some(tex) = textures.get_mut(&Handle(id.clone())) {
+                    let (texture, uv_rect) = atlases.resolve(id).unwrap_or((id, UvRect::FULL));
+                    if let Some(tex) = textures.get_mut(&Handle(texture.to_string())) {
                         let mut res = Ok(());
                         let shader = &mut self.texture_shader;
                         tex.execute_mut(|asset| {
@@ -457,6 +556,7 @@ where
                                     iface.set(&uni.projection, projection.to_cols_array_2d());
                                     iface.set(&uni.view, view.to_cols_array_2d());
                                     iface.set(&uni.tex, bound_tex.binding());
+                                    iface.set(&uni.uv_rect, uv_rect.to_array());
                                     for p in &emitter.particles.particles {
                                         if !p.alive() {
                                             continue;
@@ -485,8 +585,8 @@ where
 
                         res?;
                     } else {
-                        debug!("Texture is not loaded {}", id);
-                        textures.load(id.clone());
+                        debug!("Texture is not loaded {}", texture);
+                        textures.load(texture.to_string());
                     }
                 }
             }
@@ -495,3 +595,63 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use luminance_glfw::GlfwSurface;
+    use std::time::Instant;
+
+    #[test]
+    fn missing_effect_deletes_the_entity_but_not_its_owner() {
+        let mut resources = Resources::default();
+        crate::event::register_game_events(&mut resources);
+        crate::assets::create_asset_managers::<GlfwSurface>(&mut resources);
+        let mut reader = resources.register_reader::<Delete>();
+
+        let mut world = World::new();
+        let effect = ParticleEffect::new("does_not_exist.json");
+        let explosion = world.spawn((Transform::default(), effect.clone()));
+        let ship = world.spawn((
+            Transform::default(),
+            effect.clone(),
+            Trail {
+                should_display: true,
+                offset: 0.0,
+            },
+        ));
+
+        // the first update starts the load.
+        update_effects::<GlfwSurface>(&mut world, &resources);
+        let start = Instant::now();
+        loop {
+            let mut particle_manager = resources
+                .fetch_mut::<ParticleManager<GlfwSurface>>()
+                .unwrap();
+            particle_manager.process_jobs();
+            if particle_manager.is_error(&effect.handle) {
+                break;
+            }
+            assert!(start.elapsed() < Duration::from_secs(5), "load is stuck");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        update_effects::<GlfwSurface>(&mut world, &resources);
+
+        let deleted: Vec<_> = resources
+            .fetch::<EventChannel<Delete>>()
+            .unwrap()
+            .read(&mut reader)
+            .map(|d| d.0)
+            .collect();
+        assert_eq!(vec![explosion], deleted);
+        assert!(world.get::<ParticleEffect>(ship).is_err());
+        assert!(world.get::<Trail>(ship).is_ok());
+    }
+
+    #[test]
+    fn effect_is_the_name_of_the_file() {
+        let effect: ParticleEffect = serde_json::from_str("\"trail.json\"").unwrap();
+        assert_eq!(ParticleEffect::new("trail.json"), effect);
+        assert_eq!("\"trail.json\"", serde_json::to_string(&effect).unwrap());
+    }
+}
@@ -10,9 +10,10 @@ use luminance::texture::Dim2;
 use luminance_derive::UniformInterface;
 use luminance_gl::gl33::GL33;
 
-use crate::assets::{sprite::SpriteAsset, AssetManager, Handle};
+use crate::assets::atlas::{Atlases, UvRect};
+use crate::assets::{sprite::SpriteAsset, AssetManager, Handle, StrongHandle};
 use crate::core::colors::RgbaColor;
-use crate::core::transform::Transform;
+use crate::core::transform::{PreviousTransforms, Transform};
 use luminance::shading_gate::ShadingGate;
 use serde_derive::{Deserialize, Serialize};
 use std::time::Instant;
@@ -20,10 +21,23 @@ use std::time::Instant;
 const VS: &'static str = include_str!("texture-vs.glsl");
 const FS: &'static str = include_str!("texture-fs.glsl");
 
-/// Let's make it easy for now...
+/// Let's make it easy for now... The id is the sprite file, relative to the sprites folder. If the
+/// sprite is in an atlas that is loaded, its page is used instead.
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Sprite {
     pub id: String,
+    /// Keeps the texture loaded while the sprite uses it. Set by the renderer.
+    #[serde(skip)]
+    texture: Option<StrongHandle>,
+}
+
+impl Sprite {
+    pub fn new<S: Into<String>>(id: S) -> Self {
+        Self {
+            id: id.into(),
+            texture: None,
+        }
+    }
 }
 
 /// Attach this component to an entity with a sprite to make it BLINK! KIRA KIRA!
@@ -47,6 +61,8 @@ pub struct ShaderUniform {
 
     /// Texture for the sprite.
     tex: Uniform<TextureBinding<Dim2, NormUnsigned>>,
+    /// Part of the texture to use, see `UvRect`.
+    uv_rect: Uniform<[f32; 4]>,
 
     /// true if should blink.
     should_blink: Uniform<bool>,
@@ -83,7 +99,7 @@ where
 
 impl<S> SpriteRenderer<S>
 where
-    S: GraphicsContext<Backend = GL33>,
+    S: GraphicsContext<Backend = GL33> + 'static,
 {
     pub fn new(surface: &mut S) -> SpriteRenderer<S> {
         let render_st = RenderState::default()
@@ -114,6 +130,7 @@ where
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn render(
         &mut self,
         pipeline: &Pipeline<S::Backend>,
@@ -122,6 +139,9 @@ where
         view: &glam::Mat4,
         world: &hecs::World,
         textures: &mut AssetManager<S, SpriteAsset<S>>,
+        atlases: &Atlases,
+        previous: &PreviousTransforms,
+        alpha: f32,
     ) -> Result<(), PipelineError> {
         let shader = &mut self.shader;
         let render_state = &self.render_st;
@@ -133,8 +153,16 @@ where
             iface.set(&uni.projection, proj_matrix.to_cols_array_2d());
             iface.set(&uni.view, view.to_cols_array_2d());
 
-            for (e, (sprite, transform)) in world.query::<(&Sprite, &Transform)>().iter() {
-                if let Some(tex) = textures.get_mut(&Handle(sprite.id.to_string())) {
+            for (e, (sprite, transform)) in world.query::<(&mut Sprite, &Transform)>().iter() {
+                let (texture, uv_rect) = atlases
+                    .resolve(&sprite.id)
+                    .unwrap_or((&sprite.id, UvRect::FULL));
+                let handle = Handle(texture.to_string());
+                // the texture changes with the animations.
+                if sprite.texture.as_deref() != Some(&handle) {
+                    sprite.texture = textures.acquire(&handle);
+                }
+                if let Some(tex) = textures.get_mut(&handle) {
                     let mut res = Ok(());
                     tex.execute_mut(|asset| {
                         if let Some(tex) = asset.texture() {
@@ -160,7 +188,9 @@ where
                             match bound_tex {
                                 Ok(bound_tex) => {
                                     iface.set(&uni.tex, bound_tex.binding());
-                                    let model = transform.to_model();
+                                    iface.set(&uni.uv_rect, uv_rect.to_array());
+                                    let model =
+                                        previous.interpolate(e, transform, alpha).to_model();
                                     iface.set(&uni.model, model.to_cols_array_2d());
 
                                     res = rdr_gate.render(render_state, |mut tess_gate| {
@@ -176,8 +206,9 @@ where
 
                     res?;
                 } else {
-                    debug!("Texture is not loaded {}", sprite.id);
-                    textures.load(sprite.id.to_string());
+                    debug!("Texture is not loaded {}", handle.0);
+                    textures.load(handle.0.clone());
+                    sprite.texture = textures.acquire(&handle);
                 }
             }
 
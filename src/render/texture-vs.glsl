@@ -2,6 +2,8 @@
 uniform mat4 projection;
 uniform mat4 view;
 uniform mat4 model;
+// offset and size of the sprite in the texture, for atlases.
+uniform vec4 uv_rect;
 
 out vec2 v_uv;
 
@@ -15,5 +17,5 @@ const vec2[4] QUAD_POS = vec2[](
 void main() {
   vec2 p = QUAD_POS[gl_VertexID];
   gl_Position = projection * view * model *  vec4(p, 0., 1.);
-  v_uv = p * .5 + .5; // transform the position of the vertex into UV space
+  v_uv = uv_rect.xy + (p * .5 + .5) * uv_rect.zw; // transform the position of the vertex into UV space
 }
\ No newline at end of file
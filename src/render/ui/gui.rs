@@ -43,13 +43,27 @@ impl GuiContext {
         match window_event {
             WindowEvent::MouseButton(btn, Action::Press, _) => self.mouse_clicked.push(btn),
             WindowEvent::CursorPos(x, y) => {
-                self.mouse_pos.x = x as f32;
-                self.mouse_pos.y = y as f32;
+                // the GUI is laid out on the virtual canvas.
+                self.mouse_pos = self
+                    .window_dim
+                    .window_to_canvas(glam::vec2(x as f32, y as f32));
             }
             _ => (),
         }
     }
 
+    /// Same context but without mouse clicks. Used for GUIs that should be drawn but not
+    /// interacted with.
+    pub fn without_inputs(&self) -> GuiContext {
+        Self {
+            window_dim: self.window_dim,
+            mouse_pos: self.mouse_pos,
+            mouse_clicked: vec![],
+            style: self.style,
+            fonts: Rc::clone(&self.fonts),
+        }
+    }
+
     pub fn new_frame(&self) -> Gui {
         Gui::new(
             self.window_dim,
@@ -87,6 +101,12 @@ impl Gui {
             fonts,
         }
     }
+
+    /// Draw another GUI on top of this one.
+    pub fn append(&mut self, other: Gui) {
+        self.draw_data.extend(other.draw_data);
+    }
+
     pub fn panel(&mut self, pos: glam::Vec2, dimensions: glam::Vec2, color: RgbaColor) {
         let (vertices, indices) = Panel {
             anchor: pos,
@@ -2,7 +2,8 @@
 //! AnyMap didn't fill my usecase as there is no way to borrow mutably 2 values for different
 //! keys. (`get_mut(&mut self)`).
 //!
-//! This container is using interior mutability with `RefCell` to allow this usecase.
+//! This container is using interior mutability with `RefCell` to allow this usecase. The event
+//! channels (see `crate::event`) are resources too.
 //! Downcasting trait does not work with pure rust so I am using a crate called `downcast_rs` to
 //! do it.
 //!
@@ -28,14 +29,29 @@
 //!      println!("{}", *my_u8);
 //!  }
 //!
+//! // Same thing, but get an error instead of a panic if something is wrong.
+//! {
+//!     use spacegame::resources::{Read, Write};
+//!     let (my_u8, mut my_string) = resources.fetch_many::<(Read<u8>, Write<String>)>().unwrap();
+//!     my_string.push_str("hhh");
+//!     println!("{}", *my_u8);
+//!
+//!     // u8 is already borrowed.
+//!     assert!(resources.try_fetch_mut::<u8>().is_err());
+//! }
+//!
 //! ```
+use crate::event::Event;
 use downcast_rs::{impl_downcast, Downcast};
+use shrev::{EventChannel, ReaderId};
 use std::any::{Any, TypeId};
 use std::cell::{Ref, RefCell, RefMut};
 use std::collections::HashMap;
 use std::convert::AsRef;
+use std::fmt;
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
+use thiserror::Error;
 
 pub trait Resource: Any + 'static + Downcast {}
 impl_downcast!(Resource);
@@ -74,9 +90,46 @@ impl<'a, T: 'static> DerefMut for FetchMut<'a, T> {
     }
 }
 
+/// How a resource is borrowed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BorrowKind {
+    Shared,
+    Mutable,
+}
+
+impl fmt::Display for BorrowKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BorrowKind::Shared => write!(f, "immutably"),
+            BorrowKind::Mutable => write!(f, "mutably"),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ResourceError {
+    #[error("Resource {0} has not been inserted")]
+    Missing(&'static str),
+
+    #[error("Cannot borrow {name} {requested} because it is already borrowed {existing}")]
+    AlreadyBorrowed {
+        name: &'static str,
+        requested: BorrowKind,
+        existing: BorrowKind,
+    },
+
+    #[error("{0} is fetched several times and at least once mutably")]
+    Conflict(&'static str),
+}
+
+struct Entry {
+    name: &'static str,
+    value: RefCell<Box<dyn Resource>>,
+}
+
 #[derive(Default)]
 pub struct Resources {
-    inner: HashMap<TypeId, RefCell<Box<dyn Resource>>>,
+    inner: HashMap<TypeId, Entry>,
 }
 
 impl Resources {
@@ -89,41 +142,343 @@ impl Resources {
     /// Insert a new value for the type. It will replace the existing value
     /// if it exists.
     pub fn insert<T: Any>(&mut self, v: T) {
-        self.inner
-            .insert(TypeId::of::<T>(), RefCell::new(Box::new(v)));
+        self.inner.insert(
+            TypeId::of::<T>(),
+            Entry {
+                name: std::any::type_name::<T>(),
+                value: RefCell::new(Box::new(v)),
+            },
+        );
     }
 
-    /// Borrow data immutably from the map. Can panic if already borrowed mutably.
-    pub fn fetch<T: Any + 'static>(&self) -> Option<Fetch<T>> {
-        let cloned = {
-            let ty = TypeId::of::<T>();
-            match self.inner.get(&ty) {
-                Some(v) => v,
-                None => return None,
-            }
-        };
-        let borrowed: Ref<Box<dyn Resource>> = cloned.borrow();
-        Some(Fetch {
+    /// Remove the value for the type and return it.
+    pub fn remove<T: Any>(&mut self) -> Option<T> {
+        let entry = self.inner.remove(&TypeId::of::<T>())?;
+        entry.value.into_inner().downcast::<T>().ok().map(|v| *v)
+    }
+
+    pub fn contains<T: Any>(&self) -> bool {
+        self.inner.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Names of the types of all the resources, sorted.
+    pub fn type_names(&self) -> Vec<&'static str> {
+        let mut names: Vec<_> = self.inner.values().map(|entry| entry.name).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Borrow data immutably from the map. Returns None if the resource is not there.
+    ///
+    /// Panics if the resource is already borrowed mutably. Use `try_fetch` to get an error instead.
+    pub fn fetch<T: Any + 'static>(&self) -> Option<Fetch<'_, T>> {
+        match self.try_fetch() {
+            Ok(v) => Some(v),
+            Err(ResourceError::Missing(_)) => None,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Borrow data mutably from the map. Returns None if the resource is not there.
+    ///
+    /// Panics if the resource is already borrowed. Use `try_fetch_mut` to get an error instead.
+    pub fn fetch_mut<T: Any + 'static>(&self) -> Option<FetchMut<'_, T>> {
+        match self.try_fetch_mut() {
+            Ok(v) => Some(v),
+            Err(ResourceError::Missing(_)) => None,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Borrow data immutably from the map.
+    pub fn try_fetch<T: Any + 'static>(&self) -> Result<Fetch<'_, T>, ResourceError> {
+        let entry = self.entry::<T>()?;
+        let borrowed: Ref<Box<dyn Resource>> =
+            entry
+                .value
+                .try_borrow()
+                .map_err(|_| ResourceError::AlreadyBorrowed {
+                    name: entry.name,
+                    requested: BorrowKind::Shared,
+                    existing: BorrowKind::Mutable,
+                })?;
+        Ok(Fetch {
             inner: Ref::map(borrowed, Box::as_ref),
             phantom: PhantomData,
         })
     }
 
-    /// Borrow data mutably from the map. Can panic if already borrowed
-    pub fn fetch_mut<T: Any + 'static>(&self) -> Option<FetchMut<T>> {
-        let cloned = {
-            let ty = TypeId::of::<T>();
-            match self.inner.get(&ty) {
-                Some(v) => v,
-                None => return None,
+    /// Borrow data mutably from the map.
+    pub fn try_fetch_mut<T: Any + 'static>(&self) -> Result<FetchMut<'_, T>, ResourceError> {
+        let entry = self.entry::<T>()?;
+        let borrowed = entry.value.try_borrow_mut().map_err(|_| {
+            // if it can still be borrowed immutably, the other borrows are immutable.
+            let existing = if entry.value.try_borrow().is_ok() {
+                BorrowKind::Shared
+            } else {
+                BorrowKind::Mutable
+            };
+            ResourceError::AlreadyBorrowed {
+                name: entry.name,
+                requested: BorrowKind::Mutable,
+                existing,
             }
-        };
-
-        // this panics if already borrowed.
-        let borrowed = cloned.borrow_mut();
-        Some(FetchMut {
+        })?;
+        Ok(FetchMut {
             inner: RefMut::map(borrowed, Box::as_mut),
             phantom: PhantomData,
         })
     }
+
+    /// Borrow several resources at once, e.g. `fetch_many::<(Read<A>, Write<B>)>()`. The
+    /// accesses are checked before borrowing anything so that fetching the same resource mutably
+    /// twice returns an error.
+    pub fn fetch_many<'a, F: FetchMany<'a>>(&'a self) -> Result<F::Output, ResourceError> {
+        let accesses = F::accesses();
+        for (i, (ty, name, kind)) in accesses.iter().enumerate() {
+            let conflict = accesses[i + 1..].iter().any(|(other_ty, _, other_kind)| {
+                other_ty == ty
+                    && (*kind == BorrowKind::Mutable || *other_kind == BorrowKind::Mutable)
+            });
+            if conflict {
+                return Err(ResourceError::Conflict(name));
+            }
+        }
+        F::fetch(self)
+    }
+
+    /// Create the channel for this event type if it does not exist yet.
+    pub fn register_event<E: Event>(&mut self) {
+        if !self.contains::<EventChannel<E>>() {
+            self.insert(EventChannel::<E>::new());
+        }
+    }
+
+    /// Get a new reader for this event type. The channel is created if needed.
+    pub fn register_reader<E: Event>(&mut self) -> ReaderId<E> {
+        self.register_event::<E>();
+        self.fetch_mut::<EventChannel<E>>()
+            .unwrap()
+            .register_reader()
+    }
+
+    /// Send an event. It is dropped with an error if the channel has not been registered (see
+    /// `register_event`) or is borrowed.
+    pub fn write_event<E: Event>(&self, event: E) {
+        if let Some(mut chan) = self.event_channel::<E>() {
+            chan.single_write(event);
+        }
+    }
+
+    /// Send several events of the same type.
+    pub fn write_events<E: Event>(&self, events: Vec<E>) {
+        if let Some(mut chan) = self.event_channel::<E>() {
+            chan.iter_write(events);
+        }
+    }
+
+    fn event_channel<E: Event>(&self) -> Option<FetchMut<'_, EventChannel<E>>> {
+        self.try_fetch_mut::<EventChannel<E>>()
+            .map_err(|e| error!("Cannot write {} = {}", std::any::type_name::<E>(), e))
+            .ok()
+    }
+
+    fn entry<T: Any + 'static>(&self) -> Result<&Entry, ResourceError> {
+        self.inner
+            .get(&TypeId::of::<T>())
+            .ok_or_else(|| ResourceError::Missing(std::any::type_name::<T>()))
+    }
+}
+
+impl fmt::Debug for Resources {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.type_names()).finish()
+    }
+}
+
+/// Immutable access to a resource, used with `Resources::fetch_many`.
+pub struct Read<T>(PhantomData<T>);
+
+/// Mutable access to a resource, used with `Resources::fetch_many`.
+pub struct Write<T>(PhantomData<T>);
+
+/// A single access in `Resources::fetch_many`.
+pub trait Access<'a> {
+    type Output;
+    fn access() -> (TypeId, &'static str, BorrowKind);
+    fn fetch(resources: &'a Resources) -> Result<Self::Output, ResourceError>;
+}
+
+impl<'a, T: Any + 'static> Access<'a> for Read<T> {
+    type Output = Fetch<'a, T>;
+
+    fn access() -> (TypeId, &'static str, BorrowKind) {
+        (
+            TypeId::of::<T>(),
+            std::any::type_name::<T>(),
+            BorrowKind::Shared,
+        )
+    }
+
+    fn fetch(resources: &'a Resources) -> Result<Self::Output, ResourceError> {
+        resources.try_fetch::<T>()
+    }
+}
+
+impl<'a, T: Any + 'static> Access<'a> for Write<T> {
+    type Output = FetchMut<'a, T>;
+
+    fn access() -> (TypeId, &'static str, BorrowKind) {
+        (
+            TypeId::of::<T>(),
+            std::any::type_name::<T>(),
+            BorrowKind::Mutable,
+        )
+    }
+
+    fn fetch(resources: &'a Resources) -> Result<Self::Output, ResourceError> {
+        resources.try_fetch_mut::<T>()
+    }
+}
+
+/// Tuple of `Read` and `Write` accesses.
+pub trait FetchMany<'a> {
+    type Output;
+    fn accesses() -> Vec<(TypeId, &'static str, BorrowKind)>;
+    fn fetch(resources: &'a Resources) -> Result<Self::Output, ResourceError>;
+}
+
+macro_rules! impl_fetch_many {
+    ($($name:ident),+) => {
+        impl<'a, $($name: Access<'a>),+> FetchMany<'a> for ($($name,)+) {
+            type Output = ($($name::Output,)+);
+
+            fn accesses() -> Vec<(TypeId, &'static str, BorrowKind)> {
+                vec![$($name::access()),+]
+            }
+
+            fn fetch(resources: &'a Resources) -> Result<Self::Output, ResourceError> {
+                Ok(($($name::fetch(resources)?,)+))
+            }
+        }
+    };
+}
+
+impl_fetch_many!(A);
+impl_fetch_many!(A, B);
+impl_fetch_many!(A, B, C);
+impl_fetch_many!(A, B, C, D);
+impl_fetch_many!(A, B, C, D, E);
+impl_fetch_many!(A, B, C, D, E, F);
+impl_fetch_many!(A, B, C, D, E, F, G);
+impl_fetch_many!(A, B, C, D, E, F, G, H);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Score(u32);
+
+    fn resources() -> Resources {
+        let mut resources = Resources::new();
+        resources.insert(Score(1));
+        resources.insert(String::from("player"));
+        resources
+    }
+
+    #[test]
+    fn fetch_many_at_once() {
+        let resources = resources();
+        let (score, mut name) = resources
+            .fetch_many::<(Read<Score>, Write<String>)>()
+            .unwrap();
+        name.push_str(&score.0.to_string());
+        assert_eq!("player1", *name);
+
+        // reading the same resource twice is fine.
+        assert!(resources.fetch_many::<(Read<Score>, Read<Score>)>().is_ok());
+    }
+
+    #[test]
+    fn fetch_many_conflict() {
+        let resources = resources();
+        let err = resources
+            .fetch_many::<(Read<Score>, Write<String>, Write<Score>)>()
+            .err()
+            .unwrap();
+        assert!(matches!(err, ResourceError::Conflict(_)));
+        assert_eq!(
+            format!(
+                "{} is fetched several times and at least once mutably",
+                std::any::type_name::<Score>()
+            ),
+            err.to_string()
+        );
+        // nothing stays borrowed.
+        assert!(resources.try_fetch_mut::<Score>().is_ok());
+    }
+
+    #[test]
+    fn already_borrowed() {
+        let resources = resources();
+        let name = std::any::type_name::<Score>();
+        {
+            let _score = resources.fetch::<Score>().unwrap();
+            let err = resources.try_fetch_mut::<Score>().err().unwrap();
+            assert_eq!(
+                format!(
+                    "Cannot borrow {} mutably because it is already borrowed immutably",
+                    name
+                ),
+                err.to_string()
+            );
+            // still fine to read.
+            assert!(resources.try_fetch::<Score>().is_ok());
+        }
+        {
+            let _score = resources.fetch_mut::<Score>().unwrap();
+            let err = resources.try_fetch::<Score>().err().unwrap();
+            assert_eq!(
+                format!(
+                    "Cannot borrow {} immutably because it is already borrowed mutably",
+                    name
+                ),
+                err.to_string()
+            );
+            let err = resources
+                .fetch_many::<(Read<String>, Write<Score>)>()
+                .err()
+                .unwrap();
+            assert!(matches!(
+                err,
+                ResourceError::AlreadyBorrowed {
+                    requested: BorrowKind::Mutable,
+                    existing: BorrowKind::Mutable,
+                    ..
+                }
+            ));
+        }
+
+        let err = resources.try_fetch::<u8>().err().unwrap();
+        assert_eq!("Resource u8 has not been inserted", err.to_string());
+        assert!(resources.fetch::<u8>().is_none());
+    }
+
+    #[test]
+    fn events_without_channel_are_dropped() {
+        let mut resources = resources();
+        // not registered.
+        resources.write_event(Score(2));
+
+        let mut rdr_id = resources.register_reader::<Score>();
+        resources.write_events(vec![Score(3), Score(4)]);
+        {
+            // borrowed.
+            let _chan = resources.fetch::<EventChannel<Score>>().unwrap();
+            resources.write_event(Score(5));
+        }
+        let chan = resources.fetch::<EventChannel<Score>>().unwrap();
+        let scores: Vec<_> = chan.read(&mut rdr_id).map(|s| s.0).collect();
+        assert_eq!(vec![3, 4], scores);
+    }
 }
@@ -0,0 +1,723 @@
+//! Run the simulation without a window.
+//!
+//! The GameRunner owns the world, the resources and the scenes. It knows how to advance the game by
+//! one fixed step. `Game` wraps it to add rendering, audio and window events. The runner can also be
+//! used alone, for example to run the game in CI or from a simulation binary. In that case, nothing
+//! is uploaded to the GPU and no audio device is opened.
+//!
+//! Asset managers are still typed on GlfwSurface as this is what the gameplay code fetches from the
+//! resources. No surface is ever created though.
+use crate::assets::unload_unused_assets;
+use crate::core::animation::AnimationController;
+use crate::core::camera::{Camera, ProjectionMatrix};
+use crate::core::input::replay::{InputFrame, InputRecording, InputReplay, SceneKey};
+use crate::core::input::{Input, InputAction};
+use crate::core::profiler::{profile, Profiler};
+use crate::core::random::{RandomGenerator, Seed};
+use crate::core::scene::{Scene, SceneResult, SceneStack};
+use crate::core::transform::{
+    update_transforms, HasChildren, HasParent, LocalTransform, PreviousTransforms, Transform,
+};
+use crate::core::window::WindowDim;
+use crate::gameplay::bullet::{Bullet, Missile};
+use crate::gameplay::collision::{BoundingBox, CollisionWorld};
+use crate::gameplay::delete::GarbageCollector;
+use crate::gameplay::enemy::Enemy;
+use crate::gameplay::explosion::Explosion;
+use crate::gameplay::health::{Health, Shield};
+use crate::gameplay::physics::DynamicBody;
+use crate::gameplay::pickup::Pickup;
+use crate::gameplay::player::Player;
+use crate::gameplay::trail::Trail;
+use crate::render::mesh::MeshRender;
+use crate::render::particle::{update_effects, update_emitters, ParticleEmitter};
+use crate::render::path::debug::DebugQueue;
+use crate::render::sprite::Sprite;
+use crate::resources::Resources;
+use crate::{HEIGHT, WIDTH};
+use glfw::{Key, MouseButton, WindowEvent};
+use luminance_glfw::GlfwSurface;
+use std::any::Any;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::time::Duration;
+
+/// Default number of fixed updates per second.
+pub const DEFAULT_STEP_RATE: u32 = 60;
+
+/// Default maximum number of fixed updates that can run during one frame.
+const DEFAULT_MAX_STEPS_PER_FRAME: u32 = 5;
+
+/// Configuration of the fixed-step simulation.
+#[derive(Debug, Copy, Clone)]
+pub struct TimeStep {
+    /// Duration of one fixed update.
+    pub dt: Duration,
+    /// If the simulation is late, it will not run more updates than this during a frame. The
+    /// remaining time is dropped so that a slow frame cannot make the next ones even slower.
+    pub max_steps_per_frame: u32,
+}
+
+impl Default for TimeStep {
+    fn default() -> Self {
+        Self {
+            dt: Duration::from_secs_f64(1.0 / DEFAULT_STEP_RATE as f64),
+            max_steps_per_frame: DEFAULT_MAX_STEPS_PER_FRAME,
+        }
+    }
+}
+
+/// Create a GameRunner. Also used by the GameBuilder for everything that is not related to the
+/// window.
+pub struct GameRunnerBuilder<A>
+where
+    A: InputAction,
+{
+    scene: Option<Box<dyn Scene<WindowEvent>>>,
+    resources: Resources,
+    seed: Option<Seed>,
+    input_config: Option<(HashMap<Key, A>, HashMap<MouseButton, A>)>,
+    time_step: TimeStep,
+    input_source: InputSourceConfig<A>,
+    phantom: PhantomData<A>,
+}
+
+/// What the builder should do with the inputs.
+enum InputSourceConfig<A: InputAction> {
+    Live,
+    Record,
+    Replay(InputRecording<A>),
+}
+
+/// Where the inputs come from during the fixed update.
+enum InputSource<A: InputAction> {
+    /// Inputs from the window, nothing else to do.
+    Live,
+    /// Inputs from the window, saved every fixed update once the recording has started.
+    Record(InputRecording<A>),
+    /// Inputs from a recording. Window inputs are ignored.
+    Replay(InputReplay<A>),
+}
+
+impl<A> Default for GameRunnerBuilder<A>
+where
+    A: InputAction + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A> GameRunnerBuilder<A>
+where
+    A: InputAction + 'static,
+{
+    pub fn new() -> Self {
+        // resources will need at least the event channels and an input
+        let mut resources = Resources::default();
+        crate::event::register_game_events(&mut resources);
+
+        // and some asset manager;
+        crate::assets::create_asset_managers::<GlfwSurface>(&mut resources);
+
+        // the proj matrix.
+        resources.insert(ProjectionMatrix::new(WIDTH as f32, HEIGHT as f32));
+        resources.insert(WindowDim::new(WIDTH, HEIGHT));
+        resources.insert(CollisionWorld::default());
+        resources.insert(DebugQueue::default());
+        resources.insert(PreviousTransforms::default());
+        resources.insert(default_profiler());
+
+        Self {
+            scene: None,
+            resources,
+            seed: None,
+            input_config: None,
+            time_step: TimeStep::default(),
+            input_source: InputSourceConfig::Live,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Set up the first scene.
+    pub fn for_scene(mut self, scene: Box<dyn Scene<WindowEvent>>) -> Self {
+        self.scene = Some(scene);
+        self
+    }
+
+    pub fn with_input_config(
+        mut self,
+        key_map: HashMap<Key, A>,
+        btn_map: HashMap<MouseButton, A>,
+    ) -> Self {
+        self.input_config = Some((key_map, btn_map));
+        self
+    }
+
+    /// Add custom resources.
+    pub fn with_resource<T: Any>(mut self, r: T) -> Self {
+        self.resources.insert(r);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: Seed) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Number of fixed updates per second. Default is 60.
+    pub fn with_step_rate(mut self, step_rate: u32) -> Self {
+        assert!(step_rate > 0, "Step rate should be positive");
+        self.time_step.dt = Duration::from_secs_f64(1.0 / step_rate as f64);
+        self
+    }
+
+    /// Maximum number of fixed updates during one frame. Default is 5.
+    pub fn with_max_steps_per_frame(mut self, max_steps: u32) -> Self {
+        assert!(max_steps > 0, "Should allow at least one step per frame");
+        self.time_step.max_steps_per_frame = max_steps;
+        self
+    }
+
+    /// Record the inputs of every fixed update. Use `GameRunner::save_recording` to write them to
+    /// a file.
+    pub fn with_input_recording(mut self) -> Self {
+        self.input_source = InputSourceConfig::Record;
+        self
+    }
+
+    /// Play back a recording instead of using the window inputs. The seed of the recording is used
+    /// for the random generator.
+    pub fn with_input_replay(mut self, recording: InputRecording<A>) -> Self {
+        self.seed = Some(recording.seed);
+        self.input_source = InputSourceConfig::Replay(recording);
+        self
+    }
+
+    pub fn build(mut self) -> GameRunner<A> {
+        // Need some input :D
+        let input: Input<A> = {
+            let (key_mapping, btn_mapping) = self
+                .input_config
+                .unwrap_or((A::get_default_key_mapping(), A::get_default_mouse_mapping()));
+            Input::new(key_mapping, btn_mapping)
+        };
+        self.resources.insert(input);
+        let mut world = hecs::World::new();
+
+        // if a seed is not provided, pick one so that the run can be reproduced.
+        let seed = self.seed.unwrap_or_else(Seed::random);
+        self.resources.insert(RandomGenerator::new(seed));
+        let input_source = match self.input_source {
+            InputSourceConfig::Live => InputSource::Live,
+            InputSourceConfig::Record => InputSource::Record(InputRecording::new(seed)),
+            InputSourceConfig::Replay(recording) => {
+                InputSource::Replay(InputReplay::new(recording))
+            }
+        };
+
+        let scene_stack = {
+            let mut scenes = SceneStack::default();
+            if let Some(scene) = self.scene {
+                scenes.push(scene, &mut world, &mut self.resources);
+            }
+            scenes
+        };
+
+        let garbage_collector = GarbageCollector::new(&mut self.resources);
+
+        // we need a camera :)
+        world.spawn((Camera::new(),));
+
+        GameRunner {
+            scene_stack,
+            world,
+            resources: self.resources,
+            garbage_collector,
+            time_step: self.time_step,
+            frame: 0,
+            seed,
+            input_source,
+            recording_started: false,
+            scene_keys: vec![],
+            phantom: self.phantom,
+        }
+    }
+}
+
+/// Hold the game state and update it with fixed time steps.
+///
+/// # Generic parameters:
+/// - A: Action that is derived from the inputs. (e.g. Move Left)
+pub struct GameRunner<A>
+where
+    A: InputAction,
+{
+    /// All the scenes. Current scene will be used in the main loop.
+    pub(crate) scene_stack: SceneStack<WindowEvent>,
+
+    /// Current entities.
+    pub(crate) world: hecs::World,
+
+    /// Resources (assets, inputs...)
+    pub(crate) resources: Resources,
+
+    /// Clean up the dead entities.
+    garbage_collector: GarbageCollector,
+
+    /// Duration of a fixed update and maximum number of updates per frame.
+    pub(crate) time_step: TimeStep,
+
+    /// Number of fixed updates since the beginning.
+    frame: u64,
+
+    /// Seed of the random generator.
+    seed: Seed,
+
+    /// Record or replay the inputs.
+    input_source: InputSource<A>,
+    /// Set at the first update of a scene that starts the recording, see
+    /// `Scene::starts_input_recording`.
+    recording_started: bool,
+    /// Keys given to the current scene since the last fixed update. They are recorded with the
+    /// next one.
+    scene_keys: Vec<SceneKey>,
+
+    phantom: PhantomData<A>,
+}
+
+impl<A> GameRunner<A>
+where
+    A: InputAction + 'static,
+{
+    pub fn world(&self) -> &hecs::World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut hecs::World {
+        &mut self.world
+    }
+
+    pub fn resources(&self) -> &Resources {
+        &self.resources
+    }
+
+    pub fn resources_mut(&mut self) -> &mut Resources {
+        &mut self.resources
+    }
+
+    /// Number of fixed updates since the runner was created.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn seed(&self) -> Seed {
+        self.seed
+    }
+
+    /// Give a window event to the current scene and to the inputs. Ignored when replaying as the
+    /// inputs come from the recording.
+    pub fn process_event(&mut self, event: WindowEvent) {
+        if self.is_replaying() {
+            return;
+        }
+        if let Some(scene) = self.scene_stack.current_mut() {
+            scene.process_input(&mut self.world, event.clone(), &self.resources);
+        }
+        if let Some(key) = SceneKey::from_event(&event) {
+            self.scene_keys.push(key);
+        }
+        self.resources
+            .fetch_mut::<Input<A>>()
+            .unwrap()
+            .process_event(event);
+    }
+
+    /// Returns true if the inputs come from a recording instead of the window.
+    pub fn is_replaying(&self) -> bool {
+        matches!(self.input_source, InputSource::Replay(_))
+    }
+
+    /// Inputs recorded so far if the recording is enabled.
+    pub fn recording(&self) -> Option<&InputRecording<A>> {
+        if let InputSource::Record(ref recording) = self.input_source {
+            Some(recording)
+        } else {
+            None
+        }
+    }
+
+    /// Write the inputs recorded so far to a file. Does nothing if the recording is not enabled.
+    pub fn save_recording<P: AsRef<Path>>(&self, path: P) -> Result<(), anyhow::Error> {
+        if let Some(recording) = self.recording() {
+            recording.save(path.as_ref())?;
+            info!(
+                "Saved {} frames of inputs to {}",
+                recording.len(),
+                path.as_ref().display()
+            );
+        }
+        Ok(())
+    }
+
+    /// Hash of the current state of the world. See `hash_world`.
+    pub fn world_hash(&self) -> u64 {
+        hash_world(&self.world)
+    }
+
+    /// Returns true if there is no scene left to update.
+    pub fn is_finished(&mut self) -> bool {
+        self.scene_stack.current_mut().is_none()
+    }
+
+    /// Run one fixed update without any graphic context. Loaded assets are marked as ready and the
+    /// scene transitions are applied right away.
+    ///
+    /// Returns false when there is no scene left to update.
+    pub fn step(&mut self) -> bool {
+        let scene_result = self.fixed_update();
+        crate::assets::finish_asset_managers::<GlfwSurface>(&self.resources);
+        self.apply_scene_result(scene_result);
+        self.end_profiler_frame();
+        !self.is_finished()
+    }
+
+    /// Count the entities and save the timings of this frame.
+    pub(crate) fn end_profiler_frame(&mut self) {
+        if let Some(mut profiler) = self.resources.fetch_mut::<Profiler>() {
+            profiler.end_frame(&self.world);
+        }
+    }
+
+    /// Run `nb_steps` fixed updates or until there is no scene left. Returns the number of updates
+    /// that were executed.
+    pub fn run_for(&mut self, nb_steps: usize) -> usize {
+        for i in 0..nb_steps {
+            if !self.step() {
+                return i + 1;
+            }
+        }
+        nb_steps
+    }
+
+    /// Advance the simulation by one time step.
+    pub(crate) fn fixed_update(&mut self) -> SceneResult<WindowEvent> {
+        let dt = self.time_step.dt;
+        self.update_input_source();
+
+        // Keep the current state so that the renderer can interpolate.
+        self.resources
+            .fetch_mut::<PreviousTransforms>()
+            .unwrap()
+            .capture(&self.world);
+
+        let scene_result = self
+            .scene_stack
+            .update(dt, &mut self.world, &self.resources);
+
+        let world = &mut self.world;
+        let resources = &self.resources;
+
+        // Particles are updated at the same rate as the rest of the simulation.
+        profile(resources, "particles", || {
+            update_effects::<GlfwSurface>(world, resources);
+            update_emitters(world, dt, resources)
+        });
+
+        // Update children transforms:
+        // -----------------------------
+        profile(resources, "transforms", || update_transforms(world));
+
+        // Clean up dead entities.
+        // -----------------------------
+        let garbage_collector = &mut self.garbage_collector;
+        profile(resources, "garbage_collector", || {
+            garbage_collector.collect(world, resources)
+        });
+
+        // Update collision world for collision queries.
+        profile(resources, "collision_world", || {
+            let mut collisions = resources.fetch_mut::<CollisionWorld>().unwrap();
+            collisions.synchronize(world);
+        });
+
+        // Pressed actions have been seen by this update, do not process them twice.
+        self.resources.fetch_mut::<Input<A>>().unwrap().prepare();
+        self.frame += 1;
+
+        scene_result
+    }
+
+    /// Record the inputs for this update or replace them with the recorded ones.
+    fn update_input_source(&mut self) {
+        if !self.recording_started {
+            self.recording_started = self
+                .scene_stack
+                .current_mut()
+                .map(|scene| scene.starts_input_recording())
+                .unwrap_or(false);
+        }
+        let scene_keys = std::mem::take(&mut self.scene_keys);
+
+        let replayed_keys = {
+            let mut input = self.resources.fetch_mut::<Input<A>>().unwrap();
+            match self.input_source {
+                InputSource::Live => vec![],
+                InputSource::Record(ref mut recording) => {
+                    if self.recording_started {
+                        let window_dim = self.resources.fetch::<WindowDim>().unwrap();
+                        let mut frame = input.snapshot(&window_dim);
+                        frame.scene_keys = scene_keys;
+                        recording.push(frame);
+                    }
+                    vec![]
+                }
+                InputSource::Replay(ref mut replay) => {
+                    let frame = if self.recording_started {
+                        replay.next_frame().cloned()
+                    } else {
+                        None
+                    };
+                    // Nothing is pressed before and after the recording.
+                    let frame = frame.unwrap_or_else(InputFrame::default);
+                    input.apply_frame(&frame);
+                    frame.scene_keys
+                }
+            }
+        };
+
+        if let Some(scene) = self.scene_stack.current_mut() {
+            for key in replayed_keys {
+                scene.process_input(&mut self.world, key.to_event(), &self.resources);
+            }
+        }
+    }
+
+    /// Switch scenes if needed.
+    pub(crate) fn apply_scene_result(&mut self, scene_result: SceneResult<WindowEvent>) {
+        if let SceneResult::Noop = scene_result {
+            return;
+        }
+
+        // the assets of a scene that is gone can go too.
+        let replaces_scene = matches!(
+            scene_result,
+            SceneResult::ReplaceScene(_) | SceneResult::ReplaceAll(_)
+        );
+        self.scene_stack
+            .apply_result(scene_result, &mut self.world, &mut self.resources);
+        if replaces_scene {
+            unload_unused_assets::<GlfwSurface>(&self.resources);
+        }
+        // Entities from the previous scene should not be used for interpolation.
+        self.resources
+            .fetch_mut::<PreviousTransforms>()
+            .unwrap()
+            .capture(&self.world);
+    }
+}
+
+/// Profiler that knows the names of the main components.
+fn default_profiler() -> Profiler {
+    Profiler::default()
+        .with_component::<Transform>()
+        .with_component::<LocalTransform>()
+        .with_component::<HasParent>()
+        .with_component::<HasChildren>()
+        .with_component::<Camera>()
+        .with_component::<Sprite>()
+        .with_component::<MeshRender>()
+        .with_component::<ParticleEmitter>()
+        .with_component::<AnimationController>()
+        .with_component::<DynamicBody>()
+        .with_component::<BoundingBox>()
+        .with_component::<Health>()
+        .with_component::<Shield>()
+        .with_component::<Player>()
+        .with_component::<Enemy>()
+        .with_component::<Bullet>()
+        .with_component::<Missile>()
+        .with_component::<Pickup>()
+        .with_component::<Explosion>()
+        .with_component::<Trail>()
+}
+
+/// Compute a hash of the simulation state: entities, transforms, bodies and health. Two runs with
+/// the same seed and the same inputs should give the same hash at every frame. Floats are hashed
+/// with their bit representation so even a tiny difference will change the hash.
+pub fn hash_world(world: &hecs::World) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let mut entities: Vec<_> = world.iter().map(|(e, _)| e).collect();
+    entities.sort_by_key(|e| e.to_bits());
+
+    for e in entities {
+        e.to_bits().hash(&mut hasher);
+        if let Ok(t) = world.get::<Transform>(e) {
+            hash_floats(
+                &mut hasher,
+                &[
+                    t.translation.x,
+                    t.translation.y,
+                    t.scale.x,
+                    t.scale.y,
+                    t.rotation,
+                ],
+            );
+        }
+        if let Ok(body) = world.get::<DynamicBody>(e) {
+            hash_floats(&mut hasher, &[body.velocity.x, body.velocity.y]);
+        }
+        if let Ok(health) = world.get::<Health>(e) {
+            hash_floats(&mut hasher, &[health.current, health.max]);
+        }
+    }
+
+    hasher.finish()
+}
+
+fn hash_floats<H: Hasher>(hasher: &mut H, floats: &[f32]) {
+    for f in floats {
+        f.to_bits().hash(hasher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{load_config, PlayerConfig};
+    use crate::gameplay::inventory::Inventory;
+    use crate::gameplay::level::difficulty::DifficultyConfig;
+    use crate::gameplay::Action;
+    use crate::paths::get_assets_path;
+    use crate::save::read_saved_data;
+    use crate::scene::loading::LoadingScene;
+    use crate::scene::{main_scene_manifest, MainScene};
+    use glfw::Modifiers;
+
+    /// Wait a few updates before loading the game so that the recording and the replay do not
+    /// start at the same frame.
+    struct WaitScene(usize);
+
+    impl Scene<WindowEvent> for WaitScene {
+        fn update(
+            &mut self,
+            _dt: Duration,
+            _world: &mut hecs::World,
+            _resources: &Resources,
+        ) -> SceneResult<WindowEvent> {
+            if self.0 > 0 {
+                self.0 -= 1;
+                return SceneResult::Noop;
+            }
+            let mut manifest = main_scene_manifest(false);
+            manifest.sounds.clear();
+            manifest.atlases.clear();
+            SceneResult::ReplaceScene(Box::new(LoadingScene::new(manifest, MainScene::default())))
+        }
+    }
+
+    fn build_runner(wait: usize, source: InputSourceConfig<Action>) -> GameRunner<Action> {
+        let base_path = get_assets_path();
+        let player_config: PlayerConfig =
+            load_config(base_path.join("config/player_controller.json")).unwrap_or_default();
+        let difficulty_config: DifficultyConfig =
+            load_config(base_path.join("config/difficulty.json")).unwrap_or_default();
+
+        let mut builder = GameRunnerBuilder::new()
+            .for_scene(Box::new(WaitScene(wait)))
+            .with_seed(Seed::from_u64(12))
+            .with_resource(read_saved_data())
+            .with_resource(player_config)
+            .with_resource(difficulty_config)
+            .with_resource(Inventory::default());
+        builder = match source {
+            InputSourceConfig::Live => builder,
+            InputSourceConfig::Record => builder.with_input_recording(),
+            InputSourceConfig::Replay(recording) => builder.with_input_replay(recording),
+        };
+        builder.build()
+    }
+
+    /// Run until the gameplay starts and the first inputs are recorded or replayed.
+    fn run_until_recording(runner: &mut GameRunner<Action>) {
+        while !runner.recording_started {
+            assert!(runner.frame() < 10_000, "Game did not start");
+            assert!(runner.step());
+        }
+    }
+
+    fn press(key: Key) -> WindowEvent {
+        WindowEvent::Key(key, 0, glfw::Action::Press, Modifiers::empty())
+    }
+
+    fn release(key: Key) -> WindowEvent {
+        WindowEvent::Key(key, 0, glfw::Action::Release, Modifiers::empty())
+    }
+
+    fn events_at(step: usize) -> Vec<WindowEvent> {
+        match step {
+            0 => vec![
+                press(Key::W),
+                WindowEvent::CursorPos(600.0, 200.0),
+                WindowEvent::MouseButton(
+                    MouseButton::Button1,
+                    glfw::Action::Press,
+                    Modifiers::empty(),
+                ),
+            ],
+            30 => vec![
+                release(Key::W),
+                press(Key::D),
+                WindowEvent::CursorPos(100.0, 500.0),
+            ],
+            // pause and resume the game.
+            60 | 70 => vec![press(Key::Escape), release(Key::Escape)],
+            90 => vec![
+                release(Key::D),
+                WindowEvent::MouseButton(
+                    MouseButton::Button1,
+                    glfw::Action::Release,
+                    Modifiers::empty(),
+                ),
+            ],
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn replay_gives_the_same_world() {
+        let nb_steps = 150;
+
+        let mut recorder = build_runner(3, InputSourceConfig::Record);
+        run_until_recording(&mut recorder);
+        for step in 0..nb_steps {
+            for event in events_at(step) {
+                recorder.process_event(event);
+            }
+            recorder.step();
+        }
+        let recording = recorder.recording().unwrap().clone();
+        assert_eq!(nb_steps + 1, recording.len());
+
+        // The replay starts later, in a bigger window.
+        let mut replayer = build_runner(10, InputSourceConfig::Replay(recording));
+        replayer
+            .resources
+            .fetch_mut::<WindowDim>()
+            .unwrap()
+            .resize(1600, 1200, 1600, 1200);
+        run_until_recording(&mut replayer);
+        for step in 0..nb_steps {
+            // ignored when replaying.
+            for event in events_at(step + 5) {
+                replayer.process_event(event);
+            }
+            replayer.step();
+        }
+
+        assert_eq!(recorder.world_hash(), replayer.world_hash());
+    }
+}
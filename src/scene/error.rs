@@ -0,0 +1,83 @@
+//! Shown instead of crashing when the game cannot go on, e.g. a stage that cannot be loaded. It
+//! shows what went wrong and brings the player back to the menu.
+
+use crate::core::colors::RgbaColor;
+use crate::core::scene::{Scene, SceneResult};
+use crate::render::ui::{Gui, GuiContext};
+use crate::resources::Resources;
+use crate::scene::main_menu::MainMenu;
+use crate::ui::{draw_cursor, menu_button};
+use bitflags::_core::time::Duration;
+use glfw::{Key, WindowEvent};
+use hecs::World;
+
+pub struct ErrorScene {
+    title: String,
+    /// One line each.
+    errors: Vec<String>,
+    go_to_menu: bool,
+}
+
+impl ErrorScene {
+    pub fn new(title: String, errors: Vec<String>) -> Self {
+        for e in &errors {
+            error!("{} = {}", title, e);
+        }
+        Self {
+            title,
+            errors,
+            go_to_menu: false,
+        }
+    }
+}
+
+impl Scene<WindowEvent> for ErrorScene {
+    fn update(
+        &mut self,
+        _dt: Duration,
+        _world: &mut World,
+        _resources: &Resources,
+    ) -> SceneResult<WindowEvent> {
+        if self.go_to_menu {
+            SceneResult::ReplaceAll(Box::new(MainMenu::default()))
+        } else {
+            SceneResult::Noop
+        }
+    }
+
+    fn prepare_gui(
+        &mut self,
+        _dt: Duration,
+        _world: &mut World,
+        _resources: &Resources,
+        gui_context: &GuiContext,
+    ) -> Option<Gui> {
+        let mut gui = gui_context.new_frame();
+        draw_cursor(&mut gui);
+
+        let window_dim = gui.window_dim.to_vec2();
+        let mut anchor = glam::vec2(50.0, 50.0);
+        gui.colored_label(anchor, self.title.clone(), RgbaColor::new(255, 0, 0, 255));
+        for e in &self.errors {
+            anchor += 40.0 * glam::Vec2::unit_y();
+            gui.colored_label(anchor, e.clone(), RgbaColor::new(255, 255, 255, 255));
+        }
+
+        if menu_button(
+            "Back to Menu",
+            glam::vec2(50.0, window_dim.y - 100.0),
+            32.0,
+            &mut gui,
+        ) {
+            self.go_to_menu = true;
+        }
+
+        Some(gui)
+    }
+
+    fn process_input(&mut self, _world: &mut World, input: WindowEvent, _resources: &Resources) {
+        if let WindowEvent::Key(Key::Escape, _0, glfw::Action::Press, _2) = input {
+            self.go_to_menu = true;
+        }
+    }
+}
@@ -1,34 +1,46 @@
-use crate::assets::audio::Audio;
-use crate::assets::prefab::PrefabManager;
-use crate::assets::{AssetManager, Handle};
-use crate::core::scene::{Scene, SceneResult};
+use crate::assets::asset_stats;
+use crate::assets::manifest::{AssetManifest, ManifestHandles};
+use crate::assets::worker::Priority;
+use crate::core::colors::RgbaColor;
+use crate::core::scene::{Scene, SceneResult, SceneTransparency};
+use crate::render::ui::gui::GuiContext;
+use crate::render::ui::Gui;
 use crate::resources::Resources;
+use crate::scene::error::ErrorScene;
 use bitflags::_core::time::Duration;
 use glfw::WindowEvent;
 use hecs::World;
 use luminance_glfw::GlfwSurface;
 
+pub use crate::assets::manifest::LoadingProgress;
+
+/// Load the assets of a manifest then switch to the next scene. The next scene keeps the handles of
+/// the assets (see `WithAssets`), so they stay loaded while it is in the stack.
 pub struct LoadingScene<S: Scene<WindowEvent>> {
-    prefabs_to_load: Vec<String>,
-    audio_to_load: Vec<String>,
+    manifest: AssetManifest,
     next_scene: Option<S>,
-    audio_handles: Vec<Handle>,
-    prefab_handles: Vec<Handle>,
+    handles: ManifestHandles,
+    progress: LoadingProgress,
 }
 
 impl<S> LoadingScene<S>
 where
     S: Scene<WindowEvent> + 'static,
 {
-    pub fn new(prefabs_to_load: Vec<String>, audio_to_load: Vec<String>, next_scene: S) -> Self {
+    pub fn new(manifest: AssetManifest, next_scene: S) -> Self {
         Self {
-            prefabs_to_load,
-            audio_to_load,
+            manifest,
             next_scene: Some(next_scene),
-            prefab_handles: vec![],
-            audio_handles: vec![],
+            handles: ManifestHandles::default(),
+            progress: LoadingProgress::default(),
         }
     }
+
+    fn load(&mut self, resources: &Resources) {
+        // The game is waiting for these so they go before anything else.
+        self.manifest
+            .load_into::<GlfwSurface>(resources, Priority::High, &mut self.handles);
+    }
 }
 
 impl<S> Scene<WindowEvent> for LoadingScene<S>
@@ -36,22 +48,7 @@ where
     S: Scene<WindowEvent> + 'static,
 {
     fn on_create(&mut self, _world: &mut World, resources: &mut Resources) {
-        // Pre-load :)
-        let mut prefab_manager = resources.fetch_mut::<PrefabManager<GlfwSurface>>().unwrap();
-        self.prefab_handles = self
-            .prefabs_to_load
-            .iter()
-            .map(|name| prefab_manager.load(name.clone()))
-            .collect();
-
-        let mut audio_manager = resources
-            .fetch_mut::<AssetManager<GlfwSurface, Audio>>()
-            .unwrap();
-        self.audio_handles = self
-            .audio_to_load
-            .iter()
-            .map(|name| audio_manager.load(name.clone()))
-            .collect();
+        self.load(resources);
     }
 
     fn update(
@@ -60,43 +57,199 @@ where
         _world: &mut World,
         resources: &Resources,
     ) -> SceneResult<WindowEvent> {
-        let prefab_manager = resources.fetch_mut::<PrefabManager<GlfwSurface>>().unwrap();
-        let audio_manager = resources
-            .fetch::<AssetManager<GlfwSurface, Audio>>()
-            .unwrap();
-        // loaded.
-        let mut nb_loaded = self
-            .prefab_handles
-            .iter()
-            .filter(|h| prefab_manager.is_loaded(h))
-            .count();
-        let mut nb_error = self
-            .prefab_handles
-            .iter()
-            .filter(|h| prefab_manager.is_error(h))
-            .count();
-
-        nb_loaded += self
-            .audio_handles
-            .iter()
-            .filter(|h| {
-                info!("{:?} is loaded = {}", h, audio_manager.is_loaded(h));
-                audio_manager.is_loaded(h)
-            })
-            .count();
-        nb_error += self
-            .audio_handles
-            .iter()
-            .filter(|h| audio_manager.is_error(h))
-            .count();
-
-        if nb_error > 0 {
+        // The stages, prefabs and particles that are loaded might need other assets.
+        if self
+            .manifest
+            .add_loaded_dependencies::<GlfwSurface>(resources)
+        {
+            self.load(resources);
+        }
+
+        self.progress = self.handles.progress::<GlfwSurface>(resources);
+        debug!("Loading progress = {:?}", self.progress);
+
+        if self.progress.failed > 0 {
             // NG
-            SceneResult::Pop
-        } else if nb_loaded == self.prefab_handles.len() + self.audio_handles.len() {
-            SceneResult::ReplaceScene(Box::new(self.next_scene.take().unwrap()))
+            let mut errors = self.handles.errors::<GlfwSurface>(resources);
+            self.handles.cancel::<GlfwSurface>(resources);
+            if errors.is_empty() {
+                errors.push("See the logs for more details.".to_string());
+            }
+            SceneResult::ReplaceScene(Box::new(ErrorScene::new(
+                "Cannot load the game".to_string(),
+                errors,
+            )))
+        } else if self.progress.is_done() {
+            for (kind, stats) in asset_stats::<GlfwSurface>(resources) {
+                info!(
+                    "{}: {} assets, {} KiB, {} referenced",
+                    kind,
+                    stats.loaded,
+                    stats.bytes / 1024,
+                    stats.referenced
+                );
+            }
+            SceneResult::ReplaceScene(Box::new(WithAssets {
+                scene: self.next_scene.take().unwrap(),
+                _handles: std::mem::take(&mut self.handles),
+            }))
         } else {
             SceneResult::Noop
         }
     }
+
+    fn prepare_gui(
+        &mut self,
+        _dt: Duration,
+        _world: &mut World,
+        _resources: &Resources,
+        gui_context: &GuiContext,
+    ) -> Option<Gui> {
+        let mut gui = gui_context.new_frame();
+
+        let center = gui_context.window_dim.to_vec2() / 2.0;
+        let bar_size = glam::vec2(300.0, 12.0);
+        let anchor = center - bar_size / 2.0;
+        gui.panel(anchor, bar_size, RgbaColor::new(0, 0, 0, 255));
+        gui.panel(
+            anchor,
+            glam::vec2(bar_size.x * self.progress.ratio(), bar_size.y),
+            RgbaColor::new(255, 255, 255, 255),
+        );
+        gui.centered_label(
+            center - 30.0 * glam::Vec2::unit_y(),
+            format!(
+                "Loading... {}/{}",
+                self.progress.loaded, self.progress.total
+            ),
+        );
+
+        Some(gui)
+    }
+}
+
+/// A scene and the assets it was loaded with. The assets are released when the scene leaves the
+/// stack, not before, even if other scenes are pushed on top of it.
+pub struct WithAssets<S> {
+    scene: S,
+    _handles: ManifestHandles,
+}
+
+impl<S> Scene<WindowEvent> for WithAssets<S>
+where
+    S: Scene<WindowEvent>,
+{
+    fn on_create(&mut self, world: &mut World, resources: &mut Resources) {
+        self.scene.on_create(world, resources)
+    }
+
+    fn on_destroy(&mut self, world: &mut World) {
+        self.scene.on_destroy(world)
+    }
+
+    fn on_enter(&mut self, world: &mut World, resources: &mut Resources) {
+        self.scene.on_enter(world, resources)
+    }
+
+    fn on_exit(&mut self, world: &mut World, resources: &mut Resources) {
+        self.scene.on_exit(world, resources)
+    }
+
+    fn transparency(&self) -> SceneTransparency {
+        self.scene.transparency()
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.scene.name()
+    }
+
+    fn type_name(&self) -> &'static str {
+        self.scene.type_name()
+    }
+
+    fn starts_input_recording(&self) -> bool {
+        self.scene.starts_input_recording()
+    }
+
+    fn update(
+        &mut self,
+        dt: Duration,
+        world: &mut World,
+        resources: &Resources,
+    ) -> SceneResult<WindowEvent> {
+        self.scene.update(dt, world, resources)
+    }
+
+    fn prepare_gui(
+        &mut self,
+        dt: Duration,
+        world: &mut World,
+        resources: &Resources,
+        gui_context: &GuiContext,
+    ) -> Option<Gui> {
+        self.scene.prepare_gui(dt, world, resources, gui_context)
+    }
+
+    fn process_input(&mut self, world: &mut World, input: WindowEvent, resources: &Resources) {
+        self.scene.process_input(world, input, resources)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::source::AssetSource;
+    use crate::assets::stage::{StageLoader, StageManager};
+    use crate::assets::Handle;
+    use crate::core::scene::SceneStack;
+    use crate::gameplay::level::StageDescription;
+
+    struct Idle;
+
+    impl Scene<WindowEvent> for Idle {
+        fn update(
+            &mut self,
+            _dt: Duration,
+            _world: &mut World,
+            _resources: &Resources,
+        ) -> SceneResult<WindowEvent> {
+            SceneResult::Noop
+        }
+    }
+
+    fn with_stage(manager: &mut StageManager<GlfwSurface>, name: &str) -> Box<WithAssets<Idle>> {
+        let handle = manager.insert(name.to_string(), StageDescription::infinite());
+        let mut handles = ManifestHandles::default();
+        handles.stages.extend(manager.acquire(&handle));
+        Box::new(WithAssets {
+            scene: Idle,
+            _handles: handles,
+        })
+    }
+
+    #[test]
+    fn assets_are_released_with_their_scene() {
+        let mut manager: StageManager<GlfwSurface> =
+            StageManager::from_loader(Box::new(StageLoader::new(AssetSource::new())));
+        let mut world = World::new();
+        let mut resources = Resources::default();
+        let mut stack = SceneStack::default();
+
+        stack.push(with_stage(&mut manager, "game"), &mut world, &mut resources);
+        stack.push(
+            with_stage(&mut manager, "pause"),
+            &mut world,
+            &mut resources,
+        );
+        manager.skip_upload();
+        assert_eq!(0, manager.unload_unused());
+
+        // the scene below keeps its assets.
+        stack.pop(&mut world, &mut resources);
+        assert_eq!(1, manager.unload_unused());
+        assert!(manager.is_loaded(&Handle("game".to_string())));
+
+        stack.replace(Box::new(Idle), &mut world, &mut resources);
+        assert_eq!(1, manager.unload_unused());
+    }
 }
@@ -1,9 +1,7 @@
 use crate::core::audio;
 use crate::core::scene::{Scene, SceneResult};
 use crate::core::transform::Transform;
-use crate::paths::get_assets_path;
-use crate::prefab::enemies::ENEMY_PREFABS;
-use crate::render::particle::ParticleEmitter;
+use crate::render::particle::ParticleEffect;
 use crate::render::ui::gui::GuiContext;
 use crate::render::ui::Gui;
 use crate::resources::Resources;
@@ -11,7 +9,7 @@ use crate::save::is_infinite_unlocked;
 use crate::scene::loading::LoadingScene;
 use crate::scene::story::StoryScene;
 use crate::scene::wave_selection::WaveSelectionScene;
-use crate::scene::MainScene;
+use crate::scene::{main_scene_manifest, MainScene};
 use crate::ui::{disabled_menu_button, draw_cursor, menu_button};
 use bitflags::_core::time::Duration;
 use glfw::WindowEvent;
@@ -23,9 +21,8 @@ enum GameMode {
     Infinite,
 }
 
-#[derive(Default, Clone)]
+#[derive(Default)]
 pub struct MainMenu {
-    does_start: bool,
     game_mode: Option<GameMode>,
     emitter_entity: Option<hecs::Entity>,
 }
@@ -33,13 +30,9 @@ pub struct MainMenu {
 impl Scene<WindowEvent> for MainMenu {
     fn on_create(&mut self, world: &mut hecs::World, resources: &mut Resources) {
         //generate_terrain(world, resources);
-        let base_path = get_assets_path();
-        let emitter = ParticleEmitter::load_from_path(
-            base_path.join("particle").join("menu.json"),
-        )
-        .unwrap();
-
-        self.emitter_entity = Some(world.spawn((emitter, Transform::default())));
+        // the emitter is added once menu.json is loaded, see `update_effects`.
+        self.emitter_entity =
+            Some(world.spawn((ParticleEffect::new("menu.json"), Transform::default())));
 
         audio::play_background_music(resources, "music/spacelifeNo14.ogg");
     }
@@ -58,13 +51,9 @@ impl Scene<WindowEvent> for MainMenu {
         _world: &mut World,
         resources: &Resources,
     ) -> SceneResult<WindowEvent> {
-        let mut prefabs: Vec<String> = ENEMY_PREFABS.iter().map(|e| e.to_string()).collect();
-
-        prefabs.push("player".to_string());
         if let Some(GameMode::Normal) = self.game_mode {
             SceneResult::ReplaceScene(Box::new(LoadingScene::new(
-                prefabs,
-                vec![],
+                main_scene_manifest(false),
                 StoryScene::new(
                     vec![
                         "Humans discovered an alien artefact deep inside the moon.".to_string(),
@@ -75,8 +64,7 @@ impl Scene<WindowEvent> for MainMenu {
             )))
         } else if let Some(GameMode::Infinite) = self.game_mode {
             SceneResult::ReplaceScene(Box::new(LoadingScene::new(
-                prefabs,
-                vec![],
+                main_scene_manifest(true),
                 WaveSelectionScene::new(resources),
             )))
         } else {
@@ -1,31 +1,36 @@
+use crate::assets::manifest::{AssetManifest, ManifestHandles};
 use crate::assets::prefab::PrefabManager;
-use crate::assets::Handle;
+use crate::assets::stage::StageManager;
+use crate::assets::worker::Priority;
+use crate::assets::{Handle, StrongHandle};
 use crate::core::animation::AnimationSystem;
 use crate::core::audio;
 use crate::core::colors::RgbaColor;
 use crate::core::random::RandomGenerator;
 use crate::core::scene::{Scene, SceneResult};
+use crate::core::system::{Schedule, SystemStage};
 use crate::core::timer::Timer;
 use crate::core::transform::{HasChildren, HasParent, LocalTransform, Transform};
-use crate::event::GameEvent;
+use crate::core::tween::update_tweens;
+use crate::event::{AssetReloaded, EnemyDied, GameOver, InfoText, NextStage, YouWin};
 use crate::gameplay::bullet::{Bullet, Missile};
 use crate::gameplay::camera::update_camera;
+use crate::gameplay::delete::despawn_recursive;
 use crate::gameplay::explosion::ExplosionSystem;
 use crate::gameplay::health::{Health, HealthSystem, Shield};
 use crate::gameplay::inventory::Inventory;
-use crate::gameplay::level::{Stage, StageDescription};
+use crate::gameplay::level::{Stage, StageDescription, FIRST_STAGE};
 use crate::gameplay::physics::{PhysicConfig, PhysicSystem};
 use crate::gameplay::pickup::{process_pickups, spawn_pickup, Pickup};
 use crate::gameplay::player::get_player;
 use crate::gameplay::trail::update_trails;
 use crate::gameplay::{bullet, collision, enemy, player};
-use crate::paths::get_assets_path;
 use crate::render::mesh::{Material, MeshRender};
-use crate::render::particle::ParticleEmitter;
 use crate::render::ui::gui::GuiContext;
 use crate::render::ui::{Button, Gui, HorizontalAlign, VerticalAlign};
 use crate::resources::Resources;
 use crate::save::{get_wave_record, save_new_wave_record, save_unlocked};
+use crate::scene::error::ErrorScene;
 use crate::scene::main_menu::MainMenu;
 use crate::scene::pause::PauseScene;
 use crate::scene::story::StoryScene;
@@ -35,8 +40,11 @@ use hecs::World;
 use log::info;
 use luminance_glfw::GlfwSurface;
 use rand::Rng;
+use shrev::{EventChannel, ReaderId};
+use std::path::Path;
 use std::time::Duration;
 
+pub mod error;
 pub mod loading;
 pub mod main_menu;
 pub mod particle_scene;
@@ -52,11 +60,10 @@ enum MainSceneState {
 }
 
 pub struct MainScene {
-    stage: Option<Stage>,
-    health_system: Option<HealthSystem>,
-    physic_system: PhysicSystem,
-    animation_system: AnimationSystem,
-    explosion_system: Option<ExplosionSystem>,
+    /// Systems that update the world. The stage is added when the scene is created.
+    schedule: Schedule,
+    /// Used to create the schedule again when the game restarts.
+    schedule_factory: fn() -> Schedule,
 
     state: MainSceneState,
     return_to_menu: bool,
@@ -68,6 +75,58 @@ pub struct MainScene {
 
     is_infinite: bool,
     starting_wave_nb: usize,
+    /// File of the current stage and the wave it started at. Used to restart the stage when the
+    /// file changes. None in infinite mode.
+    stage_file: Option<(String, usize)>,
+    /// Stage to start once its description is loaded, and the wave it starts at.
+    pending_stage: Option<(String, usize)>,
+    /// Assets of the current stage that are still loading, with the dependencies found so far.
+    /// The waves wait for them.
+    stage_loading: Option<AssetManifest>,
+    /// Keep the assets of the current stage loaded. Released when the next stage starts.
+    stage_assets: ManifestHandles,
+    /// Why the game cannot go on. The error scene replaces this one.
+    errors: Vec<String>,
+    /// Keep the player loaded while the scene runs.
+    player_prefab: Option<StrongHandle>,
+    /// Keep the current and next stages loaded.
+    stages: Vec<StrongHandle>,
+
+    /// Readers for the events the scene reacts to. Created in on_create.
+    events: Option<MainSceneEvents>,
+}
+
+struct MainSceneEvents {
+    game_over: ReaderId<GameOver>,
+    you_win: ReaderId<YouWin>,
+    enemy_died: ReaderId<EnemyDied>,
+    info_text: ReaderId<InfoText>,
+    next_stage: ReaderId<NextStage>,
+    asset_reloaded: ReaderId<AssetReloaded>,
+}
+
+impl MainSceneEvents {
+    fn new(resources: &mut Resources) -> Self {
+        Self {
+            game_over: resources.register_reader(),
+            you_win: resources.register_reader(),
+            enemy_died: resources.register_reader(),
+            info_text: resources.register_reader(),
+            next_stage: resources.register_reader(),
+            asset_reloaded: resources.register_reader(),
+        }
+    }
+}
+
+/// Read all the events of a channel.
+fn read_events<E: Clone + Send + Sync + 'static>(
+    resources: &Resources,
+    rdr_id: &mut ReaderId<E>,
+) -> Vec<E> {
+    resources
+        .fetch::<EventChannel<E>>()
+        .map(|chan| chan.read(rdr_id).cloned().collect())
+        .unwrap_or_default()
 }
 
 impl Default for MainScene {
@@ -86,50 +145,334 @@ impl MainScene {
             restart: false,
             state: MainSceneState::Running,
             return_to_menu: false,
-            animation_system: AnimationSystem,
-            stage: None,
-            health_system: None,
-            explosion_system: None,
-            physic_system: PhysicSystem::new(PhysicConfig { damping: 0.99 }),
+            schedule: Schedule::default(),
+            schedule_factory: main_schedule,
             info_text_timer: Timer::of_seconds(3.0),
+            stage_file: None,
+            pending_stage: None,
+            stage_loading: None,
+            stage_assets: ManifestHandles::default(),
+            errors: vec![],
+            player_prefab: None,
+            stages: vec![],
+            events: None,
         }
     }
+
+    /// Use other systems than the default ones. The stage system will be added to the schedule.
+    pub fn with_schedule(mut self, schedule_factory: fn() -> Schedule) -> Self {
+        self.schedule_factory = schedule_factory;
+        self
+    }
+
+    fn stage(&self) -> Option<&Stage> {
+        self.schedule.get::<Stage>()
+    }
+
+    /// Replace the current stage by the pending one if its description is loaded. It should be
+    /// there already, from the loading scene or because the previous stage loaded its next stage.
+    fn start_pending_stage(&mut self, world: &mut World, resources: &Resources) {
+        let (stage_name, starting_wave_nb) = match self.pending_stage.clone() {
+            Some(pending) => pending,
+            None => return,
+        };
+        let stage_desc = {
+            let mut stage_manager = resources.fetch_mut::<StageManager<GlfwSurface>>().unwrap();
+            let handle = stage_manager.load_with_priority(stage_name.clone(), Priority::High);
+            if stage_manager.is_pending(&handle) {
+                return;
+            }
+            let asset = stage_manager.get(&handle).unwrap();
+            if let Some(e) = asset.error() {
+                self.pending_stage = None;
+                self.errors.push(e);
+                return;
+            }
+            match asset.execute(|stage_desc| stage_desc.clone()) {
+                Some(stage_desc) => stage_desc,
+                None => return,
+            }
+        };
+        self.pending_stage = None;
+
+        // the first wave waits until everything is loaded, see `update_stage_loading`.
+        let mut manifest = stage_desc.manifest();
+        let handles = manifest.load_with_dependencies::<GlfwSurface>(resources, Priority::High);
+        self.stage_loading = Some(manifest);
+        // the assets that both stages use are kept by the new handles.
+        self.stage_assets = handles;
+        {
+            let mut stage_manager = resources.fetch_mut::<StageManager<GlfwSurface>>().unwrap();
+            // the previous stage is not needed anymore.
+            self.stages = stage_manager
+                .acquire(&Handle(stage_name.clone()))
+                .into_iter()
+                .collect();
+            if let Some(ref next_stage) = stage_desc.next_stage {
+                let handle = stage_manager.load_with_priority(next_stage.clone(), Priority::Low);
+                self.stages.extend(stage_manager.acquire(&handle));
+            }
+        }
+
+        info!("Start stage {} at wave {}", stage_name, starting_wave_nb);
+        if let Some(stage) = self.schedule.get_mut::<Stage>() {
+            stage.clean(world);
+        }
+        let mut stage = Stage::new(world, resources, stage_desc, starting_wave_nb);
+        stage.set_loading_assets(true);
+        if self.schedule.get::<Stage>().is_some() {
+            self.schedule.replace_system("stage", stage);
+        } else {
+            self.schedule
+                .add_system(SystemStage::Cleanup, "stage", stage);
+        }
+        self.stage_file = Some((stage_name, starting_wave_nb));
+    }
+
+    /// The prefabs of the stage bring their own dependencies once they are loaded, so keep adding
+    /// them until everything is there. Then the waves can start.
+    fn update_stage_loading(&mut self, resources: &Resources) {
+        let manifest = match self.stage_loading {
+            Some(ref mut manifest) => manifest,
+            None => return,
+        };
+        let handles = &mut self.stage_assets;
+        if manifest.add_loaded_dependencies::<GlfwSurface>(resources) {
+            manifest.load_into::<GlfwSurface>(resources, Priority::High, handles);
+        }
+
+        let progress = handles.progress::<GlfwSurface>(resources);
+        if progress.failed > 0 {
+            self.errors.extend(handles.errors::<GlfwSurface>(resources));
+            handles.cancel::<GlfwSurface>(resources);
+            self.stage_loading = None;
+        } else if progress.is_done() {
+            self.stage_loading = None;
+            if let Some(stage) = self.schedule.get_mut::<Stage>() {
+                stage.set_loading_assets(false);
+            }
+        }
+    }
+
+    /// React to the events sent by the systems during the update.
+    fn process_events(&mut self, world: &mut World, resources: &Resources) {
+        let events = match self.events {
+            Some(ref mut events) => events,
+            None => return,
+        };
+        let enemy_died = read_events(resources, &mut events.enemy_died);
+        let info_texts = read_events(resources, &mut events.info_text);
+        let game_over = !read_events(resources, &mut events.game_over).is_empty();
+        let you_win = !read_events(resources, &mut events.you_win).is_empty();
+        let next_stages = read_events(resources, &mut events.next_stage);
+        let reloaded = read_events(resources, &mut events.asset_reloaded);
+
+        let mut drain_scratch = false;
+        for EnemyDied {
+            entity,
+            position,
+            scrap: (low_scrap, high_scrap),
+            pickup_drop,
+        } in enemy_died
+        {
+            let mut random = resources
+                .fetch_mut::<RandomGenerator>()
+                .expect("Should have a random generator");
+
+            if high_scrap > low_scrap {
+                if let Some(ref mut inv) = resources.fetch_mut::<Inventory>() {
+                    let scratch_to_add = random.rng().gen_range(low_scrap, high_scrap);
+                    inv.add_scratch(scratch_to_add);
+                }
+            }
+
+            // drop some pickups :)
+            let pick: u8 = random.rng().gen_range(0, 101);
+            if pick <= pickup_drop {
+                spawn_pickup(world, position, &mut random);
+            }
+
+            if let Some(stage) = self.schedule.get_mut::<Stage>() {
+                stage.enemy_died(entity)
+            }
+        }
+
+        for InfoText(info) in info_texts {
+            self.info_text_timer.reset();
+            self.info_text_timer.start();
+            self.info_text = Some(info);
+        }
+
+        if game_over {
+            self.state = MainSceneState::GameOver;
+
+            // if infinite, let's set new wave record if it's more than current.
+            if self.is_infinite {
+                if let Err(e) = save_new_wave_record(
+                    resources,
+                    self.stage().expect("Should have a stage...").wave_number,
+                ) {
+                    error!("could not save data = {:?}", e);
+                }
+            }
+
+            drain_scratch = true;
+        }
+
+        if you_win {
+            drain_scratch = true;
+            if let Err(e) = save_unlocked(resources) {
+                error!("could not save data = {:?}", e);
+            }
+            self.state = MainSceneState::GameWon
+        }
+
+        for NextStage(stage_name) in next_stages {
+            self.pending_stage = Some((stage_name, 0));
+            drain_scratch = true;
+        }
+
+        // The stage file changed, start the stage again with the new version once it is reloaded.
+        if let Some((stage_name, starting_wave_nb)) = self.stage_file.clone() {
+            let stage_path = Path::new("stages").join(&stage_name);
+            if reloaded.iter().any(|AssetReloaded(p)| *p == stage_path) {
+                info!("Restart stage {}", stage_name);
+                self.pending_stage = Some((stage_name, starting_wave_nb));
+            }
+        }
+        self.start_pending_stage(world, resources);
+
+        if drain_scratch {
+            // Remove all scratch :) You need to spend that money.
+            if let Some(ref mut inv) = resources.fetch_mut::<Inventory>() {
+                inv.drain_scratch();
+            }
+        }
+    }
+}
+
+/// Assets needed by the main scene: the common ones from `manifests/main_scene.json` and the ones
+/// of the first stage.
+pub fn main_scene_manifest(is_infinite: bool) -> AssetManifest {
+    let mut manifest = AssetManifest::from_file("main_scene.json").unwrap_or_else(|e| {
+        error!("Cannot read main scene manifest = {}", e);
+        AssetManifest::default()
+    });
+    if is_infinite {
+        manifest.merge(&StageDescription::infinite().manifest());
+    } else {
+        // the prefabs of the stage are added once it is loaded.
+        manifest.add_stage(FIRST_STAGE.to_string());
+    }
+    manifest
+}
+
+/// Systems of the main scene, without the stage.
+pub fn main_schedule() -> Schedule {
+    let mut schedule = Schedule::default();
+    schedule.add_system(
+        SystemStage::Input,
+        "player",
+        |world: &mut World, resources: &Resources, dt: Duration| {
+            player::update_player(world, dt, resources)
+        },
+    );
+    schedule
+        .add_system(
+            SystemStage::Input,
+            "camera",
+            |world: &mut World, resources: &Resources, dt: Duration| {
+                update_camera(world, resources, dt)
+            },
+        )
+        .after("player");
+
+    schedule.add_system(
+        SystemStage::Ai,
+        "enemies",
+        |world: &mut World, resources: &Resources, dt: Duration| {
+            enemy::update_enemies(world, resources, dt)
+        },
+    );
+    schedule.add_system(SystemStage::Ai, "animation", AnimationSystem);
+    schedule.add_system(SystemStage::Ai, "tweens", update_tweens);
+    schedule.add_system(
+        SystemStage::Ai,
+        "trails",
+        |world: &mut World, _resources: &Resources, _dt: Duration| update_trails(world),
+    );
+
+    schedule.add_system(
+        SystemStage::Physics,
+        "physics",
+        PhysicSystem::new(PhysicConfig { damping: 0.99 }),
+    );
+
+    schedule.add_system(
+        SystemStage::Collision,
+        "bullets",
+        |world: &mut World, resources: &Resources, _dt: Duration| {
+            bullet::process_bullets(world, resources)
+        },
+    );
+    schedule.add_system(
+        SystemStage::Collision,
+        "missiles",
+        |world: &mut World, resources: &Resources, _dt: Duration| {
+            bullet::process_missiles(world, resources)
+        },
+    );
+    schedule.add_system(
+        SystemStage::Collision,
+        "pickups",
+        |world: &mut World, resources: &Resources, _dt: Duration| process_pickups(world, resources),
+    );
+    schedule.add_system(
+        SystemStage::Collision,
+        "collisions",
+        |world: &mut World, resources: &Resources, _dt: Duration| {
+            let collisions = collision::find_collisions(world, resources);
+            collision::process_collisions(world, collisions, resources);
+        },
+    );
+
+    schedule.add_system(SystemStage::Damage, "health", HealthSystem::new());
+    schedule
+        .add_system(SystemStage::Damage, "explosion", ExplosionSystem::default())
+        .after("health");
+    schedule
 }
 
 impl Scene<WindowEvent> for MainScene {
     fn on_create(&mut self, world: &mut hecs::World, resources: &mut Resources) {
         info!("Create MainScene");
-        self.health_system = Some(HealthSystem::new(resources));
-        self.explosion_system = Some(ExplosionSystem::new(resources));
+        self.events = Some(MainSceneEvents::new(resources));
 
         //generate_terrain(world, resources);
-        let base_path = get_assets_path();
-        let mut emitter: ParticleEmitter = serde_json::from_str(
-            &std::fs::read_to_string(base_path.join("particle/trail.json"))
-                .unwrap(),
-        )
-        .unwrap();
-        emitter.init_pool();
-
-        let stage_desc: StageDescription = if self.is_infinite {
-            StageDescription::infinite()
+        self.schedule = (self.schedule_factory)();
+        if self.is_infinite {
+            let stage = Stage::new(
+                world,
+                resources,
+                StageDescription::infinite(),
+                self.starting_wave_nb,
+            );
+            self.schedule
+                .add_system(SystemStage::Cleanup, "stage", stage);
         } else {
-            let p = base_path.join("stages/stage1.json");
-            let content = std::fs::read_to_string(p).unwrap();
-            serde_json::from_str(&content).unwrap()
-        };
-        let stage = Stage::new(world, resources, stage_desc, self.starting_wave_nb);
-        self.stage = Some(stage);
+            self.pending_stage = Some((FIRST_STAGE.to_string(), self.starting_wave_nb));
+            self.start_pending_stage(world, resources);
+        }
+        if let Err(e) = self.schedule.setup(world, resources) {
+            self.errors.push(format!("Invalid schedule = {}", e));
+        }
 
         self.player = Some({
             let prefab_manager = resources.fetch_mut::<PrefabManager<GlfwSurface>>().unwrap();
-            let asset = prefab_manager
-                .get(&Handle("player".to_string()))
-                .expect("Player asset should have been loaded");
-            assert!(asset.is_loaded());
-            asset
-                .execute(|prefab| prefab.spawn(world))
-                .expect("Should be able to spawn player")
+            self.player_prefab = prefab_manager.acquire(&Handle("player".to_string()));
+            prefab_manager
+                .spawn("player", world)
+                .expect("Player asset should have been loaded")
         });
 
         let player_scale = { world.get::<Transform>(self.player.unwrap()).unwrap().scale * 2.0 };
@@ -176,26 +519,13 @@ impl Scene<WindowEvent> for MainScene {
     fn on_destroy(&mut self, world: &mut hecs::World) {
         // remove the player.
         if let Some(p) = self.player {
-            let mut to_despawn = vec![];
-            if let Ok(children) = world.get::<HasChildren>(p) {
-                for c in &children.children {
-                    to_despawn.push(*c);
-                }
-            }
-
-            to_despawn.iter().for_each(|&c| {
-                if let Err(e) = world.despawn(c) {
-                    error!("Cannot despawn player's children = {:?}", e);
-                }
-            });
-
-            if let Err(e) = world.despawn(p) {
+            if let Err(e) = despawn_recursive(world, p) {
                 error!("Error while despawn player = {:?}", e);
             }
         }
 
         // Clean the stage.
-        if let Some(stage) = self.stage.as_mut() {
+        if let Some(stage) = self.schedule.get_mut::<Stage>() {
             stage.clean(world);
         }
 
@@ -232,32 +562,20 @@ impl Scene<WindowEvent> for MainScene {
             self.info_text = None;
         }
 
+        self.update_stage_loading(resources);
         if let MainSceneState::Running = self.state {
-            player::update_player(world, dt, resources);
-            update_camera(world, resources);
-            enemy::update_enemies(world, &resources, dt);
-            self.animation_system.animate(world, resources);
-            update_trails(world);
-            self.physic_system.update(world, dt, resources);
-
-            bullet::process_bullets(world, resources);
-            bullet::process_missiles(world, resources);
-            process_pickups(world, resources);
-
-            let collisions = collision::find_collisions(world, resources);
-            collision::process_collisions(world, collisions, &resources);
-            if let Some(hs) = self.health_system.as_mut() {
-                hs.update(world, &resources, dt);
-            }
-            if let Some(system) = self.explosion_system.as_mut() {
-                system.update(world, &resources);
-            }
-            if let Some(ref mut stage) = self.stage {
-                stage.update(world, resources, dt);
+            if let Err(e) = self.schedule.run(world, resources, dt) {
+                self.errors.push(format!("Invalid schedule = {}", e));
             }
         }
+        self.process_events(world, resources);
 
-        if let MainSceneState::Paused = self.state {
+        if !self.errors.is_empty() {
+            SceneResult::ReplaceScene(Box::new(ErrorScene::new(
+                "Cannot start the stage".to_string(),
+                std::mem::take(&mut self.errors),
+            )))
+        } else if let MainSceneState::Paused = self.state {
             self.state = MainSceneState::Running;
             SceneResult::Push(Box::new(PauseScene::default()))
         } else if let MainSceneState::GameWon = self.state {
@@ -271,10 +589,10 @@ impl Scene<WindowEvent> for MainScene {
         } else if self.return_to_menu {
             SceneResult::ReplaceScene(Box::new(MainMenu::default()))
         } else if self.restart {
-            SceneResult::ReplaceScene(Box::new(MainScene::new(
-                self.is_infinite,
-                self.starting_wave_nb,
-            )))
+            SceneResult::ReplaceScene(Box::new(
+                MainScene::new(self.is_infinite, self.starting_wave_nb)
+                    .with_schedule(self.schedule_factory),
+            ))
         } else {
             SceneResult::Noop
         }
@@ -338,7 +656,7 @@ impl Scene<WindowEvent> for MainScene {
                 }
 
                 // information about stage and waves.
-                if let Some(stage_text) = self.stage.as_ref().and_then(|s| s.display()) {
+                if let Some(stage_text) = self.stage().and_then(|s| s.display()) {
                     let center =
                         gui_context.window_dim.to_vec2() / 2.0 - glam::Vec2::unit_y() * 100.0;
                     gui.centered_label(center, stage_text)
@@ -356,7 +674,7 @@ impl Scene<WindowEvent> for MainScene {
                 }
 
                 // information about infinite wave.
-                if let Some(ref stage) = self.stage {
+                if let Some(stage) = self.stage() {
                     if stage.is_infinite {
                         gui.colored_label(
                             glam::vec2(
@@ -377,7 +695,7 @@ impl Scene<WindowEvent> for MainScene {
             }
             MainSceneState::GameOver => {
                 let center = gui_context.window_dim.to_vec2() / 2.0 - glam::Vec2::unit_y() * 100.0;
-                if let Some(ref stage) = self.stage {
+                if let Some(stage) = self.stage() {
                     if stage.is_infinite {
                         gui.colored_label(
                             center,
@@ -410,92 +728,16 @@ impl Scene<WindowEvent> for MainScene {
         Some(gui)
     }
 
-    fn process_event(&mut self, world: &mut World, ev: GameEvent, resources: &Resources) {
-        let mut drain_scratch = false;
-        match ev {
-            GameEvent::GameOver => {
-                self.state = MainSceneState::GameOver;
-
-                // if infinite, let's set new wave record if it's more than current.
-                if self.is_infinite {
-                    if let Err(e) = save_new_wave_record(
-                        resources,
-                        self.stage
-                            .as_ref()
-                            .expect("Should have a stage...")
-                            .wave_number,
-                    ) {
-                        error!("could not save data = {:?}", e);
-                    }
-                }
-
-                drain_scratch = true;
-            }
-            GameEvent::YouWin => {
-                drain_scratch = true;
-                if let Err(e) = save_unlocked(resources) {
-                    error!("could not save data = {:?}", e);
-                }
-                self.state = MainSceneState::GameWon
-            }
-            GameEvent::EnemyDied(e, pos, (low_scrap, high_scrap), pickup_drop) => {
-                let mut random = resources
-                    .fetch_mut::<RandomGenerator>()
-                    .expect("Should have a random generator");
-
-                if high_scrap > low_scrap {
-                    if let Some(ref mut inv) = resources.fetch_mut::<Inventory>() {
-                        let scratch_to_add = random.rng().gen_range(low_scrap, high_scrap);
-                        inv.add_scratch(scratch_to_add);
-                    }
-                }
-
-                // drop some pickups :)
-                let pick: u8 = random.rng().gen_range(0, 101);
-                if pick <= pickup_drop {
-                    spawn_pickup(world, pos, &mut *random);
-                }
-
-                if let Some(stage) = self.stage.as_mut() {
-                    stage.enemy_died(e)
-                }
-            }
-            GameEvent::InfoText(info) => {
-                self.info_text_timer.reset();
-                self.info_text_timer.start();
-                self.info_text = Some(info);
-            }
-            GameEvent::NextStage(stage_name) => {
-                let base_path = get_assets_path();
-                let stage_desc: StageDescription = {
-                    let p = base_path.join("stages").join(stage_name);
-                    let content = std::fs::read_to_string(p).unwrap();
-                    serde_json::from_str(&content).unwrap()
-                };
-                if let Some(stage) = self.stage.as_mut() {
-                    stage.clean(world);
-                }
-                let stage = Stage::new(world, resources, stage_desc, 0);
-                self.stage = Some(stage);
-
-                drain_scratch = true;
-            }
-            _ => (),
-        }
-
-        if drain_scratch {
-            // Remove all scratch :) You need to spend that money.
-            if let Some(ref mut inv) = resources.fetch_mut::<Inventory>() {
-                inv.drain_scratch();
-            }
-        }
-    }
-
     fn process_input(&mut self, _world: &mut World, input: WindowEvent, _resources: &Resources) {
         if let WindowEvent::Key(Key::Escape, _0, glfw::Action::Press, _2) = input {
             self.state = MainSceneState::Paused;
         }
     }
+
+    /// The loading and the menus are not part of the recordings.
+    fn starts_input_recording(&self) -> bool {
+        true
+    }
 }
 
 fn game_button(text: &str, position: glam::Vec2, ui: &mut Gui) -> bool {
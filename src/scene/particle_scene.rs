@@ -1,9 +1,14 @@
 //! Just a scene to experiment with particles.
 
+use crate::assets::particle::load_particle;
+use crate::assets::source::AssetSource;
+use crate::assets::AssetError;
 use crate::core::camera::{screen_to_world, ProjectionMatrix};
+use crate::core::colors::RgbaColor;
 use crate::core::input::Input;
 use crate::core::scene::{Scene, SceneResult};
 use crate::core::transform::Transform;
+use crate::core::window::WindowDim;
 use crate::gameplay::Action;
 use crate::render::particle::ParticleEmitter;
 use crate::render::ui::gui::GuiContext;
@@ -14,42 +19,64 @@ use bitflags::_core::time::Duration;
 use glam::Vec2;
 use glfw::WindowEvent;
 use hecs::{Entity, World};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Clone)]
 pub struct ParticleScene {
     entity: Option<Entity>,
-    particle_emitter: ParticleEmitter,
     reload: bool,
     filename: PathBuf,
     should_follow: bool,
+    /// Why the emitter cannot be loaded. Shown until the next reload.
+    error: Option<String>,
 }
 
 impl ParticleScene {
     pub fn new(filename: PathBuf, should_follow: bool) -> Self {
-        let mut emitter: ParticleEmitter =
-            serde_json::from_str(&std::fs::read_to_string(&filename).unwrap()).unwrap();
-        emitter.init_pool();
-
         Self {
             entity: None,
-            particle_emitter: emitter,
             reload: false,
             filename,
             should_follow,
+            error: None,
+        }
+    }
+
+    fn load_emitter(&self) -> Result<ParticleEmitter, AssetError> {
+        let folder = self.filename.parent().unwrap_or_else(|| Path::new("."));
+        let name = self
+            .filename
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        load_particle(&AssetSource::loose(folder), &name)
+    }
+
+    /// Spawn the emitter from the file, or keep the error to display it.
+    fn spawn_emitter(&mut self, world: &mut World) {
+        match self.load_emitter() {
+            Ok(mut emitter) => {
+                emitter.init_pool();
+                let t = Transform {
+                    translation: Vec2::new(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0),
+                    rotation: 0.0,
+                    scale: Vec2::one(),
+                    dirty: false,
+                };
+                self.entity = Some(world.spawn((emitter, t)));
+                self.error = None;
+            }
+            Err(e) => {
+                self.entity = None;
+                self.error = Some(format!("Cannot load {} = {}", self.filename.display(), e));
+            }
         }
     }
 }
 
 impl Scene<WindowEvent> for ParticleScene {
     fn on_create(&mut self, world: &mut World, _resources: &mut Resources) {
-        let t = Transform {
-            translation: Vec2::new(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0),
-            rotation: 0.0,
-            scale: Vec2::one(),
-            dirty: false,
-        };
-        self.entity = Some(world.spawn((self.particle_emitter.clone(), t)));
+        self.spawn_emitter(world);
     }
 
     fn update(
@@ -60,30 +87,21 @@ impl Scene<WindowEvent> for ParticleScene {
     ) -> SceneResult<WindowEvent> {
         if self.reload {
             // remove entity, reload emitter from file and spawn the new emitter.
-            let _ = world.despawn(self.entity.unwrap());
-
-            let mut emitter: ParticleEmitter =
-                serde_json::from_str(&std::fs::read_to_string(&self.filename).unwrap()).unwrap();
-            emitter.init_pool();
-
-            self.particle_emitter = emitter;
-            let t = Transform {
-                translation: Vec2::new(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0),
-                rotation: 0.0,
-                scale: Vec2::one(),
-                dirty: false,
-            };
-            self.entity = Some(world.spawn((self.particle_emitter.clone(), t)));
-
+            if let Some(entity) = self.entity.take() {
+                let _ = world.despawn(entity);
+            }
+            self.spawn_emitter(world);
             self.reload = false;
         }
 
-        {
+        if let Some(entity) = self.entity {
             let input = resources.fetch::<Input<Action>>().unwrap();
             let proj = resources.fetch::<ProjectionMatrix>().unwrap();
+            let window_dim = resources.fetch::<WindowDim>().unwrap();
             if input.is_just_pressed(Action::Shoot) || self.should_follow {
-                let new_pos = screen_to_world(input.mouse_position(), proj.0, world);
-                let mut transform = world.get_mut::<Transform>(self.entity.unwrap()).unwrap();
+                let new_pos =
+                    screen_to_world(input.mouse_position(&window_dim), proj.matrix(), world);
+                let mut transform = world.get_mut::<Transform>(entity).unwrap();
                 transform.translation = new_pos;
             }
         }
@@ -102,6 +120,13 @@ impl Scene<WindowEvent> for ParticleScene {
         if gui.button(glam::vec2(10.0, 10.0), None, "Reload".to_string()) {
             self.reload = true;
         }
+        if let Some(ref error) = self.error {
+            gui.colored_label(
+                glam::vec2(10.0, 60.0),
+                error.clone(),
+                RgbaColor::new(255, 0, 0, 255),
+            );
+        }
 
         Some(gui)
     }
@@ -2,7 +2,7 @@
 //! Some buttons to abandon or resume the game.
 
 use crate::core::colors::RgbaColor;
-use crate::core::scene::{Scene, SceneResult};
+use crate::core::scene::{Scene, SceneResult, SceneTransparency};
 use crate::render::ui::{Gui, GuiContext};
 use crate::resources::Resources;
 use crate::scene::main_menu::MainMenu;
@@ -18,6 +18,11 @@ pub struct PauseScene {
 }
 
 impl Scene<WindowEvent> for PauseScene {
+    /// The game is frozen but the HUD stays visible.
+    fn transparency(&self) -> SceneTransparency {
+        SceneTransparency::ShowBelow
+    }
+
     fn update(
         &mut self,
         _dt: Duration,